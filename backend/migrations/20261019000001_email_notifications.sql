@@ -0,0 +1,39 @@
+-- Email notification channel: verified addresses, per-category opt-ins and
+-- an outbox drained by the email dispatcher.
+
+CREATE TABLE IF NOT EXISTS email_settings (
+    user_pubkey             TEXT PRIMARY KEY,
+    email                   TEXT NOT NULL,
+    verified                BOOLEAN NOT NULL DEFAULT false,
+    verification_token      UUID,
+    verification_expires_at TIMESTAMPTZ,
+    notify_renewal_success  BOOLEAN NOT NULL DEFAULT true,
+    notify_renewal_failure  BOOLEAN NOT NULL DEFAULT true,
+    notify_expiry           BOOLEAN NOT NULL DEFAULT true,
+    notify_upcoming_charge  BOOLEAN NOT NULL DEFAULT true,
+    created_at              TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at              TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS email_settings_verification_token_idx
+    ON email_settings (verification_token)
+    WHERE verification_token IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS email_queue (
+    id          UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_pubkey TEXT NOT NULL,
+    to_address  TEXT NOT NULL,
+    subject     TEXT NOT NULL,
+    text_body   TEXT NOT NULL,
+    html_body   TEXT NOT NULL,
+    status      TEXT NOT NULL DEFAULT 'pending'
+                CHECK (status IN ('pending', 'sending', 'sent', 'failed')),
+    attempts    INTEGER NOT NULL DEFAULT 0,
+    last_error  TEXT,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    sent_at     TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS email_queue_pending_idx
+    ON email_queue (created_at)
+    WHERE status = 'pending';
@@ -0,0 +1,9 @@
+-- One row per reminder sent, so each window fires once per billing cycle.
+
+CREATE TABLE IF NOT EXISTS payment_reminders (
+    subscription_pda TEXT NOT NULL,
+    next_payment_ts  BIGINT NOT NULL,
+    window_hours     INTEGER NOT NULL,
+    sent_at          TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (subscription_pda, next_payment_ts, window_hours)
+);
@@ -0,0 +1,20 @@
+-- Broadcast every new notification so each backend instance can push it to
+-- connected wallets over the real-time stream.
+
+CREATE OR REPLACE FUNCTION notify_notification_inserted() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify(
+        'notifications_inserted',
+        json_build_object('id', NEW.id, 'userPubkey', NEW.user_pubkey)::text
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS notifications_inserted_trigger ON notifications;
+CREATE TRIGGER notifications_inserted_trigger
+    AFTER INSERT ON notifications
+    FOR EACH ROW EXECUTE FUNCTION notify_notification_inserted();
+
+CREATE INDEX IF NOT EXISTS notifications_user_created_at_idx
+    ON notifications (user_pubkey, created_at);
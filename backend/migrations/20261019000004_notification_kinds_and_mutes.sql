@@ -0,0 +1,35 @@
+-- Typed notification kinds, per-plan mutes and expiry for retention.
+
+CREATE TYPE notification_kind AS ENUM ('success', 'error', 'warning', 'info', 'reminder');
+
+UPDATE notifications
+SET type = 'info'
+WHERE type NOT IN ('success', 'error', 'warning', 'info', 'reminder');
+
+ALTER TABLE notifications
+    ALTER COLUMN type TYPE notification_kind USING type::notification_kind;
+
+ALTER TABLE notifications
+    ADD COLUMN IF NOT EXISTS plan_pda TEXT,
+    ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ;
+
+DROP INDEX IF EXISTS notifications_user_created_at_idx;
+CREATE INDEX IF NOT EXISTS notifications_user_created_at_id_idx
+    ON notifications (user_pubkey, created_at DESC, id DESC);
+
+CREATE INDEX IF NOT EXISTS notifications_user_unread_idx
+    ON notifications (user_pubkey)
+    WHERE is_read = false;
+
+CREATE INDEX IF NOT EXISTS notifications_expires_at_idx
+    ON notifications (expires_at)
+    WHERE expires_at IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS notification_mutes (
+    user_pubkey TEXT NOT NULL,
+    plan_pda    TEXT NOT NULL,
+    -- NULL mutes the plan until the user unmutes it
+    muted_until TIMESTAMPTZ,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_pubkey, plan_pda)
+);
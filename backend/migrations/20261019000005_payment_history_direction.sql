@@ -0,0 +1,25 @@
+-- Distinguish the payer's and the receiver's copy of each payment and index
+-- the keyset-paginated history queries.
+
+ALTER TABLE payment_history
+    ADD COLUMN IF NOT EXISTS direction TEXT NOT NULL DEFAULT 'paid'
+        CHECK (direction IN ('paid', 'received'));
+
+-- record_payment_for_both inserted the payer row first, then the receiver row
+UPDATE payment_history ph
+SET direction = 'received'
+WHERE EXISTS (
+    SELECT 1 FROM payment_history other
+    WHERE other.subscription_pda = ph.subscription_pda
+      AND other.tx_signature IS NOT DISTINCT FROM ph.tx_signature
+      AND other.id < ph.id
+);
+
+CREATE INDEX IF NOT EXISTS payment_history_user_created_at_idx
+    ON payment_history (user_pubkey, created_at DESC, id DESC);
+
+CREATE INDEX IF NOT EXISTS payment_history_user_subscription_created_at_idx
+    ON payment_history (user_pubkey, subscription_pda, created_at DESC, id DESC);
+
+CREATE INDEX IF NOT EXISTS payment_history_user_direction_created_at_idx
+    ON payment_history (user_pubkey, direction, created_at DESC);
@@ -0,0 +1,12 @@
+-- Link payments to their on-chain plan so exports can resolve the mint.
+
+ALTER TABLE payment_history ADD COLUMN IF NOT EXISTS plan_pda TEXT;
+
+UPDATE payment_history ph
+SET plan_pda = s.plan_pda
+FROM subscriptions s
+WHERE s.subscription_pda = ph.subscription_pda
+  AND ph.plan_pda IS NULL;
+
+CREATE INDEX IF NOT EXISTS payment_history_plan_created_at_idx
+    ON payment_history (plan_pda, created_at);
@@ -0,0 +1,95 @@
+-- Subscription lifecycle facts and the materialized views behind the
+-- merchant analytics endpoints.
+
+CREATE TABLE IF NOT EXISTS subscription_events (
+    id               BIGSERIAL PRIMARY KEY,
+    subscription_pda TEXT NOT NULL,
+    plan_pda         TEXT NOT NULL,
+    payer            TEXT NOT NULL,
+    tier_name        TEXT NOT NULL,
+    event            TEXT NOT NULL CHECK (event IN ('started', 'cancelled', 'expired')),
+    occurred_at      TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS subscription_events_plan_occurred_at_idx
+    ON subscription_events (plan_pda, occurred_at);
+
+-- Every subscription so far started with the payer's first payment
+INSERT INTO subscription_events (subscription_pda, plan_pda, payer, tier_name, event, occurred_at)
+SELECT DISTINCT ON (subscription_pda)
+    subscription_pda, plan_pda, user_pubkey, tier, 'started', created_at
+FROM payment_history
+WHERE direction = 'paid' AND plan_pda IS NOT NULL
+ORDER BY subscription_pda, created_at ASC;
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS analytics_plan_monthly AS
+WITH events AS (
+    SELECT
+        plan_pda,
+        date_trunc('month', occurred_at) AS month,
+        COUNT(*) FILTER (WHERE event = 'started') AS new_subscribers,
+        COUNT(*) FILTER (WHERE event IN ('cancelled', 'expired')) AS churned_subscribers
+    FROM subscription_events
+    GROUP BY 1, 2
+),
+payments AS (
+    SELECT
+        plan_pda,
+        date_trunc('month', created_at) AS month,
+        COUNT(*) FILTER (WHERE status = 'success') AS successful_payments,
+        COUNT(*) FILTER (WHERE status = 'failed') AS failed_payments,
+        COALESCE(SUM(amount) FILTER (WHERE status = 'success'), 0) AS revenue
+    FROM payment_history
+    WHERE direction = 'paid' AND plan_pda IS NOT NULL
+    GROUP BY 1, 2
+)
+SELECT
+    COALESCE(e.plan_pda, p.plan_pda) AS plan_pda,
+    COALESCE(e.month, p.month) AS month,
+    COALESCE(e.new_subscribers, 0)::BIGINT AS new_subscribers,
+    COALESCE(e.churned_subscribers, 0)::BIGINT AS churned_subscribers,
+    COALESCE(p.successful_payments, 0)::BIGINT AS successful_payments,
+    COALESCE(p.failed_payments, 0)::BIGINT AS failed_payments,
+    COALESCE(p.revenue, 0)::BIGINT AS revenue
+FROM events e
+FULL OUTER JOIN payments p ON p.plan_pda = e.plan_pda AND p.month = e.month;
+
+CREATE UNIQUE INDEX IF NOT EXISTS analytics_plan_monthly_key
+    ON analytics_plan_monthly (plan_pda, month);
+
+-- A subscription counts as retained in a month if it paid successfully in it
+CREATE MATERIALIZED VIEW IF NOT EXISTS analytics_plan_cohorts AS
+WITH firsts AS (
+    SELECT
+        subscription_pda,
+        plan_pda,
+        date_trunc('month', MIN(created_at)) AS cohort_month
+    FROM payment_history
+    WHERE direction = 'paid' AND status = 'success' AND plan_pda IS NOT NULL
+    GROUP BY 1, 2
+),
+activity AS (
+    SELECT DISTINCT subscription_pda, date_trunc('month', created_at) AS month
+    FROM payment_history
+    WHERE direction = 'paid' AND status = 'success'
+)
+SELECT
+    f.plan_pda,
+    f.cohort_month,
+    ((EXTRACT(YEAR FROM a.month) - EXTRACT(YEAR FROM f.cohort_month)) * 12
+        + EXTRACT(MONTH FROM a.month) - EXTRACT(MONTH FROM f.cohort_month))::INTEGER
+        AS months_since,
+    COUNT(DISTINCT f.subscription_pda)::BIGINT AS active_subscribers
+FROM firsts f
+JOIN activity a ON a.subscription_pda = f.subscription_pda
+GROUP BY 1, 2, 3;
+
+CREATE UNIQUE INDEX IF NOT EXISTS analytics_plan_cohorts_key
+    ON analytics_plan_cohorts (plan_pda, cohort_month, months_since);
+
+-- Latest source row each view has seen, so refreshes are skipped when idle
+CREATE TABLE IF NOT EXISTS analytics_refresh_state (
+    view_name        TEXT PRIMARY KEY,
+    source_watermark TIMESTAMPTZ NOT NULL,
+    refreshed_at     TIMESTAMPTZ NOT NULL DEFAULT now()
+);
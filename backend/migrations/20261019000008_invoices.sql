@@ -0,0 +1,42 @@
+-- Merchant business details and one sequentially numbered invoice per payment.
+
+CREATE TABLE IF NOT EXISTS merchant_profiles (
+    merchant_pubkey     TEXT PRIMARY KEY,
+    business_name       TEXT,
+    address             TEXT,
+    email               TEXT,
+    tax_id              TEXT,
+    invoice_prefix      TEXT NOT NULL DEFAULT 'INV',
+    next_invoice_number BIGINT NOT NULL DEFAULT 1,
+    created_at          TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at          TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS invoices (
+    id               BIGSERIAL PRIMARY KEY,
+    merchant_pubkey  TEXT NOT NULL,
+    invoice_number   BIGINT NOT NULL,
+    invoice_code     TEXT NOT NULL,
+    payment_id       BIGINT UNIQUE REFERENCES payment_history (id) ON DELETE SET NULL,
+    payer            TEXT NOT NULL,
+    plan_name        TEXT NOT NULL,
+    plan_pda         TEXT,
+    tier             TEXT NOT NULL,
+    period_start     TIMESTAMPTZ NOT NULL,
+    period_end       TIMESTAMPTZ NOT NULL,
+    amount           BIGINT NOT NULL,
+    amount_ui        TEXT NOT NULL,
+    token_symbol     TEXT NOT NULL,
+    mint             TEXT NOT NULL,
+    tx_signature     TEXT,
+    -- Business details are copied so later profile edits never change issued invoices
+    merchant_name    TEXT NOT NULL,
+    merchant_address TEXT,
+    merchant_email   TEXT,
+    merchant_tax_id  TEXT,
+    issued_at        TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (merchant_pubkey, invoice_number)
+);
+
+CREATE INDEX IF NOT EXISTS invoices_payer_issued_at_idx ON invoices (payer, issued_at DESC);
+CREATE INDEX IF NOT EXISTS invoices_merchant_issued_at_idx ON invoices (merchant_pubkey, issued_at DESC);
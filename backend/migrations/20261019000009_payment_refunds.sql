@@ -0,0 +1,13 @@
+-- Link each charge to its on-chain payment record and refunds to the charge they return.
+
+ALTER TABLE payment_history
+    ADD COLUMN IF NOT EXISTS payment_record TEXT,
+    ADD COLUMN IF NOT EXISTS refund_of BIGINT REFERENCES payment_history (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS payment_history_refund_of_idx
+    ON payment_history (refund_of)
+    WHERE refund_of IS NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS payment_history_refund_signature_idx
+    ON payment_history (tx_signature, direction)
+    WHERE status = 'refunded';
@@ -0,0 +1,8 @@
+-- Mirror of the on-chain pause timestamp; paused subscriptions are never charged.
+
+ALTER TABLE subscriptions
+    ADD COLUMN IF NOT EXISTS paused_at BIGINT;
+
+CREATE INDEX IF NOT EXISTS subscriptions_paused_idx
+    ON subscriptions (paused_at)
+    WHERE paused_at IS NOT NULL;
@@ -0,0 +1,13 @@
+-- Fixed-term subscriptions stop after a number of charges or at an end time.
+
+ALTER TABLE subscriptions
+    ADD COLUMN IF NOT EXISTS max_cycles BIGINT,
+    ADD COLUMN IF NOT EXISTS end_ts BIGINT,
+    ADD COLUMN IF NOT EXISTS completed_at TIMESTAMPTZ;
+
+ALTER TABLE subscription_events
+    DROP CONSTRAINT IF EXISTS subscription_events_event_check;
+
+ALTER TABLE subscription_events
+    ADD CONSTRAINT subscription_events_event_check
+        CHECK (event IN ('started', 'cancelled', 'expired', 'completed'));
@@ -0,0 +1,5 @@
+-- Usage breakdown for charges on metered tiers; `amount` stays the total charged.
+
+ALTER TABLE payment_history
+    ADD COLUMN IF NOT EXISTS usage_units BIGINT,
+    ADD COLUMN IF NOT EXISTS usage_amount BIGINT;
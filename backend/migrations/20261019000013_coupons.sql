@@ -0,0 +1,26 @@
+-- Merchant coupons, mirrored from their on-chain accounts so plans can list them.
+
+CREATE TABLE IF NOT EXISTS coupons (
+    coupon_pda      TEXT PRIMARY KEY,
+    plan_pda        TEXT NOT NULL,
+    code            TEXT NOT NULL,
+    discount_type   TEXT NOT NULL CHECK (discount_type IN ('percent', 'fixed')),
+    -- Basis points for percent coupons, token base units for fixed ones
+    discount_value  BIGINT NOT NULL,
+    duration_cycles BIGINT,
+    max_redemptions BIGINT,
+    redemptions     BIGINT NOT NULL DEFAULT 0,
+    expires_at      BIGINT,
+    tier_name       TEXT,
+    active          BOOLEAN NOT NULL DEFAULT true,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (plan_pda, code)
+);
+
+ALTER TABLE subscriptions
+    ADD COLUMN IF NOT EXISTS coupon_pda TEXT;
+
+CREATE INDEX IF NOT EXISTS subscriptions_coupon_pda_idx
+    ON subscriptions (coupon_pda)
+    WHERE coupon_pda IS NOT NULL;
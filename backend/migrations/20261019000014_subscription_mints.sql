@@ -0,0 +1,4 @@
+-- Plans may accept several mints; NULL means the plan's primary mint.
+
+ALTER TABLE subscriptions
+    ADD COLUMN IF NOT EXISTS mint TEXT;
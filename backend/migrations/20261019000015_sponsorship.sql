@@ -0,0 +1,27 @@
+-- Fee-payer relay: merchants fund sponsored sign-ups per plan, each relayed transaction is logged.
+
+CREATE TABLE IF NOT EXISTS sponsorship_budgets (
+    plan_pda            TEXT PRIMARY KEY,
+    merchant_pubkey     TEXT NOT NULL,
+    -- Lamports the relay may spend on this plan's sign-ups, fees and rent top-ups together
+    total_lamports      BIGINT NOT NULL CHECK (total_lamports >= 0),
+    per_wallet_lamports BIGINT NOT NULL CHECK (per_wallet_lamports >= 0),
+    spent_lamports      BIGINT NOT NULL DEFAULT 0,
+    active              BOOLEAN NOT NULL DEFAULT true,
+    created_at          TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at          TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS sponsored_transactions (
+    id               BIGSERIAL PRIMARY KEY,
+    tx_signature     TEXT NOT NULL UNIQUE,
+    plan_pda         TEXT NOT NULL,
+    payer            TEXT NOT NULL,
+    subscription_pda TEXT NOT NULL,
+    fee_lamports     BIGINT NOT NULL,
+    rent_lamports    BIGINT NOT NULL,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS sponsored_transactions_payer_plan_idx
+    ON sponsored_transactions (payer, plan_pda);
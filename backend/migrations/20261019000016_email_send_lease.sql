@@ -0,0 +1,8 @@
+-- Emails claimed by a dispatcher that died mid-send are returned to the queue once
+-- their claim is older than the send lease.
+
+ALTER TABLE email_queue ADD COLUMN IF NOT EXISTS claimed_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS email_queue_sending_idx
+    ON email_queue (claimed_at)
+    WHERE status = 'sending';
@@ -0,0 +1,55 @@
+-- Plans accept several mints, so revenue in base units is only summable per
+-- mint. Monthly revenue moves to its own view keyed by mint; '' stands for the
+-- plan's primary mint, as subscriptions.mint is NULL for those.
+
+DROP MATERIALIZED VIEW IF EXISTS analytics_plan_monthly;
+
+CREATE MATERIALIZED VIEW analytics_plan_monthly AS
+WITH events AS (
+    SELECT
+        plan_pda,
+        date_trunc('month', occurred_at) AS month,
+        COUNT(*) FILTER (WHERE event = 'started') AS new_subscribers,
+        COUNT(*) FILTER (WHERE event IN ('cancelled', 'expired')) AS churned_subscribers
+    FROM subscription_events
+    GROUP BY 1, 2
+),
+payments AS (
+    SELECT
+        plan_pda,
+        date_trunc('month', created_at) AS month,
+        COUNT(*) FILTER (WHERE status = 'success') AS successful_payments,
+        COUNT(*) FILTER (WHERE status = 'failed') AS failed_payments
+    FROM payment_history
+    WHERE direction = 'paid' AND plan_pda IS NOT NULL
+    GROUP BY 1, 2
+)
+SELECT
+    COALESCE(e.plan_pda, p.plan_pda) AS plan_pda,
+    COALESCE(e.month, p.month) AS month,
+    COALESCE(e.new_subscribers, 0)::BIGINT AS new_subscribers,
+    COALESCE(e.churned_subscribers, 0)::BIGINT AS churned_subscribers,
+    COALESCE(p.successful_payments, 0)::BIGINT AS successful_payments,
+    COALESCE(p.failed_payments, 0)::BIGINT AS failed_payments
+FROM events e
+FULL OUTER JOIN payments p ON p.plan_pda = e.plan_pda AND p.month = e.month;
+
+CREATE UNIQUE INDEX IF NOT EXISTS analytics_plan_monthly_key
+    ON analytics_plan_monthly (plan_pda, month);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS analytics_plan_revenue AS
+SELECT
+    ph.plan_pda,
+    date_trunc('month', ph.created_at) AS month,
+    COALESCE(s.mint, '') AS mint,
+    SUM(ph.amount)::BIGINT AS revenue
+FROM payment_history ph
+LEFT JOIN subscriptions s ON s.subscription_pda = ph.subscription_pda
+WHERE ph.direction = 'paid' AND ph.status = 'success' AND ph.plan_pda IS NOT NULL
+GROUP BY 1, 2, 3;
+
+CREATE UNIQUE INDEX IF NOT EXISTS analytics_plan_revenue_key
+    ON analytics_plan_revenue (plan_pda, month, mint);
+
+-- Rebuild both on the next refresh
+DELETE FROM analytics_refresh_state WHERE view_name = 'analytics_plan_monthly';
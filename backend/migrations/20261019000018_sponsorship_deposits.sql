@@ -0,0 +1,17 @@
+-- Sponsorship budgets are backed by lamports the plan creator actually sent the relay sponsor.
+
+CREATE TABLE IF NOT EXISTS sponsorship_deposits (
+    id              BIGSERIAL PRIMARY KEY,
+    tx_signature    TEXT NOT NULL UNIQUE,
+    plan_pda        TEXT NOT NULL REFERENCES sponsorship_budgets (plan_pda),
+    merchant_pubkey TEXT NOT NULL,
+    lamports        BIGINT NOT NULL CHECK (lamports > 0),
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS sponsorship_deposits_plan_idx ON sponsorship_deposits (plan_pda);
+
+-- Budgets set before deposits existed were never funded
+UPDATE sponsorship_budgets SET total_lamports = spent_lamports, updated_at = now();
+
+ALTER TABLE sponsorship_budgets ALTER COLUMN total_lamports SET DEFAULT 0;
@@ -0,0 +1,7 @@
+-- Where the subscription indexer stopped reading the program's transactions.
+
+CREATE TABLE IF NOT EXISTS program_index_state (
+    name           TEXT PRIMARY KEY,
+    last_signature TEXT NOT NULL,
+    updated_at     TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,12 @@
+-- Tokens that let an EventSource reconnect to a wallet's notification stream without
+-- a fresh signature; issued on a signed request and valid until expires_at.
+
+CREATE TABLE IF NOT EXISTS notification_stream_sessions (
+    token UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_pubkey TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS notification_stream_sessions_expiry_idx
+    ON notification_stream_sessions (expires_at);
@@ -0,0 +1,16 @@
+-- Sponsored sign-ups whose submission had no definite outcome stay reserved against the
+-- budget until their signature settles. Budgets also keep the address deposits go to, so
+-- rotating the relay signer does not turn away transfers to the old one.
+
+ALTER TABLE sponsored_transactions
+    ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'confirmed'
+        CHECK (status IN ('pending', 'confirmed', 'failed'));
+ALTER TABLE sponsored_transactions ADD COLUMN IF NOT EXISTS recent_blockhash TEXT;
+
+CREATE INDEX IF NOT EXISTS sponsored_transactions_pending_idx
+    ON sponsored_transactions (created_at)
+    WHERE status = 'pending';
+
+-- Set when the budget is created; older budgets take the sponsor of their next deposit
+ALTER TABLE sponsorship_budgets ADD COLUMN IF NOT EXISTS deposit_address TEXT;
+ALTER TABLE sponsorship_deposits ADD COLUMN IF NOT EXISTS deposit_address TEXT;
@@ -0,0 +1,586 @@
+//! Operator maintenance behind the `solpay-admin` binary: one-off keeper actions, replays
+//! and repairs of the database from the chain.
+
+use crate::handlers::transaction_handler::create_transaction;
+use crate::models::transaction::PaymentHistory;
+use crate::state::AppState;
+use crate::utils::{find_tier_by_name, parse_tiers};
+use crate::worker::{mark_subscription_completed, renew_subscription_by_pda};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use solpay_client::events::{ProgramEvent, Refunded};
+use std::str::FromStr;
+
+/// Pushes the keeper's next attempt back one period without charging. The on-chain
+/// schedule is unchanged, so a later `reconcile --apply` brings the old date back.
+pub async fn skip_subscription_cycle(
+    state: &AppState,
+    subscription_pda: Pubkey,
+) -> anyhow::Result<i64> {
+    let sub = sqlx::query!(
+        r#"
+        SELECT plan_pda, tier_name, next_payment_ts
+        FROM subscriptions
+        WHERE subscription_pda = $1
+        "#,
+        subscription_pda.to_string()
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| anyhow::anyhow!("Subscription {} not found", subscription_pda))?;
+
+    let plan = state
+        .solana
+        .get_plan(Pubkey::from_str(&sub.plan_pda)?)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Plan {} not found", sub.plan_pda))?;
+    let tiers = parse_tiers(&plan.tiers)?;
+    let period_seconds: i64 = find_tier_by_name(&tiers, &sub.tier_name)?
+        .period_seconds
+        .parse()?;
+
+    let next_payment_ts = sub.next_payment_ts + period_seconds;
+    sqlx::query!(
+        "UPDATE subscriptions SET next_payment_ts = $1 WHERE subscription_pda = $2",
+        next_payment_ts,
+        subscription_pda.to_string()
+    )
+    .execute(&state.db)
+    .await?;
+
+    Ok(next_payment_ts)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatestPayment {
+    pub status: String,
+    pub amount: i64,
+    pub tx_signature: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Runs the keeper's renewal for one subscription now, ahead of the keeper's schedule,
+/// and returns the payment it recorded. The program only charges once the on-chain date
+/// has passed, so an early renewal is refused here rather than recorded as a failure.
+pub async fn force_renew(
+    state: &AppState,
+    subscription_pda: Pubkey,
+) -> anyhow::Result<Option<LatestPayment>> {
+    let account = state
+        .solana
+        .get_subscription_account(&subscription_pda)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Subscription account {} not found", subscription_pda))?;
+    if account.next_payment_ts > Utc::now().timestamp() {
+        let due = DateTime::from_timestamp(account.next_payment_ts, 0).map_or_else(
+            || account.next_payment_ts.to_string(),
+            |due| due.to_rfc3339(),
+        );
+        anyhow::bail!("Subscription {} is not due until {}", subscription_pda, due);
+    }
+
+    let before = latest_payment(state, &subscription_pda).await?;
+    renew_subscription_by_pda(state, subscription_pda).await?;
+    let after = latest_payment(state, &subscription_pda).await?;
+
+    let recorded = match (&before, &after) {
+        (Some(before), Some(after)) => after.created_at > before.created_at,
+        (None, after) => after.is_some(),
+        (Some(_), None) => false,
+    };
+    Ok(after.filter(|_| recorded))
+}
+
+async fn latest_payment(
+    state: &AppState,
+    subscription_pda: &Pubkey,
+) -> anyhow::Result<Option<LatestPayment>> {
+    Ok(sqlx::query_as!(
+        LatestPayment,
+        r#"
+        SELECT status, amount, tx_signature, created_at
+        FROM payment_history
+        WHERE subscription_pda = $1
+          AND direction = 'paid'
+        ORDER BY created_at DESC, id DESC
+        LIMIT 1
+        "#,
+        subscription_pda.to_string()
+    )
+    .fetch_optional(&state.db)
+    .await?)
+}
+
+/// Puts emails that ran out of attempts back in the queue with a fresh allowance.
+pub async fn replay_failed_emails(state: &AppState) -> anyhow::Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE email_queue
+        SET status = 'pending', attempts = 0, last_error = NULL
+        WHERE status = 'failed'
+        "#
+    )
+    .execute(&state.db)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenewalReplay {
+    pub subscription_pda: String,
+    pub payment: Option<LatestPayment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Retries due subscriptions whose last charge failed. The keeper takes the most overdue
+/// subscription first, so one that keeps failing holds the others back until replayed.
+pub async fn replay_failed_renewals(state: &AppState) -> anyhow::Result<Vec<RenewalReplay>> {
+    let now = Utc::now().timestamp();
+    let failed = sqlx::query_scalar!(
+        r#"
+        SELECT s.subscription_pda
+        FROM subscriptions s
+        WHERE s.active = true
+          AND s.paused_at IS NULL
+          AND s.completed_at IS NULL
+          AND s.next_payment_ts <= $1
+          AND (
+              SELECT p.status
+              FROM payment_history p
+              WHERE p.subscription_pda = s.subscription_pda
+                AND p.direction = 'paid'
+              ORDER BY p.created_at DESC, p.id DESC
+              LIMIT 1
+          ) = 'failed'
+        ORDER BY s.next_payment_ts ASC
+        "#,
+        now
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut replays = Vec::new();
+    for subscription_pda in failed {
+        let (payment, error) = match force_renew(state, Pubkey::from_str(&subscription_pda)?).await
+        {
+            Ok(payment) => (payment, None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        replays.push(RenewalReplay {
+            subscription_pda,
+            payment,
+            error,
+        });
+    }
+
+    Ok(replays)
+}
+
+/// A column where Postgres disagrees with the subscription account.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Drift {
+    pub subscription_pda: String,
+    pub field: &'static str,
+    pub database: String,
+    pub chain: String,
+}
+
+/// Compares every unfinished subscription with its account and, with `apply`, copies the
+/// chain's state into Postgres. Closed accounts are deactivated and finished terms completed.
+pub async fn reconcile_subscriptions(state: &AppState, apply: bool) -> anyhow::Result<Vec<Drift>> {
+    let subs = sqlx::query!(
+        r#"
+        SELECT subscription_pda, payer, plan_pda, tier_name, active, auto_renew,
+               next_payment_ts, paused_at
+        FROM subscriptions
+        WHERE completed_at IS NULL
+        ORDER BY next_payment_ts ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut drifts = Vec::new();
+    for sub in subs {
+        let mut found: Vec<(&'static str, String, String)> = Vec::new();
+
+        let Some(account) = state
+            .solana
+            .get_subscription_account(&Pubkey::from_str(&sub.subscription_pda)?)
+            .await?
+        else {
+            if sub.active {
+                found.push(("account", "active".into(), "closed".into()));
+                if apply {
+                    sqlx::query!(
+                        r#"
+                        UPDATE subscriptions
+                        SET active = false, auto_renew = false
+                        WHERE subscription_pda = $1
+                        "#,
+                        sub.subscription_pda
+                    )
+                    .execute(&state.db)
+                    .await?;
+                }
+            }
+            drifts.extend(drift(&sub.subscription_pda, found));
+            continue;
+        };
+
+        if account.term_reached() {
+            found.push(("completedAt", "null".into(), "term reached".into()));
+            if apply {
+                let plan_name = match state.solana.get_plan(account.plan_pda).await {
+                    Ok(Some(plan)) => plan.name,
+                    _ => "your plan".to_string(),
+                };
+                mark_subscription_completed(
+                    state,
+                    &sub.subscription_pda,
+                    &plan_name,
+                    &sub.plan_pda,
+                    &sub.payer,
+                    &sub.tier_name,
+                )
+                .await?;
+            }
+            drifts.extend(drift(&sub.subscription_pda, found));
+            continue;
+        }
+
+        if sub.active != account.active {
+            found.push(("active", sub.active.to_string(), account.active.to_string()));
+        }
+        if sub.auto_renew != account.auto_renew {
+            found.push((
+                "autoRenew",
+                sub.auto_renew.to_string(),
+                account.auto_renew.to_string(),
+            ));
+        }
+        if sub.next_payment_ts != account.next_payment_ts {
+            found.push((
+                "nextPaymentTs",
+                sub.next_payment_ts.to_string(),
+                account.next_payment_ts.to_string(),
+            ));
+        }
+        if sub.paused_at != account.paused_at {
+            found.push((
+                "pausedAt",
+                format!("{:?}", sub.paused_at),
+                format!("{:?}", account.paused_at),
+            ));
+        }
+
+        if apply && !found.is_empty() {
+            sqlx::query!(
+                r#"
+                UPDATE subscriptions
+                SET active = $1,
+                    auto_renew = $2,
+                    next_payment_ts = $3,
+                    paused_at = $4
+                WHERE subscription_pda = $5
+                "#,
+                account.active,
+                account.auto_renew,
+                account.next_payment_ts,
+                account.paused_at,
+                sub.subscription_pda
+            )
+            .execute(&state.db)
+            .await?;
+        }
+        drifts.extend(drift(&sub.subscription_pda, found));
+    }
+
+    Ok(drifts)
+}
+
+fn drift(
+    subscription_pda: &str,
+    found: Vec<(&'static str, String, String)>,
+) -> impl Iterator<Item = Drift> {
+    let subscription_pda = subscription_pda.to_string();
+    found
+        .into_iter()
+        .map(move |(field, database, chain)| Drift {
+            subscription_pda: subscription_pda.clone(),
+            field,
+            database,
+            chain,
+        })
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRebuild {
+    /// Charges found on chain, the sign-up charge included
+    pub charges: u64,
+    pub inserted_payments: u64,
+    pub inserted_refunds: u64,
+}
+
+/// Restores missing `payment_history` rows for a subscription from its payment records:
+/// each record's creating transaction is the charge, later ones carrying a `Refunded`
+/// event are refunds. Rows already present, matched by record or signature, are kept.
+pub async fn rebuild_payment_history(
+    state: &AppState,
+    subscription_pda: Pubkey,
+) -> anyhow::Result<HistoryRebuild> {
+    let sub = sqlx::query!(
+        "SELECT plan_pda, tier_name FROM subscriptions WHERE subscription_pda = $1",
+        subscription_pda.to_string()
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| anyhow::anyhow!("Subscription {} not found", subscription_pda))?;
+    let plan = state
+        .solana
+        .get_plan(Pubkey::from_str(&sub.plan_pda)?)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Plan {} not found", sub.plan_pda))?;
+
+    let mut rebuild = HistoryRebuild::default();
+    for index in 0.. {
+        let record_pda = state.solana.payment_record_pda(&subscription_pda, index);
+        let Some(record) = state.solana.get_payment_record(&record_pda).await? else {
+            break;
+        };
+        rebuild.charges += 1;
+
+        // Newest first; the oldest successful transaction created the record
+        let signatures: Vec<Signature> = state
+            .solana
+            .rpc
+            .get_signatures_for_address(&record_pda)
+            .await?
+            .into_iter()
+            .filter(|s| s.err.is_none())
+            .map(|s| Signature::from_str(&s.signature))
+            .collect::<Result<_, _>>()?;
+        let Some((charge_signature, later)) = signatures.split_last() else {
+            continue;
+        };
+
+        let metered = record.usage_units > 0;
+        let row = |user_pubkey: String, direction: &str| PaymentHistory {
+            id: None,
+            user_pubkey,
+            plan: plan.name.clone(),
+            tier: sub.tier_name.clone(),
+            amount: record.amount as i64,
+            status: "success".to_string(),
+            tx_signature: Some(charge_signature.to_string()),
+            subscription_pda: subscription_pda.to_string(),
+            plan_pda: Some(sub.plan_pda.clone()),
+            created_at: DateTime::from_timestamp(record.paid_at, 0).unwrap_or_else(Utc::now),
+            direction: direction.to_string(),
+            payment_record: Some(record_pda.to_string()),
+            refund_of: None,
+            usage_units: metered.then_some(record.usage_units as i64),
+            usage_amount: metered.then_some(record.usage_amount as i64),
+        };
+
+        let mut payment_id = None;
+        for (user, direction) in [(record.payer, "paid"), (plan.creator, "received")] {
+            let id = match history_row(state, &row(String::new(), direction)).await? {
+                Some(id) => id,
+                None => {
+                    rebuild.inserted_payments += 1;
+                    create_transaction(&state.db, &row(user.to_string(), direction)).await?
+                }
+            };
+            if direction == "paid" {
+                payment_id = Some(id);
+            }
+        }
+
+        if record.refunded_amount == 0 {
+            continue;
+        }
+        for signature in later {
+            let logs = state.solana.transaction_logs(signature).await?;
+            for refund in Refunded::from_logs(&logs)? {
+                if refund.payment_record != record_pda {
+                    continue;
+                }
+                let refund_row = |user_pubkey: String, direction: &str| PaymentHistory {
+                    amount: refund.amount as i64,
+                    status: "refunded".to_string(),
+                    tx_signature: Some(signature.to_string()),
+                    created_at: DateTime::from_timestamp(refund.timestamp, 0)
+                        .unwrap_or_else(Utc::now),
+                    refund_of: payment_id,
+                    usage_units: None,
+                    usage_amount: None,
+                    ..row(user_pubkey, direction)
+                };
+                // Money flows back, so the subscriber receives and the merchant pays
+                for (user, direction) in [(record.payer, "received"), (plan.creator, "paid")] {
+                    if history_row(state, &refund_row(String::new(), direction))
+                        .await?
+                        .is_none()
+                    {
+                        rebuild.inserted_refunds += 1;
+                        create_transaction(&state.db, &refund_row(user.to_string(), direction))
+                            .await?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(rebuild)
+}
+
+/// Id of the row matching `row`'s direction and status, by signature, or by payment record
+/// for charges recorded before their signature was known.
+async fn history_row(state: &AppState, row: &PaymentHistory) -> anyhow::Result<Option<i64>> {
+    Ok(sqlx::query_scalar!(
+        r#"
+        SELECT id
+        FROM payment_history
+        WHERE subscription_pda = $1
+          AND direction = $2
+          AND status = $3
+          AND (tx_signature = $4 OR ($3 = 'success' AND payment_record = $5))
+        ORDER BY id ASC
+        LIMIT 1
+        "#,
+        row.subscription_pda,
+        row.direction,
+        row.status,
+        row.tx_signature,
+        row.payment_record
+    )
+    .fetch_optional(&state.db)
+    .await?)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeeperHealth {
+    pub keeper: String,
+    pub keeper_lamports: Option<u64>,
+    pub slot: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpc_error: Option<String>,
+    /// Subscriptions the keeper should already have charged
+    pub due: i64,
+    pub oldest_due_ts: Option<i64>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub failed_last_24h: i64,
+    pub emails_pending: i64,
+    pub emails_failed: i64,
+}
+
+pub async fn keeper_health(state: &AppState) -> anyhow::Result<KeeperHealth> {
+    let keeper = state.solana.signer.pubkey();
+    let (slot, rpc_error) = match state.solana.rpc.get_slot().await {
+        Ok(slot) => (Some(slot), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let keeper_lamports = state.solana.rpc.get_balance(&keeper).await.ok();
+
+    let now = Utc::now().timestamp();
+    let due = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!", MIN(next_payment_ts) AS oldest
+        FROM subscriptions
+        WHERE active = true
+          AND paused_at IS NULL
+          AND completed_at IS NULL
+          AND next_payment_ts <= $1
+        "#,
+        now
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    let payments = sqlx::query!(
+        r#"
+        SELECT
+            MAX(created_at) FILTER (WHERE status = 'success') AS last_success_at,
+            COUNT(*) FILTER (
+                WHERE status = 'failed' AND created_at > now() - interval '24 hours'
+            ) AS "failed!"
+        FROM payment_history
+        WHERE direction = 'paid'
+        "#
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    let emails = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE status = 'pending') AS "pending!",
+            COUNT(*) FILTER (WHERE status = 'failed') AS "failed!"
+        FROM email_queue
+        "#
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok(KeeperHealth {
+        keeper: keeper.to_string(),
+        keeper_lamports,
+        slot,
+        rpc_error,
+        due: due.count,
+        oldest_due_ts: due.oldest,
+        last_success_at: payments.last_success_at,
+        failed_last_24h: payments.failed,
+        emails_pending: emails.pending,
+        emails_failed: emails.failed,
+    })
+}
+
+/// Accounts migrated per transaction; each `migrate_account` only touches its own.
+const MIGRATIONS_PER_TX: usize = 8;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Migration {
+    pub accounts: Vec<String>,
+    pub tx_signature: String,
+}
+
+/// Grows every plan and subscription still in a layout from before its type gained
+/// fields, with the keeper paying the extra rent. The keeper and builders migrate
+/// accounts as they touch them; this catches the ones nothing has touched yet.
+pub async fn migrate_legacy_accounts(state: &AppState) -> anyhow::Result<Vec<Migration>> {
+    let solana = &state.solana;
+    let addresses: Vec<Pubkey> = solana
+        .rpc
+        .get_program_accounts(&solana.program_id)
+        .await?
+        .into_iter()
+        .map(|(address, _)| address)
+        .collect();
+
+    let keeper = solana.signer.pubkey();
+    let instructions = solana.migration_instructions(&keeper, &addresses).await?;
+
+    let mut migrations = Vec::new();
+    for batch in instructions.chunks(MIGRATIONS_PER_TX) {
+        let signature = solana.send_instructions(batch).await?;
+        migrations.push(Migration {
+            accounts: batch
+                .iter()
+                .map(|ix| ix.accounts[1].pubkey.to_string())
+                .collect(),
+            tx_signature: signature.to_string(),
+        });
+    }
+    Ok(migrations)
+}
@@ -0,0 +1,42 @@
+use axum::http::StatusCode;
+use serde::Deserialize;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::str::FromStr;
+
+/// Signed requests older than this are rejected to limit replay.
+pub const WALLET_AUTH_MAX_AGE_SECS: i64 = 300;
+
+/// Proof that the caller controls a wallet: an ed25519 signature (base58) over
+/// `solpay:<action>:<pubkey>:<timestamp>`, produced with the wallet's `signMessage`.
+#[derive(Debug, Deserialize)]
+pub struct WalletAuth {
+    pub signature: String,
+    pub timestamp: i64,
+}
+
+pub fn wallet_auth_message(action: &str, pubkey: &str, timestamp: i64) -> String {
+    format!("solpay:{}:{}:{}", action, pubkey, timestamp)
+}
+
+pub fn verify_wallet_auth(
+    action: &str,
+    pubkey: &str,
+    auth: &WalletAuth,
+) -> Result<(), (StatusCode, String)> {
+    let now = chrono::Utc::now().timestamp();
+    if (now - auth.timestamp).abs() > WALLET_AUTH_MAX_AGE_SECS {
+        return Err((StatusCode::UNAUTHORIZED, "Signature expired".into()));
+    }
+
+    let wallet = Pubkey::from_str(pubkey)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid wallet address".into()))?;
+    let signature = Signature::from_str(&auth.signature)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid signature encoding".into()))?;
+
+    let message = wallet_auth_message(action, pubkey, auth.timestamp);
+    if !signature.verify(wallet.as_ref(), message.as_bytes()) {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid wallet signature".into()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,222 @@
+//! `solpay-admin`: keeper and database maintenance for operators. Takes the same config
+//! file, environment and flags as the server.
+
+use backend::admin;
+use backend::config::{Config, ConfigArgs};
+use backend::state::AppState;
+use backend::worker::purge_notifications;
+use clap::{Parser, Subcommand, ValueEnum};
+use dotenvy::dotenv;
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+#[derive(Parser)]
+#[command(
+    name = "solpay-admin",
+    version,
+    about = "Operate the SolPay keeper and database"
+)]
+struct Cli {
+    #[arg(long, short = 'o', value_enum, default_value_t = Output::Human, global = true)]
+    output: Output,
+    #[command(flatten)]
+    config: ConfigArgs,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Output {
+    Human,
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Charge a due subscription now instead of waiting for the keeper
+    Renew { subscription: String },
+    /// Defer the keeper's next attempt by one period without charging
+    Skip { subscription: String },
+    /// Retry failed work
+    #[command(subcommand)]
+    Replay(Replay),
+    /// Compare subscriptions with their accounts and report drift
+    Reconcile {
+        /// Copy the chain's state into the database
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Restore a subscription's missing payment history from its on-chain payment records
+    RebuildHistory { subscription: String },
+    /// Delete expired notifications and those older than the retention window
+    PurgeNotifications {
+        /// [default: notifications.retention_days]
+        #[arg(long)]
+        days: Option<i32>,
+    },
+    /// Grow plans and subscriptions created before their newest fields
+    MigrateAccounts,
+    /// Keeper wallet, RPC and backlog at a glance
+    Health,
+}
+
+#[derive(Subcommand)]
+enum Replay {
+    /// Re-queue emails that ran out of attempts
+    Emails,
+    /// Renew due subscriptions whose last charge failed
+    Renewals,
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    tracing_subscriber::fmt::init();
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    let output = cli.output;
+    let config = Config::load(&cli.config)?;
+    let state = AppState::new(config).await;
+
+    match cli.command {
+        Command::Renew { subscription } => {
+            let payment = admin::force_renew(&state, parse_pubkey(&subscription)?).await?;
+            print(output, &payment, |payment| match payment {
+                Some(p) => println!(
+                    "{}: {} {}",
+                    p.status,
+                    p.amount,
+                    p.tx_signature.as_deref().unwrap_or("")
+                ),
+                None => println!("No payment recorded; see the log for why"),
+            });
+        }
+        Command::Skip { subscription } => {
+            let next_payment_ts =
+                admin::skip_subscription_cycle(&state, parse_pubkey(&subscription)?).await?;
+            print(output, &next_payment_ts, |ts| {
+                println!("Next attempt at {}", format_ts(*ts))
+            });
+        }
+        Command::Replay(Replay::Emails) => {
+            let requeued = admin::replay_failed_emails(&state).await?;
+            print(output, &requeued, |n| println!("Re-queued {} emails", n));
+        }
+        Command::Replay(Replay::Renewals) => {
+            let replays = admin::replay_failed_renewals(&state).await?;
+            print(output, &replays, |replays| {
+                for replay in replays {
+                    let outcome = match (&replay.error, &replay.payment) {
+                        (Some(error), _) => format!("error: {}", error),
+                        (None, Some(payment)) => payment.status.clone(),
+                        (None, None) => "no payment recorded".to_string(),
+                    };
+                    println!("{}  {}", replay.subscription_pda, outcome);
+                }
+                println!("{} renewals replayed", replays.len());
+            });
+        }
+        Command::Reconcile { apply } => {
+            let drifts = admin::reconcile_subscriptions(&state, apply).await?;
+            print(output, &drifts, |drifts| {
+                for d in drifts {
+                    println!(
+                        "{}  {}: database {}, chain {}",
+                        d.subscription_pda, d.field, d.database, d.chain
+                    );
+                }
+                match (drifts.is_empty(), apply) {
+                    (true, _) => println!("✅ Database matches the chain"),
+                    (false, true) => println!("Applied {} fixes", drifts.len()),
+                    (false, false) => println!("{} differences; --apply to fix", drifts.len()),
+                }
+            });
+        }
+        Command::RebuildHistory { subscription } => {
+            let rebuild =
+                admin::rebuild_payment_history(&state, parse_pubkey(&subscription)?).await?;
+            print(output, &rebuild, |r| {
+                println!(
+                    "{} charges on chain; inserted {} payment rows and {} refund rows",
+                    r.charges, r.inserted_payments, r.inserted_refunds
+                )
+            });
+        }
+        Command::PurgeNotifications { days } => {
+            let days = days.unwrap_or(state.config.notifications.retention_days);
+            let deleted = purge_notifications(&state, days).await?;
+            print(output, &deleted, |n| {
+                println!("Deleted {} notifications", n)
+            });
+        }
+        Command::MigrateAccounts => {
+            let migrations = admin::migrate_legacy_accounts(&state).await?;
+            print(output, &migrations, |migrations| {
+                for m in migrations {
+                    println!("{}  {}", m.tx_signature, m.accounts.join(", "));
+                }
+                let total: usize = migrations.iter().map(|m| m.accounts.len()).sum();
+                println!("Migrated {} accounts", total);
+            });
+        }
+        Command::Health => {
+            let health = admin::keeper_health(&state).await?;
+            print(output, &health, |h| {
+                match h.slot {
+                    Some(slot) => println!("RPC:         ok, slot {}", slot),
+                    None => println!("RPC:         ❌ {}", h.rpc_error.as_deref().unwrap_or("")),
+                }
+                match h.keeper_lamports {
+                    Some(lamports) => println!(
+                        "Keeper:      {} ({:.4} SOL)",
+                        h.keeper,
+                        lamports as f64 / LAMPORTS_PER_SOL
+                    ),
+                    None => println!("Keeper:      {}", h.keeper),
+                }
+                match h.oldest_due_ts {
+                    Some(ts) => println!("Due:         {} (oldest {})", h.due, format_ts(ts)),
+                    None => println!("Due:         0"),
+                }
+                match h.last_success_at {
+                    Some(at) => println!("Last charge: {}", at.to_rfc3339()),
+                    None => println!("Last charge: never"),
+                }
+                println!("Failed 24h:  {}", h.failed_last_24h);
+                println!(
+                    "Emails:      {} pending, {} failed",
+                    h.emails_pending, h.emails_failed
+                );
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_pubkey(value: &str) -> anyhow::Result<Pubkey> {
+    Pubkey::from_str(value).map_err(|_| anyhow::anyhow!("Invalid address {}", value))
+}
+
+fn format_ts(ts: i64) -> String {
+    chrono::DateTime::from_timestamp(ts, 0).map_or(ts.to_string(), |t| t.to_rfc3339())
+}
+
+fn print<T: Serialize>(output: Output, value: &T, human: impl FnOnce(&T)) {
+    match output {
+        Output::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(value).expect("reports serialize")
+        ),
+        Output::Human => human(value),
+    }
+}
@@ -0,0 +1,661 @@
+//! `solpay`: subscribe to and manage SolPay plans from a terminal, signing with a local
+//! keypair. Talks to the chain directly through `SolanaClient`; no server or database.
+//! Reads the `[solana]` settings from the same config file, environment and flags as the
+//! server.
+
+use axum::http::StatusCode;
+use backend::config::{Config, ConfigArgs};
+use backend::handlers::builder_handler::{
+    check_plan_fields, subscribe_transaction, unit_price_instructions, usd_price_instructions,
+};
+use backend::models::builder::{BuildOptions, BuildSubscribe};
+use backend::models::subscription::Tier;
+use backend::signer::{self, KeypairSigner};
+use backend::solana_client::SolanaClient;
+use backend::tx_builder;
+use backend::types::{Plan, SubscriptionAccount, SubscriptionField, UpdateValue};
+use backend::utils::{format_token_amount, parse_tiers};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, read_keypair_file},
+    transaction::Transaction,
+};
+use solpay_client::oracle::USD_DECIMALS;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(
+    name = "solpay",
+    version,
+    about = "Subscribe to and manage SolPay plans"
+)]
+struct Cli {
+    #[arg(long, short = 'o', value_enum, default_value_t = Output::Human, global = true)]
+    output: Output,
+    #[command(flatten)]
+    config: ConfigArgs,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Output {
+    Human,
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Browse plans, or create, update and cancel your own
+    #[command(subcommand)]
+    Plans(PlanCommand),
+    /// Subscribe to a plan's tier, approving renewals unless --no-auto-renew
+    Subscribe {
+        plan: String,
+        tier: String,
+        /// Pay in one of the plan's other accepted mints
+        #[arg(long)]
+        mint: Option<String>,
+        #[arg(long)]
+        coupon: Option<String>,
+        /// Metered tiers: most the usage part of one charge may take, in token units
+        #[arg(long)]
+        usage_cap: Option<String>,
+        /// USD-priced tiers: how far above today's quote a renewal may go, in basis points
+        #[arg(long)]
+        max_slippage_bps: Option<u16>,
+        #[arg(long)]
+        no_auto_renew: bool,
+        /// Priority fee in micro-lamports per compute unit
+        #[arg(long)]
+        compute_unit_price: Option<u64>,
+    },
+    /// Cancel a subscription and reclaim its rent
+    Cancel { subscription: String },
+    /// Turn automatic renewal on or off
+    AutoRenew { subscription: String, state: Toggle },
+    /// Next payment, its amount and the allowance left for renewals
+    Status { subscription: String },
+}
+
+#[derive(Subcommand)]
+enum PlanCommand {
+    List {
+        #[arg(long)]
+        creator: Option<String>,
+    },
+    /// A plan and its decoded tiers
+    Show { plan: String },
+    /// Create your plan from a tier file
+    Create {
+        /// TOML or JSON file with a `tiers` list, fields named as in the API (`tierName`, ...)
+        #[arg(long)]
+        tiers: PathBuf,
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        mint: String,
+        #[arg(long)]
+        token_symbol: String,
+        #[arg(long, default_value = "")]
+        token_image: String,
+        /// Wallet receiving payments [default: the signing wallet]
+        #[arg(long)]
+        receiver: Option<String>,
+    },
+    /// Replace your plan's name and tiers
+    Update {
+        #[arg(long)]
+        tiers: PathBuf,
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        receiver: Option<String>,
+    },
+    /// Close your plan and reclaim its rent
+    Cancel,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Toggle {
+    On,
+    Off,
+}
+
+#[derive(Deserialize)]
+struct TierFile {
+    tiers: Vec<Tier>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PlanView {
+    address: String,
+    name: String,
+    creator: String,
+    mint: String,
+    receiver: String,
+    token_symbol: String,
+    token_image: String,
+    accepted_mints: Vec<String>,
+    /// USD price feed per mint
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    price_feeds: BTreeMap<String, String>,
+    tiers: Vec<Tier>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SubscriptionView {
+    address: String,
+    payer: String,
+    plan: String,
+    tier_name: String,
+    mint: String,
+    active: bool,
+    auto_renew: bool,
+    paused: bool,
+    next_payment_ts: i64,
+    /// Estimate for token-priced subscriptions: discounted base plus capped usage
+    next_charge: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usd_price: Option<String>,
+    payment_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_cycles: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_ts: Option<i64>,
+    term_reached: bool,
+    allowance: Option<AllowanceView>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AllowanceView {
+    token_account: String,
+    balance: String,
+    /// Approved for the subscription PDA; zero when another delegate is set
+    delegated_amount: String,
+    covers_next_charge: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TxView {
+    signature: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subscription_pda: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plan_pda: Option<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    let output = cli.output;
+    let solana = connect(&cli).await?;
+
+    match cli.command {
+        Command::Plans(PlanCommand::List { creator }) => {
+            let creator = creator.as_deref().map(parse_pubkey).transpose()?;
+            let mut plans = Vec::new();
+            for (address, plan) in solana.get_plans().await? {
+                if creator.is_none_or(|c| c == plan.creator) {
+                    plans.push(plan_view(address, &plan)?);
+                }
+            }
+            print(output, &plans, |plans| {
+                for plan in plans {
+                    println!(
+                        "{}  {}  ({} tiers, {})",
+                        plan.address,
+                        plan.name,
+                        plan.tiers.len(),
+                        plan.token_symbol
+                    );
+                }
+            });
+        }
+        Command::Plans(PlanCommand::Show { plan }) => {
+            let address = parse_pubkey(&plan)?;
+            let plan = load_plan(&solana, address).await?;
+            print(output, &plan_view(address, &plan)?, print_plan);
+        }
+        Command::Plans(PlanCommand::Create {
+            tiers,
+            name,
+            mint,
+            token_symbol,
+            token_image,
+            receiver,
+        }) => {
+            let creator = solana.signer.pubkey();
+            let tier_list = read_tiers(&tiers)?;
+            let tiers = check_plan_fields(&name, &tier_list).map_err(api_error)?;
+            let receiver = receiver.as_deref().map_or(Ok(creator), parse_pubkey)?;
+            let mint = parse_pubkey(&mint)?;
+            let decimals = solana.get_mint_decimals(&mint).await?;
+            let mut instructions = vec![tx_builder::create_plan(
+                &solana.program_id,
+                &creator,
+                &mint,
+                &receiver,
+                &name,
+                &token_symbol,
+                &token_image,
+                &tiers,
+            )];
+            instructions.extend(
+                unit_price_instructions(
+                    &solana.program_id,
+                    &creator,
+                    &mint,
+                    decimals,
+                    &tier_list,
+                    &[],
+                )
+                .map_err(api_error)?,
+            );
+            instructions.extend(
+                usd_price_instructions(&solana.program_id, &creator, &tier_list, &[])
+                    .map_err(api_error)?,
+            );
+            let signature = solana.send_instructions(&instructions).await?;
+            print_tx(output, signature, None, Some(&own_plan(&solana)));
+        }
+        Command::Plans(PlanCommand::Update {
+            tiers,
+            name,
+            receiver,
+        }) => {
+            let creator = solana.signer.pubkey();
+            let tier_list = read_tiers(&tiers)?;
+            let tiers = check_plan_fields(&name, &tier_list).map_err(api_error)?;
+            let plan = load_plan(&solana, own_plan(&solana)).await?;
+            let receiver = match receiver {
+                Some(receiver) => parse_pubkey(&receiver)?,
+                None => plan.receiver,
+            };
+            let decimals = solana.get_mint_decimals(&plan.mint).await?;
+            let mut instructions = solana
+                .migration_instructions(&creator, &[own_plan(&solana)])
+                .await?;
+            instructions.push(tx_builder::update_plan(
+                &solana.program_id,
+                &creator,
+                &receiver,
+                &name,
+                &tiers,
+            ));
+            instructions.extend(
+                unit_price_instructions(
+                    &solana.program_id,
+                    &creator,
+                    &plan.mint,
+                    decimals,
+                    &tier_list,
+                    &plan.unit_prices,
+                )
+                .map_err(api_error)?,
+            );
+            instructions.extend(
+                usd_price_instructions(&solana.program_id, &creator, &tier_list, &plan.usd_prices)
+                    .map_err(api_error)?,
+            );
+            let signature = solana.send_instructions(&instructions).await?;
+            print_tx(output, signature, None, Some(&own_plan(&solana)));
+        }
+        Command::Plans(PlanCommand::Cancel) => {
+            let ix = tx_builder::cancel_plan(&solana.program_id, &solana.signer.pubkey());
+            let signature = solana.send_instructions(&[ix]).await?;
+            print_tx(output, signature, None, Some(&own_plan(&solana)));
+        }
+        Command::Subscribe {
+            plan,
+            tier,
+            mint,
+            coupon,
+            usage_cap,
+            max_slippage_bps,
+            no_auto_renew,
+            compute_unit_price,
+        } => {
+            let payload = BuildSubscribe {
+                payer: solana.signer.pubkey().to_string(),
+                plan_pda: plan,
+                tier_name: tier,
+                mint,
+                auto_renew: !no_auto_renew,
+                coupon_code: coupon,
+                usage_cap,
+                max_slippage_bps,
+                options: BuildOptions {
+                    fee_payer: None,
+                    compute_unit_price,
+                },
+            };
+            let unsigned = subscribe_transaction(&solana, &payload)
+                .await
+                .map_err(api_error)?;
+            let signature = sign_and_send(&solana, &unsigned.transaction).await?;
+            let subscription = unsigned
+                .subscription_pda
+                .map(|s| Pubkey::from_str(&s))
+                .transpose()?;
+            print_tx(output, signature, subscription.as_ref(), None);
+        }
+        Command::Cancel { subscription } => {
+            let address = parse_pubkey(&subscription)?;
+            load_own_subscription(&solana, &address).await?;
+            let mut instructions = solana
+                .migration_instructions(&solana.signer.pubkey(), &[address])
+                .await?;
+            instructions.push(tx_builder::cancel_subscription(
+                &solana.program_id,
+                &solana.signer.pubkey(),
+                &address,
+            ));
+            let signature = solana.send_instructions(&instructions).await?;
+            print_tx(output, signature, Some(&address), None);
+        }
+        Command::AutoRenew {
+            subscription,
+            state,
+        } => {
+            let address = parse_pubkey(&subscription)?;
+            load_own_subscription(&solana, &address).await?;
+            let mut instructions = solana
+                .migration_instructions(&solana.signer.pubkey(), &[address])
+                .await?;
+            instructions.push(tx_builder::update_subscription_status(
+                &solana.program_id,
+                &solana.signer.pubkey(),
+                &address,
+                SubscriptionField::AutoRenew,
+                UpdateValue::Bool(matches!(state, Toggle::On)),
+            ));
+            let signature = solana.send_instructions(&instructions).await?;
+            print_tx(output, signature, Some(&address), None);
+        }
+        Command::Status { subscription } => {
+            let address = parse_pubkey(&subscription)?;
+            let view = subscription_view(&solana, address).await?;
+            print(output, &view, print_subscription);
+        }
+    }
+
+    Ok(())
+}
+
+/// Read-only commands still work without a wallet, using a throwaway key.
+async fn connect(cli: &Cli) -> anyhow::Result<SolanaClient> {
+    let config = Config::resolve(&cli.config)?;
+    config.validate_solana()?;
+
+    let path = &config.solana.keypair;
+    let payer = if path.exists() || cli.config.keypair.is_some() {
+        read_keypair_file(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read keypair {}: {}", path.display(), e))?
+    } else if signs(&cli.command) {
+        anyhow::bail!("No keypair at {}; pass --keypair", path.display());
+    } else {
+        Keypair::new()
+    };
+    Ok(SolanaClient::new(&config.solana, Arc::new(KeypairSigner::new(payer))).await)
+}
+
+fn signs(command: &Command) -> bool {
+    !matches!(
+        command,
+        Command::Status { .. }
+            | Command::Plans(PlanCommand::List { .. } | PlanCommand::Show { .. })
+    )
+}
+
+fn parse_pubkey(value: &str) -> anyhow::Result<Pubkey> {
+    Pubkey::from_str(value).map_err(|_| anyhow::anyhow!("Invalid address {}", value))
+}
+
+fn api_error((_, message): (StatusCode, String)) -> anyhow::Error {
+    anyhow::anyhow!(message)
+}
+
+fn own_plan(solana: &SolanaClient) -> Pubkey {
+    tx_builder::plan_pda(&solana.program_id, &solana.signer.pubkey())
+}
+
+async fn load_plan(solana: &SolanaClient, address: Pubkey) -> anyhow::Result<Plan> {
+    solana
+        .get_plan(address)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Plan {} not found", address))
+}
+
+async fn load_own_subscription(
+    solana: &SolanaClient,
+    address: &Pubkey,
+) -> anyhow::Result<SubscriptionAccount> {
+    let account = solana
+        .get_subscription_account(address)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Subscription {} not found", address))?;
+    if account.payer != solana.signer.pubkey() {
+        anyhow::bail!("Subscription {} belongs to {}", address, account.payer);
+    }
+    Ok(account)
+}
+
+fn read_tiers(path: &Path) -> anyhow::Result<Vec<Tier>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+    let file: TierFile = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&text)?,
+        Some("json") => serde_json::from_str(&text)?,
+        _ => anyhow::bail!("Tier file must be .toml or .json"),
+    };
+    Ok(file.tiers)
+}
+
+/// Signs a transaction from the builders, whose fee payer is the wallet, and sends it.
+async fn sign_and_send(solana: &SolanaClient, encoded: &str) -> anyhow::Result<Signature> {
+    let mut tx: Transaction = bincode::deserialize(&STANDARD.decode(encoded)?)?;
+    signer::sign_transaction(&*solana.signer.current(), &mut tx).await?;
+    Ok(solana.rpc.send_and_confirm_transaction(&tx).await?)
+}
+
+fn plan_view(address: Pubkey, plan: &Plan) -> anyhow::Result<PlanView> {
+    Ok(PlanView {
+        address: address.to_string(),
+        name: plan.name.clone(),
+        creator: plan.creator.to_string(),
+        mint: plan.mint.to_string(),
+        receiver: plan.receiver.to_string(),
+        token_symbol: plan.token_symbol.clone(),
+        token_image: plan.token_image.clone(),
+        accepted_mints: plan
+            .accepted_mints
+            .iter()
+            .map(|m| m.mint.to_string())
+            .collect(),
+        price_feeds: plan
+            .price_feeds
+            .iter()
+            .map(|f| (f.mint.to_string(), f.price_feed.to_string()))
+            .collect(),
+        tiers: parse_tiers(&plan.tiers)?,
+    })
+}
+
+async fn subscription_view(
+    solana: &SolanaClient,
+    address: Pubkey,
+) -> anyhow::Result<SubscriptionView> {
+    let account = solana
+        .get_subscription_account(&address)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Subscription {} not found", address))?;
+    let plan = load_plan(solana, account.plan_pda).await?;
+    let mint = account.billing_mint(&plan);
+    let decimals = solana.get_mint_decimals(&mint).await?;
+    let token_program = solana.get_token_program(&mint).await?;
+    let token_account =
+        get_associated_token_address_with_program_id(&account.payer, &mint, &token_program);
+    let next_charge = account.next_charge_amount(&plan);
+
+    let allowance = solana
+        .get_token_account_funding(&token_account)
+        .await?
+        .map(|funding| {
+            let delegated = if funding.delegate == Some(address) {
+                funding.delegated_amount
+            } else {
+                0
+            };
+            AllowanceView {
+                token_account: token_account.to_string(),
+                balance: format_token_amount(funding.amount, decimals),
+                delegated_amount: format_token_amount(delegated, decimals),
+                covers_next_charge: delegated >= next_charge && funding.amount >= next_charge,
+            }
+        });
+
+    Ok(SubscriptionView {
+        address: address.to_string(),
+        payer: account.payer.to_string(),
+        plan: account.plan_pda.to_string(),
+        tier_name: account.tier_name.clone(),
+        mint: mint.to_string(),
+        active: account.active,
+        auto_renew: account.auto_renew,
+        paused: account.paused_at.is_some(),
+        next_payment_ts: account.next_payment_ts,
+        next_charge: format!(
+            "{} {}",
+            format_token_amount(next_charge, decimals),
+            plan.token_symbol_for(&mint)
+        ),
+        usd_price: account.is_usd_priced(&plan).then(|| {
+            format!(
+                "${}",
+                format_token_amount(plan.usd_price(&account.tier_name), USD_DECIMALS)
+            )
+        }),
+        payment_count: account.payment_count,
+        max_cycles: account.max_cycles,
+        end_ts: account.end_ts,
+        term_reached: account.term_reached(),
+        allowance,
+    })
+}
+
+fn print<T: Serialize>(output: Output, value: &T, human: impl FnOnce(&T)) {
+    match output {
+        Output::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(value).expect("views serialize")
+        ),
+        Output::Human => human(value),
+    }
+}
+
+fn print_tx(
+    output: Output,
+    signature: Signature,
+    subscription: Option<&Pubkey>,
+    plan: Option<&Pubkey>,
+) {
+    let view = TxView {
+        signature: signature.to_string(),
+        subscription_pda: subscription.map(Pubkey::to_string),
+        plan_pda: plan.map(Pubkey::to_string),
+    };
+    print(output, &view, |view| {
+        println!("✅ {}", view.signature);
+        if let Some(subscription) = &view.subscription_pda {
+            println!("Subscription: {}", subscription);
+        }
+        if let Some(plan) = &view.plan_pda {
+            println!("Plan: {}", plan);
+        }
+    });
+}
+
+fn print_plan(plan: &PlanView) {
+    println!("{} ({})", plan.name, plan.address);
+    println!("Creator:  {}", plan.creator);
+    println!("Receiver: {}", plan.receiver);
+    println!("Mint:     {} {}", plan.mint, plan.token_symbol);
+    for mint in &plan.accepted_mints {
+        println!("Also accepts {}", mint);
+    }
+    for tier in &plan.tiers {
+        let price = match &tier.usd_price {
+            Some(usd) => format!("${}", usd),
+            None => format!("{} {}", tier.amount, plan.token_symbol),
+        };
+        println!(
+            "\n  {}: {} every {}s",
+            tier.tier_name, price, tier.period_seconds
+        );
+        if let Some(unit_price) = &tier.unit_price {
+            println!("    + {} per unit", unit_price);
+        }
+        if let Some(max_cycles) = &tier.max_cycles {
+            println!("    {} payments", max_cycles);
+        }
+        for price in &tier.prices {
+            println!("    or {} of {}", price.amount, price.mint);
+        }
+        if !tier.description.is_empty() {
+            println!("    {}", tier.description);
+        }
+    }
+}
+
+fn print_subscription(sub: &SubscriptionView) {
+    let status = match (sub.active, sub.paused, sub.term_reached) {
+        (_, _, true) => "completed",
+        (false, _, _) => "inactive",
+        (true, true, _) => "paused",
+        (true, false, _) => "active",
+    };
+    let next_payment = chrono::DateTime::from_timestamp(sub.next_payment_ts, 0)
+        .map_or(sub.next_payment_ts.to_string(), |t| t.to_rfc3339());
+
+    println!("{} ({})", sub.address, status);
+    println!("Plan:         {} / {}", sub.plan, sub.tier_name);
+    println!(
+        "Auto-renew:   {}",
+        if sub.auto_renew { "on" } else { "off" }
+    );
+    println!("Next payment: {}", next_payment);
+    match &sub.usd_price {
+        Some(usd) => println!("Next charge:  {} at the oracle price", usd),
+        None => println!("Next charge:  {}", sub.next_charge),
+    }
+    println!("Payments:     {}", sub.payment_count);
+    match &sub.allowance {
+        Some(allowance) => {
+            println!("Balance:      {}", allowance.balance);
+            println!("Allowance:    {}", allowance.delegated_amount);
+            if !allowance.covers_next_charge {
+                println!("⚠️  Balance or allowance will not cover the next charge");
+            }
+        }
+        None => println!("⚠️  The payer has no token account for {}", sub.mint),
+    }
+}
@@ -0,0 +1,659 @@
+//! Server and operator configuration. Each setting comes from, in increasing priority: the
+//! profile's defaults, the TOML config file, the environment and command-line flags.
+
+use anyhow::{Context, anyhow, bail};
+use axum::http::HeaderValue;
+use clap::parser::ValueSource;
+use clap::{Args, FromArgMatches, ValueEnum};
+use lettre::message::Mailbox;
+use serde::Deserialize;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Read when no `--config` is given and the file exists.
+const DEFAULT_CONFIG_FILE: &str = "solpay.toml";
+const DEFAULT_SECRET_VAR: &str = "SOLPAY_KEEPER_SECRET";
+const DEFAULT_EMAIL_FROM: &str = "SolPay <no-reply@solpay.local>";
+const DEFAULT_EMAIL_VERIFY_URL: &str = "http://localhost:3000/verify-email";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    Localnet,
+    Devnet,
+    Mainnet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl From<Commitment> for CommitmentConfig {
+    fn from(commitment: Commitment) -> Self {
+        let commitment = match commitment {
+            Commitment::Processed => CommitmentLevel::Processed,
+            Commitment::Confirmed => CommitmentLevel::Confirmed,
+            Commitment::Finalized => CommitmentLevel::Finalized,
+        };
+        CommitmentConfig { commitment }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub profile: Profile,
+    pub solana: SolanaConfig,
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub keeper: KeeperConfig,
+    pub signer: SignerConfig,
+    pub email: EmailConfig,
+    pub notifications: NotificationsConfig,
+    pub analytics: AnalyticsConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct SolanaConfig {
+    pub rpc_url: String,
+    pub commitment: Commitment,
+    /// Keeper wallet for the file signer; pays for renewals and signs admin updates
+    pub keypair: PathBuf,
+    pub program_id: Pubkey,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind: SocketAddr,
+    /// `*` allows any origin
+    pub cors_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub acquire_timeout_secs: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct KeeperConfig {
+    pub interval_secs: u64,
+    /// Due subscriptions renewed per tick
+    pub batch_size: i64,
+}
+
+/// Delivery goes through `smtp_url`, else into `outbox_dir`; with neither, emails stay queued.
+#[derive(Debug, Clone)]
+pub struct EmailConfig {
+    pub from: String,
+    /// Page the verification link points at; the token is appended as `?token=`
+    pub verify_url: String,
+    pub smtp_url: Option<String>,
+    /// Writes each message as an `.eml` file, for local development
+    pub outbox_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NotificationsConfig {
+    /// Hours before a charge at which a reminder goes out, ascending; empty sends none
+    pub reminder_windows_hours: Vec<i64>,
+    /// Notifications older than this are purged
+    pub retention_days: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnalyticsConfig {
+    /// How often the analytics views are checked for new data to refresh
+    pub refresh_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SignerKind {
+    File,
+    Env,
+    Remote,
+}
+
+/// Where the keeper's key comes from.
+#[derive(Debug, Clone)]
+pub enum SignerConfig {
+    /// `solana.keypair`
+    File(PathBuf),
+    /// Name of the variable holding a base58 secret key
+    Env(String),
+    /// Signing service; its bearer token, if any, is read from `SOLPAY_SIGNER_TOKEN`
+    Remote { url: String },
+}
+
+/// A copy of the process environment to resolve against. Reloads lay `.env` under one
+/// instead of calling `setenv`, which is unsound once other threads are running.
+#[derive(Debug, Clone, Default)]
+pub struct Environment(HashMap<String, String>);
+
+impl Environment {
+    /// The process environment; variables that are not UTF-8 are left out.
+    pub fn capture() -> Self {
+        Self(
+            std::env::vars_os()
+                .filter_map(|(name, value)| {
+                    Some((name.into_string().ok()?, value.into_string().ok()?))
+                })
+                .collect(),
+        )
+    }
+
+    /// Loads `.env` into the process environment, as `dotenv()` does, and returns the names
+    /// it added. Call it at startup, before any other thread is running.
+    pub fn load_dotenv() -> HashSet<String> {
+        let before: HashSet<OsString> = std::env::vars_os().map(|(name, _)| name).collect();
+        dotenvy::dotenv().ok();
+        std::env::vars_os()
+            .filter(|(name, _)| !before.contains(name))
+            .filter_map(|(name, _)| name.into_string().ok())
+            .collect()
+    }
+
+    /// These variables without `names`, such as the ones `.env` added at startup.
+    pub fn without(mut self, names: &HashSet<String>) -> Self {
+        self.0.retain(|name, _| !names.contains(name));
+        self
+    }
+
+    /// Fills in what these variables leave unset from the current `.env`, if there is one.
+    /// As with `dotenv()`, a variable that is already set wins over the file.
+    pub fn with_dotenv(mut self) -> anyhow::Result<Self> {
+        let entries = match dotenvy::dotenv_iter() {
+            Ok(entries) => entries,
+            Err(e) if e.not_found() => return Ok(self),
+            Err(e) => return Err(e).context("Cannot read .env"),
+        };
+        for entry in entries {
+            let (name, value) = entry.context("Invalid .env")?;
+            self.0.entry(name).or_insert(value);
+        }
+        Ok(self)
+    }
+
+    pub fn var(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+impl FromIterator<(String, String)> for Environment {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(vars: I) -> Self {
+        Self(vars.into_iter().collect())
+    }
+}
+
+/// Flags shared by the server, `solpay-admin` and `solpay`; each also reads its environment
+/// variable.
+#[derive(Debug, Clone, Default, Args)]
+pub struct ConfigArgs {
+    /// TOML config file [default: ./solpay.toml when present]
+    #[arg(long, env = "SOLPAY_CONFIG", global = true)]
+    pub config: Option<PathBuf>,
+    /// Defaults for a cluster [default: devnet]
+    #[arg(long, env = "SOLPAY_PROFILE", value_enum, global = true)]
+    pub profile: Option<Profile>,
+    #[arg(long, env = "SOLPAY_RPC_URL", global = true)]
+    pub rpc_url: Option<String>,
+    #[arg(long, env = "SOLPAY_COMMITMENT", value_enum, global = true)]
+    pub commitment: Option<Commitment>,
+    /// Wallet that signs and pays fees [default: ~/.config/solana/id.json]
+    #[arg(long, short = 'k', env = "SOLPAY_KEYPAIR", global = true)]
+    pub keypair: Option<PathBuf>,
+    #[arg(long, env = "SOLPAY_PROGRAM_ID", global = true)]
+    pub program_id: Option<String>,
+    /// Where the keeper's key comes from [default: file, the --keypair]
+    #[arg(long, env = "SOLPAY_SIGNER", value_enum, global = true)]
+    pub signer: Option<SignerKind>,
+    /// Variable holding the base58 secret for `--signer env` [default: SOLPAY_KEEPER_SECRET]
+    #[arg(long, env = "SOLPAY_SIGNER_SECRET_VAR", global = true)]
+    pub signer_secret_var: Option<String>,
+    /// Signing service for `--signer remote`
+    #[arg(long, env = "SOLPAY_SIGNER_URL", global = true)]
+    pub signer_url: Option<String>,
+    #[arg(long, env = "SOLPAY_BIND", global = true)]
+    pub bind: Option<String>,
+    /// Comma-separated; `*` allows any origin
+    #[arg(
+        long,
+        env = "SOLPAY_CORS_ORIGINS",
+        value_delimiter = ',',
+        global = true
+    )]
+    pub cors_origins: Option<Vec<String>>,
+    #[arg(long, env = "DATABASE_URL", hide_env_values = true, global = true)]
+    pub database_url: Option<String>,
+    #[arg(long, env = "SOLPAY_DB_MAX_CONNECTIONS", global = true)]
+    pub db_max_connections: Option<u32>,
+    #[arg(long, env = "SOLPAY_DB_ACQUIRE_TIMEOUT_SECS", global = true)]
+    pub db_acquire_timeout_secs: Option<u64>,
+    #[arg(long, env = "SOLPAY_KEEPER_INTERVAL_SECS", global = true)]
+    pub keeper_interval_secs: Option<u64>,
+    #[arg(long, env = "SOLPAY_KEEPER_BATCH_SIZE", global = true)]
+    pub keeper_batch_size: Option<i64>,
+    #[arg(long, env = "EMAIL_FROM", global = true)]
+    pub email_from: Option<String>,
+    #[arg(long, env = "EMAIL_VERIFY_URL", global = true)]
+    pub email_verify_url: Option<String>,
+    #[arg(long, env = "SMTP_URL", hide_env_values = true, global = true)]
+    pub smtp_url: Option<String>,
+    #[arg(long, env = "EMAIL_OUTBOX_DIR", global = true)]
+    pub email_outbox_dir: Option<PathBuf>,
+    /// Comma-separated hours before a charge to send reminders at [default: 72,24]
+    #[arg(
+        long,
+        env = "REMINDER_WINDOWS_HOURS",
+        value_delimiter = ',',
+        global = true
+    )]
+    pub reminder_windows_hours: Option<Vec<i64>>,
+    #[arg(long, env = "NOTIFICATION_RETENTION_DAYS", global = true)]
+    pub notification_retention_days: Option<i32>,
+    #[arg(long, env = "SOLPAY_ANALYTICS_REFRESH_SECS", global = true)]
+    pub analytics_refresh_secs: Option<u64>,
+}
+
+impl ConfigArgs {
+    /// Parses `argv` as clap would with `env` in place of the process environment; flags
+    /// on the command line still win.
+    pub fn parse_in(argv: &[OsString], env: &Environment) -> anyhow::Result<Self> {
+        let command = Self::augment_args(clap::Command::new("solpay"));
+        let given = command.clone().try_get_matches_from(argv)?;
+
+        let mut argv = argv.to_vec();
+        for arg in command.get_arguments() {
+            let (Some(name), Some(long)) = (arg.get_env(), arg.get_long()) else {
+                continue;
+            };
+            if given.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+                continue;
+            }
+            // clap also treats an empty variable as unset
+            let value = name.to_str().and_then(|name| env.var(name));
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                argv.push(format!("--{}={}", long, value).into());
+            }
+        }
+
+        let matches = command.try_get_matches_from(argv)?;
+        Ok(Self::from_arg_matches(&matches)?)
+    }
+}
+
+/// The config file's shape: every key optional, unknown keys rejected.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    profile: Option<Profile>,
+    solana: FileSolana,
+    server: FileServer,
+    database: FileDatabase,
+    keeper: FileKeeper,
+    signer: FileSigner,
+    email: FileEmail,
+    notifications: FileNotifications,
+    analytics: FileAnalytics,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileSolana {
+    rpc_url: Option<String>,
+    commitment: Option<Commitment>,
+    keypair: Option<PathBuf>,
+    program_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileServer {
+    bind: Option<String>,
+    cors_origins: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileDatabase {
+    url: Option<String>,
+    max_connections: Option<u32>,
+    acquire_timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileKeeper {
+    interval_secs: Option<u64>,
+    batch_size: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileSigner {
+    kind: Option<SignerKind>,
+    secret_var: Option<String>,
+    url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileEmail {
+    from: Option<String>,
+    verify_url: Option<String>,
+    smtp_url: Option<String>,
+    outbox_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileNotifications {
+    reminder_windows_hours: Option<Vec<i64>>,
+    retention_days: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileAnalytics {
+    refresh_interval_secs: Option<u64>,
+}
+
+impl Config {
+    /// Resolves and validates the configuration, naming the offending setting on error.
+    pub fn load(args: &ConfigArgs) -> anyhow::Result<Self> {
+        Self::load_in(args, &Environment::capture())
+    }
+
+    /// As `load`, checking the signer's variables in `env` rather than the process's.
+    pub fn load_in(args: &ConfigArgs, env: &Environment) -> anyhow::Result<Self> {
+        let config = Self::resolve(args)?;
+        config.validate(env)?;
+        Ok(config)
+    }
+
+    /// Merges the defaults, file, environment and flags without checking the result, for
+    /// tools that only need part of it; they validate what they use.
+    pub fn resolve(args: &ConfigArgs) -> anyhow::Result<Self> {
+        let file = match &args.config {
+            Some(path) => read_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                read_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            None => FileConfig::default(),
+        };
+
+        let profile = args.profile.or(file.profile).unwrap_or(Profile::Devnet);
+        let mut config = Self::defaults(profile);
+
+        set(&mut config.solana.rpc_url, file.solana.rpc_url);
+        set(&mut config.solana.commitment, file.solana.commitment);
+        set(&mut config.solana.keypair, file.solana.keypair);
+        set(&mut config.server.cors_origins, file.server.cors_origins);
+        set(&mut config.database.url, file.database.url);
+        set(
+            &mut config.database.max_connections,
+            file.database.max_connections,
+        );
+        set(
+            &mut config.database.acquire_timeout_secs,
+            file.database.acquire_timeout_secs,
+        );
+        set(&mut config.keeper.interval_secs, file.keeper.interval_secs);
+        set(&mut config.keeper.batch_size, file.keeper.batch_size);
+        set(&mut config.email.from, file.email.from);
+        set(&mut config.email.verify_url, file.email.verify_url);
+        set_some(&mut config.email.smtp_url, file.email.smtp_url);
+        set_some(&mut config.email.outbox_dir, file.email.outbox_dir);
+        set(
+            &mut config.notifications.reminder_windows_hours,
+            file.notifications.reminder_windows_hours,
+        );
+        set(
+            &mut config.notifications.retention_days,
+            file.notifications.retention_days,
+        );
+        set(
+            &mut config.analytics.refresh_interval_secs,
+            file.analytics.refresh_interval_secs,
+        );
+
+        set(&mut config.solana.rpc_url, args.rpc_url.clone());
+        set(&mut config.solana.commitment, args.commitment);
+        set(&mut config.solana.keypair, args.keypair.clone());
+        set(&mut config.server.cors_origins, args.cors_origins.clone());
+        set(&mut config.database.url, args.database_url.clone());
+        set(
+            &mut config.database.max_connections,
+            args.db_max_connections,
+        );
+        set(
+            &mut config.database.acquire_timeout_secs,
+            args.db_acquire_timeout_secs,
+        );
+        set(&mut config.keeper.interval_secs, args.keeper_interval_secs);
+        set(&mut config.keeper.batch_size, args.keeper_batch_size);
+        set(&mut config.email.from, args.email_from.clone());
+        set(&mut config.email.verify_url, args.email_verify_url.clone());
+        set_some(&mut config.email.smtp_url, args.smtp_url.clone());
+        set_some(&mut config.email.outbox_dir, args.email_outbox_dir.clone());
+        set(
+            &mut config.notifications.reminder_windows_hours,
+            args.reminder_windows_hours.clone(),
+        );
+        set(
+            &mut config.notifications.retention_days,
+            args.notification_retention_days,
+        );
+        set(
+            &mut config.analytics.refresh_interval_secs,
+            args.analytics_refresh_secs,
+        );
+        config.notifications.reminder_windows_hours.sort_unstable();
+        config.notifications.reminder_windows_hours.dedup();
+
+        if let Some(program_id) = args.program_id.as_ref().or(file.solana.program_id.as_ref()) {
+            config.solana.program_id = Pubkey::from_str(program_id)
+                .map_err(|_| anyhow!("solana.program_id: {} is not an address", program_id))?;
+        }
+        if let Some(bind) = args.bind.as_ref().or(file.server.bind.as_ref()) {
+            config.server.bind = bind
+                .parse()
+                .map_err(|_| anyhow!("server.bind: {} is not a host:port address", bind))?;
+        }
+
+        config.signer = match args.signer.or(file.signer.kind) {
+            None | Some(SignerKind::File) => SignerConfig::File(config.solana.keypair.clone()),
+            Some(SignerKind::Env) => SignerConfig::Env(
+                args.signer_secret_var
+                    .clone()
+                    .or(file.signer.secret_var)
+                    .unwrap_or_else(|| DEFAULT_SECRET_VAR.to_string()),
+            ),
+            Some(SignerKind::Remote) => SignerConfig::Remote {
+                url: args
+                    .signer_url
+                    .clone()
+                    .or(file.signer.url)
+                    .ok_or_else(|| anyhow!("signer.url is required for the remote signer"))?,
+            },
+        };
+
+        Ok(config)
+    }
+
+    pub fn defaults(profile: Profile) -> Self {
+        let (rpc_url, commitment, cors_origins) = match profile {
+            Profile::Localnet => ("http://127.0.0.1:8899", Commitment::Confirmed, vec!["*"]),
+            Profile::Devnet => (
+                "https://api.devnet.solana.com",
+                Commitment::Confirmed,
+                vec!["*"],
+            ),
+            // Mainnet must name the front-end's origins explicitly
+            Profile::Mainnet => (
+                "https://api.mainnet-beta.solana.com",
+                Commitment::Finalized,
+                vec![],
+            ),
+        };
+
+        let keypair =
+            PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(".config/solana/id.json");
+
+        Self {
+            profile,
+            solana: SolanaConfig {
+                rpc_url: rpc_url.to_string(),
+                commitment,
+                keypair: keypair.clone(),
+                program_id: solpay_client::ID,
+            },
+            server: ServerConfig {
+                bind: SocketAddr::from(([127, 0, 0, 1], 3001)),
+                cors_origins: cors_origins.into_iter().map(String::from).collect(),
+            },
+            database: DatabaseConfig {
+                url: String::new(),
+                max_connections: 5,
+                acquire_timeout_secs: 30,
+            },
+            keeper: KeeperConfig {
+                interval_secs: 60,
+                batch_size: 1,
+            },
+            signer: SignerConfig::File(keypair),
+            email: EmailConfig {
+                from: DEFAULT_EMAIL_FROM.to_string(),
+                verify_url: DEFAULT_EMAIL_VERIFY_URL.to_string(),
+                smtp_url: None,
+                outbox_dir: None,
+            },
+            notifications: NotificationsConfig {
+                reminder_windows_hours: vec![24, 72],
+                retention_days: 90,
+            },
+            analytics: AnalyticsConfig {
+                refresh_interval_secs: 600,
+            },
+        }
+    }
+
+    /// The checks on `[solana]`, for tools that talk only to the chain.
+    pub fn validate_solana(&self) -> anyhow::Result<()> {
+        let rpc_url = &self.solana.rpc_url;
+        if !(rpc_url.starts_with("http://") || rpc_url.starts_with("https://")) {
+            bail!("solana.rpc_url: {} is not an http(s) URL", rpc_url);
+        }
+        Ok(())
+    }
+
+    fn validate(&self, env: &Environment) -> anyhow::Result<()> {
+        self.validate_solana()?;
+        match &self.signer {
+            SignerConfig::File(path) if !path.is_file() => {
+                bail!("solana.keypair: no keypair file at {}", path.display())
+            }
+            SignerConfig::Env(var) if env.var(var).is_none() => {
+                bail!("signer.secret_var: {} is not set", var)
+            }
+            SignerConfig::Remote { url }
+                if !(url.starts_with("http://") || url.starts_with("https://")) =>
+            {
+                bail!("signer.url: {} is not an http(s) URL", url)
+            }
+            _ => {}
+        }
+
+        if self.database.url.is_empty() {
+            bail!("database.url is not set; set DATABASE_URL, --database-url or [database] url");
+        }
+        if self.database.max_connections == 0 {
+            bail!("database.max_connections must be at least 1");
+        }
+        if self.database.acquire_timeout_secs == 0 {
+            bail!("database.acquire_timeout_secs must be at least 1");
+        }
+
+        if self.keeper.interval_secs == 0 {
+            bail!("keeper.interval_secs must be at least 1");
+        }
+        if self.keeper.batch_size < 1 {
+            bail!("keeper.batch_size must be at least 1");
+        }
+
+        if self.email.from.parse::<Mailbox>().is_err() {
+            bail!("email.from: {} is not a valid mailbox", self.email.from);
+        }
+        if let Some(hours) = self
+            .notifications
+            .reminder_windows_hours
+            .iter()
+            .find(|hours| **hours < 1)
+        {
+            bail!(
+                "notifications.reminder_windows_hours: {} is not a positive number of hours",
+                hours
+            );
+        }
+        if self.notifications.retention_days < 1 {
+            bail!("notifications.retention_days must be at least 1");
+        }
+        if self.analytics.refresh_interval_secs == 0 {
+            bail!("analytics.refresh_interval_secs must be at least 1");
+        }
+
+        let any_origin = self.server.cors_origins.iter().any(|o| o == "*");
+        if self.profile == Profile::Mainnet && (any_origin || self.server.cors_origins.is_empty()) {
+            bail!("server.cors_origins must list the front-end's origins on mainnet, without *");
+        }
+        if !any_origin {
+            self.cors_origin_headers()?;
+        }
+
+        Ok(())
+    }
+
+    /// `None` when any origin is allowed.
+    pub fn cors_origin_headers(&self) -> anyhow::Result<Option<Vec<HeaderValue>>> {
+        if self.server.cors_origins.iter().any(|o| o == "*") {
+            return Ok(None);
+        }
+        self.server
+            .cors_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin)
+                    .map_err(|_| anyhow!("server.cors_origins: {} is not a valid origin", origin))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Some)
+    }
+}
+
+fn read_file(path: &Path) -> anyhow::Result<FileConfig> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read config file {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("Invalid config file {}", path.display()))
+}
+
+fn set<T>(slot: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *slot = value;
+    }
+}
+
+fn set_some<T>(slot: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *slot = value;
+    }
+}
@@ -0,0 +1,265 @@
+use crate::config::EmailConfig;
+use crate::models::email::QueuedEmail;
+use crate::models::notification::{Notification, NotificationKind};
+use crate::utils::escape_html;
+use anyhow::{Context, anyhow};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::{AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+/// Verification links stay valid for this long after an address is registered.
+pub const VERIFICATION_TTL_HOURS: i64 = 24;
+
+pub enum MailTransport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    /// Writes every message as an `.eml` file; used for local development and tests.
+    File(AsyncFileTransport<Tokio1Executor>),
+    Disabled,
+}
+
+pub struct Mailer {
+    pub from: Mailbox,
+    pub transport: MailTransport,
+    pub verify_url: String,
+}
+
+impl Mailer {
+    /// Builds the mailer from `email.smtp_url` or `email.outbox_dir`.
+    /// With neither set, emails stay queued and nothing is sent.
+    pub fn from_config(config: &EmailConfig) -> anyhow::Result<Self> {
+        let from = config
+            .from
+            .parse::<Mailbox>()
+            .context("email.from is not a valid mailbox")?;
+
+        let transport = if let Some(url) = &config.smtp_url {
+            MailTransport::Smtp(
+                AsyncSmtpTransport::<Tokio1Executor>::from_url(url)
+                    .context("email.smtp_url is not a valid SMTP URL")?
+                    .build(),
+            )
+        } else if let Some(dir) = &config.outbox_dir {
+            std::fs::create_dir_all(dir)?;
+            MailTransport::File(AsyncFileTransport::<Tokio1Executor>::new(dir))
+        } else {
+            warn!("📭 No email.smtp_url or email.outbox_dir set, email delivery disabled");
+            MailTransport::Disabled
+        };
+
+        Ok(Self {
+            from,
+            transport,
+            verify_url: config.verify_url.clone(),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self.transport, MailTransport::Disabled)
+    }
+
+    pub async fn send(&self, email: &QueuedEmail) -> anyhow::Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(email.to_address.parse::<Mailbox>()?)
+            .subject(&email.subject)
+            .multipart(MultiPart::alternative_plain_html(
+                email.text_body.clone(),
+                email.html_body.clone(),
+            ))?;
+
+        match &self.transport {
+            MailTransport::Smtp(transport) => {
+                transport.send(message).await?;
+            }
+            MailTransport::File(transport) => {
+                transport.send(message).await?;
+            }
+            MailTransport::Disabled => return Err(anyhow!("Email delivery is disabled")),
+        }
+
+        info!("📧 Sent email {} to {}", email.id, email.to_address);
+        Ok(())
+    }
+}
+
+/// Notification categories a user can opt in to by email.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailCategory {
+    RenewalSuccess,
+    RenewalFailure,
+    Expiry,
+    UpcomingCharge,
+}
+
+impl EmailCategory {
+    pub fn from_notification(notification: &Notification) -> Option<Self> {
+        match notification.r#type {
+            NotificationKind::Success => Some(Self::RenewalSuccess),
+            NotificationKind::Error => Some(Self::RenewalFailure),
+            NotificationKind::Warning => Some(Self::Expiry),
+            NotificationKind::Reminder => Some(Self::UpcomingCharge),
+            NotificationKind::Info => None,
+        }
+    }
+
+    fn accent(self) -> &'static str {
+        match self {
+            Self::RenewalSuccess => "#16a34a",
+            Self::RenewalFailure => "#dc2626",
+            Self::Expiry => "#d97706",
+            Self::UpcomingCharge => "#2563eb",
+        }
+    }
+}
+
+pub struct RenderedEmail {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+pub fn render_notification(category: EmailCategory, notification: &Notification) -> RenderedEmail {
+    let subject = format!("{} · {}", notification.title, notification.plan_name);
+
+    let text = format!(
+        "{title}\n\n{message}\n\nPlan: {plan}\nTier: {tier}\nSubscription: {pda}\n\n\
+         You are receiving this because you enabled email notifications in SolPay.",
+        title = notification.title,
+        message = notification.message,
+        plan = notification.plan_name,
+        tier = notification.tier,
+        pda = notification.subscription_pda,
+    );
+
+    let html = layout(
+        category.accent(),
+        &escape_html(&notification.title),
+        &format!(
+            "<p>{message}</p>\
+             <table cellpadding=\"4\">\
+             <tr><td><b>Plan</b></td><td>{plan}</td></tr>\
+             <tr><td><b>Tier</b></td><td>{tier}</td></tr>\
+             <tr><td><b>Subscription</b></td><td><code>{pda}</code></td></tr>\
+             </table>",
+            message = escape_html(&notification.message),
+            plan = escape_html(&notification.plan_name),
+            tier = escape_html(&notification.tier),
+            pda = escape_html(&notification.subscription_pda),
+        ),
+    );
+
+    RenderedEmail {
+        subject,
+        text,
+        html,
+    }
+}
+
+pub fn render_verification(verify_url: &str, token: uuid::Uuid) -> RenderedEmail {
+    let link = format!("{}?token={}", verify_url, token);
+
+    let text = format!(
+        "Confirm your email address\n\n\
+         Open the link below to start receiving SolPay notifications by email:\n{link}\n\n\
+         The link expires in {VERIFICATION_TTL_HOURS} hours. \
+         If you did not request this, you can ignore this message."
+    );
+
+    let html = layout(
+        "#2563eb",
+        "Confirm your email address",
+        &format!(
+            "<p>Click the button below to start receiving SolPay notifications by email.</p>\
+             <p><a href=\"{link}\" style=\"background:#2563eb;color:#fff;padding:10px 16px;\
+             border-radius:6px;text-decoration:none\">Verify email</a></p>\
+             <p style=\"color:#6b7280\">The link expires in {VERIFICATION_TTL_HOURS} hours. \
+             If you did not request this, you can ignore this message.</p>",
+            link = escape_html(&link),
+        ),
+    );
+
+    RenderedEmail {
+        subject: "Verify your email for SolPay".to_string(),
+        text,
+        html,
+    }
+}
+
+fn layout(accent: &str, heading: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><body style=\"font-family:sans-serif;color:#111827\">\
+         <div style=\"max-width:560px;margin:0 auto;padding:24px\">\
+         <h2 style=\"color:{accent}\">{heading}</h2>{body}\
+         <hr style=\"border:none;border-top:1px solid #e5e7eb\"/>\
+         <p style=\"font-size:12px;color:#6b7280\">Sent by SolPay</p>\
+         </div></body></html>"
+    )
+}
+
+pub async fn enqueue_email(
+    db: &PgPool,
+    user_pubkey: &str,
+    to_address: &str,
+    email: &RenderedEmail,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO email_queue (user_pubkey, to_address, subject, text_body, html_body)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_pubkey,
+        to_address,
+        email.subject,
+        email.text,
+        email.html,
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Queues an email copy of `notification` if the recipient has a verified
+/// address and has opted in to its category.
+pub async fn enqueue_notification_email(
+    db: &PgPool,
+    notification: &Notification,
+) -> anyhow::Result<()> {
+    let Some(category) = EmailCategory::from_notification(notification) else {
+        return Ok(());
+    };
+
+    let settings = sqlx::query!(
+        r#"
+        SELECT
+            email,
+            notify_renewal_success,
+            notify_renewal_failure,
+            notify_expiry,
+            notify_upcoming_charge
+        FROM email_settings
+        WHERE user_pubkey = $1 AND verified = true
+        "#,
+        notification.user_pubkey
+    )
+    .fetch_optional(db)
+    .await?;
+
+    let Some(settings) = settings else {
+        return Ok(());
+    };
+
+    let opted_in = match category {
+        EmailCategory::RenewalSuccess => settings.notify_renewal_success,
+        EmailCategory::RenewalFailure => settings.notify_renewal_failure,
+        EmailCategory::Expiry => settings.notify_expiry,
+        EmailCategory::UpcomingCharge => settings.notify_upcoming_charge,
+    };
+    if !opted_in {
+        return Ok(());
+    }
+
+    let rendered = render_notification(category, notification);
+    enqueue_email(db, &notification.user_pubkey, &settings.email, &rendered).await
+}
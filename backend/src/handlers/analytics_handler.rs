@@ -0,0 +1,316 @@
+use crate::models::analytics::{
+    AnalyticsQuery, Cohort, CohortCell, MintAmount, MonthlyGrowth, MonthlyRevenue, PlanSummary,
+    TierMix,
+};
+use crate::state::AppState;
+use crate::types::Plan;
+use crate::utils::{format_token_amount, parse_tiers};
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+};
+use solana_sdk::pubkey::Pubkey;
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+/// Tiers describe a month as 2592000 seconds, so MRR uses the same length.
+const SECONDS_PER_MONTH: u128 = 2_592_000;
+const DEFAULT_MONTHS: i32 = 12;
+const MAX_MONTHS: i32 = 36;
+
+pub async fn record_subscription_event(
+    db: &PgPool,
+    subscription_pda: &str,
+    plan_pda: &str,
+    payer: &str,
+    tier_name: &str,
+    event: &str,
+) -> anyhow::Result<()> {
+    if !matches!(event, "started" | "cancelled" | "expired" | "completed") {
+        anyhow::bail!("invalid subscription event");
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO subscription_events (subscription_pda, plan_pda, payer, tier_name, event)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        subscription_pda,
+        plan_pda,
+        payer,
+        tier_name,
+        event
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("DB error: {}", e),
+    )
+}
+
+/// GET /analytics/plans/{plan_pda}/summary
+pub async fn get_plan_summary(
+    Extension(state): Extension<AppState>,
+    Path(plan_pda): Path<String>,
+) -> Result<Json<PlanSummary>, (StatusCode, String)> {
+    let plan_key = Pubkey::from_str(&plan_pda)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid plan PDA".to_string()))?;
+
+    let plan = state
+        .solana
+        .get_plan(plan_key)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("RPC error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Plan not found".to_string()))?;
+
+    let tiers =
+        parse_tiers(&plan.tiers).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+    let period_by_tier: HashMap<String, u128> = tiers
+        .iter()
+        .filter_map(|t| {
+            let period = t.period_seconds.parse::<u128>().ok()?;
+            (period > 0).then(|| (t.tier_name.clone(), period))
+        })
+        .collect();
+
+    let active = sqlx::query!(
+        r#"
+        SELECT tier_name, amount, mint
+        FROM subscriptions
+        WHERE plan_pda = $1 AND active = true
+        "#,
+        plan_pda
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let mut mix: BTreeMap<(String, Pubkey), (u64, u128)> = BTreeMap::new();
+    for sub in &active {
+        let Some(period) = period_by_tier.get(&sub.tier_name) else {
+            tracing::warn!(
+                "Tier '{}' missing from plan {}, excluded from MRR",
+                sub.tier_name,
+                plan_pda
+            );
+            continue;
+        };
+        let Some(mint) = billing_mint(&plan, sub.mint.as_deref()) else {
+            tracing::warn!("Subscription on plan {} has an invalid mint", plan_pda);
+            continue;
+        };
+        let monthly = sub.amount.max(0) as u128 * SECONDS_PER_MONTH / period;
+        let entry = mix.entry((sub.tier_name.clone(), mint)).or_default();
+        entry.0 += 1;
+        entry.1 += monthly;
+    }
+
+    let mut mrr_by_mint: BTreeMap<Pubkey, u128> = BTreeMap::new();
+    for ((_, mint), (_, monthly)) in &mix {
+        *mrr_by_mint.entry(*mint).or_default() += monthly;
+    }
+    let mut mrr = Vec::new();
+    let mut arr = Vec::new();
+    for (mint, monthly) in mrr_by_mint {
+        let monthly = u64::try_from(monthly).unwrap_or(u64::MAX);
+        mrr.push(mint_amount(&state, &plan, &mint, monthly).await?);
+        arr.push(mint_amount(&state, &plan, &mint, monthly.saturating_mul(12)).await?);
+    }
+
+    let attempts = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE status = 'failed') AS "failed!",
+            COUNT(*) AS "total!"
+        FROM payment_history
+        WHERE plan_pda = $1
+          AND direction = 'paid'
+          AND status IN ('success', 'failed')
+          AND created_at > now() - INTERVAL '30 days'
+        "#,
+        plan_pda
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let failed_payment_rate = if attempts.total > 0 {
+        attempts.failed as f64 / attempts.total as f64
+    } else {
+        0.0
+    };
+
+    Ok(Json(PlanSummary {
+        plan_pda,
+        active_subscribers: active.len() as u64,
+        mrr,
+        arr,
+        tier_mix: mix
+            .into_iter()
+            .map(|((tier, mint), (subscribers, mrr))| TierMix {
+                tier,
+                mint: mint.to_string(),
+                subscribers,
+                mrr: u64::try_from(mrr).unwrap_or(u64::MAX),
+            })
+            .collect(),
+        failed_payment_rate,
+    }))
+}
+
+/// Mint a subscription bills in; `None` or empty is the plan's primary mint.
+fn billing_mint(plan: &Plan, mint: Option<&str>) -> Option<Pubkey> {
+    match mint.filter(|m| !m.is_empty()) {
+        Some(mint) => Pubkey::from_str(mint).ok(),
+        None => Some(plan.mint),
+    }
+}
+
+async fn mint_amount(
+    state: &AppState,
+    plan: &Plan,
+    mint: &Pubkey,
+    amount: u64,
+) -> Result<MintAmount, (StatusCode, String)> {
+    let decimals = state
+        .solana
+        .get_mint_decimals(mint)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("RPC error: {}", e)))?;
+
+    Ok(MintAmount {
+        mint: mint.to_string(),
+        token_symbol: plan.token_symbol_for(mint).to_string(),
+        decimals,
+        amount,
+        amount_ui: format_token_amount(amount, decimals),
+    })
+}
+
+/// GET /analytics/plans/{plan_pda}/growth?months=12
+/// New vs. churned subscribers, revenue per mint and failed payments per calendar month.
+pub async fn get_plan_growth(
+    Extension(state): Extension<AppState>,
+    Path(plan_pda): Path<String>,
+    Query(query): Query<AnalyticsQuery>,
+) -> Result<Json<Vec<MonthlyGrowth>>, (StatusCode, String)> {
+    let plan_key = Pubkey::from_str(&plan_pda)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid plan PDA".to_string()))?;
+    let months = query.months.unwrap_or(DEFAULT_MONTHS).clamp(1, MAX_MONTHS);
+
+    let mut rows = sqlx::query_as::<_, MonthlyGrowth>(
+        r#"
+        SELECT
+            month,
+            new_subscribers,
+            churned_subscribers,
+            successful_payments,
+            failed_payments
+        FROM analytics_plan_monthly
+        WHERE plan_pda = $1
+          AND month >= date_trunc('month', now()) - make_interval(months => $2 - 1)
+        ORDER BY month ASC
+        "#,
+    )
+    .bind(&plan_pda)
+    .bind(months)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let revenue = sqlx::query_as::<_, MonthlyRevenue>(
+        r#"
+        SELECT month, mint, revenue
+        FROM analytics_plan_revenue
+        WHERE plan_pda = $1
+          AND month >= date_trunc('month', now()) - make_interval(months => $2 - 1)
+        ORDER BY month ASC, mint ASC
+        "#,
+    )
+    .bind(&plan_pda)
+    .bind(months)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    if !revenue.is_empty() {
+        let plan = state
+            .solana
+            .get_plan(plan_key)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, format!("RPC error: {}", e)))?
+            .ok_or((StatusCode::NOT_FOUND, "Plan not found".to_string()))?;
+
+        for entry in revenue {
+            let Some(row) = rows.iter_mut().find(|r| r.month == entry.month) else {
+                continue;
+            };
+            let Some(mint) = billing_mint(&plan, Some(&entry.mint)) else {
+                continue;
+            };
+            let amount = u64::try_from(entry.revenue).unwrap_or(0);
+            row.revenue
+                .push(mint_amount(&state, &plan, &mint, amount).await?);
+        }
+    }
+
+    Ok(Json(rows))
+}
+
+/// GET /analytics/plans/{plan_pda}/cohorts?months=12
+/// Monthly cohorts by first payment, with the share still paying in each later month.
+pub async fn get_plan_cohorts(
+    Extension(state): Extension<AppState>,
+    Path(plan_pda): Path<String>,
+    Query(query): Query<AnalyticsQuery>,
+) -> Result<Json<Vec<Cohort>>, (StatusCode, String)> {
+    let months = query.months.unwrap_or(DEFAULT_MONTHS).clamp(1, MAX_MONTHS);
+
+    let cells = sqlx::query_as::<_, CohortCell>(
+        r#"
+        SELECT cohort_month, months_since, active_subscribers
+        FROM analytics_plan_cohorts
+        WHERE plan_pda = $1
+          AND cohort_month >= date_trunc('month', now()) - make_interval(months => $2 - 1)
+        ORDER BY cohort_month ASC, months_since ASC
+        "#,
+    )
+    .bind(&plan_pda)
+    .bind(months)
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let mut cohorts: Vec<Cohort> = Vec::new();
+    for cell in cells {
+        if cohorts.last().map(|c| c.cohort_month) != Some(cell.cohort_month) {
+            cohorts.push(Cohort {
+                cohort_month: cell.cohort_month,
+                size: 0,
+                retention: Vec::new(),
+            });
+        }
+        let cohort = cohorts.last_mut().expect("cohort pushed above");
+
+        if cell.months_since == 0 {
+            cohort.size = cell.active_subscribers;
+        }
+        let index = cell.months_since.max(0) as usize;
+        if cohort.retention.len() <= index {
+            cohort.retention.resize(index + 1, 0.0);
+        }
+        if cohort.size > 0 {
+            cohort.retention[index] = cell.active_subscribers as f64 / cohort.size as f64;
+        }
+    }
+
+    Ok(Json(cohorts))
+}
@@ -0,0 +1,641 @@
+use crate::models::builder::{
+    BuildCancelPlan, BuildCancelSubscription, BuildChangeTier, BuildCreatePlan, BuildOptions,
+    BuildRefund, BuildSubscribe, BuildUpdatePlan, UnsignedTransaction,
+};
+use crate::models::subscription::Tier;
+use crate::solana_client::{NATIVE_MINT, SolanaClient};
+use crate::state::AppState;
+use crate::tx_builder;
+use crate::types::{Plan, SubscriptionField, UnitPrice, UpdateValue, UsdPrice};
+use crate::utils::{compress_tiers, find_tier_by_name, parse_tiers, parse_token_amount};
+use axum::{Json, extract::Extension, http::StatusCode};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use solpay_client::{oracle, pda};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use std::str::FromStr;
+
+const MAX_PLAN_NAME_LEN: usize = 64;
+const MAX_TOKEN_SYMBOL_LEN: usize = 10;
+/// How far a USD-priced tier's token price may rise before renewals need a new approval.
+pub const DEFAULT_USD_SLIPPAGE_BPS: u16 = 2_000;
+
+fn rpc_error(e: anyhow::Error) -> (StatusCode, String) {
+    (StatusCode::BAD_GATEWAY, format!("RPC error: {}", e))
+}
+
+fn bad_request(e: impl std::fmt::Display) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, e.to_string())
+}
+
+fn parse_pubkey(value: &str, what: &str) -> Result<Pubkey, (StatusCode, String)> {
+    Pubkey::from_str(value).map_err(|_| bad_request(format!("Invalid {}", what)))
+}
+
+async fn load_plan(solana: &SolanaClient, plan_pda: Pubkey) -> Result<Plan, (StatusCode, String)> {
+    solana
+        .get_plan(plan_pda)
+        .await
+        .map_err(rpc_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Plan not found".to_string()))
+}
+
+/// Wraps the instructions for `signer`, or the requested fee payer, with a fresh blockhash.
+async fn finish(
+    solana: &SolanaClient,
+    signer: &Pubkey,
+    options: &BuildOptions,
+    instructions: Vec<Instruction>,
+) -> Result<UnsignedTransaction, (StatusCode, String)> {
+    let fee_payer = match options.fee_payer.as_deref() {
+        Some(fee_payer) => parse_pubkey(fee_payer, "fee payer")?,
+        None => *signer,
+    };
+    let (transaction, blockhash, last_valid_block_height) = solana
+        .unsigned_transaction(&fee_payer, instructions, options.compute_unit_price)
+        .await
+        .map_err(rpc_error)?;
+
+    Ok(UnsignedTransaction {
+        transaction,
+        blockhash: blockhash.to_string(),
+        last_valid_block_height,
+        subscription_pda: None,
+        unique_seed: None,
+    })
+}
+
+pub fn check_plan_fields(name: &str, tiers: &[Tier]) -> Result<Vec<u8>, (StatusCode, String)> {
+    if name.is_empty() || name.len() > MAX_PLAN_NAME_LEN {
+        return Err(bad_request("Plan name must be 1-64 characters"));
+    }
+    if tiers.is_empty() {
+        return Err(bad_request("Plan needs at least one tier"));
+    }
+    if tiers
+        .iter()
+        .any(|t| !t.period_seconds.parse::<i64>().is_ok_and(|p| p > 0))
+    {
+        return Err(bad_request("Tier periods must be positive seconds"));
+    }
+    compress_tiers(tiers).map_err(bad_request)
+}
+
+/// `set_tier_unit_price` in the plan's primary mint wherever `tiers` disagree with
+/// `current`, the plan's on-chain prices (empty for a new plan). Tiers that are gone
+/// or no longer metered are set back to 0; prices in other mints are left alone.
+pub fn unit_price_instructions(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    mint: &Pubkey,
+    decimals: u8,
+    tiers: &[Tier],
+    current: &[UnitPrice],
+) -> Result<Vec<Instruction>, (StatusCode, String)> {
+    let on_chain = |tier_name: &str| {
+        current
+            .iter()
+            .find(|p| p.tier_name == tier_name && p.mint == *mint)
+            .map_or(0, |p| p.unit_price)
+    };
+
+    // Removals first, so they free room for the new prices
+    let mut instructions: Vec<Instruction> = current
+        .iter()
+        .filter(|p| p.mint == *mint && !tiers.iter().any(|t| t.tier_name == p.tier_name))
+        .map(|p| tx_builder::set_tier_unit_price(program_id, creator, &p.tier_name, mint, 0))
+        .collect();
+    for tier in tiers {
+        let unit_price = match tier.unit_price.as_deref() {
+            Some(ui) => parse_token_amount(ui, decimals)
+                .ok_or_else(|| bad_request(format!("Invalid unit price {}", ui)))?,
+            None => 0,
+        };
+        if unit_price != on_chain(&tier.tier_name) {
+            instructions.push(tx_builder::set_tier_unit_price(
+                program_id,
+                creator,
+                &tier.tier_name,
+                mint,
+                unit_price,
+            ));
+        }
+    }
+    Ok(instructions)
+}
+
+/// `set_tier_usd_price` wherever `tiers` disagree with `current`, the plan's on-chain
+/// USD prices. Tiers that are gone or no longer USD-priced are set back to 0.
+pub fn usd_price_instructions(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    tiers: &[Tier],
+    current: &[UsdPrice],
+) -> Result<Vec<Instruction>, (StatusCode, String)> {
+    let on_chain = |tier_name: &str| {
+        current
+            .iter()
+            .find(|p| p.tier_name == tier_name)
+            .map_or(0, |p| p.usd_amount)
+    };
+
+    // Removals first, so they free room for the new prices
+    let mut instructions: Vec<Instruction> = current
+        .iter()
+        .filter(|p| !tiers.iter().any(|t| t.tier_name == p.tier_name))
+        .map(|p| tx_builder::set_tier_usd_price(program_id, creator, &p.tier_name, 0))
+        .collect();
+    for tier in tiers {
+        let usd_amount = match tier.usd_price.as_deref() {
+            Some(usd) => parse_token_amount(usd, oracle::USD_DECIMALS)
+                .ok_or_else(|| bad_request(format!("Invalid USD price {}", usd)))?,
+            None => 0,
+        };
+        if usd_amount != on_chain(&tier.tier_name) {
+            instructions.push(tx_builder::set_tier_usd_price(
+                program_id,
+                creator,
+                &tier.tier_name,
+                usd_amount,
+            ));
+        }
+    }
+    Ok(instructions)
+}
+
+/// One cycle of `tier` in `mint` base units, and whether it was quoted from the oracle
+/// because the tier is priced in USD.
+pub async fn tier_price(
+    solana: &SolanaClient,
+    plan: &Plan,
+    tier: &Tier,
+    mint: Pubkey,
+    decimals: u8,
+) -> Result<(u64, bool), (StatusCode, String)> {
+    let usd_amount = plan.usd_price(&tier.tier_name);
+    if usd_amount > 0 {
+        let feed = plan.feed_for(&mint).ok_or_else(|| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Plan has no USD price feed for this mint".to_string(),
+            )
+        })?;
+        let price = solana.get_oracle_price(feed).await.map_err(rpc_error)?;
+        let amount = oracle::usd_to_tokens(usd_amount, &price, decimals)
+            .ok_or_else(|| bad_request("USD price overflows the mint"))?;
+        return Ok((amount, true));
+    }
+
+    let price = if mint == plan.mint {
+        &tier.amount
+    } else {
+        &tier
+            .prices
+            .iter()
+            .find(|p| p.mint == mint.to_string())
+            .ok_or_else(|| bad_request("Tier has no price in this mint"))?
+            .amount
+    };
+    let amount = parse_token_amount(price, decimals)
+        .ok_or_else(|| bad_request(format!("Invalid token amount {}", price)))?;
+    Ok((amount, false))
+}
+
+/// POST /build/subscribe
+/// Priced from the plan's tier in the chosen mint; the wallet only has to sign.
+pub async fn build_subscribe(
+    Extension(state): Extension<AppState>,
+    Json(payload): Json<BuildSubscribe>,
+) -> Result<Json<UnsignedTransaction>, (StatusCode, String)> {
+    subscribe_transaction(&state.solana, &payload)
+        .await
+        .map(Json)
+}
+
+/// Sign-up transaction for `payload.payer`, shared with the checkout links and the CLI.
+pub async fn subscribe_transaction(
+    solana: &SolanaClient,
+    payload: &BuildSubscribe,
+) -> Result<UnsignedTransaction, (StatusCode, String)> {
+    let program_id = solana.program_id;
+    let payer = parse_pubkey(&payload.payer, "payer")?;
+    let plan_pda = parse_pubkey(&payload.plan_pda, "plan PDA")?;
+    let plan = load_plan(solana, plan_pda).await?;
+
+    let mint = match payload.mint.as_deref() {
+        Some(mint) => parse_pubkey(mint, "mint")?,
+        None => plan.mint,
+    };
+    let tiers = parse_tiers(&plan.tiers).map_err(bad_request)?;
+    let tier = find_tier_by_name(&tiers, &payload.tier_name).map_err(bad_request)?;
+    let decimals = solana.get_mint_decimals(&mint).await.map_err(rpc_error)?;
+    let to_base_units = |ui: &str| {
+        parse_token_amount(ui, decimals)
+            .ok_or_else(|| bad_request(format!("Invalid token amount {}", ui)))
+    };
+
+    // USD-priced tiers: every charge, the first included, converts at the oracle price
+    // when it runs and may take up to the slippage buffer more than the quote now
+    let (quote, usd_priced) = tier_price(solana, &plan, tier, mint, decimals).await?;
+    let amount = if usd_priced {
+        let slippage_bps = payload.max_slippage_bps.unwrap_or(DEFAULT_USD_SLIPPAGE_BPS) as u128;
+        let max = quote as u128 * (10_000 + slippage_bps) / 10_000;
+        u64::try_from(max).unwrap_or(u64::MAX)
+    } else {
+        quote
+    };
+    let price_feed = usd_priced
+        .then(|| plan.feed_for(&mint).map(|feed| feed.price_feed))
+        .flatten();
+    let period_seconds: i64 = tier.period_seconds.parse().map_err(bad_request)?;
+    let max_cycles = tier
+        .max_cycles
+        .as_deref()
+        .map(str::parse::<u64>)
+        .transpose()
+        .map_err(bad_request)?;
+    let end_ts = tier
+        .end_ts
+        .as_deref()
+        .map(str::parse::<i64>)
+        .transpose()
+        .map_err(bad_request)?;
+
+    let token_program = solana.get_token_program(&mint).await.map_err(rpc_error)?;
+    let unique_seed: [u8; 8] = uuid::Uuid::new_v4().as_bytes()[..8].try_into().unwrap();
+    let subscription = tx_builder::subscription_pda(&program_id, &payer, &unique_seed);
+    let user_token_account =
+        get_associated_token_address_with_program_id(&payer, &mint, &token_program);
+    let receiver_token_account =
+        get_associated_token_address_with_program_id(&plan.receiver, &mint, &token_program);
+
+    let mut instructions = Vec::new();
+    if mint == NATIVE_MINT {
+        // Creates the wSOL account too, so the first charge can be paid in SOL
+        instructions.push(tx_builder::wrap_sol(
+            &program_id,
+            &payer,
+            &user_token_account,
+            &mint,
+            &token_program,
+            None,
+            amount,
+        ));
+    } else {
+        instructions.extend(
+            solana
+                .create_ata_if_missing(&payer, &payer, &mint, &token_program)
+                .await
+                .map_err(rpc_error)?,
+        );
+    }
+    instructions.extend(
+        solana
+            .create_ata_if_missing(&payer, &plan.receiver, &mint, &token_program)
+            .await
+            .map_err(rpc_error)?,
+    );
+
+    let hook_accounts = solana
+        .transfer_hook_accounts(
+            &mint,
+            &user_token_account,
+            &receiver_token_account,
+            &payer,
+            amount,
+        )
+        .await
+        .map_err(rpc_error)?;
+    let coupon = payload
+        .coupon_code
+        .as_deref()
+        .map(|code| solana.coupon_pda(&plan_pda, code.trim()));
+
+    instructions.push(tx_builder::initialize_subscription(
+        &program_id,
+        tx_builder::InitializeSubscription {
+            payer,
+            subscription,
+            user_token_account,
+            receiver_token_account,
+            mint,
+            token_program,
+            payment_record: solana.payment_record_pda(&subscription, 0),
+            plan: plan_pda,
+            coupon,
+            price_feed,
+            tier_name: tier.tier_name.clone(),
+            period_seconds,
+            amount,
+            auto_renew: payload.auto_renew,
+            unique_seed,
+            max_cycles,
+            end_ts,
+            hook_accounts,
+        },
+    ));
+
+    // The merchant prices usage on the plan; the subscriber only approves a cap
+    let metered = plan.unit_price(&tier.tier_name, &mint) > 0;
+    let usage_cap = if metered {
+        to_base_units(
+            payload
+                .usage_cap
+                .as_deref()
+                .or(tier.usage_cap.as_deref())
+                .unwrap_or("0"),
+        )?
+    } else {
+        0
+    };
+    if metered {
+        instructions.push(tx_builder::set_usage_cap(
+            &program_id,
+            &payer,
+            &subscription,
+            usage_cap,
+        ));
+    }
+    if payload.auto_renew && mint == NATIVE_MINT {
+        // The wSOL account is shared by the payer's SOL subscriptions, so renewals go
+        // through one delegate, approved for this subscription's renewals on top of theirs
+        let sol_delegate = pda::sol_delegate(&program_id);
+        let approved = solana
+            .get_token_account_funding(&user_token_account)
+            .await
+            .map_err(rpc_error)?
+            .filter(|funding| funding.delegate == Some(sol_delegate))
+            .map_or(0, |funding| funding.delegated_amount);
+        let renewals = max_cycles.map_or(pda::SOL_APPROVAL_CYCLES, |max| max.saturating_sub(1));
+        let bound = amount.saturating_add(usage_cap).saturating_mul(renewals);
+        instructions.push(tx_builder::approve_delegate(
+            &token_program,
+            &user_token_account,
+            &sol_delegate,
+            &payer,
+            approved.saturating_add(bound),
+        ));
+    } else if payload.auto_renew {
+        // Renewals are pulled by the subscription PDA as delegate
+        instructions.push(tx_builder::approve_delegate(
+            &token_program,
+            &user_token_account,
+            &subscription,
+            &payer,
+            u64::MAX,
+        ));
+    }
+
+    let mut unsigned = finish(solana, &payer, &payload.options, instructions).await?;
+    unsigned.subscription_pda = Some(subscription.to_string());
+    unsigned.unique_seed = Some(unique_seed);
+    Ok(unsigned)
+}
+
+/// POST /build/cancel
+pub async fn build_cancel_subscription(
+    Extension(state): Extension<AppState>,
+    Json(payload): Json<BuildCancelSubscription>,
+) -> Result<Json<UnsignedTransaction>, (StatusCode, String)> {
+    let payer = parse_pubkey(&payload.payer, "payer")?;
+    let subscription = parse_pubkey(&payload.subscription_pda, "subscription PDA")?;
+    let mut instructions = state
+        .solana
+        .migration_instructions(&payer, &[subscription])
+        .await
+        .map_err(rpc_error)?;
+    instructions.push(tx_builder::cancel_subscription(
+        &state.solana.program_id,
+        &payer,
+        &subscription,
+    ));
+    finish(&state.solana, &payer, &payload.options, instructions)
+        .await
+        .map(Json)
+}
+
+/// POST /build/refund
+/// Signed by the plan's receiver; at most what is left of the charge after earlier refunds.
+pub async fn build_refund(
+    Extension(state): Extension<AppState>,
+    Json(payload): Json<BuildRefund>,
+) -> Result<Json<UnsignedTransaction>, (StatusCode, String)> {
+    let receiver = parse_pubkey(&payload.receiver, "receiver")?;
+    let payment_record = parse_pubkey(&payload.payment_record, "payment record")?;
+    let solana = &state.solana;
+    let record = solana
+        .get_payment_record(&payment_record)
+        .await
+        .map_err(rpc_error)?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            "Payment record not found".to_string(),
+        ))?;
+    let plan = load_plan(solana, record.plan).await?;
+    if plan.receiver != receiver {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only the plan's receiver can refund".to_string(),
+        ));
+    }
+
+    let refundable = record.amount.saturating_sub(record.refunded_amount);
+    let amount = match payload.amount.as_deref() {
+        Some(ui) => {
+            let decimals = solana
+                .get_mint_decimals(&record.mint)
+                .await
+                .map_err(rpc_error)?;
+            parse_token_amount(ui, decimals)
+                .ok_or_else(|| bad_request(format!("Invalid amount {}", ui)))?
+        }
+        None => refundable,
+    };
+    if amount == 0 || amount > refundable {
+        return Err(bad_request(format!(
+            "Refund must be between 1 and {} base units",
+            refundable
+        )));
+    }
+
+    let token_program = solana
+        .get_token_program(&record.mint)
+        .await
+        .map_err(rpc_error)?;
+    let receiver_token_account =
+        get_associated_token_address_with_program_id(&receiver, &record.mint, &token_program);
+    let payer_token_account =
+        get_associated_token_address_with_program_id(&record.payer, &record.mint, &token_program);
+    let hook_accounts = solana
+        .transfer_hook_accounts(
+            &record.mint,
+            &receiver_token_account,
+            &payer_token_account,
+            &receiver,
+            amount,
+        )
+        .await
+        .map_err(rpc_error)?;
+
+    let instructions = vec![tx_builder::refund_payment(
+        &solana.program_id,
+        &receiver,
+        &payment_record,
+        &record,
+        &receiver_token_account,
+        &payer_token_account,
+        &token_program,
+        amount,
+        hook_accounts,
+    )];
+    finish(solana, &receiver, &payload.options, instructions)
+        .await
+        .map(Json)
+}
+
+/// POST /build/change-tier
+/// The tier must exist on the subscription's plan.
+pub async fn build_change_tier(
+    Extension(state): Extension<AppState>,
+    Json(payload): Json<BuildChangeTier>,
+) -> Result<Json<UnsignedTransaction>, (StatusCode, String)> {
+    let payer = parse_pubkey(&payload.payer, "payer")?;
+    let subscription = parse_pubkey(&payload.subscription_pda, "subscription PDA")?;
+    let account = state
+        .solana
+        .get_subscription_account(&subscription)
+        .await
+        .map_err(rpc_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Subscription not found".to_string()))?;
+    if account.payer != payer {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only the subscriber can change tier".to_string(),
+        ));
+    }
+
+    let plan = load_plan(&state.solana, account.plan_pda).await?;
+    let tiers = parse_tiers(&plan.tiers).map_err(bad_request)?;
+    find_tier_by_name(&tiers, &payload.tier_name).map_err(bad_request)?;
+
+    let mut instructions = state
+        .solana
+        .migration_instructions(&payer, &[subscription])
+        .await
+        .map_err(rpc_error)?;
+    instructions.push(tx_builder::update_subscription_status(
+        &state.solana.program_id,
+        &payer,
+        &subscription,
+        SubscriptionField::Tier,
+        UpdateValue::String(payload.tier_name),
+    ));
+    finish(&state.solana, &payer, &payload.options, instructions)
+        .await
+        .map(Json)
+}
+
+/// POST /build/plans
+pub async fn build_create_plan(
+    Extension(state): Extension<AppState>,
+    Json(payload): Json<BuildCreatePlan>,
+) -> Result<Json<UnsignedTransaction>, (StatusCode, String)> {
+    let creator = parse_pubkey(&payload.creator, "creator")?;
+    let mint = parse_pubkey(&payload.mint, "mint")?;
+    let receiver = parse_pubkey(&payload.receiver, "receiver")?;
+    if payload.token_symbol.len() > MAX_TOKEN_SYMBOL_LEN {
+        return Err(bad_request("Token symbol must be at most 10 characters"));
+    }
+    let tiers = check_plan_fields(&payload.name, &payload.tiers)?;
+    let decimals = state
+        .solana
+        .get_mint_decimals(&mint)
+        .await
+        .map_err(rpc_error)?;
+
+    let mut instructions = vec![tx_builder::create_plan(
+        &state.solana.program_id,
+        &creator,
+        &mint,
+        &receiver,
+        &payload.name,
+        &payload.token_symbol,
+        &payload.token_image,
+        &tiers,
+    )];
+    instructions.extend(unit_price_instructions(
+        &state.solana.program_id,
+        &creator,
+        &mint,
+        decimals,
+        &payload.tiers,
+        &[],
+    )?);
+    instructions.extend(usd_price_instructions(
+        &state.solana.program_id,
+        &creator,
+        &payload.tiers,
+        &[],
+    )?);
+    finish(&state.solana, &creator, &payload.options, instructions)
+        .await
+        .map(Json)
+}
+
+/// PUT /build/plans
+pub async fn build_update_plan(
+    Extension(state): Extension<AppState>,
+    Json(payload): Json<BuildUpdatePlan>,
+) -> Result<Json<UnsignedTransaction>, (StatusCode, String)> {
+    let creator = parse_pubkey(&payload.creator, "creator")?;
+    let receiver = parse_pubkey(&payload.receiver, "receiver")?;
+    let tiers = check_plan_fields(&payload.name, &payload.tiers)?;
+    let plan_pda = tx_builder::plan_pda(&state.solana.program_id, &creator);
+    let plan = load_plan(&state.solana, plan_pda).await?;
+    let decimals = state
+        .solana
+        .get_mint_decimals(&plan.mint)
+        .await
+        .map_err(rpc_error)?;
+
+    let mut instructions = state
+        .solana
+        .migration_instructions(&creator, &[plan_pda])
+        .await
+        .map_err(rpc_error)?;
+    instructions.push(tx_builder::update_plan(
+        &state.solana.program_id,
+        &creator,
+        &receiver,
+        &payload.name,
+        &tiers,
+    ));
+    instructions.extend(unit_price_instructions(
+        &state.solana.program_id,
+        &creator,
+        &plan.mint,
+        decimals,
+        &payload.tiers,
+        &plan.unit_prices,
+    )?);
+    instructions.extend(usd_price_instructions(
+        &state.solana.program_id,
+        &creator,
+        &payload.tiers,
+        &plan.usd_prices,
+    )?);
+    finish(&state.solana, &creator, &payload.options, instructions)
+        .await
+        .map(Json)
+}
+
+/// POST /build/plans/cancel
+pub async fn build_cancel_plan(
+    Extension(state): Extension<AppState>,
+    Json(payload): Json<BuildCancelPlan>,
+) -> Result<Json<UnsignedTransaction>, (StatusCode, String)> {
+    let creator = parse_pubkey(&payload.creator, "creator")?;
+    let ix = tx_builder::cancel_plan(&state.solana.program_id, &creator);
+    finish(&state.solana, &creator, &payload.options, vec![ix])
+        .await
+        .map(Json)
+}
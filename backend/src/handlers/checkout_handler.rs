@@ -0,0 +1,297 @@
+use crate::handlers::builder_handler::subscribe_transaction;
+use crate::models::builder::{BuildOptions, BuildSubscribe};
+use crate::models::checkout::{
+    ActionError, ActionGetResponse, ActionLinks, ActionPostResponse, ActionRule, ActionsManifest,
+    CheckoutAccount, CheckoutQuery, LinkedAction, PayMetadata, PayTransaction,
+};
+use crate::models::subscription::Tier;
+use crate::state::AppState;
+use crate::types::Plan;
+use crate::utils::{find_tier_by_name, format_token_amount, parse_tiers};
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+    http::{HeaderName, HeaderValue, StatusCode},
+};
+use solana_sdk::pubkey::Pubkey;
+use solpay_client::oracle;
+use std::str::FromStr;
+
+const ACTION_VERSION: &str = "2.4";
+
+type ActionHeaders = [(HeaderName, HeaderValue); 2];
+type ActionResult<T> = Result<(ActionHeaders, Json<T>), (StatusCode, Json<ActionError>)>;
+
+/// Wallets check these on every Actions response; the chain is whichever cluster the
+/// configured RPC serves.
+async fn action_headers(state: &AppState) -> Result<ActionHeaders, (StatusCode, String)> {
+    let blockchain_id = state
+        .solana
+        .blockchain_id()
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("RPC error: {}", e)))?;
+    let blockchain_id = HeaderValue::from_str(blockchain_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok([
+        (
+            HeaderName::from_static("x-action-version"),
+            HeaderValue::from_static(ACTION_VERSION),
+        ),
+        (HeaderName::from_static("x-blockchain-ids"), blockchain_id),
+    ])
+}
+
+fn action_error((status, message): (StatusCode, String)) -> (StatusCode, Json<ActionError>) {
+    (status, Json(ActionError { message }))
+}
+
+/// Tier names go into link paths, so anything but unreserved characters is escaped.
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Period in words for link labels; tiers use 30-day months.
+fn describe_period(seconds: &str) -> String {
+    match seconds.parse::<i64>() {
+        Ok(86_400) => "day".to_string(),
+        Ok(604_800) => "week".to_string(),
+        Ok(2_592_000) => "month".to_string(),
+        Ok(31_536_000) => "year".to_string(),
+        Ok(s) if s > 0 && s % 86_400 == 0 => format!("{} days", s / 86_400),
+        _ => format!("{} seconds", seconds),
+    }
+}
+
+struct Checkout {
+    plan_pda: Pubkey,
+    plan: Plan,
+    tiers: Vec<Tier>,
+    mint: Pubkey,
+}
+
+async fn load_checkout(
+    state: &AppState,
+    plan_pda: &str,
+    query: &CheckoutQuery,
+) -> Result<Checkout, (StatusCode, String)> {
+    let bad_request = |what: &str| (StatusCode::BAD_REQUEST, format!("Invalid {}", what));
+    let plan_pda = Pubkey::from_str(plan_pda).map_err(|_| bad_request("plan PDA"))?;
+    let plan = state
+        .solana
+        .get_plan(plan_pda)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("RPC error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Plan not found".to_string()))?;
+    let tiers =
+        parse_tiers(&plan.tiers).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+    let mint = match query.mint.as_deref() {
+        Some(mint) => Pubkey::from_str(mint).map_err(|_| bad_request("mint"))?,
+        None => plan.mint,
+    };
+
+    Ok(Checkout {
+        plan_pda,
+        plan,
+        tiers,
+        mint,
+    })
+}
+
+impl Checkout {
+    /// e.g. `10 USDC / month`
+    fn price_label(&self, tier: &Tier) -> String {
+        let usd_amount = self.plan.usd_price(&tier.tier_name);
+        if usd_amount > 0 {
+            return format!(
+                "${} / {} in {}",
+                format_token_amount(usd_amount, oracle::USD_DECIMALS),
+                describe_period(&tier.period_seconds),
+                self.plan.token_symbol_for(&self.mint)
+            );
+        }
+        let amount = if self.mint == self.plan.mint {
+            tier.amount.as_str()
+        } else {
+            tier.prices
+                .iter()
+                .find(|p| p.mint == self.mint.to_string())
+                .map_or("?", |p| p.amount.as_str())
+        };
+        format!(
+            "{} {} / {}",
+            amount,
+            self.plan.token_symbol_for(&self.mint),
+            describe_period(&tier.period_seconds)
+        )
+    }
+
+    /// Query string carried from the shared link onto the POST.
+    fn query_suffix(&self, query: &CheckoutQuery) -> String {
+        let mut params = Vec::new();
+        if self.mint != self.plan.mint {
+            params.push(format!("mint={}", self.mint));
+        }
+        if let Some(coupon) = query.coupon.as_deref() {
+            params.push(format!("coupon={}", encode_path_segment(coupon)));
+        }
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", params.join("&"))
+        }
+    }
+
+    async fn transaction(
+        &self,
+        state: &AppState,
+        tier: &Tier,
+        account: &str,
+        query: &CheckoutQuery,
+    ) -> Result<(String, String), (StatusCode, String)> {
+        let payload = BuildSubscribe {
+            payer: account.to_string(),
+            plan_pda: self.plan_pda.to_string(),
+            tier_name: tier.tier_name.clone(),
+            mint: Some(self.mint.to_string()),
+            auto_renew: true,
+            coupon_code: query.coupon.clone(),
+            usage_cap: None,
+            max_slippage_bps: None,
+            options: BuildOptions::default(),
+        };
+        let unsigned = subscribe_transaction(&state.solana, &payload).await?;
+        let message = format!(
+            "Subscribe to {} ({}) for {}",
+            self.plan.name,
+            tier.tier_name,
+            self.price_label(tier)
+        );
+        Ok((unsigned.transaction, message))
+    }
+}
+
+/// GET /pay/plans/{plan_pda}/tiers/{tier_name}
+/// Solana Pay transaction request: what the wallet shows before asking for the account.
+pub async fn get_pay_metadata(
+    Extension(state): Extension<AppState>,
+    Path((plan_pda, tier_name)): Path<(String, String)>,
+    Query(query): Query<CheckoutQuery>,
+) -> Result<Json<PayMetadata>, (StatusCode, String)> {
+    let checkout = load_checkout(&state, &plan_pda, &query).await?;
+    let tier = find_tier_by_name(&checkout.tiers, &tier_name)
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+
+    Ok(Json(PayMetadata {
+        label: format!("{} · {}", checkout.plan.name, tier.tier_name),
+        icon: checkout.plan.token_image.clone(),
+    }))
+}
+
+/// POST /pay/plans/{plan_pda}/tiers/{tier_name}
+pub async fn create_pay_transaction(
+    Extension(state): Extension<AppState>,
+    Path((plan_pda, tier_name)): Path<(String, String)>,
+    Query(query): Query<CheckoutQuery>,
+    Json(body): Json<CheckoutAccount>,
+) -> Result<Json<PayTransaction>, (StatusCode, String)> {
+    let checkout = load_checkout(&state, &plan_pda, &query).await?;
+    let tier = find_tier_by_name(&checkout.tiers, &tier_name)
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    let (transaction, message) = checkout
+        .transaction(&state, tier, &body.account, &query)
+        .await?;
+
+    Ok(Json(PayTransaction {
+        transaction,
+        message,
+    }))
+}
+
+/// GET /actions/plans/{plan_pda}
+/// Blink for a plan: one button per tier, priced in the requested mint.
+pub async fn get_plan_action(
+    Extension(state): Extension<AppState>,
+    Path(plan_pda): Path<String>,
+    Query(query): Query<CheckoutQuery>,
+) -> ActionResult<ActionGetResponse> {
+    let checkout = load_checkout(&state, &plan_pda, &query)
+        .await
+        .map_err(action_error)?;
+    let suffix = checkout.query_suffix(&query);
+
+    let actions = checkout
+        .tiers
+        .iter()
+        .map(|tier| LinkedAction {
+            kind: "transaction",
+            label: format!("{} · {}", tier.tier_name, checkout.price_label(tier)),
+            href: format!(
+                "/api/actions/plans/{}/tiers/{}{}",
+                checkout.plan_pda,
+                encode_path_segment(&tier.tier_name),
+                suffix
+            ),
+        })
+        .collect();
+
+    Ok((
+        action_headers(&state).await.map_err(action_error)?,
+        Json(ActionGetResponse {
+            kind: "action",
+            icon: checkout.plan.token_image.clone(),
+            title: checkout.plan.name.clone(),
+            description: format!(
+                "Subscribe with {}; renewals are charged automatically and can be cancelled any time.",
+                checkout.plan.token_symbol_for(&checkout.mint)
+            ),
+            label: "Subscribe".to_string(),
+            links: ActionLinks { actions },
+        }),
+    ))
+}
+
+/// POST /actions/plans/{plan_pda}/tiers/{tier_name}
+pub async fn create_plan_action_transaction(
+    Extension(state): Extension<AppState>,
+    Path((plan_pda, tier_name)): Path<(String, String)>,
+    Query(query): Query<CheckoutQuery>,
+    Json(body): Json<CheckoutAccount>,
+) -> ActionResult<ActionPostResponse> {
+    let checkout = load_checkout(&state, &plan_pda, &query)
+        .await
+        .map_err(action_error)?;
+    let tier = find_tier_by_name(&checkout.tiers, &tier_name)
+        .map_err(|e| action_error((StatusCode::NOT_FOUND, e.to_string())))?;
+    let (transaction, message) = checkout
+        .transaction(&state, tier, &body.account, &query)
+        .await
+        .map_err(action_error)?;
+
+    Ok((
+        action_headers(&state).await.map_err(action_error)?,
+        Json(ActionPostResponse {
+            kind: "transaction",
+            transaction,
+            message,
+        }),
+    ))
+}
+
+/// GET /actions.json
+/// Served from the site root so blink clients can resolve plan links.
+pub async fn get_actions_manifest() -> Json<ActionsManifest> {
+    Json(ActionsManifest {
+        rules: vec![ActionRule {
+            path_pattern: "/plans/*".to_string(),
+            api_path: "/api/actions/plans/*".to_string(),
+        }],
+    })
+}
@@ -0,0 +1,185 @@
+use crate::models::coupon::{Coupon, CouponRedemption, RegisterCoupon};
+use crate::state::AppState;
+use crate::types::{self, Discount};
+use axum::{
+    Json,
+    extract::{Extension, Path},
+    http::StatusCode,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("DB error: {}", e),
+    )
+}
+
+fn rpc_error(e: anyhow::Error) -> (StatusCode, String) {
+    (StatusCode::BAD_GATEWAY, format!("RPC error: {}", e))
+}
+
+fn parse_pubkey(value: &str, what: &str) -> Result<Pubkey, (StatusCode, String)> {
+    Pubkey::from_str(value).map_err(|_| (StatusCode::BAD_REQUEST, format!("Invalid {}", what)))
+}
+
+/// Copies the on-chain coupon into the database, returning `None` if it does not exist.
+pub async fn sync_coupon(state: &AppState, coupon_pda: Pubkey) -> anyhow::Result<Option<Coupon>> {
+    let Some(account) = state.solana.get_coupon(&coupon_pda).await? else {
+        return Ok(None);
+    };
+    let types::Coupon {
+        plan,
+        code,
+        discount,
+        duration_cycles,
+        max_redemptions,
+        redemptions,
+        expires_at,
+        tier_name,
+        active,
+        ..
+    } = account;
+
+    let (discount_type, discount_value) = match discount {
+        Discount::Percent(bps) => ("percent", bps as i64),
+        Discount::Fixed(amount) => ("fixed", amount as i64),
+    };
+
+    let coupon = sqlx::query_as!(
+        Coupon,
+        r#"
+        INSERT INTO coupons (
+            coupon_pda, plan_pda, code, discount_type, discount_value,
+            duration_cycles, max_redemptions, redemptions, expires_at, tier_name, active
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ON CONFLICT (coupon_pda) DO UPDATE SET
+            redemptions = EXCLUDED.redemptions,
+            active = EXCLUDED.active,
+            updated_at = now()
+        RETURNING
+            coupon_pda, plan_pda, code, discount_type, discount_value,
+            duration_cycles, max_redemptions, redemptions, expires_at, tier_name, active,
+            created_at, updated_at
+        "#,
+        coupon_pda.to_string(),
+        plan.to_string(),
+        code,
+        discount_type,
+        discount_value,
+        duration_cycles.map(|c| c as i64),
+        max_redemptions.map(|m| m as i64),
+        redemptions as i64,
+        expires_at,
+        tier_name,
+        active
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok(Some(coupon))
+}
+
+async fn load_coupon(state: &AppState, coupon_pda: Pubkey) -> Result<Coupon, (StatusCode, String)> {
+    sync_coupon(state, coupon_pda)
+        .await
+        .map_err(rpc_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Coupon not found".to_string()))
+}
+
+/// POST /coupons
+/// Registers a coupon the merchant has created on-chain.
+pub async fn register_coupon(
+    Extension(state): Extension<AppState>,
+    Json(payload): Json<RegisterCoupon>,
+) -> Result<(StatusCode, Json<Coupon>), (StatusCode, String)> {
+    let coupon_pda = parse_pubkey(&payload.coupon_pda, "coupon PDA")?;
+    let coupon = load_coupon(&state, coupon_pda).await?;
+    Ok((StatusCode::CREATED, Json(coupon)))
+}
+
+/// GET /coupons/{coupon_pda}
+/// Refreshed from chain, so `redemptions` and `active` are current.
+pub async fn get_coupon(
+    Extension(state): Extension<AppState>,
+    Path(coupon_pda): Path<String>,
+) -> Result<Json<Coupon>, (StatusCode, String)> {
+    let coupon_pda = parse_pubkey(&coupon_pda, "coupon PDA")?;
+    load_coupon(&state, coupon_pda).await.map(Json)
+}
+
+/// GET /coupons/plan/{plan_pda}/code/{code}
+/// Lets a subscriber check a code before applying it.
+pub async fn get_coupon_by_code(
+    Extension(state): Extension<AppState>,
+    Path((plan_pda, code)): Path<(String, String)>,
+) -> Result<Json<Coupon>, (StatusCode, String)> {
+    let plan_pda = parse_pubkey(&plan_pda, "plan PDA")?;
+    let coupon_pda = state.solana.coupon_pda(&plan_pda, code.trim());
+    load_coupon(&state, coupon_pda).await.map(Json)
+}
+
+/// GET /coupons/plan/{plan_pda}
+pub async fn get_plan_coupons(
+    Extension(state): Extension<AppState>,
+    Path(plan_pda): Path<String>,
+) -> Result<Json<Vec<Coupon>>, (StatusCode, String)> {
+    let coupons = sqlx::query_as!(
+        Coupon,
+        r#"
+        SELECT
+            coupon_pda, plan_pda, code, discount_type, discount_value,
+            duration_cycles, max_redemptions, redemptions, expires_at, tier_name, active,
+            created_at, updated_at
+        FROM coupons
+        WHERE plan_pda = $1
+        ORDER BY created_at DESC
+        "#,
+        plan_pda
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(coupons))
+}
+
+/// POST /coupons/{coupon_pda}/redemptions
+/// Records a coupon applied to an existing subscription, once it shows on-chain.
+pub async fn record_coupon_redemption(
+    Extension(state): Extension<AppState>,
+    Path(coupon_pda): Path<String>,
+    Json(payload): Json<CouponRedemption>,
+) -> Result<Json<Coupon>, (StatusCode, String)> {
+    let coupon_key = parse_pubkey(&coupon_pda, "coupon PDA")?;
+    let subscription_key = parse_pubkey(&payload.subscription_pda, "subscription PDA")?;
+
+    let account = state
+        .solana
+        .get_subscription_account(&subscription_key)
+        .await
+        .map_err(rpc_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Subscription not found".to_string()))?;
+    if account.coupon != Some(coupon_key) {
+        return Err((
+            StatusCode::CONFLICT,
+            "Coupon is not applied to this subscription on-chain".into(),
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET coupon_pda = $1
+        WHERE subscription_pda = $2
+        "#,
+        coupon_pda,
+        payload.subscription_pda
+    )
+    .execute(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    load_coupon(&state, coupon_key).await.map(Json)
+}
@@ -0,0 +1,253 @@
+use crate::auth::{WalletAuth, verify_wallet_auth};
+use crate::email::{VERIFICATION_TTL_HOURS, enqueue_email, render_verification};
+use crate::models::email::{EmailPreferences, EmailSettings, RegisterEmail, VerifyEmail};
+use crate::state::AppState;
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Every email route acts on the wallet in its path, so the wallet must sign for it.
+fn authorize(user_pubkey: &str, auth: &WalletAuth) -> Result<(), Response> {
+    verify_wallet_auth("email", user_pubkey, auth)
+        .map_err(|(status, error)| (status, Json(json!({ "error": error }))).into_response())
+}
+
+/// GET /email/{user_pubkey}
+pub async fn get_email_settings(
+    Extension(state): Extension<AppState>,
+    Path(user_pubkey): Path<String>,
+    Query(auth): Query<WalletAuth>,
+) -> Result<Json<EmailSettings>, Response> {
+    authorize(&user_pubkey, &auth)?;
+    let settings = sqlx::query_as!(
+        EmailSettings,
+        r#"
+        SELECT
+            user_pubkey,
+            email,
+            verified,
+            notify_renewal_success,
+            notify_renewal_failure,
+            notify_expiry,
+            notify_upcoming_charge,
+            created_at,
+            updated_at
+        FROM email_settings
+        WHERE user_pubkey = $1
+        "#,
+        user_pubkey
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+
+    settings
+        .map(Json)
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())
+}
+
+/// PUT /email/{user_pubkey}
+/// Registers (or replaces) the user's address and sends a verification link.
+pub async fn register_email(
+    Extension(state): Extension<AppState>,
+    Path(user_pubkey): Path<String>,
+    Query(auth): Query<WalletAuth>,
+    Json(payload): Json<RegisterEmail>,
+) -> impl IntoResponse {
+    if let Err(response) = authorize(&user_pubkey, &auth) {
+        return response;
+    }
+    if payload.validate().is_err() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "Invalid email address" })),
+        )
+            .into_response();
+    }
+
+    let token = Uuid::new_v4();
+    let expires_at = Utc::now() + Duration::hours(VERIFICATION_TTL_HOURS);
+
+    // Changing the address always resets verification
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO email_settings (
+            user_pubkey,
+            email,
+            verified,
+            verification_token,
+            verification_expires_at
+        )
+        VALUES ($1, $2, false, $3, $4)
+        ON CONFLICT (user_pubkey) DO UPDATE
+        SET email = EXCLUDED.email,
+            verified = false,
+            verification_token = EXCLUDED.verification_token,
+            verification_expires_at = EXCLUDED.verification_expires_at,
+            updated_at = now()
+        "#,
+        user_pubkey,
+        payload.email,
+        token,
+        expires_at
+    )
+    .execute(&state.db)
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("Failed to register email: {:?}", e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "Database error" })),
+        )
+            .into_response();
+    }
+
+    let rendered = render_verification(&state.mailer.verify_url, token);
+    if let Err(e) = enqueue_email(&state.db, &user_pubkey, &payload.email, &rendered).await {
+        tracing::error!("Failed to queue verification email: {:?}", e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "Failed to send verification email" })),
+        )
+            .into_response();
+    }
+
+    (
+        StatusCode::ACCEPTED,
+        Json(json!({ "message": "Verification email sent" })),
+    )
+        .into_response()
+}
+
+/// POST /email/verify
+pub async fn verify_email(
+    Extension(state): Extension<AppState>,
+    Json(payload): Json<VerifyEmail>,
+) -> impl IntoResponse {
+    let result = sqlx::query!(
+        r#"
+        UPDATE email_settings
+        SET verified = true,
+            verification_token = NULL,
+            verification_expires_at = NULL,
+            updated_at = now()
+        WHERE verification_token = $1
+          AND verification_expires_at > now()
+        "#,
+        payload.token
+    )
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 1 => {
+            (StatusCode::OK, Json(json!({ "message": "Email verified" }))).into_response()
+        }
+        Ok(_) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "Invalid or expired verification token" })),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to verify email: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Database error" })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// PUT /email/{user_pubkey}/preferences
+pub async fn update_email_preferences(
+    Extension(state): Extension<AppState>,
+    Path(user_pubkey): Path<String>,
+    Query(auth): Query<WalletAuth>,
+    Json(payload): Json<EmailPreferences>,
+) -> impl IntoResponse {
+    if let Err(response) = authorize(&user_pubkey, &auth) {
+        return response;
+    }
+    let result = sqlx::query!(
+        r#"
+        UPDATE email_settings
+        SET notify_renewal_success = $1,
+            notify_renewal_failure = $2,
+            notify_expiry = $3,
+            notify_upcoming_charge = $4,
+            updated_at = now()
+        WHERE user_pubkey = $5
+        "#,
+        payload.renewal_success,
+        payload.renewal_failure,
+        payload.expiry,
+        payload.upcoming_charge,
+        user_pubkey
+    )
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "No email registered for this wallet" })),
+        )
+            .into_response(),
+        Ok(_) => (
+            StatusCode::OK,
+            Json(json!({ "message": "Email preferences updated" })),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to update email preferences: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Database error" })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// DELETE /email/{user_pubkey}
+pub async fn delete_email(
+    Extension(state): Extension<AppState>,
+    Path(user_pubkey): Path<String>,
+    Query(auth): Query<WalletAuth>,
+) -> impl IntoResponse {
+    if let Err(response) = authorize(&user_pubkey, &auth) {
+        return response;
+    }
+    let result = sqlx::query!(
+        "DELETE FROM email_settings WHERE user_pubkey = $1",
+        user_pubkey
+    )
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "No email registered for this wallet" })),
+        )
+            .into_response(),
+        Ok(_) => (StatusCode::OK, Json(json!({ "message": "Email removed" }))).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to delete email settings: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Database error" })),
+            )
+                .into_response()
+        }
+    }
+}
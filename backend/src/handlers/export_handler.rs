@@ -0,0 +1,282 @@
+use crate::models::transaction::{ExportFormat, ExportQuery, ExportRow};
+use crate::state::AppState;
+use crate::utils::format_token_amount;
+use axum::{
+    body::{Body, Bytes},
+    extract::{Extension, Path, Query},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use tokio::sync::mpsc;
+use tokio_stream::{StreamExt, wrappers::ReceiverStream};
+
+/// Mint details resolved once per plan and mint while an export runs.
+#[derive(Clone)]
+struct PlanToken {
+    mint: String,
+    decimals: u8,
+    symbol: String,
+}
+
+enum ExportScope {
+    Wallet(String),
+    Plan(String),
+}
+
+/// GET /exports/transactions/user/{user_pubkey}?format=csv|jsonl|quickbooks&from=&to=
+pub async fn export_wallet_transactions(
+    Extension(state): Extension<AppState>,
+    Path(user_pubkey): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    stream_export(state, ExportScope::Wallet(user_pubkey), query)
+}
+
+/// GET /exports/transactions/plan/{plan_pda}?format=csv|jsonl|quickbooks&from=&to=
+/// Every subscriber payment into the plan, one row per payment.
+pub async fn export_plan_transactions(
+    Extension(state): Extension<AppState>,
+    Path(plan_pda): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    stream_export(state, ExportScope::Plan(plan_pda), query)
+}
+
+fn stream_export(state: AppState, scope: ExportScope, query: ExportQuery) -> Response {
+    let subject = match &scope {
+        ExportScope::Wallet(key) | ExportScope::Plan(key) => key.clone(),
+    };
+    let (extension, content_type) = match query.format {
+        ExportFormat::Csv => ("csv", "text/csv; charset=utf-8"),
+        ExportFormat::Jsonl => ("jsonl", "application/x-ndjson"),
+        ExportFormat::Quickbooks => ("quickbooks.csv", "text/csv; charset=utf-8"),
+    };
+    let filename = format!("solpay-{}.{}", subject, extension);
+
+    // Rows are written by a background task so large histories never sit in memory
+    let (tx, rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(64);
+    tokio::spawn(async move {
+        if let Err(e) = write_export(&state, scope, &query, &tx).await {
+            tracing::error!("Export failed: {:?}", e);
+            let _ = tx.send(Err(std::io::Error::other(e.to_string()))).await;
+        }
+    });
+
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response()
+}
+
+async fn write_export(
+    state: &AppState,
+    scope: ExportScope,
+    query: &ExportQuery,
+    tx: &mpsc::Sender<Result<Bytes, std::io::Error>>,
+) -> anyhow::Result<()> {
+    let (user_pubkey, plan_pda) = match scope {
+        ExportScope::Wallet(w) => (Some(w), None),
+        ExportScope::Plan(p) => (None, Some(p)),
+    };
+
+    // The subscription's mint prices the row; NULL means the plan's primary mint
+    let mut rows = sqlx::query!(
+        r#"
+        SELECT
+            ph.user_pubkey,
+            ph.plan,
+            ph.tier,
+            ph.amount,
+            ph.status,
+            ph.tx_signature,
+            ph.subscription_pda,
+            ph.plan_pda,
+            ph.created_at,
+            ph.direction,
+            ph.usage_units,
+            ph.usage_amount,
+            s.mint AS "mint?"
+        FROM payment_history ph
+        LEFT JOIN subscriptions s ON s.subscription_pda = ph.subscription_pda
+        WHERE ($1::text IS NULL OR ph.user_pubkey = $1)
+          AND ($2::text IS NULL OR (ph.plan_pda = $2 AND ph.direction = 'paid'))
+          AND ($3::timestamptz IS NULL OR ph.created_at >= $3)
+          AND ($4::timestamptz IS NULL OR ph.created_at < $4)
+        ORDER BY ph.created_at ASC, ph.id ASC
+        "#,
+        user_pubkey,
+        plan_pda,
+        query.from,
+        query.to
+    )
+    .fetch(&state.db);
+
+    if let Some(header) = export_header(query.format) {
+        send(tx, header).await?;
+    }
+
+    let mut tokens: HashMap<(String, Option<String>), Option<PlanToken>> = HashMap::new();
+
+    while let Some(record) = rows.next().await {
+        let record = record?;
+        let token = match &record.plan_pda {
+            Some(plan_pda) => {
+                let key = (plan_pda.clone(), record.mint.clone());
+                if !tokens.contains_key(&key) {
+                    let resolved =
+                        resolve_plan_token(state, plan_pda, record.mint.as_deref()).await;
+                    tokens.insert(key.clone(), resolved);
+                }
+                tokens.get(&key).cloned().flatten()
+            }
+            None => None,
+        };
+
+        let format_amount = |raw: i64| match &token {
+            Some(t) => format_token_amount(raw as u64, t.decimals),
+            None => raw.to_string(),
+        };
+
+        let row = ExportRow {
+            date: record.created_at,
+            amount: format_amount(record.amount),
+            usage_units: record.usage_units,
+            usage_amount: record.usage_amount.map(format_amount),
+            raw_amount: record.amount,
+            token_symbol: token.as_ref().map(|t| t.symbol.clone()).unwrap_or_default(),
+            mint: token.map(|t| t.mint),
+            tx_signature: record.tx_signature,
+            direction: record.direction,
+            status: record.status,
+            wallet: record.user_pubkey,
+            plan: record.plan,
+            plan_pda: record.plan_pda,
+            tier: record.tier,
+            subscription_pda: record.subscription_pda,
+        };
+
+        send(tx, format_row(query.format, &row)?).await?;
+    }
+
+    Ok(())
+}
+
+/// Decimals and symbol of the mint a row was paid in: the subscription's own mint,
+/// or the plan's primary mint when it has none.
+async fn resolve_plan_token(
+    state: &AppState,
+    plan_pda: &str,
+    mint: Option<&str>,
+) -> Option<PlanToken> {
+    let plan_key = Pubkey::from_str(plan_pda).ok()?;
+    let plan = match state.solana.get_plan(plan_key).await {
+        Ok(Some(plan)) => plan,
+        Ok(None) => return None,
+        Err(e) => {
+            tracing::warn!("Failed to load plan {} for export: {}", plan_pda, e);
+            return None;
+        }
+    };
+    let mint = match mint {
+        Some(mint) => Pubkey::from_str(mint).ok()?,
+        None => plan.mint,
+    };
+    let decimals = state.solana.get_mint_decimals(&mint).await.ok()?;
+
+    Some(PlanToken {
+        mint: mint.to_string(),
+        decimals,
+        symbol: plan.token_symbol_for(&mint).to_string(),
+    })
+}
+
+async fn send(
+    tx: &mpsc::Sender<Result<Bytes, std::io::Error>>,
+    chunk: String,
+) -> anyhow::Result<()> {
+    tx.send(Ok(Bytes::from(chunk)))
+        .await
+        .map_err(|_| anyhow::anyhow!("Client disconnected"))
+}
+
+fn export_header(format: ExportFormat) -> Option<String> {
+    match format {
+        ExportFormat::Csv => Some(
+            "date,tx_signature,direction,status,wallet,plan,plan_pda,tier,subscription_pda,amount,token_symbol,mint,raw_amount,usage_units,usage_amount\n"
+                .to_string(),
+        ),
+        ExportFormat::Quickbooks => Some("Date,Description,Amount\n".to_string()),
+        ExportFormat::Jsonl => None,
+    }
+}
+
+fn format_row(format: ExportFormat, row: &ExportRow) -> anyhow::Result<String> {
+    Ok(match format {
+        ExportFormat::Csv => {
+            let fields = [
+                row.date.to_rfc3339(),
+                row.tx_signature.clone().unwrap_or_default(),
+                row.direction.clone(),
+                row.status.clone(),
+                row.wallet.clone(),
+                row.plan.clone(),
+                row.plan_pda.clone().unwrap_or_default(),
+                row.tier.clone(),
+                row.subscription_pda.clone(),
+                row.amount.clone(),
+                row.token_symbol.clone(),
+                row.mint.clone().unwrap_or_default(),
+                row.raw_amount.to_string(),
+                row.usage_units.map(|u| u.to_string()).unwrap_or_default(),
+                row.usage_amount.clone().unwrap_or_default(),
+            ];
+            csv_line(&fields)
+        }
+        ExportFormat::Jsonl => format!("{}\n", serde_json::to_string(row)?),
+        // A bank register only lists money that actually moved
+        ExportFormat::Quickbooks if !matches!(row.status.as_str(), "success" | "refunded") => {
+            String::new()
+        }
+        ExportFormat::Quickbooks => {
+            // Money leaving the wallet is negative in a bank register
+            let sign = if row.direction == "paid" { "-" } else { "" };
+            let description = format!(
+                "{} {} ({}) {}",
+                row.plan,
+                row.tier,
+                row.token_symbol,
+                row.tx_signature.as_deref().unwrap_or("")
+            );
+            csv_line(&[
+                row.date.format("%m/%d/%Y").to_string(),
+                description.trim_end().to_string(),
+                format!("{}{}", sign, row.amount),
+            ])
+        }
+    })
+}
+
+fn csv_line(fields: &[String]) -> String {
+    let escaped: Vec<String> = fields
+        .iter()
+        .map(|f| {
+            if f.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.clone()
+            }
+        })
+        .collect();
+    format!("{}\n", escaped.join(","))
+}
@@ -0,0 +1,514 @@
+use crate::auth::{WalletAuth, verify_wallet_auth};
+use crate::models::invoice::{
+    Invoice, InvoicePage, InvoiceQuery, MerchantProfile, NewInvoice, UpdateMerchantProfile,
+};
+use crate::state::AppState;
+use crate::utils::{decode_cursor, encode_cursor, escape_html, format_token_amount};
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::Html,
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+const MAX_PREFIX_LEN: usize = 12;
+
+/// Issues the invoice for a successful payment, taking the merchant's next number.
+/// Calling it again for the same payment returns the invoice already issued.
+pub async fn issue_invoice(state: &AppState, new: &NewInvoice) -> anyhow::Result<Invoice> {
+    if let Some(existing) = fetch_invoice_by_payment(&state.db, new.payment_id).await? {
+        return Ok(existing);
+    }
+
+    let decimals = state.solana.get_mint_decimals(&new.mint).await?;
+    let amount_ui = format_token_amount(new.amount.max(0) as u64, decimals);
+
+    let mut tx = state.db.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO merchant_profiles (merchant_pubkey)
+        VALUES ($1)
+        ON CONFLICT (merchant_pubkey) DO NOTHING
+        "#,
+        new.merchant_pubkey
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // The row lock held until commit keeps numbers gapless per merchant
+    let merchant = sqlx::query!(
+        r#"
+        UPDATE merchant_profiles
+        SET next_invoice_number = next_invoice_number + 1
+        WHERE merchant_pubkey = $1
+        RETURNING
+            next_invoice_number - 1 AS "invoice_number!",
+            invoice_prefix,
+            business_name,
+            address,
+            email,
+            tax_id
+        "#,
+        new.merchant_pubkey
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let invoice_code = format!("{}-{:06}", merchant.invoice_prefix, merchant.invoice_number);
+    let merchant_name = merchant
+        .business_name
+        .unwrap_or_else(|| new.plan_name.clone());
+
+    let invoice = sqlx::query_as!(
+        Invoice,
+        r#"
+        INSERT INTO invoices (
+            merchant_pubkey,
+            invoice_number,
+            invoice_code,
+            payment_id,
+            payer,
+            plan_name,
+            plan_pda,
+            tier,
+            period_start,
+            period_end,
+            amount,
+            amount_ui,
+            token_symbol,
+            mint,
+            tx_signature,
+            merchant_name,
+            merchant_address,
+            merchant_email,
+            merchant_tax_id
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)
+        RETURNING
+            id,
+            merchant_pubkey,
+            invoice_number,
+            invoice_code,
+            payment_id,
+            payer,
+            plan_name,
+            plan_pda,
+            tier,
+            period_start,
+            period_end,
+            amount,
+            amount_ui,
+            token_symbol,
+            mint,
+            tx_signature,
+            merchant_name,
+            merchant_address,
+            merchant_email,
+            merchant_tax_id,
+            issued_at
+        "#,
+        new.merchant_pubkey,
+        merchant.invoice_number,
+        invoice_code,
+        new.payment_id,
+        new.payer,
+        new.plan_name,
+        new.plan_pda,
+        new.tier,
+        new.period_start,
+        new.period_end,
+        new.amount,
+        amount_ui,
+        new.token_symbol,
+        new.mint.to_string(),
+        new.tx_signature,
+        merchant_name,
+        merchant.address,
+        merchant.email,
+        merchant.tax_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(invoice)
+}
+
+async fn fetch_invoice_by_payment(
+    db: &sqlx::PgPool,
+    payment_id: i64,
+) -> Result<Option<Invoice>, sqlx::Error> {
+    sqlx::query_as!(
+        Invoice,
+        r#"
+        SELECT
+            id,
+            merchant_pubkey,
+            invoice_number,
+            invoice_code,
+            payment_id,
+            payer,
+            plan_name,
+            plan_pda,
+            tier,
+            period_start,
+            period_end,
+            amount,
+            amount_ui,
+            token_symbol,
+            mint,
+            tx_signature,
+            merchant_name,
+            merchant_address,
+            merchant_email,
+            merchant_tax_id,
+            issued_at
+        FROM invoices
+        WHERE payment_id = $1
+        "#,
+        payment_id
+    )
+    .fetch_optional(db)
+    .await
+}
+
+async fn fetch_invoice(db: &sqlx::PgPool, invoice_id: i64) -> Result<Option<Invoice>, sqlx::Error> {
+    sqlx::query_as!(
+        Invoice,
+        r#"
+        SELECT
+            id,
+            merchant_pubkey,
+            invoice_number,
+            invoice_code,
+            payment_id,
+            payer,
+            plan_name,
+            plan_pda,
+            tier,
+            period_start,
+            period_end,
+            amount,
+            amount_ui,
+            token_symbol,
+            mint,
+            tx_signature,
+            merchant_name,
+            merchant_address,
+            merchant_email,
+            merchant_tax_id,
+            issued_at
+        FROM invoices
+        WHERE id = $1
+        "#,
+        invoice_id
+    )
+    .fetch_optional(db)
+    .await
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("DB error: {}", e),
+    )
+}
+
+/// GET /invoices/{invoice_id}
+pub async fn get_invoice(
+    Extension(state): Extension<AppState>,
+    Path(invoice_id): Path<i64>,
+) -> Result<Json<Invoice>, (StatusCode, String)> {
+    fetch_invoice(&state.db, invoice_id)
+        .await
+        .map_err(db_error)?
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "Invoice not found".to_string()))
+}
+
+/// GET /invoices/payment/{payment_id}
+pub async fn get_invoice_for_payment(
+    Extension(state): Extension<AppState>,
+    Path(payment_id): Path<i64>,
+) -> Result<Json<Invoice>, (StatusCode, String)> {
+    fetch_invoice_by_payment(&state.db, payment_id)
+        .await
+        .map_err(db_error)?
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "Invoice not found".to_string()))
+}
+
+/// GET /invoices/{invoice_id}/receipt
+/// Printable HTML receipt; browsers can save it as PDF.
+pub async fn get_invoice_receipt(
+    Extension(state): Extension<AppState>,
+    Path(invoice_id): Path<i64>,
+) -> Result<Html<String>, (StatusCode, String)> {
+    let invoice = fetch_invoice(&state.db, invoice_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Invoice not found".to_string()))?;
+
+    Ok(Html(render_receipt(&invoice)))
+}
+
+/// GET /invoices/payer/{payer}?cursor=&limit=
+pub async fn get_payer_invoices(
+    Extension(state): Extension<AppState>,
+    Path(payer): Path<String>,
+    Query(query): Query<InvoiceQuery>,
+) -> Result<Json<InvoicePage>, (StatusCode, String)> {
+    list_invoices(&state.db, Some(&payer), None, &query)
+        .await
+        .map(Json)
+}
+
+/// GET /invoices/merchant/{merchant_pubkey}?cursor=&limit=
+pub async fn get_merchant_invoices(
+    Extension(state): Extension<AppState>,
+    Path(merchant_pubkey): Path<String>,
+    Query(query): Query<InvoiceQuery>,
+) -> Result<Json<InvoicePage>, (StatusCode, String)> {
+    list_invoices(&state.db, None, Some(&merchant_pubkey), &query)
+        .await
+        .map(Json)
+}
+
+async fn list_invoices(
+    db: &sqlx::PgPool,
+    payer: Option<&str>,
+    merchant_pubkey: Option<&str>,
+    query: &InvoiceQuery,
+) -> Result<InvoicePage, (StatusCode, String)> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let (cursor_ts, cursor_id) = match query.cursor.as_deref() {
+        Some(cursor) => {
+            let (ts, id) = decode_cursor::<i64>(cursor)
+                .ok_or((StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))?;
+            (Some(ts), Some(id))
+        }
+        None => (None, None),
+    };
+
+    // Fetch one extra row to learn whether another page exists
+    let mut items = sqlx::query_as!(
+        Invoice,
+        r#"
+        SELECT
+            id,
+            merchant_pubkey,
+            invoice_number,
+            invoice_code,
+            payment_id,
+            payer,
+            plan_name,
+            plan_pda,
+            tier,
+            period_start,
+            period_end,
+            amount,
+            amount_ui,
+            token_symbol,
+            mint,
+            tx_signature,
+            merchant_name,
+            merchant_address,
+            merchant_email,
+            merchant_tax_id,
+            issued_at
+        FROM invoices
+        WHERE ($1::text IS NULL OR payer = $1)
+          AND ($2::text IS NULL OR merchant_pubkey = $2)
+          AND ($3::timestamptz IS NULL OR (issued_at, id) < ($3, $4::bigint))
+        ORDER BY issued_at DESC, id DESC
+        LIMIT $5
+        "#,
+        payer,
+        merchant_pubkey,
+        cursor_ts,
+        cursor_id,
+        limit + 1
+    )
+    .fetch_all(db)
+    .await
+    .map_err(db_error)?;
+
+    let next_cursor = if items.len() as i64 > limit {
+        items.truncate(limit as usize);
+        items.last().map(|i| encode_cursor(i.issued_at, i.id))
+    } else {
+        None
+    };
+
+    Ok(InvoicePage { items, next_cursor })
+}
+
+/// GET /merchants/{merchant_pubkey}/profile
+pub async fn get_merchant_profile(
+    Extension(state): Extension<AppState>,
+    Path(merchant_pubkey): Path<String>,
+) -> Result<Json<MerchantProfile>, (StatusCode, String)> {
+    sqlx::query_as!(
+        MerchantProfile,
+        r#"
+        SELECT
+            merchant_pubkey,
+            business_name,
+            address,
+            email,
+            tax_id,
+            invoice_prefix,
+            next_invoice_number,
+            updated_at
+        FROM merchant_profiles
+        WHERE merchant_pubkey = $1
+        "#,
+        merchant_pubkey
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .map(Json)
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        "Merchant profile not found".to_string(),
+    ))
+}
+
+/// PUT /merchants/{merchant_pubkey}/profile?signature=&timestamp=
+/// Details apply to invoices issued from now on; earlier invoices keep their copy.
+pub async fn update_merchant_profile(
+    Extension(state): Extension<AppState>,
+    Path(merchant_pubkey): Path<String>,
+    Query(auth): Query<WalletAuth>,
+    Json(payload): Json<UpdateMerchantProfile>,
+) -> Result<Json<MerchantProfile>, (StatusCode, String)> {
+    verify_wallet_auth("merchant-profile", &merchant_pubkey, &auth)?;
+
+    if let Some(prefix) = payload.invoice_prefix.as_deref() {
+        let valid = !prefix.is_empty()
+            && prefix.len() <= MAX_PREFIX_LEN
+            && prefix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !valid {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Invoice prefix must be 1-12 letters, digits or dashes".to_string(),
+            ));
+        }
+    }
+
+    let profile = sqlx::query_as!(
+        MerchantProfile,
+        r#"
+        INSERT INTO merchant_profiles (
+            merchant_pubkey,
+            business_name,
+            address,
+            email,
+            tax_id,
+            invoice_prefix
+        )
+        VALUES ($1, $2, $3, $4, $5, COALESCE($6, 'INV'))
+        ON CONFLICT (merchant_pubkey) DO UPDATE
+        SET business_name = EXCLUDED.business_name,
+            address = EXCLUDED.address,
+            email = EXCLUDED.email,
+            tax_id = EXCLUDED.tax_id,
+            invoice_prefix = COALESCE($6, merchant_profiles.invoice_prefix),
+            updated_at = now()
+        RETURNING
+            merchant_pubkey,
+            business_name,
+            address,
+            email,
+            tax_id,
+            invoice_prefix,
+            next_invoice_number,
+            updated_at
+        "#,
+        merchant_pubkey,
+        payload.business_name,
+        payload.address,
+        payload.email,
+        payload.tax_id,
+        payload.invoice_prefix
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(profile))
+}
+
+fn render_receipt(invoice: &Invoice) -> String {
+    let optional_line = |value: &Option<String>| match value {
+        Some(v) => format!("<div>{}</div>", escape_html(v)),
+        None => String::new(),
+    };
+    let tax_id = match &invoice.merchant_tax_id {
+        Some(v) => format!("<div>Tax ID: {}</div>", escape_html(v)),
+        None => String::new(),
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <title>Receipt {code}</title>
+    <style>
+      body {{ font-family: sans-serif; color: #111; max-width: 640px; margin: 40px auto; }}
+      table {{ width: 100%; border-collapse: collapse; margin-top: 24px; }}
+      td {{ padding: 8px 0; border-bottom: 1px solid #eee; vertical-align: top; }}
+      td:last-child {{ text-align: right; word-break: break-all; }}
+      .muted {{ color: #666; font-size: 13px; }}
+    </style>
+  </head>
+  <body>
+    <h2>{merchant_name}</h2>
+    <div class="muted">
+      {address}{email}{tax_id}
+      <div>{merchant_pubkey}</div>
+    </div>
+    <h3>Receipt {code}</h3>
+    <div class="muted">Issued {issued_at}</div>
+    <table>
+      <tr><td>Plan</td><td>{plan_name}</td></tr>
+      <tr><td>Tier</td><td>{tier}</td></tr>
+      <tr><td>Period</td><td>{period_start} – {period_end}</td></tr>
+      <tr><td>Amount</td><td><strong>{amount} {token}</strong></td></tr>
+      <tr><td>Token mint</td><td>{mint}</td></tr>
+      <tr><td>Paid by</td><td>{payer}</td></tr>
+      <tr><td>Transaction</td><td>{tx_signature}</td></tr>
+    </table>
+  </body>
+</html>
+"#,
+        code = escape_html(&invoice.invoice_code),
+        merchant_name = escape_html(&invoice.merchant_name),
+        address = optional_line(&invoice.merchant_address),
+        email = optional_line(&invoice.merchant_email),
+        tax_id = tax_id,
+        merchant_pubkey = escape_html(&invoice.merchant_pubkey),
+        issued_at = invoice.issued_at.format("%Y-%m-%d %H:%M UTC"),
+        plan_name = escape_html(&invoice.plan_name),
+        tier = escape_html(&invoice.tier),
+        period_start = invoice.period_start.format("%Y-%m-%d"),
+        period_end = invoice.period_end.format("%Y-%m-%d"),
+        amount = escape_html(&invoice.amount_ui),
+        token = escape_html(&invoice.token_symbol),
+        mint = escape_html(&invoice.mint),
+        payer = escape_html(&invoice.payer),
+        tx_signature = escape_html(invoice.tx_signature.as_deref().unwrap_or("—")),
+    )
+}
@@ -1,4 +1,12 @@
+pub mod analytics_handler;
+pub mod builder_handler;
+pub mod checkout_handler;
+pub mod coupon_handler;
+pub mod email_handler;
+pub mod export_handler;
+pub mod invoice_handler;
 pub mod notification_handler;
+pub mod relay_handler;
 pub mod subscription_handler;
 pub mod transaction_handler;
 pub mod user_handler;
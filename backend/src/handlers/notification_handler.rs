@@ -1,12 +1,56 @@
-use crate::models::notification::Notification;
+use crate::auth::{WalletAuth, verify_wallet_auth};
+use crate::email::enqueue_notification_email;
+use crate::models::notification::{
+    BulkDeleteNotifications, MutePlan, Notification, NotificationIds, NotificationKind,
+    NotificationMute, NotificationPage, NotificationQuery, NotificationStreamSession,
+};
 use crate::state::AppState;
-use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use crate::utils::{decode_cursor, encode_cursor};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query},
+    http::HeaderMap,
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
 use hyper::StatusCode;
+use serde::Deserialize;
 use serde_json::json;
 use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
 use uuid::Uuid;
 
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+/// How long a stream session lets an EventSource reconnect without a new signature.
+const STREAM_SESSION_SECS: i64 = 12 * 60 * 60;
+
+/// Changes to a wallet's notifications must be signed by that wallet.
+fn authorize(
+    user_pubkey: &str,
+    auth: &WalletAuth,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    verify_wallet_auth("notifications", user_pubkey, auth)
+        .map_err(|(status, error)| (status, Json(json!({ "error": error }))))
+}
+
+/// Names the wallet that owns a notification addressed by id.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationOwner {
+    pub user_pubkey: String,
+}
+
 pub async fn create_notification(db: &PgPool, notification: &Notification) -> anyhow::Result<()> {
+    if let Some(plan_pda) = &notification.plan_pda {
+        if is_plan_muted(db, &notification.user_pubkey, plan_pda).await? {
+            return Ok(());
+        }
+    }
+
     sqlx::query!(
         r#"
         INSERT INTO notifications (
@@ -14,69 +58,218 @@ pub async fn create_notification(db: &PgPool, notification: &Notification) -> an
             subscription_pda,
             title,
             plan_name,
+            plan_pda,
             tier,
             message,
             is_read,
-            type
+            type,
+            expires_at
         )
-        VALUES ($1, $2, $3, $4,$5,$6,$7,$8)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
         "#,
         notification.user_pubkey,
         notification.subscription_pda,
         notification.title,
         notification.plan_name,
+        notification.plan_pda,
         notification.tier,
         notification.message,
         notification.is_read,
-        notification.r#type,
+        notification.r#type as NotificationKind,
+        notification.expires_at,
     )
     .execute(db)
     .await?;
 
+    // Email is best-effort: a failed enqueue must not lose the in-app notification
+    if let Err(e) = enqueue_notification_email(db, notification).await {
+        tracing::warn!(
+            "Failed to queue notification email for {}: {:?}",
+            notification.user_pubkey,
+            e
+        );
+    }
+
     Ok(())
 }
 
+async fn is_plan_muted(db: &PgPool, user_pubkey: &str, plan_pda: &str) -> anyhow::Result<bool> {
+    let muted = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM notification_mutes
+            WHERE user_pubkey = $1
+              AND plan_pda = $2
+              AND (muted_until IS NULL OR muted_until > now())
+        ) AS "muted!"
+        "#,
+        user_pubkey,
+        plan_pda
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(muted)
+}
+
+async fn count_unread(db: &PgPool, user_pubkey: &str) -> Result<HashMap<String, i64>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT type as "type: NotificationKind", COUNT(*) as "count!"
+        FROM notifications
+        WHERE user_pubkey = $1
+          AND is_read = false
+          AND (expires_at IS NULL OR expires_at > now())
+        GROUP BY type
+        "#,
+        user_pubkey
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| (r.r#type.as_str().to_string(), r.count))
+        .collect())
+}
+
+/// GET /notifications/user/{user_pubkey}?cursor=&limit=&unread=&type=&plan=
+/// Newest first; pass `nextCursor` back as `cursor` for the following page.
 pub async fn get_notifications(
     Path(user_pubkey): Path<String>,
+    Query(query): Query<NotificationQuery>,
     Extension(state): Extension<AppState>,
-) -> Result<Json<Vec<Notification>>, axum::http::StatusCode> {
-    let records = sqlx::query_as!(
+) -> Result<Json<NotificationPage>, (StatusCode, String)> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let (cursor_ts, cursor_id) = match query.cursor.as_deref() {
+        Some(cursor) => {
+            let (ts, id) = decode_cursor::<Uuid>(cursor)
+                .ok_or((StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))?;
+            (Some(ts), Some(id))
+        }
+        None => (None, None),
+    };
+
+    let db_error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {}", e),
+        )
+    };
+
+    // Fetch one extra row to learn whether another page exists
+    let mut records = sqlx::query_as!(
         Notification,
         r#"
         SELECT
             id,
             user_pubkey,
             plan_name,
+            plan_pda,
             tier,
-            subscription_pda, 
+            subscription_pda,
             title,
             message,
             created_at,
+            expires_at,
             is_read,
-            type as "type"
+            type as "type: NotificationKind"
         FROM notifications
         WHERE user_pubkey = $1
-        ORDER BY created_at DESC
+          AND (expires_at IS NULL OR expires_at > now())
+          AND (NOT $2 OR is_read = false)
+          AND ($3::notification_kind IS NULL OR type = $3)
+          AND ($4::text IS NULL OR plan_pda = $4)
+          AND ($5::timestamptz IS NULL OR (created_at, id) < ($5, $6::uuid))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $7
         "#,
-        user_pubkey
+        user_pubkey,
+        query.unread,
+        query.r#type as Option<NotificationKind>,
+        query.plan,
+        cursor_ts,
+        cursor_id,
+        limit + 1
     )
     .fetch_all(&state.db)
     .await
-    .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(Json(records))
+    .map_err(db_error)?;
+
+    let next_cursor = if records.len() as i64 > limit {
+        records.truncate(limit as usize);
+        records
+            .last()
+            .and_then(|n| Some(encode_cursor(n.created_at?, n.id?)))
+    } else {
+        None
+    };
+
+    let unread_count = count_unread(&state.db, &user_pubkey)
+        .await
+        .map_err(db_error)?
+        .values()
+        .sum();
+
+    Ok(Json(NotificationPage {
+        items: records,
+        next_cursor,
+        unread_count,
+    }))
+}
+
+/// GET /notifications/user/{user_pubkey}/unread
+pub async fn get_unread_count(
+    Path(user_pubkey): Path<String>,
+    Extension(state): Extension<AppState>,
+) -> impl IntoResponse {
+    match count_unread(&state.db, &user_pubkey).await {
+        Ok(by_type) => {
+            let total: i64 = by_type.values().sum();
+            (
+                StatusCode::OK,
+                Json(json!({
+                    "unreadCount": total,
+                    "byType": by_type
+                })),
+            )
+        }
+        Err(e) => {
+            tracing::error!("Failed to count unread notifications: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Database error" })),
+            )
+        }
+    }
 }
 
+/// PUT /notifications/read/{user_pubkey}
+/// Marks the given `ids` as read, or every unread notification when no body is sent.
 pub async fn mark_notifications_as_read(
     Path(user_pubkey): Path<String>,
+    Query(auth): Query<WalletAuth>,
     Extension(state): Extension<AppState>,
+    payload: Option<Json<NotificationIds>>,
 ) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&user_pubkey, &auth) {
+        return rejection;
+    }
+    let ids = payload.map(|Json(p)| p.ids);
     let result = sqlx::query!(
         r#"
         UPDATE notifications 
         SET is_read = true 
-        WHERE user_pubkey = $1 AND is_read = false
+        WHERE user_pubkey = $1
+          AND is_read = false
+          AND ($2::uuid[] IS NULL OR id = ANY($2))
         "#,
-        user_pubkey
+        user_pubkey,
+        ids.as_deref()
     )
     .execute(&state.db)
     .await;
@@ -103,10 +296,16 @@ pub async fn mark_notifications_as_read(
     }
 }
 
+/// DELETE /notifications/{notification_id}?userPubkey=
 pub async fn delete_notification(
     Path(notification_id): Path<String>,
+    Query(owner): Query<NotificationOwner>,
+    Query(auth): Query<WalletAuth>,
     Extension(state): Extension<AppState>,
 ) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&owner.user_pubkey, &auth) {
+        return rejection.into_response();
+    }
     let notification_id = match Uuid::parse_str(&notification_id) {
         Ok(uuid) => uuid,
         Err(_) => {
@@ -116,9 +315,10 @@ pub async fn delete_notification(
     let result = sqlx::query!(
         r#"
         DELETE FROM notifications
-        WHERE id = $1
+        WHERE id = $1 AND user_pubkey = $2
         "#,
         notification_id,
+        owner.user_pubkey,
     )
     .execute(&state.db)
     .await;
@@ -152,3 +352,375 @@ pub async fn delete_notification(
         }
     }
 }
+
+pub async fn fetch_notification_by_id(
+    db: &PgPool,
+    notification_id: Uuid,
+) -> anyhow::Result<Option<Notification>> {
+    let record = sqlx::query_as!(
+        Notification,
+        r#"
+        SELECT
+            id,
+            user_pubkey,
+            plan_name,
+            plan_pda,
+            tier,
+            subscription_pda,
+            title,
+            message,
+            created_at,
+            expires_at,
+            is_read,
+            type as "type: NotificationKind"
+        FROM notifications
+        WHERE id = $1
+        "#,
+        notification_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(record)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamQuery {
+    /// From `POST /notifications/stream/{user_pubkey}/session`
+    pub token: Uuid,
+    /// Fallback for clients that cannot send the `Last-Event-ID` header.
+    pub last_event_id: Option<Uuid>,
+}
+
+/// POST /notifications/stream/{user_pubkey}/session
+/// Trades a wallet signature for a token the stream URL carries. EventSource reconnects
+/// with the same URL, long after the signature itself would have expired.
+pub async fn open_notification_stream(
+    Path(user_pubkey): Path<String>,
+    Query(auth): Query<WalletAuth>,
+    Extension(state): Extension<AppState>,
+) -> Result<Json<NotificationStreamSession>, (StatusCode, String)> {
+    verify_wallet_auth("notifications", &user_pubkey, &auth)?;
+
+    let db_error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {}", e),
+        )
+    };
+    sqlx::query!("DELETE FROM notification_stream_sessions WHERE expires_at <= NOW()")
+        .execute(&state.db)
+        .await
+        .map_err(db_error)?;
+    let session = sqlx::query_as!(
+        NotificationStreamSession,
+        r#"
+        INSERT INTO notification_stream_sessions (user_pubkey, expires_at)
+        VALUES ($1, NOW() + make_interval(secs => $2))
+        RETURNING token, expires_at
+        "#,
+        user_pubkey,
+        STREAM_SESSION_SECS as f64
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(session))
+}
+
+/// GET /notifications/stream/{user_pubkey}?token=
+/// Server-sent events for a wallet. Reconnecting with `Last-Event-ID` replays
+/// everything created after that notification before switching to live events.
+pub async fn stream_notifications(
+    Path(user_pubkey): Path<String>,
+    Query(query): Query<StreamQuery>,
+    headers: HeaderMap,
+    Extension(state): Extension<AppState>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, (StatusCode, String)> {
+    // An open stream outlives its session; only connecting needs a live one
+    let authorized = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM notification_stream_sessions
+            WHERE token = $1 AND user_pubkey = $2 AND expires_at > NOW()
+        ) AS "exists!"
+        "#,
+        query.token,
+        user_pubkey
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {}", e),
+        )
+    })?;
+    if !authorized {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Stream session expired; open a new one".to_string(),
+        ));
+    }
+
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| Uuid::parse_str(v).ok())
+        .or(query.last_event_id);
+
+    // Subscribe before reading the backlog so nothing inserted in between is lost
+    let live = BroadcastStream::new(state.notifications_tx.subscribe());
+
+    let backlog = match last_event_id {
+        Some(last_id) => sqlx::query_as!(
+            Notification,
+            r#"
+            SELECT
+                id,
+                user_pubkey,
+                plan_name,
+                plan_pda,
+                tier,
+                subscription_pda,
+                title,
+                message,
+                created_at,
+                expires_at,
+                is_read,
+                type as "type: NotificationKind"
+            FROM notifications
+            WHERE user_pubkey = $1
+              AND created_at > (SELECT created_at FROM notifications WHERE id = $2)
+            ORDER BY created_at ASC
+            "#,
+            user_pubkey,
+            last_id
+        )
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {}", e),
+            )
+        })?,
+        None => Vec::new(),
+    };
+
+    let replayed: HashSet<Uuid> = backlog.iter().filter_map(|n| n.id).collect();
+
+    let live = live.filter_map(move |message| match message {
+        Ok(notification) => {
+            let is_new = notification.user_pubkey == user_pubkey
+                && !notification.id.is_some_and(|id| replayed.contains(&id));
+            is_new.then(|| notification_event(&notification))
+        }
+        // Tell the client it missed events so it can refetch the list
+        Err(_) => Some(Ok(Event::default().event("lagged").data("resync"))),
+    });
+
+    let stream = tokio_stream::iter(backlog)
+        .map(|notification| notification_event(&notification))
+        .chain(live);
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+fn notification_event(notification: &Notification) -> Result<Event, axum::Error> {
+    let event = Event::default().event("notification");
+    let event = match notification.id {
+        Some(id) => event.id(id.to_string()),
+        None => event,
+    };
+    event.json_data(notification)
+}
+
+/// PUT /notifications/{notification_id}/read?userPubkey=
+pub async fn mark_notification_as_read(
+    Path(notification_id): Path<Uuid>,
+    Query(owner): Query<NotificationOwner>,
+    Query(auth): Query<WalletAuth>,
+    Extension(state): Extension<AppState>,
+) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&owner.user_pubkey, &auth) {
+        return rejection;
+    }
+    let result = sqlx::query!(
+        "UPDATE notifications SET is_read = true WHERE id = $1 AND user_pubkey = $2",
+        notification_id,
+        owner.user_pubkey
+    )
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "Notification not found" })),
+        ),
+        Ok(_) => (
+            StatusCode::OK,
+            Json(json!({ "message": "Notification marked as read" })),
+        ),
+        Err(e) => {
+            tracing::error!("Failed to mark notification read: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Database error" })),
+            )
+        }
+    }
+}
+
+/// DELETE /notifications/user/{user_pubkey}
+pub async fn delete_notifications(
+    Path(user_pubkey): Path<String>,
+    Query(auth): Query<WalletAuth>,
+    Extension(state): Extension<AppState>,
+    payload: Option<Json<BulkDeleteNotifications>>,
+) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&user_pubkey, &auth) {
+        return rejection;
+    }
+    let (ids, read_only) = match payload {
+        Some(Json(p)) => (p.ids, p.read_only),
+        None => (None, false),
+    };
+
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM notifications
+        WHERE user_pubkey = $1
+          AND ($2::uuid[] IS NULL OR id = ANY($2))
+          AND (NOT $3 OR is_read = true)
+        "#,
+        user_pubkey,
+        ids.as_deref(),
+        read_only
+    )
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(res) => (
+            StatusCode::OK,
+            Json(json!({
+                "message": "Notifications deleted",
+                "deleted_count": res.rows_affected()
+            })),
+        ),
+        Err(e) => {
+            tracing::error!("Failed to delete notifications: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Database error" })),
+            )
+        }
+    }
+}
+
+/// GET /notifications/mutes/{user_pubkey}
+pub async fn get_notification_mutes(
+    Path(user_pubkey): Path<String>,
+    Extension(state): Extension<AppState>,
+) -> Result<Json<Vec<NotificationMute>>, (StatusCode, String)> {
+    let mutes = sqlx::query_as!(
+        NotificationMute,
+        r#"
+        SELECT plan_pda, muted_until, created_at
+        FROM notification_mutes
+        WHERE user_pubkey = $1
+          AND (muted_until IS NULL OR muted_until > now())
+        ORDER BY created_at DESC
+        "#,
+        user_pubkey
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {}", e),
+        )
+    })?;
+
+    Ok(Json(mutes))
+}
+
+/// PUT /notifications/mutes/{user_pubkey}/{plan_pda}
+pub async fn mute_plan(
+    Path((user_pubkey, plan_pda)): Path<(String, String)>,
+    Query(auth): Query<WalletAuth>,
+    Extension(state): Extension<AppState>,
+    Json(payload): Json<MutePlan>,
+) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&user_pubkey, &auth) {
+        return rejection;
+    }
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO notification_mutes (user_pubkey, plan_pda, muted_until)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_pubkey, plan_pda) DO UPDATE
+        SET muted_until = EXCLUDED.muted_until
+        "#,
+        user_pubkey,
+        plan_pda,
+        payload.muted_until
+    )
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(json!({ "message": "Plan notifications muted" })),
+        ),
+        Err(e) => {
+            tracing::error!("Failed to mute plan: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Database error" })),
+            )
+        }
+    }
+}
+
+/// DELETE /notifications/mutes/{user_pubkey}/{plan_pda}
+pub async fn unmute_plan(
+    Path((user_pubkey, plan_pda)): Path<(String, String)>,
+    Query(auth): Query<WalletAuth>,
+    Extension(state): Extension<AppState>,
+) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&user_pubkey, &auth) {
+        return rejection;
+    }
+    let result = sqlx::query!(
+        "DELETE FROM notification_mutes WHERE user_pubkey = $1 AND plan_pda = $2",
+        user_pubkey,
+        plan_pda
+    )
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "Plan is not muted" })),
+        ),
+        Ok(_) => (
+            StatusCode::OK,
+            Json(json!({ "message": "Plan notifications unmuted" })),
+        ),
+        Err(e) => {
+            tracing::error!("Failed to unmute plan: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Database error" })),
+            )
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use crate::auth::{WalletAuth, verify_wallet_auth};
+use crate::handlers::builder_handler::tier_price;
+use crate::handlers::subscription_handler::register_confirmed_subscriptions;
+use crate::models::relay::{
+    RecordSponsorshipDeposit, RelayRequest, RelayResponse, RelaySponsor, SponsorshipBudget,
+    UpdateSponsorshipBudget,
+};
+use crate::relay::{self, SponsoredSubscription};
+use crate::signer;
+use crate::solana_client::is_preflight_rejection;
+use crate::state::AppState;
+use crate::utils::{find_tier_by_name, parse_tiers};
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::Transaction};
+use std::str::FromStr;
+use tracing::{error, info};
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("DB error: {}", e),
+    )
+}
+
+fn rpc_error(e: anyhow::Error) -> (StatusCode, String) {
+    (StatusCode::BAD_GATEWAY, format!("RPC error: {}", e))
+}
+
+fn bad_request(e: impl std::fmt::Display) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, e.to_string())
+}
+
+/// GET /relay/sponsor
+pub async fn get_relay_sponsor(Extension(state): Extension<AppState>) -> Json<RelaySponsor> {
+    Json(RelaySponsor {
+        fee_payer: state.solana.signer.pubkey().to_string(),
+        max_rent_top_up_lamports: relay::max_rent_top_up_lamports(),
+    })
+}
+
+/// The sign-up must match a tier of the plan it names and pay at least its price, in a
+/// mint the plan accepts.
+async fn check_against_plan(
+    state: &AppState,
+    sign_up: &SponsoredSubscription,
+) -> Result<(), (StatusCode, String)> {
+    let plan = state
+        .solana
+        .get_plan(sign_up.plan_pda)
+        .await
+        .map_err(rpc_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Plan not found".to_string()))?;
+
+    if sign_up.mint != plan.mint && !plan.accepted_mints.iter().any(|m| m.mint == sign_up.mint) {
+        return Err(bad_request("Plan does not accept this mint"));
+    }
+
+    let tiers = parse_tiers(&plan.tiers).map_err(bad_request)?;
+    let tier = find_tier_by_name(&tiers, &sign_up.tier_name).map_err(bad_request)?;
+    if tier.period_seconds.parse::<i64>().ok() != Some(sign_up.period_seconds) {
+        return Err(bad_request("Billing period does not match the tier"));
+    }
+
+    // USD tiers are quoted now, so the oracle may have moved since the wallet built it
+    let decimals = state
+        .solana
+        .get_mint_decimals(&sign_up.mint)
+        .await
+        .map_err(rpc_error)?;
+    let (price, usd_priced) =
+        tier_price(&state.solana, &plan, tier, sign_up.mint, decimals).await?;
+    let priced = if usd_priced {
+        sign_up.amount >= price
+    } else {
+        sign_up.amount == price
+    };
+    if !priced {
+        return Err(bad_request("Amount does not match the tier price"));
+    }
+
+    Ok(())
+}
+
+/// POST /relay/subscriptions
+/// Co-signs a subscriber's sign-up as fee payer and submits it, charging the plan's budget.
+pub async fn relay_subscription(
+    Extension(state): Extension<AppState>,
+    Json(payload): Json<RelayRequest>,
+) -> Result<Json<RelayResponse>, (StatusCode, String)> {
+    let bytes = STANDARD
+        .decode(payload.transaction.trim())
+        .map_err(|_| bad_request("Transaction is not valid base64"))?;
+    let mut tx: Transaction =
+        bincode::deserialize(&bytes).map_err(|_| bad_request("Malformed transaction"))?;
+
+    let sponsor = state.solana.signer.current();
+    let sign_up = relay::validate_subscription_tx(&tx, &sponsor.pubkey(), &state.solana.program_id)
+        .map_err(bad_request)?;
+    check_against_plan(&state, &sign_up).await?;
+
+    signer::sign_transaction(&*sponsor, &mut tx)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_GATEWAY,
+                format!("Sponsor could not sign: {}", e),
+            )
+        })?;
+    tx.verify()
+        .map_err(|_| bad_request("Transaction is missing the subscriber's signature"))?;
+
+    let signature = tx.signatures[0].to_string();
+    let plan_pda = sign_up.plan_pda.to_string();
+    let payer = sign_up.payer.to_string();
+    let cost = (sign_up.fee_lamports + sign_up.rent_lamports) as i64;
+
+    // Reserve the spend before submitting so concurrent sign-ups cannot overdraw the budget
+    let mut db_tx = state.db.begin().await.map_err(db_error)?;
+    let budget = sqlx::query!(
+        r#"
+        SELECT total_lamports, per_wallet_lamports, spent_lamports
+        FROM sponsorship_budgets
+        WHERE plan_pda = $1 AND active
+        FOR UPDATE
+        "#,
+        plan_pda
+    )
+    .fetch_optional(&mut *db_tx)
+    .await
+    .map_err(db_error)?
+    .ok_or((
+        StatusCode::FORBIDDEN,
+        "Plan does not sponsor sign-ups".to_string(),
+    ))?;
+
+    let wallet_spent: i64 = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(SUM(fee_lamports + rent_lamports), 0)::BIGINT AS "spent!"
+        FROM sponsored_transactions
+        WHERE plan_pda = $1 AND payer = $2
+        "#,
+        plan_pda,
+        payer
+    )
+    .fetch_one(&mut *db_tx)
+    .await
+    .map_err(db_error)?;
+
+    if budget.spent_lamports + cost > budget.total_lamports {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Plan sponsorship budget is exhausted".to_string(),
+        ));
+    }
+    if wallet_spent + cost > budget.per_wallet_lamports {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Wallet sponsorship limit reached for this plan".to_string(),
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO sponsored_transactions (
+            tx_signature, plan_pda, payer, subscription_pda, fee_lamports, rent_lamports,
+            status, recent_blockhash
+        ) VALUES ($1, $2, $3, $4, $5, $6, 'pending', $7)
+        "#,
+        signature,
+        plan_pda,
+        payer,
+        sign_up.subscription.to_string(),
+        sign_up.fee_lamports as i64,
+        sign_up.rent_lamports as i64,
+        tx.message.recent_blockhash.to_string()
+    )
+    .execute(&mut *db_tx)
+    .await
+    .map_err(db_error)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE sponsorship_budgets
+        SET spent_lamports = spent_lamports + $2, updated_at = now()
+        WHERE plan_pda = $1
+        "#,
+        plan_pda,
+        cost
+    )
+    .execute(&mut *db_tx)
+    .await
+    .map_err(db_error)?;
+    db_tx.commit().await.map_err(db_error)?;
+
+    if let Err(e) = state.solana.rpc.send_and_confirm_transaction(&tx).await {
+        error!("❌ Sponsored sign-up {} failed: {}", signature, e);
+        // Only a preflight rejection is sure never to land; anything else may still be
+        // processed, so the spend stays reserved until the settlement worker sees the outcome
+        if is_preflight_rejection(&e) {
+            release_reservation(&state, &signature)
+                .await
+                .map_err(db_error)?;
+            return Err(rpc_error(e.into()));
+        }
+        return Err((
+            StatusCode::GATEWAY_TIMEOUT,
+            format!(
+                "Submitted as {} but not yet confirmed; the budget stays reserved until it settles",
+                signature
+            ),
+        ));
+    }
+
+    // The settlement worker confirms it later if this fails
+    if let Err(e) = confirm_reservation(&state, &signature).await {
+        error!(
+            "❌ Failed to confirm sponsored sign-up {}: {:?}",
+            signature, e
+        );
+    }
+    info!("✅ Sponsored sign-up {} for {}", signature, payer);
+    // The indexer would find it too, but the keeper should know about it right away
+    if let Err(e) = register_confirmed_subscriptions(&state, &tx.signatures[0]).await {
+        error!(
+            "❌ Failed to register sponsored sign-up {}: {:?}",
+            signature, e
+        );
+    }
+    Ok(Json(RelayResponse {
+        signature,
+        subscription_pda: sign_up.subscription.to_string(),
+        fee_lamports: sign_up.fee_lamports,
+        rent_lamports: sign_up.rent_lamports,
+    }))
+}
+
+/// Marks a pending sponsored sign-up as landed, keeping its spend.
+pub async fn confirm_reservation(state: &AppState, signature: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE sponsored_transactions SET status = 'confirmed'
+        WHERE tx_signature = $1 AND status = 'pending'
+        "#,
+        signature
+    )
+    .execute(&state.db)
+    .await?;
+    Ok(())
+}
+
+/// Returns a pending sponsored sign-up that never landed to its plan's budget.
+pub async fn release_reservation(state: &AppState, signature: &str) -> Result<(), sqlx::Error> {
+    let mut db_tx = state.db.begin().await?;
+    let released = sqlx::query!(
+        r#"
+        DELETE FROM sponsored_transactions
+        WHERE tx_signature = $1 AND status = 'pending'
+        RETURNING plan_pda, fee_lamports + rent_lamports AS "cost!"
+        "#,
+        signature
+    )
+    .fetch_optional(&mut *db_tx)
+    .await?;
+    if let Some(released) = released {
+        sqlx::query!(
+            r#"
+            UPDATE sponsorship_budgets
+            SET spent_lamports = spent_lamports - $2, updated_at = now()
+            WHERE plan_pda = $1
+            "#,
+            released.plan_pda,
+            released.cost
+        )
+        .execute(&mut *db_tx)
+        .await?;
+    }
+    db_tx.commit().await
+}
+
+/// Settles a pending sponsored sign-up that landed but failed: the fee was spent, the
+/// rent was not.
+pub async fn fail_reservation(state: &AppState, signature: &str) -> Result<(), sqlx::Error> {
+    let mut db_tx = state.db.begin().await?;
+    let failed = sqlx::query!(
+        r#"
+        SELECT plan_pda, rent_lamports FROM sponsored_transactions
+        WHERE tx_signature = $1 AND status = 'pending'
+        FOR UPDATE
+        "#,
+        signature
+    )
+    .fetch_optional(&mut *db_tx)
+    .await?;
+    if let Some(failed) = failed {
+        sqlx::query!(
+            r#"
+            UPDATE sponsored_transactions SET status = 'failed', rent_lamports = 0
+            WHERE tx_signature = $1
+            "#,
+            signature
+        )
+        .execute(&mut *db_tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            UPDATE sponsorship_budgets
+            SET spent_lamports = spent_lamports - $2, updated_at = now()
+            WHERE plan_pda = $1
+            "#,
+            failed.plan_pda,
+            failed.rent_lamports
+        )
+        .execute(&mut *db_tx)
+        .await?;
+    }
+    db_tx.commit().await
+}
+
+/// GET /relay/budgets/{plan_pda}
+pub async fn get_sponsorship_budget(
+    Extension(state): Extension<AppState>,
+    Path(plan_pda): Path<String>,
+) -> Result<Json<SponsorshipBudget>, (StatusCode, String)> {
+    sqlx::query_as!(
+        SponsorshipBudget,
+        r#"
+        SELECT
+            plan_pda, merchant_pubkey, total_lamports, per_wallet_lamports, spent_lamports,
+            active, deposit_address, created_at, updated_at
+        FROM sponsorship_budgets
+        WHERE plan_pda = $1
+        "#,
+        plan_pda
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .map(Json)
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        "Sponsorship budget not found".to_string(),
+    ))
+}
+
+/// Plan creator of `plan_pda`, once they have signed for `auth`.
+async fn authorize_merchant(
+    state: &AppState,
+    plan_pda: &str,
+    auth: &WalletAuth,
+) -> Result<Pubkey, (StatusCode, String)> {
+    let plan_key = Pubkey::from_str(plan_pda).map_err(|_| bad_request("Invalid plan PDA"))?;
+    let plan = state
+        .solana
+        .get_plan(plan_key)
+        .await
+        .map_err(rpc_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Plan not found".to_string()))?;
+    verify_wallet_auth("sponsorship-budget", &plan.creator.to_string(), auth)?;
+    Ok(plan.creator)
+}
+
+/// PUT /relay/budgets/{plan_pda}?signature=&timestamp=
+/// Signed by the plan creator. Sets limits only; the budget is funded by deposits.
+pub async fn update_sponsorship_budget(
+    Extension(state): Extension<AppState>,
+    Path(plan_pda): Path<String>,
+    Query(auth): Query<WalletAuth>,
+    Json(payload): Json<UpdateSponsorshipBudget>,
+) -> Result<Json<SponsorshipBudget>, (StatusCode, String)> {
+    if payload.per_wallet_lamports < 0 {
+        return Err(bad_request("Budgets cannot be negative"));
+    }
+    let merchant_pubkey = authorize_merchant(&state, &plan_pda, &auth)
+        .await?
+        .to_string();
+
+    let budget = sqlx::query_as!(
+        SponsorshipBudget,
+        r#"
+        INSERT INTO sponsorship_budgets (
+            plan_pda, merchant_pubkey, per_wallet_lamports, active, deposit_address
+        ) VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (plan_pda) DO UPDATE SET
+            per_wallet_lamports = EXCLUDED.per_wallet_lamports,
+            active = EXCLUDED.active,
+            deposit_address = COALESCE(
+                sponsorship_budgets.deposit_address, EXCLUDED.deposit_address
+            ),
+            updated_at = now()
+        RETURNING
+            plan_pda, merchant_pubkey, total_lamports, per_wallet_lamports, spent_lamports,
+            active, deposit_address, created_at, updated_at
+        "#,
+        plan_pda,
+        merchant_pubkey,
+        payload.per_wallet_lamports,
+        payload.active,
+        state.solana.signer.pubkey().to_string()
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(budget))
+}
+
+/// POST /relay/budgets/{plan_pda}/deposits?signature=&timestamp=
+/// Credits the budget with what the plan creator sent its deposit address in a confirmed
+/// transfer: the sponsor the budget was opened with, so a signer rotation does not strand it.
+pub async fn record_sponsorship_deposit(
+    Extension(state): Extension<AppState>,
+    Path(plan_pda): Path<String>,
+    Query(auth): Query<WalletAuth>,
+    Json(payload): Json<RecordSponsorshipDeposit>,
+) -> Result<Json<SponsorshipBudget>, (StatusCode, String)> {
+    let merchant = authorize_merchant(&state, &plan_pda, &auth).await?;
+    let signature = Signature::from_str(payload.tx_signature.trim())
+        .map_err(|_| bad_request("Invalid transaction signature"))?;
+
+    let deposit_address = sqlx::query_scalar!(
+        "SELECT deposit_address FROM sponsorship_budgets WHERE plan_pda = $1",
+        plan_pda
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        "Sponsorship budget not found".to_string(),
+    ))?;
+    // Budgets opened before deposit addresses were kept take the current sponsor
+    let deposit_address = match deposit_address {
+        Some(address) => Pubkey::from_str(&address).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Invalid deposit address: {}", e),
+            )
+        })?,
+        None => state.solana.signer.pubkey(),
+    };
+
+    let lamports = state
+        .solana
+        .system_transfer_total(&signature, &merchant, &deposit_address)
+        .await
+        .map_err(rpc_error)?;
+    if lamports == 0 {
+        return Err(bad_request(
+            "Transaction sends nothing from the plan creator to the deposit address",
+        ));
+    }
+    let lamports = i64::try_from(lamports).map_err(bad_request)?;
+
+    let mut db_tx = state.db.begin().await.map_err(db_error)?;
+    sqlx::query_scalar!(
+        "SELECT plan_pda FROM sponsorship_budgets WHERE plan_pda = $1 FOR UPDATE",
+        plan_pda
+    )
+    .fetch_optional(&mut *db_tx)
+    .await
+    .map_err(db_error)?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        "Sponsorship budget not found".to_string(),
+    ))?;
+
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO sponsorship_deposits (
+            tx_signature, plan_pda, merchant_pubkey, lamports, deposit_address
+        ) VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (tx_signature) DO NOTHING
+        "#,
+        signature.to_string(),
+        plan_pda,
+        merchant.to_string(),
+        lamports,
+        deposit_address.to_string()
+    )
+    .execute(&mut *db_tx)
+    .await
+    .map_err(db_error)?
+    .rows_affected();
+    if inserted == 0 {
+        return Err((
+            StatusCode::CONFLICT,
+            "Deposit was already recorded".to_string(),
+        ));
+    }
+
+    let budget = sqlx::query_as!(
+        SponsorshipBudget,
+        r#"
+        UPDATE sponsorship_budgets
+        SET
+            total_lamports = total_lamports + $2,
+            deposit_address = COALESCE(deposit_address, $3),
+            updated_at = now()
+        WHERE plan_pda = $1
+        RETURNING
+            plan_pda, merchant_pubkey, total_lamports, per_wallet_lamports, spent_lamports,
+            active, deposit_address, created_at, updated_at
+        "#,
+        plan_pda,
+        lamports,
+        deposit_address.to_string()
+    )
+    .fetch_one(&mut *db_tx)
+    .await
+    .map_err(db_error)?;
+    db_tx.commit().await.map_err(db_error)?;
+
+    info!("💰 Sponsorship deposit {} for {}", signature, plan_pda);
+    Ok(Json(budget))
+}
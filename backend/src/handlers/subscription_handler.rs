@@ -1,7 +1,12 @@
+use crate::handlers::analytics_handler::record_subscription_event;
+use crate::handlers::coupon_handler::sync_coupon;
+use crate::handlers::invoice_handler::issue_invoice;
 use crate::handlers::notification_handler::create_notification;
 use crate::handlers::transaction_handler::create_transaction;
-use crate::models::notification::Notification;
+use crate::models::invoice::NewInvoice;
+use crate::models::notification::{Notification, NotificationKind};
 use crate::models::subscription::Subscription;
+use crate::types::SubscriptionAccount;
 use crate::worker::renew_subscription_by_pda;
 use crate::{AppState, models::transaction::PaymentHistory};
 use anyhow::Result;
@@ -10,10 +15,10 @@ use axum::{
     http::StatusCode,
     response::IntoResponse,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
 use sqlx::PgPool;
 use std::str::FromStr;
 
@@ -27,7 +32,9 @@ pub async fn record_payment_for_both(
     status: String,
     tx_signature: Option<String>,
     subscription_pda: String,
-) -> Result<()> {
+    plan_pda: Option<String>,
+    payment_record: Option<String>,
+) -> Result<Option<i64>> {
     let now = Utc::now();
 
     // Record for the USER (payer)
@@ -40,13 +47,23 @@ pub async fn record_payment_for_both(
         status: status.clone(),
         tx_signature: tx_signature.clone(),
         subscription_pda: subscription_pda.clone(),
+        plan_pda: plan_pda.clone(),
         created_at: now,
+        direction: "paid".to_string(),
+        payment_record: payment_record.clone(),
+        refund_of: None,
+        usage_units: None,
+        usage_amount: None,
     };
 
-    if let Err(e) = create_transaction(db, &user_record).await {
-        eprintln!("Failed to record user payment history: {:?}", e);
-        // Continue — don't fail the whole flow
-    }
+    let payment_id = match create_transaction(db, &user_record).await {
+        Ok(id) => Some(id),
+        Err(e) => {
+            eprintln!("Failed to record user payment history: {:?}", e);
+            // Continue — don't fail the whole flow
+            None
+        }
+    };
 
     // Record for the CREATOR (receiver)
     let creator_record = PaymentHistory {
@@ -58,31 +75,175 @@ pub async fn record_payment_for_both(
         status,
         tx_signature,
         subscription_pda,
+        plan_pda,
         created_at: now,
+        direction: "received".to_string(),
+        payment_record,
+        refund_of: None,
+        usage_units: None,
+        usage_amount: None,
     };
 
     if let Err(e) = create_transaction(db, &creator_record).await {
         eprintln!("Failed to record creator payment history: {:?}", e);
     }
 
+    Ok(payment_id)
+}
+
+/// The first charge happens at subscribe time and covers up to the first renewal.
+async fn issue_first_invoice(
+    state: &AppState,
+    payload: &Subscription,
+    payment_id: i64,
+    amount: i64,
+    next_payment_ts: i64,
+) -> Result<()> {
+    let plan = state
+        .solana
+        .get_plan(Pubkey::from_str(&payload.plan_pda)?)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Plan {} not found", payload.plan_pda))?;
+    let mint = match &payload.mint {
+        Some(mint) => Pubkey::from_str(mint)?,
+        None => plan.mint,
+    };
+    let token_symbol = plan.token_symbol_for(&mint).to_string();
+    let now = Utc::now();
+
+    issue_invoice(
+        state,
+        &NewInvoice {
+            payment_id,
+            merchant_pubkey: plan.creator.to_string(),
+            payer: payload.payer.clone(),
+            plan_name: plan.name,
+            plan_pda: payload.plan_pda.clone(),
+            tier: payload.tier_name.clone(),
+            period_start: now,
+            period_end: DateTime::from_timestamp(next_payment_ts, 0).unwrap_or(now),
+            amount,
+            mint,
+            token_symbol,
+            tx_signature: Some(payload.tx_signature.clone()),
+        },
+    )
+    .await?;
+
     Ok(())
 }
 
+/// Reads the first payment record, and refreshes the coupon's redemption count.
+async fn first_charge_amount(
+    state: &AppState,
+    payload: &Subscription,
+    coupon: &str,
+) -> Option<i64> {
+    if let Ok(coupon) = Pubkey::from_str(coupon) {
+        if let Err(e) = sync_coupon(state, coupon).await {
+            eprintln!("Failed to sync coupon {}: {:?}", coupon, e);
+        }
+    }
+
+    let subscription = Pubkey::from_str(&payload.subscription).ok()?;
+    let record = state
+        .solana
+        .get_payment_record(&state.solana.payment_record_pda(&subscription, 0))
+        .await
+        .ok()??;
+    Some(record.amount as i64)
+}
+
 pub async fn create_subscription(
     Extension(state): Extension<AppState>,
     Json(payload): Json<Subscription>,
 ) -> impl IntoResponse {
     println!("🧾 Subscription data: {:?}", payload);
+    let (status, body) = register_subscription(&state, payload).await;
+    (status, Json(body)).into_response()
+}
+
+/// Registers a sign-up found on chain, so the keeper renews it however it was submitted.
+/// Returns the subscription PDAs the transaction created that were not yet known.
+pub async fn register_confirmed_subscriptions(
+    state: &AppState,
+    signature: &Signature,
+) -> Result<Vec<String>> {
+    let mut registered = Vec::new();
+    for event in state
+        .solana
+        .subscription_initialized_events(signature)
+        .await?
+    {
+        let subscription_pda = event.subscription.to_string();
+        let known = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM subscriptions WHERE subscription_pda = $1) AS "exists!""#,
+            subscription_pda
+        )
+        .fetch_one(&state.db)
+        .await?;
+        if known {
+            continue;
+        }
+
+        let Some(plan) = state
+            .solana
+            .get_plan(Pubkey::from_str(&event.plan_pda)?)
+            .await?
+        else {
+            eprintln!(
+                "Plan {} of {} no longer exists",
+                event.plan_pda, subscription_pda
+            );
+            continue;
+        };
+        let coupon = state
+            .solana
+            .get_subscription_account(&event.subscription)
+            .await?
+            .and_then(|account| account.coupon);
+
+        let payload = Subscription {
+            payer: event.payer.to_string(),
+            tier_name: event.tier_name,
+            plan_pda: event.plan_pda,
+            plan_name: Some(plan.name.clone()),
+            next_payment_ts: format!("{:x}", event.next_payment_ts),
+            auto_renew: event.auto_renew,
+            active: event.active,
+            amount: format!("{:x}", event.amount),
+            unique_seed: event.unique_seed,
+            bump: event.bump,
+            plan_creator: plan.creator.to_string(),
+            subscription: subscription_pda.clone(),
+            tx_signature: signature.to_string(),
+            max_cycles: event.max_cycles.map(|cycles| format!("{:x}", cycles)),
+            end_ts: event.end_ts.map(|ts| format!("{:x}", ts)),
+            coupon: coupon.map(|coupon| coupon.to_string()),
+            mint: (event.mint != plan.mint).then(|| event.mint.to_string()),
+        };
+        match register_subscription(state, payload).await {
+            (StatusCode::CREATED, _) => registered.push(subscription_pda),
+            // Registered by the web app in the meantime
+            (StatusCode::CONFLICT, _) => {}
+            (_, body) => anyhow::bail!("Failed to register {}: {}", subscription_pda, body),
+        }
+    }
+    Ok(registered)
+}
+
+/// Inserts the subscription row and records the first charge, invoice and notifications.
+pub async fn register_subscription(
+    state: &AppState,
+    payload: Subscription,
+) -> (StatusCode, serde_json::Value) {
     let next_payment_ts = match i64::from_str_radix(&payload.next_payment_ts, 16) {
         Ok(v) => v,
         Err(_) => {
             return (
                 StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({
-                    "error": "Invalid hex timestamp"
-                })),
-            )
-                .into_response();
+                json!({ "error": "Invalid hex timestamp" }),
+            );
         }
     };
     let amount = match i64::from_str_radix(&payload.amount, 16) {
@@ -90,13 +251,27 @@ pub async fn create_subscription(
         Err(_) => {
             return (
                 StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({
-                    "error": "Invalid hex timestamp"
-                })),
-            )
-                .into_response();
+                json!({ "error": "Invalid hex timestamp" }),
+            );
+        }
+    };
+    let parse_term = |value: &Option<String>| match value {
+        Some(hex) => i64::from_str_radix(hex, 16).map(Some),
+        None => Ok(None),
+    };
+    let (max_cycles, end_ts) = match (parse_term(&payload.max_cycles), parse_term(&payload.end_ts))
+    {
+        (Ok(max_cycles), Ok(end_ts)) => (max_cycles, end_ts),
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                json!({ "error": "Invalid hex subscription term" }),
+            );
         }
     };
+    // A one-payment term is already complete when it is created
+    let completed = !payload.active && (max_cycles.is_some() || end_ts.is_some());
+
     let result = sqlx::query!(
         r#"
         INSERT INTO subscriptions (
@@ -109,8 +284,14 @@ pub async fn create_subscription(
             amount,
             unique_seed,
             bump,
-            subscription_pda
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9,$10)
+            subscription_pda,
+            max_cycles,
+            end_ts,
+            completed_at,
+            coupon_pda,
+            mint
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9,$10,$11,$12,
+            CASE WHEN $13 THEN now() END, $14, $15)
         "#,
         payload.payer,
         payload.tier_name,
@@ -121,15 +302,27 @@ pub async fn create_subscription(
         amount,
         &payload.unique_seed,
         payload.bump as i16,
-        payload.subscription
+        payload.subscription,
+        max_cycles,
+        end_ts,
+        completed,
+        payload.coupon,
+        payload.mint
     )
     .execute(&state.db)
     .await;
 
-    let (status, body) = match result {
+    match result {
         Ok(_) => {
+            // A coupon may have discounted the first charge, which only the record knows
+            let amount = match &payload.coupon {
+                Some(coupon) => first_charge_amount(state, &payload, coupon)
+                    .await
+                    .unwrap_or(amount),
+                None => amount,
+            };
             // Record initial transaction history (fire and forget)
-            let _ = record_payment_for_both(
+            let payment = record_payment_for_both(
                 &state.db,
                 payload.payer.clone(),
                 payload.plan_creator.clone(), // ← creator pubkey
@@ -141,10 +334,38 @@ pub async fn create_subscription(
                 payload.tier_name.clone(),
                 amount,
                 "success".to_string(),
-                Some(payload.tx_signature),
+                Some(payload.tx_signature.clone()),
                 payload.subscription.clone(),
+                Some(payload.plan_pda.clone()),
+                Pubkey::from_str(&payload.subscription)
+                    .ok()
+                    .map(|s| state.solana.payment_record_pda(&s, 0).to_string()),
             )
             .await;
+            if let Ok(Some(payment_id)) = payment {
+                let state = state.clone();
+                let payload = payload.clone();
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        issue_first_invoice(&state, &payload, payment_id, amount, next_payment_ts)
+                            .await
+                    {
+                        eprintln!("Failed to issue invoice: {:?}", e);
+                    }
+                });
+            }
+            if let Err(e) = record_subscription_event(
+                &state.db,
+                &payload.subscription,
+                &payload.plan_pda,
+                &payload.payer,
+                &payload.tier_name,
+                "started",
+            )
+            .await
+            {
+                eprintln!("Failed to record subscription start: {:?}", e);
+            }
             let short_payer = if payload.payer.len() > 8 {
                 format!(
                     "{}...{}",
@@ -159,6 +380,7 @@ pub async fn create_subscription(
                 user_pubkey: payload.plan_creator.clone(), // ← creator's pubkey (you need to add this to payload)
                 subscription_pda: payload.subscription.clone(),
                 plan_name: payload.plan_name.clone().unwrap_or_default(),
+                plan_pda: Some(payload.plan_pda.clone()),
                 title: "New Subscriber".to_string(),
                 tier: payload.tier_name.clone(),
                 message: format!(
@@ -168,8 +390,9 @@ pub async fn create_subscription(
                     payload.tier_name
                 ),
                 is_read: false,
-                r#type: "info".to_string(),
+                r#type: NotificationKind::Info,
                 created_at: Some(chrono::Utc::now()),
+                expires_at: None,
             };
 
             if let Err(e) = create_notification(&state.db, &creator_notification).await {
@@ -202,9 +425,7 @@ pub async fn create_subscription(
                 json!({ "error": "Failed to create subscription" }),
             )
         }
-    };
-
-    (status, Json(body)).into_response()
+    }
 }
 
 #[derive(serde::Serialize)]
@@ -407,23 +628,38 @@ pub async fn delete_subscription(
         r#"
         DELETE FROM subscriptions
         WHERE subscription_pda = $1
+        RETURNING payer, plan_pda, tier_name
         "#,
         subscription_pda
     )
-    .execute(&state.db)
+    .fetch_optional(&state.db)
     .await;
 
     match result {
-        Ok(res) if res.rows_affected() == 0 => (
+        Ok(None) => (
             StatusCode::NOT_FOUND,
             Json(json!({ "error": "Subscription not found" })),
         )
             .into_response(),
-        Ok(_) => (
-            StatusCode::OK,
-            Json(json!({ "message": "Subscription deleted successfully" })),
-        )
-            .into_response(),
+        Ok(Some(deleted)) => {
+            if let Err(e) = record_subscription_event(
+                &state.db,
+                &subscription_pda,
+                &deleted.plan_pda,
+                &deleted.payer,
+                &deleted.tier_name,
+                "cancelled",
+            )
+            .await
+            {
+                eprintln!("Failed to record subscription cancellation: {:?}", e);
+            }
+            (
+                StatusCode::OK,
+                Json(json!({ "message": "Subscription deleted successfully" })),
+            )
+                .into_response()
+        }
         Err(e) => {
             eprintln!("Failed to delete subscription: {:?}", e);
             (
@@ -456,3 +692,166 @@ pub async fn renew_subscription(
         "message": "Subscription renewal triggered"
     })))
 }
+
+/// Copies the on-chain pause state into Postgres, notifying the subscriber when it changed.
+/// Returns `None` when the subscription account no longer exists.
+pub async fn sync_pause_state(
+    state: &AppState,
+    subscription_pda: Pubkey,
+) -> Result<Option<SubscriptionAccount>> {
+    let Some(account) = state
+        .solana
+        .get_subscription_account(&subscription_pda)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    // Resuming moves the schedule, so both columns follow the chain
+    let changed = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET paused_at = $1,
+            next_payment_ts = $2
+        WHERE subscription_pda = $3
+          AND paused_at IS DISTINCT FROM $1
+        RETURNING payer, plan_pda, tier_name
+        "#,
+        account.paused_at,
+        account.next_payment_ts,
+        subscription_pda.to_string()
+    )
+    .fetch_optional(&state.db)
+    .await?;
+
+    let Some(row) = changed else {
+        return Ok(Some(account));
+    };
+
+    let plan_name = match state.solana.get_plan(account.plan_pda).await {
+        Ok(Some(plan)) => plan.name,
+        _ => "your plan".to_string(),
+    };
+    let (title, message) = match account.paused_at {
+        Some(_) => (
+            "Subscription Paused",
+            format!(
+                "{} ({}) is paused. You won't be charged until it resumes.",
+                plan_name, row.tier_name
+            ),
+        ),
+        None => {
+            let next_payment = DateTime::from_timestamp(account.next_payment_ts, 0)
+                .map(|d| d.format("%b %-d, %Y").to_string())
+                .unwrap_or_default();
+            (
+                "Subscription Resumed",
+                format!(
+                    "{} ({}) is active again. Your next payment is due {}.",
+                    plan_name, row.tier_name, next_payment
+                ),
+            )
+        }
+    };
+
+    let notification = Notification {
+        id: None,
+        user_pubkey: row.payer,
+        subscription_pda: subscription_pda.to_string(),
+        plan_name,
+        plan_pda: Some(row.plan_pda),
+        title: title.to_string(),
+        tier: row.tier_name,
+        message,
+        is_read: false,
+        r#type: NotificationKind::Info,
+        created_at: Some(Utc::now()),
+        expires_at: None,
+    };
+    if let Err(e) = create_notification(&state.db, &notification).await {
+        eprintln!("Failed to notify pause change: {:?}", e);
+    }
+
+    Ok(Some(account))
+}
+
+async fn confirm_pause_state(
+    state: &AppState,
+    subscription_pda: &str,
+    expect_paused: bool,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let subscription_key = Pubkey::from_str(subscription_pda)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid subscription PDA".into()))?;
+
+    let account = sync_pause_state(state, subscription_key)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("Sync failed: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Subscription not found".to_string()))?;
+
+    if account.paused_at.is_some() != expect_paused {
+        let error = if expect_paused {
+            "Subscription is not paused on-chain"
+        } else {
+            "Subscription is still paused on-chain"
+        };
+        return Err((StatusCode::CONFLICT, error.into()));
+    }
+
+    Ok(Json(json!({
+        "pausedAt": account.paused_at,
+        "nextPaymentTs": account.next_payment_ts
+    })))
+}
+
+/// POST /subscriptions/{subscription_pda}/pause
+/// Called once the subscriber's `pause_subscription` transaction has confirmed.
+pub async fn pause_subscription(
+    Extension(state): Extension<AppState>,
+    Path(subscription_pda): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    confirm_pause_state(&state, &subscription_pda, true).await
+}
+
+/// POST /subscriptions/{subscription_pda}/resume
+/// Called once the subscriber's `resume_subscription` transaction has confirmed.
+pub async fn resume_subscription(
+    Extension(state): Extension<AppState>,
+    Path(subscription_pda): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    confirm_pause_state(&state, &subscription_pda, false).await
+}
+
+/// GET /subscriptions/{subscription_pda}/usage
+/// Usage accrued on-chain since the last charge and what it would add to the next one.
+pub async fn get_subscription_usage(
+    Extension(state): Extension<AppState>,
+    Path(subscription_pda): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let subscription_key = Pubkey::from_str(&subscription_pda)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid subscription PDA".into()))?;
+
+    let account = state
+        .solana
+        .get_subscription_account(&subscription_key)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("RPC error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Subscription not found".to_string()))?;
+    let plan = state
+        .solana
+        .get_plan(account.plan_pda)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("RPC error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, "Plan not found".to_string()))?;
+    let unit_price = account.unit_price(&plan);
+
+    Ok(Json(json!({
+        "metered": unit_price > 0,
+        "baseAmount": account.amount,
+        "unitPrice": unit_price,
+        "usageCap": account.usage_cap,
+        "pendingUsageCap": account.pending_usage_cap,
+        "accruedUnits": account.accrued_units,
+        "pendingUsageAmount": account.pending_usage_amount(&plan),
+        "nextPaymentTs": account.next_payment_ts
+    })))
+}
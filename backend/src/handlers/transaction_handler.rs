@@ -1,25 +1,45 @@
-use crate::models::transaction::PaymentHistory;
+use crate::auth::{WalletAuth, verify_wallet_auth};
+use crate::handlers::notification_handler::create_notification;
+use crate::models::notification::{Notification, NotificationKind};
+use crate::models::transaction::{
+    PaymentHistory, RecordRefund, TransactionPage, TransactionQuery, TransactionTotals,
+};
 use crate::state::AppState;
+use crate::utils::{decode_cursor, encode_cursor};
 use anyhow::Result;
 use axum::{
     Json,
-    extract::{Extension, Path},
+    extract::{Extension, Path, Query},
     http::StatusCode,
     response::IntoResponse,
 };
+use chrono::Utc;
 use serde_json::json;
-use sqlx::PgPool;
+use solana_sdk::pubkey::Pubkey;
+use sqlx::{PgExecutor, PgPool};
+use std::str::FromStr;
 
-pub async fn create_transaction(db: &PgPool, record: &PaymentHistory) -> Result<()> {
+/// Inserts a payment row and returns its id.
+pub async fn create_transaction<'e>(
+    db: impl PgExecutor<'e>,
+    record: &PaymentHistory,
+) -> Result<i64> {
     if record.amount <= 0 {
         anyhow::bail!("amount must be greater than zero");
     }
 
-    if !matches!(record.status.as_str(), "pending" | "success" | "failed") {
+    if !matches!(
+        record.status.as_str(),
+        "pending" | "success" | "failed" | "refunded"
+    ) {
         anyhow::bail!("invalid payment status");
     }
 
-    sqlx::query!(
+    if !matches!(record.direction.as_str(), "paid" | "received") {
+        anyhow::bail!("invalid payment direction");
+    }
+
+    let row = sqlx::query!(
         r#"
         INSERT INTO payment_history (
             user_pubkey,
@@ -29,8 +49,15 @@ pub async fn create_transaction(db: &PgPool, record: &PaymentHistory) -> Result<
             status,
             tx_signature,
             subscription_pda,
-            created_at )
-        VALUES ($1, $2, $3, $4, $5, $6,$7,$8)
+            created_at,
+            direction,
+            plan_pda,
+            payment_record,
+            refund_of,
+            usage_units,
+            usage_amount )
+        VALUES ($1, $2, $3, $4, $5, $6,$7,$8,$9,$10,$11,$12,$13,$14)
+        RETURNING id
         "#,
         record.user_pubkey,
         record.plan,
@@ -39,38 +66,100 @@ pub async fn create_transaction(db: &PgPool, record: &PaymentHistory) -> Result<
         record.status,
         record.tx_signature,
         record.subscription_pda,
-        record.created_at
+        record.created_at,
+        record.direction,
+        record.plan_pda,
+        record.payment_record,
+        record.refund_of,
+        record.usage_units,
+        record.usage_amount
     )
-    .execute(db)
+    .fetch_one(db)
     .await?;
 
-    Ok(())
+    Ok(row.id)
 }
 
-pub async fn get_transactions(
-    Extension(state): Extension<AppState>,
-    Path(user_pubkey): Path<String>,
-) -> Result<Json<Vec<PaymentHistory>>, (StatusCode, String)> {
-    let records = sqlx::query_as!(
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+async fn list_transactions(
+    db: &PgPool,
+    user_pubkey: &str,
+    subscription_pda: Option<&str>,
+    query: &TransactionQuery,
+) -> Result<TransactionPage, (StatusCode, String)> {
+    if let Some(status) = query.status.as_deref() {
+        if !matches!(status, "pending" | "success" | "failed" | "refunded") {
+            return Err((StatusCode::BAD_REQUEST, "Invalid status filter".into()));
+        }
+    }
+    if let Some(direction) = query.direction.as_deref() {
+        if !matches!(direction, "paid" | "received") {
+            return Err((StatusCode::BAD_REQUEST, "Invalid direction filter".into()));
+        }
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let (cursor_ts, cursor_id) = match query.cursor.as_deref() {
+        Some(cursor) => {
+            let (ts, id) = decode_cursor::<i64>(cursor)
+                .ok_or((StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))?;
+            (Some(ts), Some(id))
+        }
+        None => (None, None),
+    };
+
+    // Fetch one extra row to learn whether another page exists
+    let mut records = sqlx::query_as!(
         PaymentHistory,
         r#"
         SELECT
             id,
-            user_pubkey, 
+            user_pubkey,
             plan,
             tier,
             amount,
             status,
             tx_signature,
             subscription_pda,
-            created_at
+            plan_pda,
+            created_at,
+            direction,
+            payment_record,
+            refund_of,
+            usage_units,
+            usage_amount
         FROM payment_history
         WHERE user_pubkey = $1
-        ORDER BY created_at DESC
+          AND ($2::text IS NULL OR subscription_pda = $2)
+          AND ($3::timestamptz IS NULL OR created_at >= $3)
+          AND ($4::timestamptz IS NULL OR created_at < $4)
+          AND ($5::text IS NULL OR status = $5)
+          AND ($6::text IS NULL OR plan = $6)
+          AND ($7::text IS NULL OR tier = $7)
+          AND ($8::text IS NULL OR direction = $8)
+          AND ($9::timestamptz IS NULL OR (created_at, id) < ($9, $10::bigint))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $11
         "#,
-        user_pubkey
+        user_pubkey,
+        subscription_pda,
+        query.from,
+        query.to,
+        query.status,
+        query.plan,
+        query.tier,
+        query.direction,
+        cursor_ts,
+        cursor_id,
+        limit + 1
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await
     .map_err(|e| {
         (
@@ -79,43 +168,54 @@ pub async fn get_transactions(
         )
     })?;
 
-    Ok(Json(records))
+    let next_cursor = if records.len() as i64 > limit {
+        records.truncate(limit as usize);
+        records
+            .last()
+            .and_then(|r| Some(encode_cursor(r.created_at, r.id?)))
+    } else {
+        None
+    };
+
+    let mut totals = TransactionTotals {
+        count: records.len(),
+        ..Default::default()
+    };
+    // Refund rows carry the opposite direction, so they net against payments
+    for record in records
+        .iter()
+        .filter(|r| matches!(r.status.as_str(), "success" | "refunded"))
+    {
+        match record.direction.as_str() {
+            "received" => totals.received += record.amount,
+            _ => totals.paid += record.amount,
+        }
+    }
+
+    Ok(TransactionPage {
+        items: records,
+        next_cursor,
+        totals,
+    })
+}
+
+/// GET /transactions/user/{user_pubkey}?cursor=&limit=&from=&to=&status=&plan=&tier=&direction=
+pub async fn get_transactions(
+    Extension(state): Extension<AppState>,
+    Path(user_pubkey): Path<String>,
+    Query(query): Query<TransactionQuery>,
+) -> Result<Json<TransactionPage>, (StatusCode, String)> {
+    let page = list_transactions(&state.db, &user_pubkey, None, &query).await?;
+    Ok(Json(page))
 }
 
 pub async fn get_subscription_transactions(
     Extension(state): Extension<AppState>,
     Path((user_pubkey, subscription_pda)): Path<(String, String)>,
-) -> Result<Json<Vec<PaymentHistory>>, (StatusCode, String)> {
-    let records = sqlx::query_as!(
-        PaymentHistory,
-        r#"
-        SELECT
-            id,
-            user_pubkey,
-            plan,
-            tier,
-            amount,
-            status,
-            tx_signature,
-            subscription_pda,
-            created_at
-        FROM payment_history
-        WHERE user_pubkey = $1 AND subscription_pda = $2
-        ORDER BY created_at DESC
-        "#,
-        user_pubkey,
-        subscription_pda
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("DB error: {}", e),
-        )
-    })?;
-
-    Ok(Json(records))
+    Query(query): Query<TransactionQuery>,
+) -> Result<Json<TransactionPage>, (StatusCode, String)> {
+    let page = list_transactions(&state.db, &user_pubkey, Some(&subscription_pda), &query).await?;
+    Ok(Json(page))
 }
 
 /// DELETE /transactions/:tx_signature/:user_pubkey
@@ -163,3 +263,168 @@ pub async fn delete_transaction(
         }
     }
 }
+
+/// POST /transactions/refunds/{id}?signature=&timestamp=
+/// Signed by the plan creator. Records a refund the merchant already sent on-chain against
+/// the subscriber's payment row. The payment's on-chain record must show at least this much
+/// refunded in total.
+pub async fn record_refund(
+    Extension(state): Extension<AppState>,
+    Path(id): Path<i64>,
+    Query(auth): Query<WalletAuth>,
+    Json(payload): Json<RecordRefund>,
+) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+    if payload.amount <= 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Refund amount must be greater than zero".into(),
+        ));
+    }
+
+    let db_error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {}", e),
+        )
+    };
+
+    let payment_record = sqlx::query_scalar!(
+        "SELECT payment_record FROM payment_history WHERE id = $1",
+        id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Transaction not found".to_string()))?;
+
+    let record_key = payment_record
+        .as_deref()
+        .and_then(|k| Pubkey::from_str(k).ok())
+        .ok_or((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Payment has no on-chain payment record".to_string(),
+        ))?;
+
+    let record = state
+        .solana
+        .get_payment_record(&record_key)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("RPC error: {}", e)))?
+        .ok_or((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Payment record not found on-chain".to_string(),
+        ))?;
+
+    let plan = state
+        .solana
+        .get_plan(record.plan)
+        .await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("RPC error: {}", e)))?;
+    let merchant = plan
+        .map(|p| p.creator.to_string())
+        .ok_or((StatusCode::NOT_FOUND, "Plan not found".to_string()))?;
+    verify_wallet_auth("refunds", &merchant, &auth)?;
+
+    // Lock the charge so concurrent refunds of it are checked against each other's totals
+    let mut db_tx = state.db.begin().await.map_err(db_error)?;
+    let original = sqlx::query!(
+        r#"
+        SELECT
+            p.user_pubkey,
+            p.plan,
+            p.tier,
+            p.subscription_pda,
+            p.plan_pda,
+            p.payment_record,
+            p.status,
+            p.direction,
+            (SELECT COALESCE(SUM(r.amount), 0)
+             FROM payment_history r
+             WHERE r.refund_of = p.id AND r.direction = 'received') AS "refunded!: i64"
+        FROM payment_history p
+        WHERE p.id = $1
+        FOR UPDATE OF p
+        "#,
+        id
+    )
+    .fetch_optional(&mut *db_tx)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, "Transaction not found".to_string()))?;
+
+    if original.direction != "paid" || original.status != "success" {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Only successful subscriber payments can be refunded".into(),
+        ));
+    }
+
+    let total_refunded = original.refunded + payload.amount;
+    if total_refunded as u64 > record.refunded_amount {
+        return Err((
+            StatusCode::CONFLICT,
+            "Refund not found on-chain for this payment".into(),
+        ));
+    }
+
+    let now = Utc::now();
+    let refund = |user_pubkey: String, direction: &str| PaymentHistory {
+        id: None,
+        user_pubkey,
+        plan: original.plan.clone(),
+        tier: original.tier.clone(),
+        amount: payload.amount,
+        status: "refunded".to_string(),
+        tx_signature: Some(payload.tx_signature.clone()),
+        subscription_pda: original.subscription_pda.clone(),
+        plan_pda: original.plan_pda.clone(),
+        created_at: now,
+        direction: direction.to_string(),
+        payment_record: original.payment_record.clone(),
+        refund_of: Some(id),
+        usage_units: None,
+        usage_amount: None,
+    };
+
+    // Money flows back, so the subscriber receives and the merchant pays
+    let refund_id = create_transaction(
+        &mut *db_tx,
+        &refund(original.user_pubkey.clone(), "received"),
+    )
+    .await
+    .map_err(|e| (StatusCode::CONFLICT, format!("Refund not recorded: {}", e)))?;
+    create_transaction(&mut *db_tx, &refund(merchant, "paid"))
+        .await
+        .map_err(|e| (StatusCode::CONFLICT, format!("Refund not recorded: {}", e)))?;
+    db_tx.commit().await.map_err(db_error)?;
+
+    let notification = Notification {
+        id: None,
+        user_pubkey: original.user_pubkey.clone(),
+        subscription_pda: original.subscription_pda.clone(),
+        plan_name: original.plan.clone(),
+        plan_pda: original.plan_pda.clone(),
+        title: "Refund Received".to_string(),
+        tier: original.tier.clone(),
+        message: format!(
+            "{} issued a refund for your {} payment.",
+            original.plan, original.tier
+        ),
+        is_read: false,
+        r#type: NotificationKind::Info,
+        created_at: Some(now),
+        expires_at: None,
+    };
+    if let Err(e) = create_notification(&state.db, &notification).await {
+        eprintln!("Failed to notify refund: {:?}", e);
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "id": refund_id,
+            "refundOf": id,
+            "totalRefunded": total_refunded
+        })),
+    ))
+}
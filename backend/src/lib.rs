@@ -0,0 +1,16 @@
+pub mod admin;
+pub mod auth;
+pub mod config;
+pub mod email;
+pub mod handlers;
+pub mod models;
+pub mod relay;
+pub mod routes;
+pub mod signer;
+pub mod solana_client;
+pub mod state;
+pub mod transfer_hook;
+pub mod tx_builder;
+pub mod types;
+pub mod utils;
+pub mod worker;
@@ -1,44 +1,75 @@
-use axum::{Extension, Router};
-use dotenvy::dotenv;
-use std::env;
+use axum::{Extension, Router, routing::get};
+use backend::config::{Config, ConfigArgs, Environment};
+use backend::routes::checkout_routes::{actions_cors, checkout_routes};
+use backend::state::AppState;
+use backend::worker::{
+    run_analytics_refresh, run_email_dispatcher, run_keeper, run_notification_listener,
+    run_notification_retention, run_reminder_scheduler, run_signer_rotation,
+    run_sponsorship_settlement, run_subscription_indexer,
+};
+use backend::{handlers, routes};
+use clap::Parser;
 use std::sync::Arc;
 use tokio::net::TcpListener;
-mod handlers;
-mod models;
-mod routes;
-mod state;
-mod utils;
-mod worker;
-use crate::state::AppState;
 use tower_http::cors::Any;
-use tower_http::cors::CorsLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 use tracing_subscriber;
-mod solana_client;
-mod types;
-use crate::worker::run_keeper;
+
+#[derive(Parser)]
+#[command(name = "backend", about = "SolPay API server and keeper")]
+struct Args {
+    #[command(flatten)]
+    config: ConfigArgs,
+}
 
 #[tokio::main]
 async fn main() {
-    dotenv().ok();
+    let dotenv_names = Environment::load_dotenv();
     tracing_subscriber::fmt::init();
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let solana_url = "https://api.devnet.solana.com";
-    let keypair_path = "/home/ayu/.config/solana/id.json";
-    let program_id = "DUNyVxYZBG7YvU5Nsbci75stbBnjBtBjjibH6FtVPFaL";
+    let args = Args::parse();
+    let config = Config::load(&args.config).unwrap_or_else(|e| {
+        eprintln!("❌ Invalid configuration: {:#}", e);
+        std::process::exit(1);
+    });
+    let bind = config.server.bind;
+    let allow_origin = match config
+        .cors_origin_headers()
+        .expect("validated by Config::load")
+    {
+        Some(origins) => AllowOrigin::list(origins),
+        None => AllowOrigin::any(),
+    };
 
-    let app_state = AppState::new(&database_url, solana_url, keypair_path, program_id).await;
+    let app_state = AppState::new(config).await;
     let cors = CorsLayer::new()
-        .allow_origin(Any)
+        .allow_origin(allow_origin)
         .allow_methods(Any)
         .allow_headers(Any);
     tokio::spawn(run_keeper(Arc::new(app_state.clone())));
+    tokio::spawn(run_email_dispatcher(Arc::new(app_state.clone())));
+    tokio::spawn(run_reminder_scheduler(Arc::new(app_state.clone())));
+    tokio::spawn(run_notification_listener(Arc::new(app_state.clone())));
+    tokio::spawn(run_notification_retention(Arc::new(app_state.clone())));
+    tokio::spawn(run_analytics_refresh(Arc::new(app_state.clone())));
+    tokio::spawn(run_subscription_indexer(Arc::new(app_state.clone())));
+    tokio::spawn(run_sponsorship_settlement(Arc::new(app_state.clone())));
+    tokio::spawn(run_signer_rotation(
+        Arc::new(app_state.clone()),
+        std::env::args_os().collect(),
+        dotenv_names,
+    ));
 
+    // Checkout and Actions routes keep their own permissive CORS, outside `cors`
+    let api = routes::create_routes().layer(cors).merge(checkout_routes());
     let app = Router::new()
-        .nest("/api", routes::create_routes())
-        .layer(cors)
+        .nest("/api", api)
+        .route(
+            "/actions.json",
+            get(handlers::checkout_handler::get_actions_manifest).layer(actions_cors()),
+        )
         .layer(Extension(app_state.clone()));
 
-    println!("🚀 Server running on http://127.0.0.1:3000");
-    let listener = TcpListener::bind("127.0.0.1:3001").await.unwrap();
+    let listener = TcpListener::bind(bind).await.unwrap();
+    println!("🚀 Server running on http://{}", bind);
     axum::serve(listener, app).await.unwrap();
 }
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsQuery {
+    pub months: Option<i32>,
+}
+
+/// A sum in one of the plan's mints; raw units of different mints never add up.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MintAmount {
+    pub mint: String,
+    pub token_symbol: String,
+    pub decimals: u8,
+    pub amount: u64,
+    pub amount_ui: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TierMix {
+    pub tier: String,
+    pub mint: String,
+    pub subscribers: u64,
+    pub mrr: u64,
+}
+
+/// Recurring revenue per mint in raw token units, normalized to a 30-day month.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanSummary {
+    pub plan_pda: String,
+    pub active_subscribers: u64,
+    pub mrr: Vec<MintAmount>,
+    pub arr: Vec<MintAmount>,
+    pub tier_mix: Vec<TierMix>,
+    /// Share of renewal attempts in the last 30 days that failed.
+    pub failed_payment_rate: f64,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthlyGrowth {
+    pub month: DateTime<Utc>,
+    pub new_subscribers: i64,
+    pub churned_subscribers: i64,
+    pub successful_payments: i64,
+    pub failed_payments: i64,
+    #[sqlx(skip)]
+    pub revenue: Vec<MintAmount>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct MonthlyRevenue {
+    pub month: DateTime<Utc>,
+    /// Empty for the plan's primary mint
+    pub mint: String,
+    pub revenue: i64,
+}
+
+#[derive(Debug, FromRow)]
+pub struct CohortCell {
+    pub cohort_month: DateTime<Utc>,
+    pub months_since: i32,
+    pub active_subscribers: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cohort {
+    pub cohort_month: DateTime<Utc>,
+    pub size: i64,
+    /// Fraction of the cohort still paying `n` months after its first payment.
+    pub retention: Vec<f64>,
+}
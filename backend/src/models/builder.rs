@@ -0,0 +1,111 @@
+use crate::models::subscription::Tier;
+use serde::{Deserialize, Serialize};
+
+/// Ready for the wallet to sign: bincode-serialized legacy transaction, base64-encoded.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsignedTransaction {
+    pub transaction: String,
+    pub blockhash: String,
+    pub last_valid_block_height: u64,
+    /// Set for subscribe, whose PDA is derived from a fresh seed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription_pda: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unique_seed: Option<[u8; 8]>,
+}
+
+/// Shared by every builder: who pays fees and an optional priority fee.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildOptions {
+    /// Defaults to the signing wallet; set to the relay sponsor for gasless sign-ups
+    pub fee_payer: Option<String>,
+    pub compute_unit_price: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildSubscribe {
+    pub payer: String,
+    pub plan_pda: String,
+    pub tier_name: String,
+    /// One of the plan's accepted mints, the primary one if absent
+    pub mint: Option<String>,
+    #[serde(default = "default_auto_renew")]
+    pub auto_renew: bool,
+    pub coupon_code: Option<String>,
+    /// Metered tiers: most the usage part of one charge may take, in UI units
+    pub usage_cap: Option<String>,
+    /// USD-priced tiers: how far above today's quote a charge may go, in basis points
+    pub max_slippage_bps: Option<u16>,
+    #[serde(flatten)]
+    pub options: BuildOptions,
+}
+
+fn default_auto_renew() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildCancelSubscription {
+    pub payer: String,
+    pub subscription_pda: String,
+    #[serde(flatten)]
+    pub options: BuildOptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildChangeTier {
+    pub payer: String,
+    pub subscription_pda: String,
+    pub tier_name: String,
+    #[serde(flatten)]
+    pub options: BuildOptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildCreatePlan {
+    pub creator: String,
+    pub mint: String,
+    pub receiver: String,
+    pub name: String,
+    pub token_symbol: String,
+    pub token_image: String,
+    pub tiers: Vec<Tier>,
+    #[serde(flatten)]
+    pub options: BuildOptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildUpdatePlan {
+    pub creator: String,
+    pub receiver: String,
+    pub name: String,
+    pub tiers: Vec<Tier>,
+    #[serde(flatten)]
+    pub options: BuildOptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildCancelPlan {
+    pub creator: String,
+    #[serde(flatten)]
+    pub options: BuildOptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildRefund {
+    pub receiver: String,
+    pub payment_record: String,
+    /// In UI units; the part of the charge not yet refunded if absent
+    pub amount: Option<String>,
+    #[serde(flatten)]
+    pub options: BuildOptions,
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+/// Body wallets POST to a Solana Pay transaction request or an Action.
+#[derive(Debug, Deserialize)]
+pub struct CheckoutAccount {
+    pub account: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CheckoutQuery {
+    /// One of the plan's accepted mints, the primary one if absent
+    pub mint: Option<String>,
+    pub coupon: Option<String>,
+}
+
+/// Solana Pay transaction request, GET.
+#[derive(Debug, Serialize)]
+pub struct PayMetadata {
+    pub label: String,
+    pub icon: String,
+}
+
+/// Solana Pay transaction request, POST.
+#[derive(Debug, Serialize)]
+pub struct PayTransaction {
+    pub transaction: String,
+    pub message: String,
+}
+
+/// Solana Actions GET response for a plan, one button per tier.
+#[derive(Debug, Serialize)]
+pub struct ActionGetResponse {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub icon: String,
+    pub title: String,
+    pub description: String,
+    pub label: String,
+    pub links: ActionLinks,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActionLinks {
+    pub actions: Vec<LinkedAction>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkedAction {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub label: String,
+    pub href: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActionPostResponse {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub transaction: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActionError {
+    pub message: String,
+}
+
+/// `/actions.json`, mapping shared links onto the API's action routes.
+#[derive(Debug, Serialize)]
+pub struct ActionsManifest {
+    pub rules: Vec<ActionRule>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionRule {
+    pub path_pattern: String,
+    pub api_path: String,
+}
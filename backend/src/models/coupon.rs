@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Coupon {
+    pub coupon_pda: String,
+    pub plan_pda: String,
+    pub code: String,
+    /// "percent" or "fixed"
+    pub discount_type: String,
+    /// Basis points for percent coupons, token base units for fixed ones
+    pub discount_value: i64,
+    pub duration_cycles: Option<i64>,
+    pub max_redemptions: Option<i64>,
+    pub redemptions: i64,
+    pub expires_at: Option<i64>,
+    pub tier_name: Option<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Sent by the merchant once their `create_coupon` transaction has confirmed.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterCoupon {
+    pub coupon_pda: String,
+}
+
+/// Sent by the subscriber once their `apply_coupon` transaction has confirmed.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CouponRedemption {
+    pub subscription_pda: String,
+}
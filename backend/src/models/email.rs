@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailSettings {
+    pub user_pubkey: String,
+    pub email: String,
+    pub verified: bool,
+    pub notify_renewal_success: bool,
+    pub notify_renewal_failure: bool,
+    pub notify_expiry: bool,
+    pub notify_upcoming_charge: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RegisterEmail {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmail {
+    pub token: uuid::Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailPreferences {
+    pub renewal_success: bool,
+    pub renewal_failure: bool,
+    pub expiry: bool,
+    pub upcoming_charge: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct QueuedEmail {
+    pub id: uuid::Uuid,
+    pub to_address: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+    pub attempts: i32,
+}
@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Invoice {
+    pub id: i64,
+    pub merchant_pubkey: String,
+    pub invoice_number: i64,
+    pub invoice_code: String,
+    pub payment_id: Option<i64>,
+    pub payer: String,
+    pub plan_name: String,
+    pub plan_pda: Option<String>,
+    pub tier: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub amount: i64,
+    pub amount_ui: String,
+    pub token_symbol: String,
+    pub mint: String,
+    pub tx_signature: Option<String>,
+    pub merchant_name: String,
+    pub merchant_address: Option<String>,
+    pub merchant_email: Option<String>,
+    pub merchant_tax_id: Option<String>,
+    pub issued_at: DateTime<Utc>,
+}
+
+/// Everything known about a payment when its invoice is issued.
+#[derive(Debug, Clone)]
+pub struct NewInvoice {
+    pub payment_id: i64,
+    pub merchant_pubkey: String,
+    pub payer: String,
+    pub plan_name: String,
+    pub plan_pda: String,
+    pub tier: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub amount: i64,
+    pub mint: Pubkey,
+    pub token_symbol: String,
+    pub tx_signature: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MerchantProfile {
+    pub merchant_pubkey: String,
+    pub business_name: Option<String>,
+    pub address: Option<String>,
+    pub email: Option<String>,
+    pub tax_id: Option<String>,
+    pub invoice_prefix: String,
+    pub next_invoice_number: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateMerchantProfile {
+    pub business_name: Option<String>,
+    pub address: Option<String>,
+    pub email: Option<String>,
+    pub tax_id: Option<String>,
+    pub invoice_prefix: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoicePage {
+    pub items: Vec<Invoice>,
+    pub next_cursor: Option<String>,
+}
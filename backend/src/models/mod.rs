@@ -1,4 +1,11 @@
+pub mod analytics;
+pub mod builder;
+pub mod checkout;
+pub mod coupon;
+pub mod email;
+pub mod invoice;
 pub mod notification;
+pub mod relay;
 pub mod subscription;
 pub mod transaction;
 pub mod user;
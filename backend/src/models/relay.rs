@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Lamports a merchant lets the relay spend on sign-ups to one plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorshipBudget {
+    pub plan_pda: String,
+    pub merchant_pubkey: String,
+    pub total_lamports: i64,
+    pub per_wallet_lamports: i64,
+    pub spent_lamports: i64,
+    pub active: bool,
+    /// Where deposits must be sent; the relay sponsor when the budget was created
+    pub deposit_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Limits only; the budget itself grows with deposits.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSponsorshipBudget {
+    pub per_wallet_lamports: i64,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+/// A confirmed SOL transfer from the plan creator to the relay sponsor.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordSponsorshipDeposit {
+    pub tx_signature: String,
+}
+
+/// A legacy transaction, bincode-serialized and base64-encoded, signed by the subscriber
+/// with the relay sponsor as fee payer.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayRequest {
+    pub transaction: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayResponse {
+    pub signature: String,
+    pub subscription_pda: String,
+    pub fee_lamports: u64,
+    pub rent_lamports: u64,
+}
+
+/// Where the client should point `feePayer` before signing.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelaySponsor {
+    pub fee_payer: String,
+    pub max_rent_top_up_lamports: u64,
+}
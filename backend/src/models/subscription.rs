@@ -17,6 +17,17 @@ pub struct Subscription {
     pub plan_creator: String,
     pub subscription: String,
     pub tx_signature: String,
+    /// Hex, like `amount`; absent for open-ended subscriptions
+    #[serde(default)]
+    pub max_cycles: Option<String>,
+    #[serde(default)]
+    pub end_ts: Option<String>,
+    /// Coupon PDA redeemed at subscribe time
+    #[serde(default)]
+    pub coupon: Option<String>,
+    /// Mint chosen at subscribe time, absent for the plan's primary mint
+    #[serde(default)]
+    pub mint: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,4 +37,30 @@ pub struct Tier {
     pub amount: String, // or u64 if you want to parse
     pub period_seconds: String,
     pub description: String,
+    /// Installment tiers: number of charges before the subscription completes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cycles: Option<String>,
+    /// Unix timestamp after which no further charges are taken
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_ts: Option<String>,
+    /// Metered tiers: `amount` is the base fee and each reported unit adds this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit_price: Option<String>,
+    /// Suggested cap on the usage part of one charge; the subscriber approves the final value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_cap: Option<String>,
+    /// Prices in the plan's other accepted mints; `amount` is in the primary mint
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prices: Vec<TierPrice>,
+    /// USD-priced tiers: dollars per cycle, mirrored on chain by `set_tier_usd_price` and
+    /// charged at the plan's oracle price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usd_price: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TierPrice {
+    pub mint: String,
+    pub amount: String,
 }
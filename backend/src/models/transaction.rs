@@ -11,5 +11,92 @@ pub struct PaymentHistory {
     pub status: String,
     pub tx_signature: Option<String>,
     pub subscription_pda: String,
+    pub plan_pda: Option<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// "paid" for the subscriber's copy, "received" for the merchant's.
+    pub direction: String,
+    /// On-chain `PaymentRecord` for the charge, which caps refunds.
+    pub payment_record: Option<String>,
+    /// For `refunded` rows, the subscriber's original payment row.
+    pub refund_of: Option<i64>,
+    /// Metered part of `amount`, when the tier bills usage
+    pub usage_units: Option<i64>,
+    pub usage_amount: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordRefund {
+    pub amount: i64,
+    pub tx_signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    pub status: Option<String>,
+    pub plan: Option<String>,
+    pub tier: Option<String>,
+    pub direction: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionTotals {
+    pub count: usize,
+    pub paid: i64,
+    pub received: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionPage {
+    pub items: Vec<PaymentHistory>,
+    pub next_cursor: Option<String>,
+    /// Sums over `items` only, not the whole filtered history.
+    pub totals: TransactionTotals,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Jsonl,
+    /// QuickBooks three-column bank CSV (Date, Description, Amount).
+    Quickbooks,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// One exported payment, with the amount rendered in the mint's UI units.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRow {
+    pub date: chrono::DateTime<chrono::Utc>,
+    pub tx_signature: Option<String>,
+    pub direction: String,
+    pub status: String,
+    pub wallet: String,
+    pub plan: String,
+    pub plan_pda: Option<String>,
+    pub tier: String,
+    pub subscription_pda: String,
+    pub amount: String,
+    pub raw_amount: i64,
+    pub token_symbol: String,
+    pub mint: Option<String>,
+    pub usage_units: Option<i64>,
+    pub usage_amount: Option<String>,
 }
@@ -0,0 +1,229 @@
+//! Fee-payer relay: checks a subscriber's partially signed sign-up transaction before the
+//! sponsor co-signs it, so the sponsor key can only ever pay fees and the rent of the
+//! accounts the sign-up creates.
+
+use anchor_lang::prelude::*;
+use solana_sdk::{message::compiled_instruction::CompiledInstruction, transaction::Transaction};
+use solpay_client::accounts::{Migratable, Subscription};
+use solpay_client::instructions::{
+    AccountSpec, initialize_subscription, set_max_token_amount, set_usage_cap, wrap_sol,
+};
+
+/// Fee the runtime charges per signature, before any priority fee.
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+/// Space `initialize_subscription` allocates for the first payment record.
+const PAYMENT_RECORD_SPACE: usize = 201;
+
+const TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const TOKEN_2022_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("TokenzQdBNbLqP5VEhdkAS6EHFLwAJDa1idNq5fTBsR");
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+const COMPUTE_BUDGET_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("ComputeBudget111111111111111111111111111111");
+// Priority fees would come out of the sponsor, so only the unit limit may be set
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+// SPL token instruction tags a sign-up may carry to delegate renewals
+const TOKEN_APPROVE: u8 = 4;
+const TOKEN_APPROVE_CHECKED: u8 = 13;
+const SYSTEM_TRANSFER: u32 = 2;
+
+/// Rent the sponsor may put into the subscription and first payment record, together.
+pub fn max_rent_top_up_lamports() -> u64 {
+    let rent = Rent::default();
+    rent.minimum_balance(Subscription::SPACE) + rent.minimum_balance(PAYMENT_RECORD_SPACE)
+}
+
+/// What a validated sign-up asks for, read from its `initialize_subscription` instruction.
+#[derive(Debug, Clone)]
+pub struct SponsoredSubscription {
+    pub payer: Pubkey,
+    pub subscription: Pubkey,
+    pub plan_pda: Pubkey,
+    pub mint: Pubkey,
+    pub tier_name: String,
+    pub period_seconds: i64,
+    pub amount: u64,
+    pub fee_lamports: u64,
+    pub rent_lamports: u64,
+}
+
+/// Position of one of an instruction's accounts.
+fn position(accounts: &[AccountSpec], name: &str) -> usize {
+    accounts
+        .iter()
+        .position(|a| a.name == name)
+        .expect("instruction account")
+}
+
+/// Accepts only a single SolPay sign-up, companion instructions on that same subscription,
+/// token approvals and account creation, a compute unit limit, and rent transfers from the
+/// sponsor straight into the subscription and payment record being created.
+pub fn validate_subscription_tx(
+    tx: &Transaction,
+    sponsor: &Pubkey,
+    program_id: &Pubkey,
+) -> anyhow::Result<SponsoredSubscription> {
+    let keys = &tx.message.account_keys;
+    if keys.first() != Some(sponsor) {
+        anyhow::bail!("Fee payer must be the relay sponsor {}", sponsor);
+    }
+
+    let key_at = |index: u8| {
+        keys.get(index as usize)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Account index {} out of range", index))
+    };
+    let account = |ix: &CompiledInstruction, position: usize| -> anyhow::Result<Pubkey> {
+        let index = *ix
+            .accounts
+            .get(position)
+            .ok_or_else(|| anyhow::anyhow!("Instruction is missing account {}", position))?;
+        key_at(index)
+    };
+    let uses_sponsor = |ix: &CompiledInstruction| ix.accounts.iter().any(|&index| index == 0);
+
+    let init = initialize_subscription::discriminator();
+    let mut sign_up = None;
+    let mut companions = Vec::new();
+    let mut top_ups: Vec<(Pubkey, u64)> = Vec::new();
+
+    for ix in &tx.message.instructions {
+        let program = key_at(ix.program_id_index)?;
+
+        if program == *program_id {
+            if uses_sponsor(ix) {
+                anyhow::bail!("SolPay instructions may not reference the sponsor");
+            }
+            let tag = ix.data.get(..8).unwrap_or_default();
+            if tag == init {
+                if sign_up.is_some() {
+                    anyhow::bail!("Only one subscription may be sponsored per transaction");
+                }
+                let accounts = initialize_subscription::ACCOUNTS;
+                let args = initialize_subscription::Args::deserialize(&mut &ix.data[8..])?;
+                sign_up = Some((
+                    args,
+                    account(ix, position(accounts, "payer"))?,
+                    account(ix, position(accounts, "subscription"))?,
+                    account(ix, position(accounts, "mint"))?,
+                    account(ix, position(accounts, "payment_record"))?,
+                ));
+            } else {
+                companions.push(ix);
+            }
+        } else if program == system_program::ID {
+            let kind = ix
+                .data
+                .get(..4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+            if kind != Some(SYSTEM_TRANSFER) || ix.data.len() != 12 {
+                anyhow::bail!("Only rent transfers are allowed");
+            }
+            if account(ix, 0)? != *sponsor {
+                anyhow::bail!("Rent transfers must come from the sponsor");
+            }
+            let lamports = u64::from_le_bytes(ix.data[4..12].try_into()?);
+            top_ups.push((account(ix, 1)?, lamports));
+        } else if program == TOKEN_PROGRAM_ID || program == TOKEN_2022_PROGRAM_ID {
+            let tag = ix.data.first().copied();
+            if !matches!(tag, Some(TOKEN_APPROVE | TOKEN_APPROVE_CHECKED)) || uses_sponsor(ix) {
+                anyhow::bail!("Only token approvals by the subscriber are allowed");
+            }
+        } else if program == ASSOCIATED_TOKEN_PROGRAM_ID {
+            if uses_sponsor(ix) {
+                anyhow::bail!("Token accounts must be funded by the subscriber");
+            }
+        } else if program == COMPUTE_BUDGET_PROGRAM_ID {
+            if ix.data.first() != Some(&SET_COMPUTE_UNIT_LIMIT) {
+                anyhow::bail!("Only a compute unit limit may be set");
+            }
+        } else {
+            anyhow::bail!(
+                "Program {} is not allowed in a sponsored transaction",
+                program
+            );
+        }
+    }
+
+    let (args, payer, subscription, mint, payment_record) =
+        sign_up.ok_or_else(|| anyhow::anyhow!("Transaction has no initialize_subscription"))?;
+    if args.amount == 0 || args.period_seconds <= 0 {
+        anyhow::bail!("Subscription amount and period must be positive");
+    }
+
+    // Companions may only configure the subscription being created, for its own payer
+    for ix in companions {
+        let tag = ix.data.get(..8).unwrap_or_default();
+        let on_sign_up = |accounts: &[AccountSpec]| -> anyhow::Result<bool> {
+            Ok(account(ix, position(accounts, "payer"))? == payer
+                && account(ix, position(accounts, "subscription"))? == subscription)
+        };
+        if tag == set_usage_cap::discriminator() {
+            set_usage_cap::Args::deserialize(&mut &ix.data[8..])?;
+            if !on_sign_up(set_usage_cap::ACCOUNTS)? {
+                anyhow::bail!("set_usage_cap must target the sponsored subscription");
+            }
+        } else if tag == set_max_token_amount::discriminator() {
+            let companion = set_max_token_amount::Args::deserialize(&mut &ix.data[8..])?;
+            if !on_sign_up(set_max_token_amount::ACCOUNTS)? {
+                anyhow::bail!("set_max_token_amount must target the sponsored subscription");
+            }
+            if companion.max_token_amount < args.amount {
+                anyhow::bail!("Token limit is below the first charge");
+            }
+        } else if tag == wrap_sol::discriminator() {
+            let companion = wrap_sol::Args::deserialize(&mut &ix.data[8..])?;
+            let accounts = wrap_sol::ACCOUNTS;
+            if account(ix, position(accounts, "owner"))? != payer {
+                anyhow::bail!("wrap_sol must be signed by the subscriber");
+            }
+            // An absent optional account is passed as the program id
+            let wrapped_for = account(ix, position(accounts, "subscription"))?;
+            if wrapped_for != subscription && wrapped_for != *program_id {
+                anyhow::bail!("wrap_sol must target the sponsored subscription");
+            }
+            if companion.lamports > args.amount {
+                anyhow::bail!("wrap_sol may wrap at most the first charge");
+            }
+        } else {
+            anyhow::bail!("SolPay instruction is not allowed in a sponsored transaction");
+        }
+    }
+
+    // Rent goes into the accounts the sign-up creates, never to a wallet
+    let rent = Rent::default();
+    let mut rent_lamports = 0;
+    for (index, (recipient, lamports)) in top_ups.iter().enumerate() {
+        let space = if *recipient == subscription {
+            Subscription::SPACE
+        } else if *recipient == payment_record {
+            PAYMENT_RECORD_SPACE
+        } else {
+            anyhow::bail!("Rent may only fund the subscription and its payment record");
+        };
+        if top_ups[..index]
+            .iter()
+            .any(|(earlier, _)| earlier == recipient)
+        {
+            anyhow::bail!("Each account may be funded only once");
+        }
+        if *lamports > rent.minimum_balance(space) {
+            anyhow::bail!("Rent transfer to {} exceeds its rent", recipient);
+        }
+        rent_lamports += lamports;
+    }
+
+    Ok(SponsoredSubscription {
+        payer,
+        subscription,
+        plan_pda: args.plan_pda,
+        mint,
+        tier_name: args.tier_name,
+        period_seconds: args.period_seconds,
+        amount: args.amount,
+        fee_lamports: tx.signatures.len() as u64 * LAMPORTS_PER_SIGNATURE,
+        rent_lamports,
+    })
+}
@@ -0,0 +1,9 @@
+use crate::handlers::analytics_handler::{get_plan_cohorts, get_plan_growth, get_plan_summary};
+use axum::{Router, routing::get};
+
+pub fn analytics_routes() -> Router {
+    Router::new()
+        .route("/analytics/plans/{plan_pda}/summary", get(get_plan_summary))
+        .route("/analytics/plans/{plan_pda}/growth", get(get_plan_growth))
+        .route("/analytics/plans/{plan_pda}/cohorts", get(get_plan_cohorts))
+}
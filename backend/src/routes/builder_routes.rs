@@ -0,0 +1,18 @@
+use crate::handlers::builder_handler::{
+    build_cancel_plan, build_cancel_subscription, build_change_tier, build_create_plan,
+    build_refund, build_subscribe, build_update_plan,
+};
+use axum::{Router, routing::post};
+
+pub fn builder_routes() -> Router {
+    Router::new()
+        .route("/build/subscribe", post(build_subscribe))
+        .route("/build/cancel", post(build_cancel_subscription))
+        .route("/build/change-tier", post(build_change_tier))
+        .route(
+            "/build/plans",
+            post(build_create_plan).put(build_update_plan),
+        )
+        .route("/build/plans/cancel", post(build_cancel_plan))
+        .route("/build/refund", post(build_refund))
+}
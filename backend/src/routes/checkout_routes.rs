@@ -0,0 +1,43 @@
+use crate::handlers::checkout_handler::{
+    create_pay_transaction, create_plan_action_transaction, get_pay_metadata, get_plan_action,
+};
+use axum::{
+    Router,
+    http::{HeaderName, Method, header},
+    routing::{get, post},
+};
+use tower_http::cors::{Any, CorsLayer};
+
+/// CORS for Solana Actions and pay links, which any wallet or Action client may call
+/// regardless of the origins the rest of the API allows.
+pub fn actions_cors() -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            header::CONTENT_ENCODING,
+            header::ACCEPT_ENCODING,
+            HeaderName::from_static("x-accept-action-version"),
+            HeaderName::from_static("x-accept-blockchain-ids"),
+        ])
+        .expose_headers([
+            HeaderName::from_static("x-action-version"),
+            HeaderName::from_static("x-blockchain-ids"),
+        ])
+}
+
+pub fn checkout_routes() -> Router {
+    Router::new()
+        .route(
+            "/pay/plans/{plan_pda}/tiers/{tier_name}",
+            get(get_pay_metadata).post(create_pay_transaction),
+        )
+        .route("/actions/plans/{plan_pda}", get(get_plan_action))
+        .route(
+            "/actions/plans/{plan_pda}/tiers/{tier_name}",
+            post(create_plan_action_transaction),
+        )
+        .layer(actions_cors())
+}
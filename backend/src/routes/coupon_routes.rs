@@ -0,0 +1,22 @@
+use crate::handlers::coupon_handler::{
+    get_coupon, get_coupon_by_code, get_plan_coupons, record_coupon_redemption, register_coupon,
+};
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+pub fn coupon_routes() -> Router {
+    Router::new()
+        .route("/coupons", post(register_coupon))
+        .route("/coupons/{coupon_pda}", get(get_coupon))
+        .route(
+            "/coupons/{coupon_pda}/redemptions",
+            post(record_coupon_redemption),
+        )
+        .route("/coupons/plan/{plan_pda}", get(get_plan_coupons))
+        .route(
+            "/coupons/plan/{plan_pda}/code/{code}",
+            get(get_coupon_by_code),
+        )
+}
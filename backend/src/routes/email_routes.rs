@@ -0,0 +1,22 @@
+use crate::handlers::email_handler::{
+    delete_email, get_email_settings, register_email, update_email_preferences, verify_email,
+};
+use axum::{
+    Router,
+    routing::{get, post, put},
+};
+
+pub fn email_routes() -> Router {
+    Router::new()
+        .route(
+            "/email/{user_pubkey}",
+            get(get_email_settings)
+                .put(register_email)
+                .delete(delete_email),
+        )
+        .route(
+            "/email/{user_pubkey}/preferences",
+            put(update_email_preferences),
+        )
+        .route("/email/verify", post(verify_email))
+}
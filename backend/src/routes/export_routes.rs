@@ -0,0 +1,14 @@
+use crate::handlers::export_handler::{export_plan_transactions, export_wallet_transactions};
+use axum::{Router, routing::get};
+
+pub fn export_routes() -> Router {
+    Router::new()
+        .route(
+            "/exports/transactions/user/{user_pubkey}",
+            get(export_wallet_transactions),
+        )
+        .route(
+            "/exports/transactions/plan/{plan_pda}",
+            get(export_plan_transactions),
+        )
+}
@@ -0,0 +1,24 @@
+use crate::handlers::invoice_handler::{
+    get_invoice, get_invoice_for_payment, get_invoice_receipt, get_merchant_invoices,
+    get_merchant_profile, get_payer_invoices, update_merchant_profile,
+};
+use axum::{Router, routing::get};
+
+pub fn invoice_routes() -> Router {
+    Router::new()
+        .route("/invoices/{invoice_id}", get(get_invoice))
+        .route("/invoices/{invoice_id}/receipt", get(get_invoice_receipt))
+        .route(
+            "/invoices/payment/{payment_id}",
+            get(get_invoice_for_payment),
+        )
+        .route("/invoices/payer/{payer}", get(get_payer_invoices))
+        .route(
+            "/invoices/merchant/{merchant_pubkey}",
+            get(get_merchant_invoices),
+        )
+        .route(
+            "/merchants/{merchant_pubkey}/profile",
+            get(get_merchant_profile).put(update_merchant_profile),
+        )
+}
@@ -1,13 +1,30 @@
+pub mod analytics_routes;
+pub mod builder_routes;
+pub mod checkout_routes;
+pub mod coupon_routes;
+pub mod email_routes;
+pub mod export_routes;
+pub mod invoice_routes;
 pub mod notification_routes;
+pub mod relay_routes;
 pub mod subscription_routes;
 pub mod transaction_routes;
 pub mod user_routes;
 use axum::Router;
 
+/// Everything but `checkout_routes`, which wallets and Action clients call from any
+/// origin and so carry their own CORS policy.
 pub fn create_routes() -> Router {
     Router::new()
         .merge(user_routes::user_routes())
         .merge(subscription_routes::subscription_routes())
         .merge(transaction_routes::transaction_routes())
         .merge(notification_routes::notification_routes())
+        .merge(email_routes::email_routes())
+        .merge(export_routes::export_routes())
+        .merge(invoice_routes::invoice_routes())
+        .merge(analytics_routes::analytics_routes())
+        .merge(coupon_routes::coupon_routes())
+        .merge(relay_routes::relay_routes())
+        .merge(builder_routes::builder_routes())
 }
@@ -1,20 +1,49 @@
 use crate::handlers::notification_handler::{
-    delete_notification, get_notifications, mark_notifications_as_read,
+    delete_notification, delete_notifications, get_notification_mutes, get_notifications,
+    get_unread_count, mark_notification_as_read, mark_notifications_as_read, mute_plan,
+    open_notification_stream, stream_notifications, unmute_plan,
 };
 use axum::{
     Router,
-    routing::{delete, get, put},
+    routing::{delete, get, post, put},
 };
 
 pub fn notification_routes() -> Router {
     Router::new()
-        .route("/notifications/user/{user_pubkey}", get(get_notifications))
+        .route(
+            "/notifications/user/{user_pubkey}",
+            get(get_notifications).delete(delete_notifications),
+        )
+        .route(
+            "/notifications/user/{user_pubkey}/unread",
+            get(get_unread_count),
+        )
         .route(
             "/notifications/{notification_id}",
             delete(delete_notification),
         )
+        .route(
+            "/notifications/{notification_id}/read",
+            put(mark_notification_as_read),
+        )
         .route(
             "/notifications/read/{user_pubkey}",
             put(mark_notifications_as_read),
         )
+        .route(
+            "/notifications/stream/{user_pubkey}",
+            get(stream_notifications),
+        )
+        .route(
+            "/notifications/stream/{user_pubkey}/session",
+            post(open_notification_stream),
+        )
+        .route(
+            "/notifications/mutes/{user_pubkey}",
+            get(get_notification_mutes),
+        )
+        .route(
+            "/notifications/mutes/{user_pubkey}/{plan_pda}",
+            put(mute_plan).delete(unmute_plan),
+        )
 }
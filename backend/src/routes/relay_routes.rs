@@ -0,0 +1,22 @@
+use crate::handlers::relay_handler::{
+    get_relay_sponsor, get_sponsorship_budget, record_sponsorship_deposit, relay_subscription,
+    update_sponsorship_budget,
+};
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+pub fn relay_routes() -> Router {
+    Router::new()
+        .route("/relay/sponsor", get(get_relay_sponsor))
+        .route("/relay/subscriptions", post(relay_subscription))
+        .route(
+            "/relay/budgets/{plan_pda}",
+            get(get_sponsorship_budget).put(update_sponsorship_budget),
+        )
+        .route(
+            "/relay/budgets/{plan_pda}/deposits",
+            post(record_sponsorship_deposit),
+        )
+}
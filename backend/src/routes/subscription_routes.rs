@@ -1,6 +1,7 @@
 use crate::handlers::subscription_handler::{
-    create_subscription, delete_subscription, get_subscriptions, get_subscriptions_by_plan,
-    renew_subscription, update_subscription,
+    create_subscription, delete_subscription, get_subscription_usage, get_subscriptions,
+    get_subscriptions_by_plan, pause_subscription, renew_subscription, resume_subscription,
+    update_subscription,
 };
 use axum::{
     Router,
@@ -16,6 +17,18 @@ pub fn subscription_routes() -> Router {
                 .patch(update_subscription)
                 .post(renew_subscription),
         )
+        .route(
+            "/subscriptions/{subscription_pda}/pause",
+            post(pause_subscription),
+        )
+        .route(
+            "/subscriptions/{subscription_pda}/resume",
+            post(resume_subscription),
+        )
+        .route(
+            "/subscriptions/{subscription_pda}/usage",
+            get(get_subscription_usage),
+        )
         .route("/subscriptions", post(create_subscription))
         .route(
             "/subscriptions/plan/{plan_pda}",
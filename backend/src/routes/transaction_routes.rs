@@ -1,9 +1,9 @@
 use crate::handlers::transaction_handler::{
-    delete_transaction, get_subscription_transactions, get_transactions,
+    delete_transaction, get_subscription_transactions, get_transactions, record_refund,
 };
 use axum::{
     Router,
-    routing::{delete, get},
+    routing::{delete, get, post},
 };
 
 pub fn transaction_routes() -> Router {
@@ -17,4 +17,5 @@ pub fn transaction_routes() -> Router {
             "/transactions/{id}",
             delete(delete_transaction), // ← new delete route
         )
+        .route("/transactions/refunds/{id}", post(record_refund))
 }
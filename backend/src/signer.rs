@@ -0,0 +1,225 @@
+//! The keeper's signing key, kept behind `KeeperSigner` so it can live in a file, in the
+//! environment or in a remote signing service, and be swapped while the server runs.
+
+use crate::config::{Environment, SignerConfig};
+use anyhow::{Context, anyhow, bail};
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer, read_keypair_file},
+    transaction::Transaction,
+};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+/// Bearer token for the remote signer, kept out of config files and flags.
+pub const SIGNER_TOKEN_VAR: &str = "SOLPAY_SIGNER_TOKEN";
+
+#[async_trait]
+pub trait KeeperSigner: Send + Sync {
+    fn pubkey(&self) -> Pubkey;
+
+    async fn sign_message(&self, message: &[u8]) -> anyhow::Result<Signature>;
+}
+
+/// A key held in memory, read from a keypair file or a base58 secret.
+pub struct KeypairSigner(Keypair);
+
+impl KeypairSigner {
+    pub fn new(keypair: Keypair) -> Self {
+        Self(keypair)
+    }
+
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        read_keypair_file(path)
+            .map(Self)
+            .map_err(|e| anyhow!("Cannot read keypair {}: {}", path.display(), e))
+    }
+
+    /// `var` holds the 64-byte secret key in base58, as wallets export it.
+    pub fn from_env(var: &str) -> anyhow::Result<Self> {
+        Self::from_env_in(var, &Environment::capture())
+    }
+
+    /// As `from_env`, reading `var` from `env`.
+    pub fn from_env_in(var: &str, env: &Environment) -> anyhow::Result<Self> {
+        let secret = env
+            .var(var)
+            .with_context(|| format!("{} is not set", var))?;
+        let bytes = bs58::decode(secret.trim())
+            .into_vec()
+            .with_context(|| format!("{} is not base58", var))?;
+        Keypair::try_from(bytes.as_slice())
+            .map(Self)
+            .map_err(|_| anyhow!("{} is not a 64-byte secret key", var))
+    }
+}
+
+#[async_trait]
+impl KeeperSigner for KeypairSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.0.pubkey()
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> anyhow::Result<Signature> {
+        Ok(self.0.sign_message(message))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemotePubkey {
+    pub pubkey: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteSignRequest {
+    pub pubkey: String,
+    /// Base64 of the serialized transaction message
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteSignResponse {
+    /// Base58
+    pub signature: String,
+}
+
+/// A signing service that never reveals the key: `GET {url}/pubkey` names it and
+/// `POST {url}/sign` signs a message with it. Signatures are verified before use.
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    pubkey: Pubkey,
+}
+
+impl RemoteSigner {
+    pub async fn connect(url: &str, token: Option<String>) -> anyhow::Result<Self> {
+        let url = url.trim_end_matches('/').to_string();
+        let client = reqwest::Client::new();
+        let mut request = client.get(format!("{}/pubkey", url));
+        if let Some(token) = &token {
+            request = request.bearer_auth(token);
+        }
+        let response: RemotePubkey = request
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("Signing service {} is unreachable", url))?
+            .json()
+            .await
+            .context("Signing service sent a malformed pubkey")?;
+        let pubkey = Pubkey::from_str(&response.pubkey)
+            .map_err(|_| anyhow!("Signing service key {} is not an address", response.pubkey))?;
+
+        Ok(Self {
+            client,
+            url,
+            token,
+            pubkey,
+        })
+    }
+}
+
+#[async_trait]
+impl KeeperSigner for RemoteSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> anyhow::Result<Signature> {
+        let mut request = self
+            .client
+            .post(format!("{}/sign", self.url))
+            .json(&RemoteSignRequest {
+                pubkey: self.pubkey.to_string(),
+                message: STANDARD.encode(message),
+            });
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response: RemoteSignResponse = request
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .context("Signing service refused to sign")?
+            .json()
+            .await
+            .context("Signing service sent a malformed signature")?;
+
+        let signature = Signature::from_str(&response.signature)
+            .map_err(|_| anyhow!("Signing service sent a malformed signature"))?;
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            bail!("Signing service signature does not match {}", self.pubkey);
+        }
+        Ok(signature)
+    }
+}
+
+/// Builds the signer the configuration names, reading secrets from `env`; remote signers
+/// are contacted for their key.
+pub async fn from_config(
+    config: &SignerConfig,
+    env: &Environment,
+) -> anyhow::Result<Arc<dyn KeeperSigner>> {
+    Ok(match config {
+        SignerConfig::File(path) => Arc::new(KeypairSigner::from_file(path)?),
+        SignerConfig::Env(var) => Arc::new(KeypairSigner::from_env_in(var, env)?),
+        SignerConfig::Remote { url } => {
+            let token = env.var(SIGNER_TOKEN_VAR).map(String::from);
+            Arc::new(RemoteSigner::connect(url, token).await?)
+        }
+    })
+}
+
+/// Adds `signer`'s signature in its slot of `tx`, whose blockhash must already be set.
+pub async fn sign_transaction(
+    signer: &dyn KeeperSigner,
+    tx: &mut Transaction,
+) -> anyhow::Result<()> {
+    let pubkey = signer.pubkey();
+    let required = tx.message.header.num_required_signatures as usize;
+    let slot = tx.message.account_keys[..required]
+        .iter()
+        .position(|key| *key == pubkey)
+        .ok_or_else(|| anyhow!("{} is not a signer of this transaction", pubkey))?;
+
+    let signature = signer.sign_message(&tx.message_data()).await?;
+    tx.signatures.resize(required, Signature::default());
+    tx.signatures[slot] = signature;
+    Ok(())
+}
+
+/// The keeper's current signer. Readers take a snapshot, so a rotation never changes the
+/// key halfway through building and signing a transaction.
+pub struct ActiveSigner {
+    current: RwLock<Arc<dyn KeeperSigner>>,
+}
+
+impl ActiveSigner {
+    pub fn new(signer: Arc<dyn KeeperSigner>) -> Self {
+        Self {
+            current: RwLock::new(signer),
+        }
+    }
+
+    pub fn current(&self) -> Arc<dyn KeeperSigner> {
+        self.current.read().expect("signer lock poisoned").clone()
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.current().pubkey()
+    }
+
+    /// Swaps in `signer`, returning the key it replaced.
+    pub fn rotate(&self, signer: Arc<dyn KeeperSigner>) -> Pubkey {
+        let mut current = self.current.write().expect("signer lock poisoned");
+        std::mem::replace(&mut *current, signer).pubkey()
+    }
+}
@@ -1,74 +1,169 @@
-use crate::types::{Plan, SubscriptionField, UpdateValue};
+use crate::config::SolanaConfig;
+use crate::signer::{self, ActiveSigner, KeeperSigner};
+use crate::transfer_hook::{self, Seed};
+use crate::tx_builder;
+use crate::types::{
+    Coupon, PaymentRecord, Plan, SubscriptionAccount, SubscriptionField, TokenAccountFunding,
+    UpdateValue,
+};
 use crate::utils::decompress_tiers;
 use anchor_lang::prelude::*;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use solana_client::client_error::{ClientError, ClientErrorKind};
 use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_request::{RpcError, RpcResponseErrorData};
 use solana_sdk::signature::Signature;
 use solana_sdk::{
-    hash::hash,
-    instruction::{AccountMeta, Instruction},
+    hash::Hash,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    message::Message,
     pubkey::Pubkey,
-    signature::{Keypair, Signer, read_keypair_file},
-    transaction::Transaction,
+    transaction::{Transaction, TransactionError},
 };
+use solana_transaction_status::UiTransactionEncoding;
+use solpay_client::accounts::{Migratable, ProgramAccount};
+use solpay_client::events::{PaymentExecuted, ProgramEvent, SubscriptionInitialized};
+use solpay_client::oracle::{self, OraclePrice};
+use solpay_client::types::PriceFeed;
+use solpay_client::{instructions as ix, pda};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
 use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
 use tracing::{error, info};
 
+// Base SPL Token layout offsets, shared by Token-2022 accounts before their extensions.
+const MINT_DECIMALS_OFFSET: usize = 44;
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+const TOKEN_ACCOUNT_DELEGATE_OFFSET: usize = 72;
+const TOKEN_ACCOUNT_DELEGATED_AMOUNT_OFFSET: usize = 121;
+const TOKEN_ACCOUNT_BASE_LEN: usize = 165;
+/// Most keys `getMultipleAccounts` takes in one request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+/// Most signatures `getSignaturesForAddress` returns in one request.
+const MAX_SIGNATURES_PER_PAGE: usize = 1_000;
+/// Anchor numbers program errors from 6000, and `PaymentNotDue` is declared as 6000 on top.
+const PAYMENT_NOT_DUE: u32 = 12000;
+
+/// Wrapped SOL, the mint of plans priced in SOL.
+pub const NATIVE_MINT: Pubkey =
+    Pubkey::from_str_const("So11111111111111111111111111111111111111112");
+
+/// Whether a failed charge was the program refusing one that is not due yet.
+pub fn is_payment_not_due(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<ClientError>()
+        .and_then(ClientError::get_transaction_error)
+        .is_some_and(|e| {
+            matches!(
+                e,
+                TransactionError::InstructionError(_, InstructionError::Custom(PAYMENT_NOT_DUE))
+            )
+        })
+}
+
+/// Whether a send was turned away by preflight simulation, so it can never land.
+pub fn is_preflight_rejection(err: &ClientError) -> bool {
+    matches!(
+        err.kind(),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(_),
+            ..
+        })
+    )
+}
+
 pub struct SolanaClient {
     pub rpc: RpcClient,
-    pub payer: Keypair,
+    /// The keeper; pays fees and signs renewals and admin updates
+    pub signer: ActiveSigner,
     pub program_id: Pubkey, // The Anchor Program ID
+    blockchain_id: OnceCell<String>,
 }
 
 impl SolanaClient {
-    // Constructor
-    pub async fn new(rpc_url: &str, keypair_path: &str, program_id: &str) -> Self {
-        // NOTE: The `read_keypair_file` takes care of the "~" expansion for the path
-        let payer =
-            read_keypair_file(keypair_path).expect("❌ Failed to read keypair file for Payer");
-        let program_id = Pubkey::from_str(program_id).expect("❌ Invalid program ID");
-        let rpc = RpcClient::new(rpc_url.to_string());
+    pub async fn new(config: &SolanaConfig, signer: Arc<dyn KeeperSigner>) -> Self {
+        let rpc = RpcClient::new_with_commitment(config.rpc_url.clone(), config.commitment.into());
 
         Self {
             rpc,
-            payer,
-            program_id,
+            signer: ActiveSigner::new(signer),
+            program_id: config.program_id,
+            blockchain_id: OnceCell::new(),
         }
     }
 
+    /// CAIP-2 id of the cluster the RPC serves: `solana:` and the first 32 characters of
+    /// its genesis hash, as Solana Actions expect in `X-Blockchain-Ids`.
+    pub async fn blockchain_id(&self) -> anyhow::Result<&str> {
+        let id = self
+            .blockchain_id
+            .get_or_try_init(|| async {
+                let genesis = self.rpc.get_genesis_hash().await?.to_string();
+                let prefix = genesis.get(..32).unwrap_or(&genesis);
+                anyhow::Ok(format!("solana:{}", prefix))
+            })
+            .await?;
+        Ok(id)
+    }
+
     pub async fn execute_subscription_payment(
         &self,
         subscription: Pubkey,
         plan: Pubkey,
         user_token_account: Pubkey,
+        receiver: Pubkey,
         receiver_token_account: Pubkey,
         mint: Pubkey,
         token_program: Pubkey,
+        payment_record: Pubkey,
         new_amount: u64,
         new_period_seconds: i64,
+        price_feed: Option<Pubkey>,
+        hook_accounts: Vec<AccountMeta>,
     ) -> anyhow::Result<Signature> {
         info!("🔁 Executing subscription payment on-chain");
+        let keeper = self.signer.current();
 
-        // ---------- 1️⃣ Build instruction data ----------
-        let discriminator = &hash(b"global:execute_payment").to_bytes()[..8];
-
-        let mut data = Vec::with_capacity(8 + 8 + 8);
-        data.extend_from_slice(discriminator);
-        data.extend_from_slice(&new_amount.to_le_bytes());
-        data.extend_from_slice(&new_period_seconds.to_le_bytes());
-
-        let ix = Instruction {
-            program_id: self.program_id,
-            accounts: vec![
-                AccountMeta::new(subscription, false),
-                AccountMeta::new_readonly(plan, false),
-                AccountMeta::new(user_token_account, false),
-                AccountMeta::new(receiver_token_account, false),
-                AccountMeta::new_readonly(mint, false),
-                AccountMeta::new_readonly(system_program::ID, false),
-                AccountMeta::new_readonly(token_program, false),
-            ],
-            data,
-        };
+        // ---------- 1️⃣ Build instruction ----------
+        let mut ix = ix::execute_payment::instruction(
+            &self.program_id,
+            &ix::execute_payment::Accounts {
+                subscription,
+                plan,
+                user_token_account,
+                receiver_token_account,
+                mint,
+                system_program: system_program::ID,
+                token_program,
+                keeper: keeper.pubkey(), // keeper pays record rent
+                payment_record,
+                price_feed,
+                // Only signs when the payer's account approved it, as wSOL accounts do
+                sol_delegate: Some(pda::sol_delegate(&self.program_id)),
+            },
+            &ix::execute_payment::Args {
+                new_amount,
+                new_period_seconds,
+            },
+        );
+        // Transfer-hook mints read these from the remaining accounts
+        ix.accounts.extend(hook_accounts);
+
+        let mut instructions = self
+            .migration_instructions(&keeper.pubkey(), &[subscription, plan])
+            .await?;
+        // Merchants of SOL plans may never have opened a wSOL account
+        if mint == NATIVE_MINT {
+            instructions.push(create_associated_token_account_idempotent(
+                &keeper.pubkey(),
+                &receiver,
+                &mint,
+                &token_program,
+            ));
+        }
+        instructions.push(ix);
 
         // ---------- 2️⃣ Get blockhash ----------
         let blockhash = match self.rpc.get_latest_blockhash().await {
@@ -80,12 +175,7 @@ impl SolanaClient {
         };
 
         // ---------- 3️⃣ Build transaction ----------
-        let tx = Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&self.payer.pubkey()),
-            &[&self.payer],
-            blockhash,
-        );
+        let tx = Self::signed_transaction(&*keeper, &instructions, blockhash).await?;
 
         // ---------- 4️⃣ Send transaction ----------
         let sig = match self.rpc.send_and_confirm_transaction(&tx).await {
@@ -100,6 +190,99 @@ impl SolanaClient {
         Ok(sig)
     }
 
+    /// Extra accounts a transfer-hook mint needs on a transfer, empty for other mints.
+    pub async fn transfer_hook_accounts(
+        &self,
+        mint: &Pubkey,
+        source: &Pubkey,
+        destination: &Pubkey,
+        authority: &Pubkey,
+        amount: u64,
+    ) -> anyhow::Result<Vec<AccountMeta>> {
+        let mint_account = self.rpc.get_account(mint).await?;
+        let Some(hook_program) = transfer_hook::transfer_hook_program(&mint_account.data) else {
+            return Ok(Vec::new());
+        };
+
+        let validation = transfer_hook::validation_account(mint, &hook_program);
+        let validation_data = self.rpc.get_account(&validation).await?.data;
+        let extras = transfer_hook::parse_extra_account_metas(&validation_data)?;
+        let instruction_data = transfer_hook::execute_instruction_data(amount);
+
+        // Seeds index into the hook's `execute` accounts, then the extras resolved so far
+        let mut keys = vec![*source, *mint, *destination, *authority, validation];
+        let mut metas = Vec::with_capacity(extras.len() + 2);
+        for extra in &extras {
+            if extra.is_signer {
+                anyhow::bail!(
+                    "Transfer hook on {} needs a signer the keeper cannot provide",
+                    mint
+                );
+            }
+            let address = match extra.discriminator {
+                0 => Pubkey::new_from_array(extra.address_config),
+                discriminator => {
+                    let program = match discriminator {
+                        1 => hook_program,
+                        d if d >= 128 => *keys.get(usize::from(d - 128)).ok_or_else(|| {
+                            anyhow::anyhow!("Hook seed program index out of range")
+                        })?,
+                        d => anyhow::bail!("Unknown extra account type {}", d),
+                    };
+                    let seeds = self
+                        .resolve_hook_seeds(&extra.address_config, &keys, &instruction_data)
+                        .await?;
+                    let seeds: Vec<&[u8]> = seeds.iter().map(Vec::as_slice).collect();
+                    Pubkey::find_program_address(&seeds, &program).0
+                }
+            };
+            keys.push(address);
+            metas.push(transfer_hook::meta_for(address, extra));
+        }
+
+        metas.push(AccountMeta::new_readonly(hook_program, false));
+        metas.push(AccountMeta::new_readonly(validation, false));
+        Ok(metas)
+    }
+
+    async fn resolve_hook_seeds(
+        &self,
+        config: &[u8; 32],
+        keys: &[Pubkey],
+        instruction_data: &[u8],
+    ) -> anyhow::Result<Vec<Vec<u8>>> {
+        let key_at = |index: usize| {
+            keys.get(index)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("Hook seed account index {} out of range", index))
+        };
+
+        let mut resolved = Vec::new();
+        for seed in transfer_hook::parse_seeds(config)? {
+            let bytes = match seed {
+                Seed::Literal(bytes) => bytes,
+                Seed::InstructionData { index, length } => instruction_data
+                    .get(index..index + length)
+                    .ok_or_else(|| anyhow::anyhow!("Hook seed reads past instruction data"))?
+                    .to_vec(),
+                Seed::AccountKey { index } => key_at(index)?.to_bytes().to_vec(),
+                Seed::AccountData {
+                    account_index,
+                    data_index,
+                    length,
+                } => {
+                    let data = self.rpc.get_account(&key_at(account_index)?).await?.data;
+                    data.get(data_index..data_index + length)
+                        .ok_or_else(|| anyhow::anyhow!("Hook seed reads past account data"))?
+                        .to_vec()
+                }
+            };
+            resolved.push(bytes);
+        }
+
+        Ok(resolved)
+    }
+
     pub async fn get_plan(&self, plan_pda: Pubkey) -> anyhow::Result<Option<Plan>> {
         // 1️⃣ Fetch raw account
         let account = match self.rpc.get_account(&plan_pda).await {
@@ -113,16 +296,8 @@ impl SolanaClient {
             }
         };
 
-        let data: &[u8] = &account.data;
-
-        // CHECK: Ensure data is long enough for discriminator (8 bytes)
-        if data.len() < 8 {
-            return Err(anyhow::anyhow!("Account data too small"));
-        }
-
-        // FIX: Skip the first 8 bytes (Discriminator) and use standard deserialize
-        let mut data_slice = &data[8..];
-        let mut plan = Plan::deserialize(&mut data_slice)?;
+        // Checks the discriminator, so a wrong address is an error rather than garbage
+        let mut plan = Plan::decode(&account.data)?;
 
         // 3️⃣ Decompress tiers (pako-compatible)
         plan.tiers = decompress_tiers(&plan.tiers)?;
@@ -131,6 +306,32 @@ impl SolanaClient {
         Ok(Some(plan))
     }
 
+    /// Every plan the program owns, with tiers decompressed like `get_plan`.
+    pub async fn get_plans(&self) -> anyhow::Result<Vec<(Pubkey, Plan)>> {
+        let accounts = self.rpc.get_program_accounts(&self.program_id).await?;
+        let mut plans = Vec::new();
+        for (address, account) in accounts {
+            // Subscriptions, records and coupons live under the same program
+            if !account.data.starts_with(&Plan::discriminator()) {
+                continue;
+            }
+            let mut plan = Plan::decode(&account.data)?;
+            plan.tiers = decompress_tiers(&plan.tiers)?;
+            plans.push((address, plan));
+        }
+        Ok(plans)
+    }
+
+    /// Signs with the client's signer, which also pays the fee, and waits for confirmation.
+    pub async fn send_instructions(
+        &self,
+        instructions: &[Instruction],
+    ) -> anyhow::Result<Signature> {
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        let tx = Self::signed_transaction(&*self.signer.current(), instructions, blockhash).await?;
+        Ok(self.rpc.send_and_confirm_transaction(&tx).await?)
+    }
+
     pub async fn update_subscription_status(
         &self,
         subscription_pda: Pubkey,
@@ -138,25 +339,18 @@ impl SolanaClient {
         value: UpdateValue,
     ) -> anyhow::Result<Signature> {
         info!("📝 Updating subscription status on-chain");
+        let keeper = self.signer.current();
 
-        let discriminator = &hash(b"global:update_subscription_status").to_bytes()[..8];
-
-        let mut data = Vec::with_capacity(100);
-        data.extend_from_slice(discriminator);
-
-        field.serialize(&mut data)?;
-        value.serialize(&mut data)?;
-
-        let accounts = vec![
-            AccountMeta::new(self.payer.pubkey(), true), // Payer must sign
-            AccountMeta::new(subscription_pda, false),   // Subscription is mutable
-        ];
-
-        let ix = Instruction {
-            program_id: self.program_id,
-            accounts,
-            data,
-        };
+        let mut instructions = self
+            .migration_instructions(&keeper.pubkey(), &[subscription_pda])
+            .await?;
+        instructions.push(tx_builder::update_subscription_status(
+            &self.program_id,
+            &keeper.pubkey(),
+            &subscription_pda,
+            field,
+            value,
+        ));
 
         // ---------- 3️⃣ Get blockhash ----------
         let blockhash = match self.rpc.get_latest_blockhash().await {
@@ -168,12 +362,7 @@ impl SolanaClient {
         };
 
         // ---------- 4️⃣ Build transaction ----------
-        let tx = Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&self.payer.pubkey()),
-            &[&self.payer],
-            blockhash,
-        );
+        let tx = Self::signed_transaction(&*keeper, &instructions, blockhash).await?;
 
         // ---------- 5️⃣ Send transaction ----------
         let sig = match self.rpc.send_and_confirm_transaction(&tx).await {
@@ -187,4 +376,369 @@ impl SolanaClient {
         info!("✅ update_subscription_status success: {}", sig);
         Ok(sig)
     }
+
+    /// Ends a pause whose plan limit has passed; the keeper signs as authority.
+    pub async fn resume_subscription(
+        &self,
+        subscription: Pubkey,
+        plan: Pubkey,
+    ) -> anyhow::Result<Signature> {
+        let keeper = self.signer.current();
+        let mut instructions = self
+            .migration_instructions(&keeper.pubkey(), &[subscription, plan])
+            .await?;
+        instructions.push(ix::resume_subscription::instruction(
+            &self.program_id,
+            &ix::resume_subscription::Accounts {
+                authority: keeper.pubkey(),
+                subscription,
+                plan,
+            },
+            &ix::resume_subscription::Args {},
+        ));
+
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        let tx = Self::signed_transaction(&*keeper, &instructions, blockhash).await?;
+
+        let sig = match self.rpc.send_and_confirm_transaction(&tx).await {
+            Ok(sig) => sig,
+            Err(e) => {
+                error!("❌ resume_subscription failed: {}", e);
+                return Err(e.into());
+            }
+        };
+
+        info!("✅ resume_subscription success: {}", sig);
+        Ok(sig)
+    }
+
+    /// `migrate_account` for each of `accounts` still in a layout from before its type
+    /// grew, paid by `payer`. The program cannot load those accounts for writing, so
+    /// these go ahead of any instruction that does.
+    pub async fn migration_instructions(
+        &self,
+        payer: &Pubkey,
+        accounts: &[Pubkey],
+    ) -> anyhow::Result<Vec<Instruction>> {
+        let mut instructions = Vec::new();
+        for chunk in accounts.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let fetched = self.rpc.get_multiple_accounts(chunk).await?;
+            for (address, account) in chunk.iter().zip(fetched) {
+                let Some(account) = account.filter(|a| a.owner == self.program_id) else {
+                    continue;
+                };
+                let data = &account.data;
+                let legacy = if data.starts_with(&Plan::discriminator()) {
+                    Plan::needs_migration(data)
+                } else if data.starts_with(&SubscriptionAccount::discriminator()) {
+                    SubscriptionAccount::needs_migration(data)
+                } else {
+                    false
+                };
+                if legacy {
+                    instructions.push(tx_builder::migrate_account(
+                        &self.program_id,
+                        payer,
+                        address,
+                    ));
+                }
+            }
+        }
+        Ok(instructions)
+    }
+
+    /// `instructions` paid for and signed by `keeper` alone.
+    async fn signed_transaction(
+        keeper: &dyn KeeperSigner,
+        instructions: &[Instruction],
+        blockhash: Hash,
+    ) -> anyhow::Result<Transaction> {
+        let message = Message::new_with_blockhash(instructions, Some(&keeper.pubkey()), &blockhash);
+        let mut tx = Transaction::new_unsigned(message);
+        signer::sign_transaction(keeper, &mut tx).await?;
+        Ok(tx)
+    }
+
+    /// Unsigned transaction for `fee_payer` with a compute budget and a fresh blockhash,
+    /// bincode-serialized and base64-encoded, plus the block height it stays valid until.
+    pub async fn unsigned_transaction(
+        &self,
+        fee_payer: &Pubkey,
+        instructions: Vec<Instruction>,
+        compute_unit_price: Option<u64>,
+    ) -> anyhow::Result<(String, Hash, u64)> {
+        let mut all = vec![tx_builder::compute_unit_limit(
+            tx_builder::DEFAULT_COMPUTE_UNIT_LIMIT,
+        )];
+        all.extend(compute_unit_price.map(tx_builder::compute_unit_price));
+        all.extend(instructions);
+
+        let (blockhash, last_valid_block_height) = self
+            .rpc
+            .get_latest_blockhash_with_commitment(self.rpc.commitment())
+            .await?;
+        let message = Message::new_with_blockhash(&all, Some(fee_payer), &blockhash);
+        let tx = Transaction::new_unsigned(message);
+
+        Ok((
+            STANDARD.encode(bincode::serialize(&tx)?),
+            blockhash,
+            last_valid_block_height,
+        ))
+    }
+
+    /// Instruction creating `owner`'s ATA if it is missing, paid by `funder`.
+    pub async fn create_ata_if_missing(
+        &self,
+        funder: &Pubkey,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        token_program: &Pubkey,
+    ) -> anyhow::Result<Option<Instruction>> {
+        let ata = get_associated_token_address_with_program_id(owner, mint, token_program);
+        let exists = self
+            .rpc
+            .get_account_with_commitment(&ata, self.rpc.commitment())
+            .await?
+            .value
+            .is_some();
+
+        Ok((!exists).then(|| {
+            create_associated_token_account_idempotent(funder, owner, mint, token_program)
+        }))
+    }
+
+    /// Owner of the mint account, i.e. Token or Token-2022.
+    pub async fn get_token_program(&self, mint: &Pubkey) -> anyhow::Result<Pubkey> {
+        Ok(self.rpc.get_account(mint).await?.owner)
+    }
+
+    /// Each charge gets a record at `[b"payment", subscription, index]`.
+    pub fn payment_record_pda(&self, subscription: &Pubkey, index: u64) -> Pubkey {
+        pda::payment_record(&self.program_id, subscription, index)
+    }
+
+    /// Coupons live at `[b"coupon", plan, code]`.
+    pub fn coupon_pda(&self, plan: &Pubkey, code: &str) -> Pubkey {
+        pda::coupon(&self.program_id, plan, code)
+    }
+
+    pub async fn get_coupon(&self, coupon_pda: &Pubkey) -> anyhow::Result<Option<Coupon>> {
+        self.get_anchor_account(coupon_pda).await
+    }
+
+    pub async fn get_subscription_account(
+        &self,
+        subscription_pda: &Pubkey,
+    ) -> anyhow::Result<Option<SubscriptionAccount>> {
+        self.get_anchor_account(subscription_pda).await
+    }
+
+    pub async fn get_payment_record(
+        &self,
+        payment_record: &Pubkey,
+    ) -> anyhow::Result<Option<PaymentRecord>> {
+        self.get_anchor_account(payment_record).await
+    }
+
+    async fn get_anchor_account<T: ProgramAccount>(
+        &self,
+        address: &Pubkey,
+    ) -> anyhow::Result<Option<T>> {
+        let account = match self.rpc.get_account(address).await {
+            Ok(acc) => acc,
+            Err(err) => {
+                if err.to_string().contains("AccountNotFound") {
+                    return Ok(None);
+                }
+                return Err(err.into());
+            }
+        };
+
+        Ok(Some(T::decode(&account.data)?))
+    }
+
+    /// What a confirmed `execute_payment` transaction actually charged, from its logs.
+    pub async fn payment_executed_event(
+        &self,
+        signature: &Signature,
+    ) -> anyhow::Result<Option<PaymentExecuted>> {
+        let logs = self.transaction_logs(signature).await?;
+        Ok(PaymentExecuted::from_logs(&logs)?.into_iter().next())
+    }
+
+    /// Subscriptions a confirmed transaction created, from its logs.
+    pub async fn subscription_initialized_events(
+        &self,
+        signature: &Signature,
+    ) -> anyhow::Result<Vec<SubscriptionInitialized>> {
+        let logs = self.transaction_logs(signature).await?;
+        SubscriptionInitialized::from_logs(&logs)
+    }
+
+    /// Newest transaction that touched the program, if any has.
+    pub async fn latest_program_signature(&self) -> anyhow::Result<Option<Signature>> {
+        let config = GetConfirmedSignaturesForAddress2Config {
+            limit: Some(1),
+            commitment: Some(self.rpc.commitment()),
+            ..Default::default()
+        };
+        let page = self
+            .rpc
+            .get_signatures_for_address_with_config(&self.program_id, config)
+            .await?;
+        page.first()
+            .map(|status| Signature::from_str(&status.signature))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// Program transactions after `until`, oldest first, each with whether it succeeded.
+    pub async fn program_signatures_since(
+        &self,
+        until: Signature,
+    ) -> anyhow::Result<Vec<(Signature, bool)>> {
+        let mut signatures = Vec::new();
+        let mut before = None;
+        loop {
+            let config = GetConfirmedSignaturesForAddress2Config {
+                before,
+                until: Some(until),
+                limit: Some(MAX_SIGNATURES_PER_PAGE),
+                commitment: Some(self.rpc.commitment()),
+            };
+            let page = self
+                .rpc
+                .get_signatures_for_address_with_config(&self.program_id, config)
+                .await?;
+            let full = page.len() == MAX_SIGNATURES_PER_PAGE;
+            for status in page {
+                let signature = Signature::from_str(&status.signature)?;
+                signatures.push((signature, status.err.is_none()));
+                before = Some(signature);
+            }
+            if !full {
+                break;
+            }
+        }
+        signatures.reverse();
+        Ok(signatures)
+    }
+
+    /// Log messages of a confirmed transaction, where the program's events are.
+    pub async fn transaction_logs(&self, signature: &Signature) -> anyhow::Result<Vec<String>> {
+        let tx = self
+            .rpc
+            .get_transaction(signature, UiTransactionEncoding::Json)
+            .await?;
+        let logs: Option<Vec<String>> = tx
+            .transaction
+            .meta
+            .and_then(|meta| meta.log_messages.into());
+        Ok(logs.unwrap_or_default())
+    }
+
+    /// Lamports a successful transaction moved from `from` to `to` in plain system transfers.
+    pub async fn system_transfer_total(
+        &self,
+        signature: &Signature,
+        from: &Pubkey,
+        to: &Pubkey,
+    ) -> anyhow::Result<u64> {
+        let confirmed = self
+            .rpc
+            .get_transaction(signature, UiTransactionEncoding::Base64)
+            .await?;
+        let meta = confirmed
+            .transaction
+            .meta
+            .ok_or_else(|| anyhow::anyhow!("Transaction {} has no status", signature))?;
+        if meta.err.is_some() {
+            anyhow::bail!("Transaction {} failed", signature);
+        }
+        let tx = confirmed
+            .transaction
+            .transaction
+            .decode()
+            .ok_or_else(|| anyhow::anyhow!("Transaction {} could not be decoded", signature))?;
+
+        let keys = tx.message.static_account_keys();
+        let key = |index: u8| keys.get(index as usize);
+        let mut total: u64 = 0;
+        for ix in tx.message.instructions() {
+            let is_transfer = key(ix.program_id_index) == Some(&system_program::ID)
+                && ix.data.len() == 12
+                && ix.data[..4] == 2u32.to_le_bytes();
+            let between = ix.accounts.first().and_then(|&i| key(i)) == Some(from)
+                && ix.accounts.get(1).and_then(|&i| key(i)) == Some(to);
+            if is_transfer && between {
+                let lamports = u64::from_le_bytes(ix.data[4..12].try_into()?);
+                total = total
+                    .checked_add(lamports)
+                    .ok_or_else(|| anyhow::anyhow!("Transfer total overflows"))?;
+            }
+        }
+        Ok(total)
+    }
+
+    pub async fn get_mint_decimals(&self, mint: &Pubkey) -> anyhow::Result<u8> {
+        let account = self.rpc.get_account(mint).await?;
+        account
+            .data
+            .get(MINT_DECIMALS_OFFSET)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Account {} is not a mint", mint))
+    }
+
+    /// Latest verified price in one of a plan's Pyth price update accounts.
+    pub async fn get_oracle_price(&self, feed: &PriceFeed) -> anyhow::Result<OraclePrice> {
+        let account = self.rpc.get_account(&feed.price_feed).await?;
+        if account.owner != oracle::PYTH_RECEIVER_ID {
+            anyhow::bail!("{} is not a Pyth price update", feed.price_feed);
+        }
+        oracle::parse_price_update(&account.data, &feed.feed_id)
+    }
+
+    /// Returns `None` when the token account does not exist.
+    pub async fn get_token_account_funding(
+        &self,
+        token_account: &Pubkey,
+    ) -> anyhow::Result<Option<TokenAccountFunding>> {
+        let account = match self.rpc.get_account(token_account).await {
+            Ok(acc) => acc,
+            Err(err) => {
+                if err.to_string().contains("AccountNotFound") {
+                    return Ok(None);
+                }
+                return Err(err.into());
+            }
+        };
+
+        let data: &[u8] = &account.data;
+        if data.len() < TOKEN_ACCOUNT_BASE_LEN {
+            return Err(anyhow::anyhow!(
+                "Account {} is not a token account",
+                token_account
+            ));
+        }
+
+        let read_u64 = |offset: usize| {
+            u64::from_le_bytes(data[offset..offset + 8].try_into().expect("8-byte slice"))
+        };
+
+        // COption<Pubkey>: 4-byte tag followed by the key
+        let delegate_tag = &data[TOKEN_ACCOUNT_DELEGATE_OFFSET..TOKEN_ACCOUNT_DELEGATE_OFFSET + 4];
+        let delegate = if delegate_tag == [1, 0, 0, 0] {
+            let start = TOKEN_ACCOUNT_DELEGATE_OFFSET + 4;
+            Some(Pubkey::try_from(&data[start..start + 32])?)
+        } else {
+            None
+        };
+
+        Ok(Some(TokenAccountFunding {
+            amount: read_u64(TOKEN_ACCOUNT_AMOUNT_OFFSET),
+            delegate,
+            delegated_amount: read_u64(TOKEN_ACCOUNT_DELEGATED_AMOUNT_OFFSET),
+        }))
+    }
 }
@@ -1,30 +1,47 @@
+use crate::config::{Config, Environment};
+use crate::email::Mailer;
+use crate::models::notification::Notification;
+use crate::signer;
 use crate::solana_client::SolanaClient;
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Live notifications buffered per instance before slow stream subscribers lag.
+const NOTIFICATION_CHANNEL_CAPACITY: usize = 1024;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: PgPool,
     pub solana: Arc<SolanaClient>,
+    pub mailer: Arc<Mailer>,
+    pub config: Arc<Config>,
+    /// Fan-out of newly inserted notifications, fed by the Postgres listener.
+    pub notifications_tx: broadcast::Sender<Notification>,
 }
 
 impl AppState {
-    pub async fn new(
-        database_url: &str,
-        rpc_url: &str,
-        keypair_path: &str,
-        program_id: &str,
-    ) -> Self {
+    pub async fn new(config: Config) -> Self {
         let db = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(database_url)
+            .max_connections(config.database.max_connections)
+            .acquire_timeout(Duration::from_secs(config.database.acquire_timeout_secs))
+            .connect(&config.database.url)
             .await
             .expect("❌ Failed to connect to DB");
-        let solana = SolanaClient::new(rpc_url, keypair_path, program_id).await;
+        let signer = signer::from_config(&config.signer, &Environment::capture())
+            .await
+            .expect("❌ Failed to load keeper signer");
+        let solana = SolanaClient::new(&config.solana, signer).await;
+        let mailer = Mailer::from_config(&config.email).expect("❌ Failed to configure mailer");
+        let (notifications_tx, _) = broadcast::channel(NOTIFICATION_CHANNEL_CAPACITY);
         Self {
             db,
             solana: Arc::new(solana),
+            mailer: Arc::new(mailer),
+            config: Arc::new(config),
+            notifications_tx,
         }
     }
 }
@@ -0,0 +1,180 @@
+//! Token-2022 transfer hooks: the hook program a mint names, and the extra accounts its
+//! validation account asks for, so renewals can pass them to `execute_payment`.
+
+use solana_sdk::{hash::hash, instruction::AccountMeta, pubkey::Pubkey};
+
+// Extended mints are padded to the token account length, then tagged with their type
+const ACCOUNT_TYPE_OFFSET: usize = 165;
+const TLV_START: usize = ACCOUNT_TYPE_OFFSET + 1;
+const EXTENSION_TRANSFER_HOOK: u16 = 14;
+
+const EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"extra-account-metas";
+const EXTRA_ACCOUNT_META_LEN: usize = 35;
+
+/// Hook program set on a Token-2022 mint, if any.
+pub fn transfer_hook_program(mint_data: &[u8]) -> Option<Pubkey> {
+    if mint_data.len() <= TLV_START {
+        return None;
+    }
+
+    let mut offset = TLV_START;
+    while offset + 4 <= mint_data.len() {
+        let kind = u16::from_le_bytes([mint_data[offset], mint_data[offset + 1]]);
+        let len = u16::from_le_bytes([mint_data[offset + 2], mint_data[offset + 3]]) as usize;
+        let value = mint_data.get(offset + 4..offset + 4 + len)?;
+        if kind == EXTENSION_TRANSFER_HOOK {
+            // authority (32) then program id (32); all zeroes means no hook
+            let program = Pubkey::try_from(value.get(32..64)?).ok()?;
+            return (program != Pubkey::default()).then_some(program);
+        }
+        if kind == 0 {
+            break;
+        }
+        offset += 4 + len;
+    }
+
+    None
+}
+
+pub fn validation_account(mint: &Pubkey, hook_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()], hook_program).0
+}
+
+/// Data of the hook's `execute` instruction, which `InstructionData` seeds read from.
+pub fn execute_instruction_data(amount: u64) -> Vec<u8> {
+    let mut data = execute_discriminator().to_vec();
+    data.extend_from_slice(&amount.to_le_bytes());
+    data
+}
+
+fn execute_discriminator() -> [u8; 8] {
+    let digest = hash(b"spl-transfer-hook-interface:execute").to_bytes();
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&digest[..8]);
+    discriminator
+}
+
+/// One entry of the validation account's list, still unresolved.
+#[derive(Debug, Clone)]
+pub struct ExtraAccountMeta {
+    pub discriminator: u8,
+    pub address_config: [u8; 32],
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// Reads the metas stored for the `execute` instruction.
+pub fn parse_extra_account_metas(data: &[u8]) -> anyhow::Result<Vec<ExtraAccountMeta>> {
+    let discriminator = execute_discriminator();
+    let mut offset = 0;
+
+    while offset + 12 <= data.len() {
+        let entry_discriminator = &data[offset..offset + 8];
+        let len = u32::from_le_bytes(data[offset + 8..offset + 12].try_into()?) as usize;
+        let value = data
+            .get(offset + 12..offset + 12 + len)
+            .ok_or_else(|| anyhow::anyhow!("Validation account data is truncated"))?;
+
+        if entry_discriminator == discriminator {
+            let count = u32::from_le_bytes(
+                value
+                    .get(..4)
+                    .ok_or_else(|| anyhow::anyhow!("Missing extra account count"))?
+                    .try_into()?,
+            ) as usize;
+            return (0..count)
+                .map(|i| {
+                    let start = 4 + i * EXTRA_ACCOUNT_META_LEN;
+                    let raw = value
+                        .get(start..start + EXTRA_ACCOUNT_META_LEN)
+                        .ok_or_else(|| anyhow::anyhow!("Extra account meta {} is truncated", i))?;
+                    Ok(ExtraAccountMeta {
+                        discriminator: raw[0],
+                        address_config: raw[1..33].try_into()?,
+                        is_signer: raw[33] != 0,
+                        is_writable: raw[34] != 0,
+                    })
+                })
+                .collect();
+        }
+        offset += 12 + len;
+    }
+
+    Ok(Vec::new())
+}
+
+/// Seed of a PDA extra account, as packed into `address_config`.
+#[derive(Debug, Clone)]
+pub enum Seed {
+    Literal(Vec<u8>),
+    InstructionData {
+        index: usize,
+        length: usize,
+    },
+    AccountKey {
+        index: usize,
+    },
+    AccountData {
+        account_index: usize,
+        data_index: usize,
+        length: usize,
+    },
+}
+
+pub fn parse_seeds(config: &[u8; 32]) -> anyhow::Result<Vec<Seed>> {
+    let mut seeds = Vec::new();
+    let mut i = 0;
+    let byte = |at: usize| {
+        config
+            .get(at)
+            .copied()
+            .map(usize::from)
+            .ok_or_else(|| anyhow::anyhow!("Seed config is truncated"))
+    };
+
+    while i < config.len() {
+        match config[i] {
+            0 => break,
+            1 => {
+                let len = byte(i + 1)?;
+                let bytes = config
+                    .get(i + 2..i + 2 + len)
+                    .ok_or_else(|| anyhow::anyhow!("Literal seed is truncated"))?;
+                seeds.push(Seed::Literal(bytes.to_vec()));
+                i += 2 + len;
+            }
+            2 => {
+                seeds.push(Seed::InstructionData {
+                    index: byte(i + 1)?,
+                    length: byte(i + 2)?,
+                });
+                i += 3;
+            }
+            3 => {
+                seeds.push(Seed::AccountKey {
+                    index: byte(i + 1)?,
+                });
+                i += 2;
+            }
+            4 => {
+                seeds.push(Seed::AccountData {
+                    account_index: byte(i + 1)?,
+                    data_index: byte(i + 2)?,
+                    length: byte(i + 3)?,
+                });
+                i += 4;
+            }
+            other => anyhow::bail!("Unknown seed type {}", other),
+        }
+    }
+
+    Ok(seeds)
+}
+
+pub fn meta_for(address: Pubkey, extra: &ExtraAccountMeta) -> AccountMeta {
+    if extra.is_writable {
+        AccountMeta::new(address, false)
+    } else {
+        AccountMeta::new_readonly(address, false)
+    }
+}
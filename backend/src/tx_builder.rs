@@ -0,0 +1,362 @@
+//! Instructions for the user-facing program calls, so the API can hand out unsigned
+//! transactions instead of every client reimplementing them. Program instructions are
+//! encoded by `solpay_client`; this fills in the accounts the API can derive itself.
+
+use crate::types::{PaymentRecord, SubscriptionField, UpdateValue};
+use anchor_lang::prelude::*;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solpay_client::{instructions as ix, pda};
+
+pub use solpay_client::pda::{plan as plan_pda, subscription as subscription_pda};
+
+const COMPUTE_BUDGET_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("ComputeBudget111111111111111111111111111111");
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+const SYSVAR_RENT_ID: Pubkey =
+    Pubkey::from_str_const("SysvarRent111111111111111111111111111111111");
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+const TOKEN_APPROVE: u8 = 4;
+
+/// Compute units requested when a builder has no better estimate.
+pub const DEFAULT_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+
+pub fn compute_unit_limit(units: u32) -> Instruction {
+    let mut data = vec![SET_COMPUTE_UNIT_LIMIT];
+    data.extend_from_slice(&units.to_le_bytes());
+    Instruction {
+        program_id: COMPUTE_BUDGET_PROGRAM_ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+pub fn compute_unit_price(micro_lamports: u64) -> Instruction {
+    let mut data = vec![SET_COMPUTE_UNIT_PRICE];
+    data.extend_from_slice(&micro_lamports.to_le_bytes());
+    Instruction {
+        program_id: COMPUTE_BUDGET_PROGRAM_ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+/// Lets `delegate` pull renewals: the subscription PDA, or for wSOL the shared SOL delegate.
+pub fn approve_delegate(
+    token_program: &Pubkey,
+    source: &Pubkey,
+    delegate: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![TOKEN_APPROVE];
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: *token_program,
+        accounts: vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(*delegate, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data,
+    }
+}
+
+/// `initialize_subscription` and the accounts it takes that the API cannot derive.
+pub struct InitializeSubscription {
+    pub payer: Pubkey,
+    pub subscription: Pubkey,
+    pub user_token_account: Pubkey,
+    pub receiver_token_account: Pubkey,
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub payment_record: Pubkey,
+    pub plan: Pubkey,
+    pub coupon: Option<Pubkey>,
+    /// The mint's USD price feed, for USD-priced tiers
+    pub price_feed: Option<Pubkey>,
+    pub tier_name: String,
+    pub period_seconds: i64,
+    pub amount: u64,
+    pub auto_renew: bool,
+    pub unique_seed: [u8; 8],
+    pub max_cycles: Option<u64>,
+    pub end_ts: Option<i64>,
+    /// Transfer-hook accounts for the first charge
+    pub hook_accounts: Vec<AccountMeta>,
+}
+
+pub fn initialize_subscription(program_id: &Pubkey, args: InitializeSubscription) -> Instruction {
+    let mut instruction = ix::initialize_subscription::instruction(
+        program_id,
+        &ix::initialize_subscription::Accounts {
+            payer: args.payer,
+            subscription: args.subscription,
+            user_token_account: args.user_token_account,
+            receiver_token_account: args.receiver_token_account,
+            mint: args.mint,
+            token_program: args.token_program,
+            global_stats: pda::global_stats(program_id),
+            system_program: system_program::ID,
+            rent: SYSVAR_RENT_ID,
+            payment_record: args.payment_record,
+            plan: args.plan,
+            coupon: args.coupon,
+            price_feed: args.price_feed,
+        },
+        &ix::initialize_subscription::Args {
+            tier_name: args.tier_name,
+            plan_pda: args.plan,
+            period_seconds: args.period_seconds,
+            amount: args.amount,
+            auto_renew: args.auto_renew,
+            unique_seed: args.unique_seed,
+            max_cycles: args.max_cycles,
+            end_ts: args.end_ts,
+        },
+    );
+    instruction.accounts.extend(args.hook_accounts);
+    instruction
+}
+
+/// Sends `amount` of a charge back from the plan's receiver; transfer-hook mints need
+/// `hook_accounts` resolved for the receiver-to-payer transfer.
+#[allow(clippy::too_many_arguments)]
+pub fn refund_payment(
+    program_id: &Pubkey,
+    receiver: &Pubkey,
+    payment_record: &Pubkey,
+    record: &PaymentRecord,
+    receiver_token_account: &Pubkey,
+    payer_token_account: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+    hook_accounts: Vec<AccountMeta>,
+) -> Instruction {
+    let mut instruction = ix::refund_payment::instruction(
+        program_id,
+        &ix::refund_payment::Accounts {
+            receiver: *receiver,
+            plan: record.plan,
+            payment_record: *payment_record,
+            receiver_token_account: *receiver_token_account,
+            payer_token_account: *payer_token_account,
+            mint: record.mint,
+            token_program: *token_program,
+        },
+        &ix::refund_payment::Args { amount },
+    );
+    instruction.accounts.extend(hook_accounts);
+    instruction
+}
+
+/// The subscriber's limit on the usage part of one charge, in the billing mint.
+pub fn set_usage_cap(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    subscription: &Pubkey,
+    usage_cap: u64,
+) -> Instruction {
+    ix::set_usage_cap::instruction(
+        program_id,
+        &ix::set_usage_cap::Accounts {
+            payer: *payer,
+            subscription: *subscription,
+        },
+        &ix::set_usage_cap::Args { usage_cap },
+    )
+}
+
+/// The subscriber's limit on one charge of a USD-priced tier, in the billing mint.
+pub fn set_max_token_amount(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    subscription: &Pubkey,
+    max_token_amount: u64,
+) -> Instruction {
+    ix::set_max_token_amount::instruction(
+        program_id,
+        &ix::set_max_token_amount::Accounts {
+            payer: *payer,
+            subscription: *subscription,
+        },
+        &ix::set_max_token_amount::Args { max_token_amount },
+    )
+}
+
+/// Moves `lamports` into the owner's wSOL account ahead of a SOL plan's charges.
+/// Passing `subscription` adds its remaining renewals to the shared delegate's approval.
+pub fn wrap_sol(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    wsol_account: &Pubkey,
+    native_mint: &Pubkey,
+    token_program: &Pubkey,
+    subscription: Option<Pubkey>,
+    lamports: u64,
+) -> Instruction {
+    ix::wrap_sol::instruction(
+        program_id,
+        &ix::wrap_sol::Accounts {
+            owner: *owner,
+            wsol_account: *wsol_account,
+            native_mint: *native_mint,
+            token_program: *token_program,
+            associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+            subscription,
+            sol_delegate: pda::sol_delegate(program_id),
+        },
+        &ix::wrap_sol::Args { lamports },
+    )
+}
+
+pub fn cancel_subscription(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    subscription: &Pubkey,
+) -> Instruction {
+    ix::cancel_subscription::instruction(
+        program_id,
+        &ix::cancel_subscription::Accounts {
+            payer: *payer,
+            subscription: *subscription,
+            global_stats: pda::global_stats(program_id),
+        },
+        &ix::cancel_subscription::Args {},
+    )
+}
+
+pub fn update_subscription_status(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    subscription: &Pubkey,
+    field: SubscriptionField,
+    value: UpdateValue,
+) -> Instruction {
+    ix::update_subscription_status::instruction(
+        program_id,
+        &ix::update_subscription_status::Accounts {
+            payer: *payer,
+            subscription: *subscription,
+        },
+        &ix::update_subscription_status::Args { field, value },
+    )
+}
+
+/// `tiers` is the compressed tier list, as stored on the plan.
+pub fn create_plan(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    mint: &Pubkey,
+    receiver: &Pubkey,
+    name: &str,
+    token_symbol: &str,
+    token_image: &str,
+    tiers: &[u8],
+) -> Instruction {
+    ix::create_plan::instruction(
+        program_id,
+        &ix::create_plan::Accounts {
+            creator: *creator,
+            plan: plan_pda(program_id, creator),
+            mint: *mint,
+            receiver: *receiver,
+            system_program: system_program::ID,
+        },
+        &ix::create_plan::Args {
+            name: name.to_string(),
+            token_symbol: token_symbol.to_string(),
+            token_image: token_image.to_string(),
+            tiers: tiers.to_vec(),
+        },
+    )
+}
+
+pub fn update_plan(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    receiver: &Pubkey,
+    name: &str,
+    tiers: &[u8],
+) -> Instruction {
+    ix::update_plan::instruction(
+        program_id,
+        &ix::update_plan::Accounts {
+            plan: plan_pda(program_id, creator),
+            creator: *creator,
+            receiver: *receiver,
+        },
+        &ix::update_plan::Args {
+            name: name.to_string(),
+            tiers: tiers.to_vec(),
+        },
+    )
+}
+
+/// Prices usage on the creator's tier in `mint`; 0 makes the tier flat.
+pub fn set_tier_unit_price(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    tier_name: &str,
+    mint: &Pubkey,
+    unit_price: u64,
+) -> Instruction {
+    ix::set_tier_unit_price::instruction(
+        program_id,
+        &ix::set_tier_unit_price::Accounts {
+            plan: plan_pda(program_id, creator),
+            creator: *creator,
+        },
+        &ix::set_tier_unit_price::Args {
+            tier_name: tier_name.to_string(),
+            mint: *mint,
+            unit_price,
+        },
+    )
+}
+
+/// Prices a tier in USD with 6 decimals; 0 prices it in tokens again.
+pub fn set_tier_usd_price(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    tier_name: &str,
+    usd_amount: u64,
+) -> Instruction {
+    ix::set_tier_usd_price::instruction(
+        program_id,
+        &ix::set_tier_usd_price::Accounts {
+            plan: plan_pda(program_id, creator),
+            creator: *creator,
+        },
+        &ix::set_tier_usd_price::Args {
+            tier_name: tier_name.to_string(),
+            usd_amount,
+        },
+    )
+}
+
+/// Grows a subscription or plan made before its newest fields, paid by `payer`.
+pub fn migrate_account(program_id: &Pubkey, payer: &Pubkey, account: &Pubkey) -> Instruction {
+    ix::migrate_account::instruction(
+        program_id,
+        &ix::migrate_account::Accounts {
+            payer: *payer,
+            account: *account,
+            system_program: system_program::ID,
+        },
+        &ix::migrate_account::Args {},
+    )
+}
+
+pub fn cancel_plan(program_id: &Pubkey, creator: &Pubkey) -> Instruction {
+    ix::cancel_plan::instruction(
+        program_id,
+        &ix::cancel_plan::Accounts {
+            creator: *creator,
+            plan: plan_pda(program_id, creator),
+        },
+        &ix::cancel_plan::Args {},
+    )
+}
@@ -1,27 +1,15 @@
-use anchor_lang::prelude::*;
+use anchor_lang::prelude::Pubkey;
 
-#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq)]
-pub enum SubscriptionField {
-    AutoRenew, // 0
-    Active,    // 1
-    Tier,      // 2
-}
-
-#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq)]
-pub enum UpdateValue {
-    Bool(bool),     // 0
-    U64(u64),       // 1
-    String(String), // 2
-}
+// On-chain types come from the shared client crate, which is checked against the IDL
+pub use solpay_client::accounts::{
+    Coupon, PaymentRecord, Plan, Subscription as SubscriptionAccount,
+};
+pub use solpay_client::types::{Discount, SubscriptionField, UnitPrice, UpdateValue, UsdPrice};
 
-#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
-pub struct Plan {
-    pub creator: Pubkey,
-    pub mint: Pubkey,
-    pub receiver: Pubkey,
-    pub name: String,
-    pub token_symbol: String,
-    pub token_image: String,
-    pub tiers: Vec<u8>, // compressed bytes
-    pub bump: u8,
+/// Balance and delegation of an SPL / Token-2022 token account.
+#[derive(Debug, Clone)]
+pub struct TokenAccountFunding {
+    pub amount: u64,
+    pub delegate: Option<Pubkey>,
+    pub delegated_amount: u64,
 }
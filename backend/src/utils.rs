@@ -1,7 +1,11 @@
 use crate::models::subscription::Tier;
 use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use flate2::Compression;
 use flate2::read::ZlibDecoder;
-use std::io::Read;
+use flate2::write::ZlibEncoder;
+use std::io::{Read, Write};
+use std::str::FromStr;
 
 pub fn parse_tiers(tiers: &[u8]) -> Result<Vec<Tier>> {
     // 1️⃣ bytes → string
@@ -28,3 +32,74 @@ pub fn decompress_tiers(data: &[u8]) -> anyhow::Result<Vec<u8>> {
     decoder.read_to_end(&mut out)?;
     Ok(out)
 }
+
+/// Inverse of `decompress_tiers`, zlib like the front-end's pako.
+pub fn compress_tiers(tiers: &[Tier]) -> anyhow::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(serde_json::to_string(tiers)?.as_bytes())?;
+    Ok(encoder.finish()?)
+}
+
+/// Parses a UI amount like `12.5` into base units; `None` if it is malformed or too precise.
+pub fn parse_token_amount(ui: &str, decimals: u8) -> Option<u64> {
+    let (whole, frac) = ui.trim().split_once('.').unwrap_or((ui.trim(), ""));
+    if frac.len() > decimals as usize || (whole.is_empty() && frac.is_empty()) {
+        return None;
+    }
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let frac: u64 = if frac.is_empty() {
+        0
+    } else {
+        format!("{:0<width$}", frac, width = decimals as usize)
+            .parse()
+            .ok()?
+    };
+    whole
+        .checked_mul(10u64.checked_pow(decimals as u32)?)?
+        .checked_add(frac)
+}
+
+/// Renders a raw token amount in UI units, e.g. `12500000` with 6 decimals → `12.5`.
+pub fn format_token_amount(raw: u64, decimals: u8) -> String {
+    if decimals == 0 {
+        return raw.to_string();
+    }
+    let scale = 10u128.pow(decimals as u32);
+    let whole = raw as u128 / scale;
+    let frac = raw as u128 % scale;
+    if frac == 0 {
+        return whole.to_string();
+    }
+    let frac = format!("{:0width$}", frac, width = decimals as usize);
+    format!("{}.{}", whole, frac.trim_end_matches('0'))
+}
+
+/// Opaque keyset cursor: `<created_at micros>_<id>`.
+pub fn encode_cursor(created_at: DateTime<Utc>, id: impl std::fmt::Display) -> String {
+    format!("{}_{}", created_at.timestamp_micros(), id)
+}
+
+pub fn decode_cursor<T: FromStr>(cursor: &str) -> Option<(DateTime<Utc>, T)> {
+    let (micros, id) = cursor.split_once('_')?;
+    let created_at = DateTime::from_timestamp_micros(micros.parse().ok()?)?;
+    Some((created_at, id.parse().ok()?))
+}
+
+pub fn escape_html(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -1,36 +1,124 @@
-use crate::handlers::notification_handler::create_notification;
+use crate::config::{Config, ConfigArgs, Environment};
+use crate::handlers::analytics_handler::record_subscription_event;
+use crate::handlers::invoice_handler::issue_invoice;
+use crate::handlers::notification_handler::{create_notification, fetch_notification_by_id};
+use crate::handlers::relay_handler::{confirm_reservation, fail_reservation, release_reservation};
+use crate::handlers::subscription_handler::{register_confirmed_subscriptions, sync_pause_state};
 // use crate::handlers::subscription_handler::UpdateValue;
 use crate::handlers::transaction_handler::create_transaction;
-use crate::models::notification::Notification;
+use crate::models::email::QueuedEmail;
+use crate::models::invoice::NewInvoice;
+use crate::models::notification::{Notification, NotificationKind};
 use crate::models::transaction::PaymentHistory;
+use crate::signer;
+use crate::solana_client::{NATIVE_MINT, is_payment_not_due};
 use crate::state::AppState;
-use crate::types::{SubscriptionField, UpdateValue};
-use crate::utils::{find_tier_by_name, parse_tiers};
-use solana_sdk::pubkey::Pubkey;
+use crate::types::{Plan, SubscriptionField, UpdateValue};
+use crate::utils::{find_tier_by_name, format_token_amount, parse_tiers};
+use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature};
+use solpay_client::{oracle, pda};
 use spl_associated_token_account::get_associated_token_address_with_program_id;
 use sqlx::Row;
+use sqlx::postgres::PgListener;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::signal::unix::{SignalKind, signal};
 use tokio::time;
 use tracing::error;
 
 pub async fn run_keeper(state: Arc<AppState>) {
-    let interval = Duration::from_secs(60);
+    let interval = Duration::from_secs(state.config.keeper.interval_secs);
     let mut ticker = time::interval(interval);
 
     loop {
         ticker.tick().await;
+        if let Err(err) = resume_expired_pauses(&state).await {
+            error!("Keeper pause error: {:?}", err);
+        }
         if let Err(err) = scan_and_renew_subscriptions(&state).await {
             error!("Keeper error: {:?}", err);
         }
     }
 }
 
+/// Resumes paused subscriptions whose plan pause limit has run out.
+pub async fn resume_expired_pauses(state: &AppState) -> anyhow::Result<()> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+
+    let paused = sqlx::query!(
+        r#"
+        SELECT subscription_pda, plan_pda, paused_at AS "paused_at!"
+        FROM subscriptions
+        WHERE paused_at IS NOT NULL
+        ORDER BY paused_at ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    // A row that fails is logged and retried on the next tick without holding up the rest
+    let mut limits: HashMap<String, i64> = HashMap::new();
+    for sub in paused {
+        if let Err(e) = resume_if_expired(
+            state,
+            &mut limits,
+            &sub.subscription_pda,
+            &sub.plan_pda,
+            sub.paused_at,
+            now,
+        )
+        .await
+        {
+            error!("Failed to resume {}: {:?}", sub.subscription_pda, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Resumes one paused subscription once its plan's pause limit, cached in `limits`, is up.
+async fn resume_if_expired(
+    state: &AppState,
+    limits: &mut HashMap<String, i64>,
+    subscription_pda: &str,
+    plan_pda: &str,
+    paused_at: i64,
+    now: i64,
+) -> anyhow::Result<()> {
+    let plan = Pubkey::from_str(plan_pda)?;
+    let max_pause = match limits.get(plan_pda) {
+        Some(limit) => *limit,
+        None => {
+            let limit = state
+                .solana
+                .get_plan(plan)
+                .await?
+                .map(|p| p.max_pause_seconds)
+                .unwrap_or(0);
+            limits.insert(plan_pda.to_string(), limit);
+            limit
+        }
+    };
+    if max_pause <= 0 || now < paused_at + max_pause {
+        return Ok(());
+    }
+
+    let subscription_pda = Pubkey::from_str(subscription_pda)?;
+    state
+        .solana
+        .resume_subscription(subscription_pda, plan)
+        .await?;
+    sync_pause_state(state, subscription_pda).await?;
+    Ok(())
+}
+
 pub async fn scan_and_renew_subscriptions(state: &AppState) -> anyhow::Result<()> {
     let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
 
-    let sub = sqlx::query(
+    let subs = sqlx::query(
         r#"
         SELECT
             payer,
@@ -45,19 +133,36 @@ pub async fn scan_and_renew_subscriptions(state: &AppState) -> anyhow::Result<()
             subscription_pda AS subscription
         FROM subscriptions
         WHERE active = true
+          AND paused_at IS NULL
+          AND completed_at IS NULL
           AND next_payment_ts <= $1
         ORDER BY next_payment_ts ASC
-        LIMIT 1
+        LIMIT $2
         "#,
     )
     .bind(now)
-    .fetch_optional(&state.db)
+    .bind(state.config.keeper.batch_size)
+    .fetch_all(&state.db)
     .await?;
 
-    let Some(sub) = sub else {
+    if subs.is_empty() {
         tracing::info!("✅ No subscriptions due for renewal");
         return Ok(());
-    };
+    }
+    // One failure must not hold back the rest of the batch
+    for sub in subs {
+        if let Err(err) = renew_due_subscription(state, &sub).await {
+            error!("Keeper error: {:?}", err);
+        }
+    }
+
+    Ok(())
+}
+
+async fn renew_due_subscription(
+    state: &AppState,
+    sub: &sqlx::postgres::PgRow,
+) -> anyhow::Result<()> {
     let subscription_pda = Pubkey::from_str(sub.get("subscription"))?;
 
     let plan_opt = state
@@ -78,6 +183,7 @@ pub async fn scan_and_renew_subscriptions(state: &AppState) -> anyhow::Result<()
             id: None,
             user_pubkey: Pubkey::from_str(sub.get("payer"))?.to_string(),
             plan_name: plan.name.clone(),
+            plan_pda: Some(sub.get("plan_pda")),
             tier: tier_name.clone(),
             subscription_pda: subscription_pda.to_string(),
             title: "Subscription Expired".to_string(),
@@ -86,8 +192,9 @@ pub async fn scan_and_renew_subscriptions(state: &AppState) -> anyhow::Result<()
                 plan.name, tier_name
             ),
             created_at: Some(chrono::Utc::now()),
+            expires_at: None,
             is_read: false,
-            r#type: "warning".to_string(),
+            r#type: NotificationKind::Warning,
         };
 
         let _ = create_notification(&state.db, &notification).await;
@@ -102,6 +209,15 @@ pub async fn scan_and_renew_subscriptions(state: &AppState) -> anyhow::Result<()
             );
         } else {
             tracing::info!("Deactivated expired subscription {}", subscription_pda);
+            let _ = record_subscription_event(
+                &state.db,
+                &subscription_pda.to_string(),
+                sub.get("plan_pda"),
+                sub.get("payer"),
+                &tier_name,
+                "expired",
+            )
+            .await;
         }
 
         return Ok(());
@@ -125,6 +241,7 @@ pub async fn renew_subscription_by_pda(
             amount,
             unique_seed,
             bump,
+            mint,
             subscription_pda AS subscription
         FROM subscriptions
         WHERE subscription_pda = $1
@@ -158,13 +275,11 @@ pub async fn renew_subscription_by_pda(
     let tiers = parse_tiers(&plan.tiers)?;
     let tier = find_tier_by_name(&tiers, sub.get("tier_name"))?;
 
-    let token_program = state.solana.rpc.get_account(&plan.mint).await?;
+    let mint = billing_mint(&sub, &plan)?;
+    let token_program = state.solana.rpc.get_account(&mint).await?;
 
-    let payer_token_account = get_associated_token_address_with_program_id(
-        &payer_pubkey,
-        &plan.mint,
-        &token_program.owner,
-    );
+    let payer_token_account =
+        get_associated_token_address_with_program_id(&payer_pubkey, &mint, &token_program.owner);
 
     state
         .solana
@@ -175,29 +290,68 @@ pub async fn renew_subscription_by_pda(
             anyhow::anyhow!("User token account {} does not exist", payer_token_account)
         })?;
 
-    let receiver_token_account = get_associated_token_address_with_program_id(
-        &plan.receiver,
-        &plan.mint,
-        &token_program.owner,
-    );
+    let receiver_token_account =
+        get_associated_token_address_with_program_id(&plan.receiver, &mint, &token_program.owner);
 
     let amount: u64 = sub.get::<i64, _>("amount") as u64;
     let period_seconds: i64 = tier.period_seconds.parse()?;
 
+    let account = state
+        .solana
+        .get_subscription_account(&subscription_pda)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Subscription account {} not found", subscription_pda))?;
+
+    // The program refuses to charge past the term, so just catch the database up
+    if account.term_reached() {
+        return mark_subscription_completed(
+            state,
+            &subscription_pda.to_string(),
+            &plan.name,
+            sub.get("plan_pda"),
+            sub.get("payer"),
+            sub.get("tier_name"),
+        )
+        .await;
+    }
+
+    let payment_index = account.payment_count;
+    let payment_record = state
+        .solana
+        .payment_record_pda(&subscription_pda, payment_index);
+
+    let hook_accounts = state
+        .solana
+        .transfer_hook_accounts(
+            &mint,
+            &payer_token_account,
+            &receiver_token_account,
+            &subscription_pda,
+            account.next_charge_amount(&plan),
+        )
+        .await?;
+
     let result = state
         .solana
         .execute_subscription_payment(
             subscription_pda,
             Pubkey::from_str(sub.get("plan_pda"))?,
             payer_token_account,
+            plan.receiver,
             receiver_token_account,
-            plan.mint,
+            mint,
             token_program.owner,
+            payment_record,
             amount,
             period_seconds,
+            plan.feed_for(&mint)
+                .filter(|_| account.is_usd_priced(&plan))
+                .map(|feed| feed.price_feed),
+            hook_accounts,
         )
         .await;
 
+    let charged = result.is_ok();
     let (notification_title, notification_message, notification_type) = match result {
         Ok(signature) => {
             let next_ts = sub.get::<i64, _>("next_payment_ts") + period_seconds;
@@ -215,36 +369,126 @@ pub async fn renew_subscription_by_pda(
             .await?;
             let tier_name: String = sub.get("tier_name");
 
+            // The payment record holds what was actually charged, usage included; the
+            // transaction's PaymentExecuted event says the same if the record cannot be read
+            let (charged_amount, usage_units, usage_amount) =
+                match state.solana.get_payment_record(&payment_record).await {
+                    Ok(Some(record)) => (record.amount, record.usage_units, record.usage_amount),
+                    _ => match state.solana.payment_executed_event(&signature).await {
+                        Ok(Some(event)) => (event.amount, event.usage_units, event.usage_amount),
+                        _ => (
+                            account.next_charge_amount(&plan),
+                            account.accrued_units,
+                            account.pending_usage_amount(&plan),
+                        ),
+                    },
+                };
+            let metered = account.unit_price(&plan) > 0;
+
             let history = PaymentHistory {
                 id: None,
                 user_pubkey: payer_pubkey.to_string(),
                 plan: plan.name.clone(),
                 tier: tier_name.clone(),
-                amount: amount as i64,
+                amount: charged_amount as i64,
                 status: "success".to_string(),
                 tx_signature: Some(signature.to_string()),
                 subscription_pda: subscription_pda.to_string(),
+                plan_pda: Some(sub.get("plan_pda")),
                 created_at: chrono::Utc::now(),
+                direction: "paid".to_string(),
+                payment_record: Some(payment_record.to_string()),
+                refund_of: None,
+                usage_units: metered.then_some(usage_units as i64),
+                usage_amount: metered.then_some(usage_amount as i64),
             };
 
-            let _ = create_transaction(&state.db, &history).await;
+            match create_transaction(&state.db, &history).await {
+                Ok(payment_id) => {
+                    let due_ts = sub.get::<i64, _>("next_payment_ts");
+                    let invoice = NewInvoice {
+                        payment_id,
+                        merchant_pubkey: plan.creator.to_string(),
+                        payer: payer_pubkey.to_string(),
+                        plan_name: plan.name.clone(),
+                        plan_pda: sub.get("plan_pda"),
+                        tier: tier_name.clone(),
+                        period_start: chrono::DateTime::from_timestamp(due_ts, 0)
+                            .unwrap_or_else(chrono::Utc::now),
+                        period_end: chrono::DateTime::from_timestamp(next_ts, 0)
+                            .unwrap_or_else(chrono::Utc::now),
+                        amount: charged_amount as i64,
+                        mint,
+                        token_symbol: plan.token_symbol_for(&mint).to_string(),
+                        tx_signature: Some(signature.to_string()),
+                    };
+                    if let Err(e) = issue_invoice(state, &invoice).await {
+                        error!("Failed to issue invoice for {}: {:?}", subscription_pda, e);
+                    }
+                }
+                Err(e) => error!("Failed to record payment for {}: {:?}", subscription_pda, e),
+            }
 
             (
                 "Payment Received".to_string(), // Clear Title
                 format!("You successfully renewed {} ({})", plan.name, tier_name),
-                "success".to_string(), // UI Type (Green Icon)
+                NotificationKind::Success, // UI Type (Green Icon)
             )
         }
 
+        // Nothing was attempted, so there is no failure to record; the chain's date wins
+        Err(e) if is_payment_not_due(&e) => {
+            tracing::warn!(
+                "Renewal of {} is not due until {}",
+                subscription_pda,
+                account.next_payment_ts
+            );
+            sqlx::query!(
+                r#"
+                UPDATE subscriptions
+                SET next_payment_ts = GREATEST(next_payment_ts, $1)
+                WHERE subscription_pda = $2
+                "#,
+                account.next_payment_ts,
+                subscription_pda.to_string()
+            )
+            .execute(&state.db)
+            .await?;
+            return Ok(());
+        }
+
         Err(e) => {
             tracing::error!("❌ Renewal failed {}: {}", subscription_pda, e);
+
+            let history = PaymentHistory {
+                id: None,
+                user_pubkey: payer_pubkey.to_string(),
+                plan: plan.name.clone(),
+                tier: sub.get("tier_name"),
+                amount: account.next_charge_amount(&plan) as i64,
+                status: "failed".to_string(),
+                tx_signature: None,
+                subscription_pda: subscription_pda.to_string(),
+                plan_pda: Some(sub.get("plan_pda")),
+                created_at: chrono::Utc::now(),
+                direction: "paid".to_string(),
+                payment_record: None,
+                refund_of: None,
+                usage_units: (account.unit_price(&plan) > 0)
+                    .then_some(account.accrued_units as i64),
+                usage_amount: (account.unit_price(&plan) > 0)
+                    .then_some(account.pending_usage_amount(&plan) as i64),
+            };
+
+            let _ = create_transaction(&state.db, &history).await;
+
             (
                 "Payment Failed".to_string(), // Clear Title
                 format!(
                     "We could not renew your subscription for {}. Please check your wallet balance.",
                     plan.name
                 ),
-                "error".to_string(), // UI Type (Red Icon)
+                NotificationKind::Error, // UI Type (Red Icon)
             )
         }
     };
@@ -252,18 +496,107 @@ pub async fn renew_subscription_by_pda(
     let notification = Notification {
         id: None,
         user_pubkey: payer_pubkey.to_string(),
-        plan_name: plan.name,
+        plan_name: plan.name.clone(),
+        plan_pda: Some(sub.get("plan_pda")),
         tier: sub.get("tier_name"),
         subscription_pda: subscription_pda.to_string(),
         title: notification_title,
         message: notification_message,
         created_at: Some(chrono::Utc::now()),
+        expires_at: None,
         is_read: false,
         r#type: notification_type,
     };
 
     let _ = create_notification(&state.db, &notification).await;
 
+    // A fixed-term subscription completes on its final charge
+    if charged {
+        let completed = state
+            .solana
+            .get_subscription_account(&subscription_pda)
+            .await?
+            .is_some_and(|account| account.term_reached());
+        if completed {
+            mark_subscription_completed(
+                state,
+                &subscription_pda.to_string(),
+                &plan.name,
+                sub.get("plan_pda"),
+                sub.get("payer"),
+                sub.get("tier_name"),
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Subscriptions recorded before plans accepted several mints pay in the primary one.
+fn billing_mint(sub: &sqlx::postgres::PgRow, plan: &Plan) -> anyhow::Result<Pubkey> {
+    match sub.get::<Option<String>, _>("mint") {
+        Some(mint) => Ok(Pubkey::from_str(&mint)?),
+        None => Ok(plan.mint),
+    }
+}
+
+pub(crate) async fn mark_subscription_completed(
+    state: &AppState,
+    subscription_pda: &str,
+    plan_name: &str,
+    plan_pda: &str,
+    payer: &str,
+    tier_name: &str,
+) -> anyhow::Result<()> {
+    let updated = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET active = false,
+            auto_renew = false,
+            completed_at = now()
+        WHERE subscription_pda = $1
+          AND completed_at IS NULL
+        "#,
+        subscription_pda
+    )
+    .execute(&state.db)
+    .await?;
+
+    if updated.rows_affected() == 0 {
+        return Ok(());
+    }
+    tracing::info!("Subscription {} completed its term", subscription_pda);
+
+    let _ = record_subscription_event(
+        &state.db,
+        subscription_pda,
+        plan_pda,
+        payer,
+        tier_name,
+        "completed",
+    )
+    .await;
+
+    let notification = Notification {
+        id: None,
+        user_pubkey: payer.to_string(),
+        plan_name: plan_name.to_string(),
+        plan_pda: Some(plan_pda.to_string()),
+        tier: tier_name.to_string(),
+        subscription_pda: subscription_pda.to_string(),
+        title: "Subscription Completed".to_string(),
+        message: format!(
+            "Your {} ({}) term is complete. No further payments will be taken.",
+            plan_name, tier_name
+        ),
+        created_at: Some(chrono::Utc::now()),
+        expires_at: None,
+        is_read: false,
+        r#type: NotificationKind::Info,
+    };
+    let _ = create_notification(&state.db, &notification).await;
+
     Ok(())
 }
 
@@ -309,3 +642,652 @@ pub async fn update_subscription_active(
 
     Ok(())
 }
+
+const EMAIL_BATCH_SIZE: i64 = 20;
+const MAX_EMAIL_ATTEMPTS: i32 = 5;
+/// A claim older than this belongs to a dispatcher that stopped mid-send.
+const EMAIL_SEND_LEASE_SECS: f64 = 300.0;
+
+pub async fn run_email_dispatcher(state: Arc<AppState>) {
+    if !state.mailer.is_enabled() {
+        return;
+    }
+
+    let mut ticker = time::interval(Duration::from_secs(15));
+
+    loop {
+        ticker.tick().await;
+        if let Err(err) = dispatch_pending_emails(&state).await {
+            error!("Email dispatcher error: {:?}", err);
+        }
+    }
+}
+
+pub async fn dispatch_pending_emails(state: &AppState) -> anyhow::Result<()> {
+    release_stale_email_claims(state).await?;
+
+    // Claim a batch so concurrent backend instances never send the same email twice
+    let batch = sqlx::query_as!(
+        QueuedEmail,
+        r#"
+        UPDATE email_queue
+        SET status = 'sending', attempts = attempts + 1, claimed_at = now()
+        WHERE id IN (
+            SELECT id FROM email_queue
+            WHERE status = 'pending'
+            ORDER BY created_at ASC
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, to_address, subject, text_body, html_body, attempts
+        "#,
+        EMAIL_BATCH_SIZE
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    for email in batch {
+        match state.mailer.send(&email).await {
+            Ok(()) => {
+                sqlx::query!(
+                    "UPDATE email_queue SET status = 'sent', sent_at = now() WHERE id = $1",
+                    email.id
+                )
+                .execute(&state.db)
+                .await?;
+            }
+            Err(e) => {
+                tracing::warn!("❌ Failed to send email {}: {}", email.id, e);
+                let status = if email.attempts >= MAX_EMAIL_ATTEMPTS {
+                    "failed"
+                } else {
+                    "pending"
+                };
+                sqlx::query!(
+                    "UPDATE email_queue SET status = $1, last_error = $2 WHERE id = $3",
+                    status,
+                    e.to_string(),
+                    email.id
+                )
+                .execute(&state.db)
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns emails whose claim outlived the send lease to the queue, or fails them once
+/// they are out of attempts; an email may then be sent twice, but is never lost.
+async fn release_stale_email_claims(state: &AppState) -> anyhow::Result<()> {
+    let released = sqlx::query!(
+        r#"
+        UPDATE email_queue
+        SET status = CASE WHEN attempts >= $1 THEN 'failed' ELSE 'pending' END,
+            last_error = 'Send interrupted before completing'
+        WHERE status = 'sending'
+          AND (claimed_at IS NULL OR claimed_at < now() - make_interval(secs => $2))
+        "#,
+        MAX_EMAIL_ATTEMPTS,
+        EMAIL_SEND_LEASE_SECS
+    )
+    .execute(&state.db)
+    .await?
+    .rows_affected();
+
+    if released > 0 {
+        tracing::warn!("📧 Released {} emails left mid-send", released);
+    }
+
+    Ok(())
+}
+
+pub async fn run_reminder_scheduler(state: Arc<AppState>) {
+    let windows = state.config.notifications.reminder_windows_hours.clone();
+    if windows.is_empty() {
+        return;
+    }
+
+    let mut ticker = time::interval(Duration::from_secs(300));
+
+    loop {
+        ticker.tick().await;
+        if let Err(err) = send_upcoming_payment_reminders(&state, &windows).await {
+            error!("Reminder scheduler error: {:?}", err);
+        }
+    }
+}
+
+pub async fn send_upcoming_payment_reminders(
+    state: &AppState,
+    windows_hours: &[i64],
+) -> anyhow::Result<()> {
+    let Some(max_window) = windows_hours.iter().max() else {
+        return Ok(());
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+
+    let subs = sqlx::query(
+        r#"
+        SELECT
+            payer,
+            tier_name,
+            plan_pda,
+            next_payment_ts,
+            amount,
+            mint,
+            subscription_pda AS subscription
+        FROM subscriptions
+        WHERE active = true
+          AND auto_renew = true
+          AND paused_at IS NULL
+          AND next_payment_ts > $1
+          AND next_payment_ts <= $2
+        ORDER BY next_payment_ts ASC
+        "#,
+    )
+    .bind(now)
+    .bind(now + max_window * 3600)
+    .fetch_all(&state.db)
+    .await?;
+
+    for sub in subs {
+        let subscription_pda: String = sub.get("subscription");
+        let next_payment_ts: i64 = sub.get("next_payment_ts");
+
+        // Only the tightest window applies, so a subscription created 20h before
+        // its charge gets the 24h reminder and never a late 72h one.
+        let remaining = next_payment_ts - now;
+        let Some(window) = windows_hours
+            .iter()
+            .copied()
+            .filter(|w| remaining <= w * 3600)
+            .min()
+        else {
+            continue;
+        };
+
+        // Claim first so concurrent keepers never send the same reminder twice
+        let claimed = sqlx::query!(
+            r#"
+            INSERT INTO payment_reminders (subscription_pda, next_payment_ts, window_hours)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+            subscription_pda,
+            next_payment_ts,
+            window as i32
+        )
+        .execute(&state.db)
+        .await?
+        .rows_affected()
+            == 1;
+
+        if !claimed {
+            continue;
+        }
+
+        if let Err(e) = notify_upcoming_payment(state, &sub, remaining).await {
+            tracing::warn!("Failed to send reminder for {}: {:?}", subscription_pda, e);
+            // Release the claim so the next tick retries
+            sqlx::query!(
+                r#"
+                DELETE FROM payment_reminders
+                WHERE subscription_pda = $1 AND next_payment_ts = $2 AND window_hours = $3
+                "#,
+                subscription_pda,
+                next_payment_ts,
+                window as i32
+            )
+            .execute(&state.db)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn notify_upcoming_payment(
+    state: &AppState,
+    sub: &sqlx::postgres::PgRow,
+    seconds_until_due: i64,
+) -> anyhow::Result<()> {
+    let subscription_pda = Pubkey::from_str(sub.get("subscription"))?;
+    let payer_pubkey = Pubkey::from_str(sub.get("payer"))?;
+    let tier_name: String = sub.get("tier_name");
+
+    let Some(plan) = state
+        .solana
+        .get_plan(Pubkey::from_str(sub.get("plan_pda"))?)
+        .await?
+    else {
+        tracing::warn!("Plan not found for subscription {}", subscription_pda);
+        return Ok(());
+    };
+
+    let mint = billing_mint(sub, &plan)?;
+    let mint_account = state.solana.rpc.get_account(&mint).await?;
+    let decimals = state.solana.get_mint_decimals(&mint).await?;
+    let payer_token_account =
+        get_associated_token_address_with_program_id(&payer_pubkey, &mint, &mint_account.owner);
+    let funding = state
+        .solana
+        .get_token_account_funding(&payer_token_account)
+        .await?;
+    let Some(account) = state
+        .solana
+        .get_subscription_account(&subscription_pda)
+        .await?
+    else {
+        tracing::warn!("Subscription account {} not found", subscription_pda);
+        return Ok(());
+    };
+
+    // Priced as the keeper will charge it; a USD tier at today's price, which may move
+    // up to the subscriber's token limit by the time it is due
+    let usd_amount = plan.usd_price(&tier_name);
+    let usd_quote = match plan.feed_for(&mint) {
+        Some(feed) if usd_amount > 0 => {
+            let price = state.solana.get_oracle_price(feed).await?;
+            oracle::usd_to_tokens(usd_amount, &price, decimals)
+        }
+        _ => None,
+    };
+    let amount = account.charge_on(&plan, usd_quote.unwrap_or(account.amount));
+    let symbol = plan.token_symbol_for(&mint);
+    let price = match usd_quote {
+        Some(_) => format!(
+            "about {} {} at today's price, at most {} {}",
+            format_token_amount(amount, decimals),
+            symbol,
+            format_token_amount(account.charge_on(&plan, account.max_token_amount), decimals),
+            symbol
+        ),
+        None => format!("{} {}", format_token_amount(amount, decimals), symbol),
+    };
+
+    let sufficient_balance = funding.as_ref().is_some_and(|f| f.amount >= amount);
+    // wSOL accounts approve the delegate every SOL subscription shares
+    let delegate = if mint == NATIVE_MINT {
+        pda::sol_delegate(&state.solana.program_id)
+    } else {
+        subscription_pda
+    };
+    let sufficient_allowance = funding
+        .as_ref()
+        .is_some_and(|f| f.delegate == Some(delegate) && f.delegated_amount >= amount);
+
+    let hours = (seconds_until_due + 1800) / 3600;
+    let when = if hours >= 24 {
+        format!("in {} days", (hours + 12) / 24)
+    } else {
+        format!("in {} hours", hours.max(1))
+    };
+
+    let mut message = format!(
+        "{} ({}) renews {} for {}.",
+        plan.name, tier_name, when, price
+    );
+    if !sufficient_balance && mint == NATIVE_MINT {
+        // SOL plans charge the wrapped balance, not the wallet's SOL
+        message.push_str(" Top up the SOL set aside for subscriptions to cover this payment.");
+    } else if !sufficient_balance {
+        message.push_str(" Your wallet balance is too low to cover this payment.");
+    }
+    if !sufficient_allowance {
+        message.push_str(" Your spending approval for this subscription is too low, re-enable auto-renew to refresh it.");
+    }
+
+    let notification = Notification {
+        id: None,
+        user_pubkey: payer_pubkey.to_string(),
+        plan_name: plan.name,
+        plan_pda: Some(sub.get("plan_pda")),
+        tier: tier_name,
+        subscription_pda: subscription_pda.to_string(),
+        title: "Upcoming Payment".to_string(),
+        message,
+        created_at: Some(chrono::Utc::now()),
+        // A reminder is stale once the charge it announces is due
+        expires_at: chrono::DateTime::from_timestamp(sub.get("next_payment_ts"), 0),
+        is_read: false,
+        r#type: NotificationKind::Reminder,
+    };
+
+    create_notification(&state.db, &notification).await
+}
+
+const NOTIFICATION_CHANNEL: &str = "notifications_inserted";
+
+#[derive(serde::Deserialize)]
+struct InsertedNotification {
+    id: uuid::Uuid,
+}
+
+/// Relays Postgres `NOTIFY` events into `AppState::notifications_tx`, so
+/// streams on every backend instance see notifications inserted by any of them.
+pub async fn run_notification_listener(state: Arc<AppState>) {
+    loop {
+        if let Err(err) = listen_for_notifications(&state).await {
+            error!("Notification listener error: {:?}", err);
+        }
+        time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+async fn listen_for_notifications(state: &AppState) -> anyhow::Result<()> {
+    let mut listener = PgListener::connect_with(&state.db).await?;
+    listener.listen(NOTIFICATION_CHANNEL).await?;
+    tracing::info!("👂 Listening for new notifications");
+
+    loop {
+        let event = listener.recv().await?;
+        let inserted: InsertedNotification = match serde_json::from_str(event.payload()) {
+            Ok(n) => n,
+            Err(e) => {
+                tracing::warn!("Malformed notification payload: {}", e);
+                continue;
+            }
+        };
+
+        // No subscribers just means nobody is connected to this instance
+        if state.notifications_tx.receiver_count() == 0 {
+            continue;
+        }
+
+        if let Some(notification) = fetch_notification_by_id(&state.db, inserted.id).await? {
+            let _ = state.notifications_tx.send(notification);
+        }
+    }
+}
+
+pub async fn run_notification_retention(state: Arc<AppState>) {
+    let retention_days = state.config.notifications.retention_days;
+
+    let mut ticker = time::interval(Duration::from_secs(3600));
+
+    loop {
+        ticker.tick().await;
+        if let Err(err) = purge_notifications(&state, retention_days).await {
+            error!("Notification retention error: {:?}", err);
+        }
+    }
+}
+
+/// Deletes expired notifications and anything older than the retention window.
+pub async fn purge_notifications(state: &AppState, retention_days: i32) -> anyhow::Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM notifications
+        WHERE (expires_at IS NOT NULL AND expires_at < now())
+           OR created_at < now() - make_interval(days => $1)
+        "#,
+        retention_days
+    )
+    .execute(&state.db)
+    .await?;
+
+    let deleted = result.rows_affected();
+    if deleted > 0 {
+        tracing::info!("🧹 Purged {} old notifications", deleted);
+    }
+
+    Ok(deleted)
+}
+
+const SUBSCRIPTION_INDEX: &str = "subscriptions";
+
+/// Registers sign-ups submitted outside the web app, such as checkout links, the relay
+/// and the CLI, so the keeper renews them too.
+pub async fn run_subscription_indexer(state: Arc<AppState>) {
+    let mut ticker = time::interval(Duration::from_secs(30));
+
+    loop {
+        ticker.tick().await;
+        if let Err(err) = index_new_subscriptions(&state).await {
+            error!("Subscription indexer error: {:?}", err);
+        }
+    }
+}
+
+/// Reads the program's transactions since the last run for `SubscriptionInitialized`
+/// events. The first run starts from the newest transaction rather than all of history.
+pub async fn index_new_subscriptions(state: &AppState) -> anyhow::Result<()> {
+    let cursor = sqlx::query_scalar!(
+        "SELECT last_signature FROM program_index_state WHERE name = $1",
+        SUBSCRIPTION_INDEX
+    )
+    .fetch_optional(&state.db)
+    .await?;
+
+    let signatures = match cursor {
+        Some(last) => {
+            state
+                .solana
+                .program_signatures_since(Signature::from_str(&last)?)
+                .await?
+        }
+        None => match state.solana.latest_program_signature().await? {
+            Some(latest) => vec![(latest, false)],
+            None => return Ok(()),
+        },
+    };
+
+    for (signature, succeeded) in signatures {
+        if succeeded {
+            for subscription_pda in register_confirmed_subscriptions(state, &signature).await? {
+                tracing::info!("🗂️ Indexed subscription {}", subscription_pda);
+            }
+        }
+        // Advance one transaction at a time so a failure retries only what is left
+        sqlx::query!(
+            r#"
+            INSERT INTO program_index_state (name, last_signature, updated_at)
+            VALUES ($1, $2, now())
+            ON CONFLICT (name) DO UPDATE
+            SET last_signature = EXCLUDED.last_signature,
+                updated_at = EXCLUDED.updated_at
+            "#,
+            SUBSCRIPTION_INDEX,
+            signature.to_string()
+        )
+        .execute(&state.db)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Settles sponsored sign-ups whose submission ended without a definite outcome, so the
+/// budget keeps what landed and gets back what never will.
+pub async fn run_sponsorship_settlement(state: Arc<AppState>) {
+    let mut ticker = time::interval(Duration::from_secs(30));
+
+    loop {
+        ticker.tick().await;
+        if let Err(err) = settle_sponsored_transactions(&state).await {
+            error!("Sponsorship settlement error: {:?}", err);
+        }
+    }
+}
+
+/// A pending sign-up that landed keeps its spend, one that landed and failed keeps only
+/// the fee, and one missing once its blockhash has expired can no longer land and is
+/// released in full.
+pub async fn settle_sponsored_transactions(state: &AppState) -> anyhow::Result<()> {
+    let pending = sqlx::query!(
+        r#"
+        SELECT tx_signature, recent_blockhash AS "recent_blockhash!"
+        FROM sponsored_transactions
+        WHERE status = 'pending' AND recent_blockhash IS NOT NULL
+        ORDER BY created_at
+        "#
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let rpc = &state.solana.rpc;
+    for row in pending {
+        let signature = Signature::from_str(&row.tx_signature)?;
+        // Checked before the status so a sign-up that lands in between is not released
+        let expired = !rpc
+            .is_blockhash_valid(&Hash::from_str(&row.recent_blockhash)?, rpc.commitment())
+            .await?;
+        let status = rpc
+            .get_signature_statuses_with_history(&[signature])
+            .await?
+            .value
+            .into_iter()
+            .next()
+            .flatten()
+            .filter(|status| status.satisfies_commitment(rpc.commitment()));
+
+        match status {
+            Some(status) if status.err.is_none() => {
+                confirm_reservation(state, &row.tx_signature).await?;
+                tracing::info!("✅ Sponsored sign-up {} settled", row.tx_signature);
+                if let Err(err) = register_confirmed_subscriptions(state, &signature).await {
+                    error!(
+                        "Failed to register sponsored sign-up {}: {:?}",
+                        row.tx_signature, err
+                    );
+                }
+            }
+            Some(status) => {
+                fail_reservation(state, &row.tx_signature).await?;
+                tracing::info!(
+                    "❌ Sponsored sign-up {} failed on chain: {:?}",
+                    row.tx_signature,
+                    status.err
+                );
+            }
+            None if expired => {
+                release_reservation(state, &row.tx_signature).await?;
+                tracing::info!(
+                    "↩️ Sponsored sign-up {} expired unsent, budget released",
+                    row.tx_signature
+                );
+            }
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+const ANALYTICS_VIEWS: &[&str] = &[
+    "analytics_plan_monthly",
+    "analytics_plan_revenue",
+    "analytics_plan_cohorts",
+];
+
+pub async fn run_analytics_refresh(state: Arc<AppState>) {
+    let interval = Duration::from_secs(state.config.analytics.refresh_interval_secs);
+    let mut ticker = time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+        if let Err(err) = refresh_analytics_views(&state).await {
+            error!("Analytics refresh error: {:?}", err);
+        }
+    }
+}
+
+/// Refreshes the analytics materialized views, skipping any whose sources
+/// have not changed since the last refresh.
+pub async fn refresh_analytics_views(state: &AppState) -> anyhow::Result<()> {
+    let watermark = sqlx::query_scalar!(
+        r#"
+        SELECT GREATEST(
+            (SELECT MAX(created_at) FROM payment_history),
+            (SELECT MAX(occurred_at) FROM subscription_events)
+        )
+        "#
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    let Some(watermark) = watermark else {
+        return Ok(());
+    };
+
+    for view in ANALYTICS_VIEWS {
+        let last = sqlx::query_scalar!(
+            "SELECT source_watermark FROM analytics_refresh_state WHERE view_name = $1",
+            view
+        )
+        .fetch_optional(&state.db)
+        .await?;
+
+        if last.is_some_and(|last| last >= watermark) {
+            continue;
+        }
+
+        // CONCURRENTLY keeps the view readable while it rebuilds
+        sqlx::query(&format!("REFRESH MATERIALIZED VIEW CONCURRENTLY {}", view))
+            .execute(&state.db)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO analytics_refresh_state (view_name, source_watermark, refreshed_at)
+            VALUES ($1, $2, now())
+            ON CONFLICT (view_name) DO UPDATE
+            SET source_watermark = EXCLUDED.source_watermark,
+                refreshed_at = EXCLUDED.refreshed_at
+            "#,
+            view,
+            watermark
+        )
+        .execute(&state.db)
+        .await?;
+
+        tracing::info!("📊 Refreshed {}", view);
+    }
+
+    Ok(())
+}
+
+/// Swaps in a freshly loaded keeper signer on SIGHUP. `argv` is re-parsed against the
+/// environment with the current `.env` in place of the one read at startup, whose
+/// `dotenv_names` are dropped first, and the config file re-read, so a new key, secret or
+/// signing service can be pointed at without a restart. As at startup, a variable the
+/// process was launched with wins over `.env`. If loading fails the current signer stays.
+pub async fn run_signer_rotation(
+    state: Arc<AppState>,
+    argv: Vec<OsString>,
+    dotenv_names: HashSet<String>,
+) {
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(err) => {
+            error!("Signer rotation disabled: {:?}", err);
+            return;
+        }
+    };
+
+    while hangups.recv().await.is_some() {
+        match rotate_signer(&state, &argv, &dotenv_names).await {
+            Ok((old, new)) => tracing::info!("🔑 Keeper signer rotated from {} to {}", old, new),
+            Err(err) => error!(
+                "Signer rotation error, keeping the current signer: {:#}",
+                err
+            ),
+        }
+    }
+}
+
+async fn rotate_signer(
+    state: &AppState,
+    argv: &[OsString],
+    dotenv_names: &HashSet<String>,
+) -> anyhow::Result<(Pubkey, Pubkey)> {
+    let env = Environment::capture().without(dotenv_names).with_dotenv()?;
+    let args = ConfigArgs::parse_in(argv, &env)?;
+    let config = Config::load_in(&args, &env)?;
+    let signer = signer::from_config(&config.signer, &env).await?;
+    let new = signer.pubkey();
+    let old = state.solana.signer.rotate(signer);
+    Ok((old, new))
+}
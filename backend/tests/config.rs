@@ -0,0 +1,48 @@
+use backend::config::{ConfigArgs, Environment};
+use std::collections::HashSet;
+use std::ffi::OsString;
+
+fn argv(args: &[&str]) -> Vec<OsString> {
+    args.iter().map(OsString::from).collect()
+}
+
+#[test]
+fn reparsing_reads_the_snapshot_under_the_flags() {
+    let env: Environment = [
+        ("SOLPAY_RPC_URL", "http://snapshot:8899"),
+        ("SOLPAY_KEEPER_BATCH_SIZE", "7"),
+        ("SOLPAY_CORS_ORIGINS", "https://a.example,https://b.example"),
+        ("SOLPAY_SIGNER_URL", ""),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value.to_string()))
+    .collect();
+
+    let args =
+        ConfigArgs::parse_in(&argv(&["backend", "--rpc-url", "http://flag:8899"]), &env).unwrap();
+    assert_eq!(args.rpc_url.as_deref(), Some("http://flag:8899"));
+    assert_eq!(args.keeper_batch_size, Some(7));
+    assert_eq!(
+        args.cors_origins,
+        Some(vec![
+            "https://a.example".to_string(),
+            "https://b.example".to_string()
+        ])
+    );
+    assert_eq!(args.signer_url, None);
+}
+
+#[test]
+fn dropping_startup_dotenv_names_keeps_launch_variables() {
+    let env: Environment = [
+        ("SOLPAY_RPC_URL", "http://launch:8899"),
+        ("SOLPAY_SIGNER_URL", "http://startup-dotenv:9000"),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value.to_string()))
+    .collect();
+
+    let env = env.without(&HashSet::from(["SOLPAY_SIGNER_URL".to_string()]));
+    assert_eq!(env.var("SOLPAY_RPC_URL"), Some("http://launch:8899"));
+    assert_eq!(env.var("SOLPAY_SIGNER_URL"), None);
+}
@@ -0,0 +1,144 @@
+use backend::email::{
+    EmailCategory, MailTransport, Mailer, render_notification, render_verification,
+};
+use backend::models::email::QueuedEmail;
+use backend::models::notification::{Notification, NotificationKind};
+use lettre::AsyncFileTransport;
+use std::path::{Path, PathBuf};
+
+/// A fresh outbox directory for one test.
+fn outbox() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("solpay-outbox-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn file_mailer(dir: &Path) -> Mailer {
+    Mailer {
+        from: "SolPay <no-reply@solpay.local>".parse().unwrap(),
+        transport: MailTransport::File(AsyncFileTransport::new(dir)),
+        verify_url: "https://solpay.test/verify-email".to_string(),
+    }
+}
+
+fn notification(kind: NotificationKind) -> Notification {
+    Notification {
+        id: None,
+        plan_name: "Pro <Team>".to_string(),
+        plan_pda: None,
+        tier: "Monthly".to_string(),
+        user_pubkey: "11111111111111111111111111111111".to_string(),
+        subscription_pda: "Sub1111111111111111111111111111111111111111".to_string(),
+        title: "Payment Successful".to_string(),
+        message: "Charged 10 USDC & renewed".to_string(),
+        created_at: None,
+        expires_at: None,
+        is_read: false,
+        r#type: kind,
+    }
+}
+
+fn sent_messages(dir: &Path) -> Vec<String> {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "eml"))
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn file_transport_writes_plain_and_html_parts() {
+    let dir = outbox();
+    let mailer = file_mailer(&dir);
+    let rendered = render_notification(
+        EmailCategory::RenewalSuccess,
+        &notification(NotificationKind::Success),
+    );
+
+    mailer
+        .send(&QueuedEmail {
+            id: uuid::Uuid::new_v4(),
+            to_address: "subscriber@example.com".to_string(),
+            subject: rendered.subject.clone(),
+            text_body: rendered.text,
+            html_body: rendered.html,
+            attempts: 1,
+        })
+        .await
+        .unwrap();
+
+    let messages = sent_messages(&dir);
+    assert_eq!(messages.len(), 1);
+    let message = &messages[0];
+    assert!(message.contains("To: subscriber@example.com"));
+    assert!(message.contains("multipart/alternative"));
+    assert!(message.contains("Content-Type: text/plain"));
+    assert!(message.contains("Content-Type: text/html"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn disabled_transport_keeps_emails_queued() {
+    let mailer = Mailer {
+        transport: MailTransport::Disabled,
+        ..file_mailer(&std::env::temp_dir())
+    };
+    assert!(!mailer.is_enabled());
+
+    let result = mailer
+        .send(&QueuedEmail {
+            id: uuid::Uuid::new_v4(),
+            to_address: "subscriber@example.com".to_string(),
+            subject: "Subject".to_string(),
+            text_body: "Text".to_string(),
+            html_body: "<p>Html</p>".to_string(),
+            attempts: 1,
+        })
+        .await;
+    assert!(result.is_err());
+}
+
+#[test]
+fn notification_html_is_escaped() {
+    let rendered = render_notification(
+        EmailCategory::RenewalSuccess,
+        &notification(NotificationKind::Success),
+    );
+
+    assert!(rendered.subject.contains("Pro <Team>"));
+    assert!(rendered.text.contains("Charged 10 USDC & renewed"));
+    assert!(rendered.html.contains("Pro &lt;Team&gt;"));
+    assert!(rendered.html.contains("Charged 10 USDC &amp; renewed"));
+    assert!(!rendered.html.contains("<Team>"));
+}
+
+#[test]
+fn verification_links_carry_the_token() {
+    let token = uuid::Uuid::new_v4();
+    let rendered = render_verification("https://solpay.test/verify-email", token);
+
+    let link = format!("https://solpay.test/verify-email?token={}", token);
+    assert!(rendered.text.contains(&link));
+    assert!(rendered.html.contains(&link));
+}
+
+#[test]
+fn only_actionable_kinds_are_emailed() {
+    let category = |kind| EmailCategory::from_notification(&notification(kind));
+
+    assert_eq!(
+        category(NotificationKind::Success),
+        Some(EmailCategory::RenewalSuccess)
+    );
+    assert_eq!(
+        category(NotificationKind::Error),
+        Some(EmailCategory::RenewalFailure)
+    );
+    assert_eq!(
+        category(NotificationKind::Reminder),
+        Some(EmailCategory::UpcomingCharge)
+    );
+    assert_eq!(category(NotificationKind::Info), None);
+}
@@ -0,0 +1,167 @@
+use axum::{Json, Router, extract::State, http::HeaderMap, http::StatusCode, routing::get};
+use backend::config::Environment;
+use backend::signer::{
+    ActiveSigner, KeeperSigner, KeypairSigner, RemotePubkey, RemoteSignRequest, RemoteSignResponse,
+    RemoteSigner, sign_transaction,
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use solana_sdk::{
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use std::sync::Arc;
+
+const TOKEN: &str = "stub-token";
+
+struct Stub {
+    keypair: Keypair,
+    /// Signs with this key instead, to act as a misbehaving service
+    forger: Option<Keypair>,
+}
+
+async fn pubkey(State(stub): State<Arc<Stub>>) -> Json<RemotePubkey> {
+    Json(RemotePubkey {
+        pubkey: stub.keypair.pubkey().to_string(),
+    })
+}
+
+async fn sign(
+    State(stub): State<Arc<Stub>>,
+    headers: HeaderMap,
+    Json(request): Json<RemoteSignRequest>,
+) -> Result<Json<RemoteSignResponse>, StatusCode> {
+    let authorization = headers.get("authorization").and_then(|v| v.to_str().ok());
+    if authorization != Some(&format!("Bearer {}", TOKEN)) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    if request.pubkey != stub.keypair.pubkey().to_string() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let message = STANDARD
+        .decode(&request.message)
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let key = stub.forger.as_ref().unwrap_or(&stub.keypair);
+    Ok(Json(RemoteSignResponse {
+        signature: key.sign_message(&message).to_string(),
+    }))
+}
+
+/// Serves the signing protocol on an ephemeral port and returns its URL.
+async fn spawn_stub(keypair: Keypair, forger: Option<Keypair>) -> String {
+    let app = Router::new()
+        .route("/pubkey", get(pubkey))
+        .route("/sign", axum::routing::post(sign))
+        .with_state(Arc::new(Stub { keypair, forger }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+fn paid_by(payer: &Pubkey) -> Transaction {
+    let ix = Instruction::new_with_bytes(
+        Pubkey::new_unique(),
+        &[],
+        vec![AccountMeta::new(*payer, true)],
+    );
+    let message = Message::new_with_blockhash(&[ix], Some(payer), &Hash::new_unique());
+    Transaction::new_unsigned(message)
+}
+
+#[tokio::test]
+async fn remote_signer_signs_transactions() {
+    let keypair = Keypair::new();
+    let expected = keypair.pubkey();
+    let url = spawn_stub(keypair, None).await;
+
+    let signer = RemoteSigner::connect(&url, Some(TOKEN.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(signer.pubkey(), expected);
+
+    let mut tx = paid_by(&expected);
+    sign_transaction(&signer, &mut tx).await.unwrap();
+    tx.verify().unwrap();
+}
+
+#[tokio::test]
+async fn remote_signer_rejects_bad_signatures() {
+    let url = spawn_stub(Keypair::new(), Some(Keypair::new())).await;
+    let signer = RemoteSigner::connect(&url, Some(TOKEN.to_string()))
+        .await
+        .unwrap();
+
+    let mut tx = paid_by(&signer.pubkey());
+    let err = sign_transaction(&signer, &mut tx).await.unwrap_err();
+    assert!(err.to_string().contains("does not match"), "{}", err);
+}
+
+#[tokio::test]
+async fn remote_signer_requires_the_token() {
+    let url = spawn_stub(Keypair::new(), None).await;
+    let signer = RemoteSigner::connect(&url, None).await.unwrap();
+
+    let mut tx = paid_by(&signer.pubkey());
+    assert!(sign_transaction(&signer, &mut tx).await.is_err());
+}
+
+#[tokio::test]
+async fn env_signer_reads_base58_secrets() {
+    let keypair = Keypair::new();
+    let var = "SOLPAY_TEST_KEEPER_SECRET";
+    // SAFETY: no other test reads or writes this variable
+    unsafe { std::env::set_var(var, bs58::encode(keypair.to_bytes()).into_string()) };
+
+    let signer = KeypairSigner::from_env(var).unwrap();
+    assert_eq!(signer.pubkey(), keypair.pubkey());
+
+    let mut tx = paid_by(&keypair.pubkey());
+    sign_transaction(&signer, &mut tx).await.unwrap();
+    tx.verify().unwrap();
+
+    unsafe { std::env::set_var(var, "not a key") };
+    assert!(KeypairSigner::from_env(var).is_err());
+}
+
+#[test]
+fn env_signer_reads_a_snapshot_without_the_process_env() {
+    let keypair = Keypair::new();
+    let var = "SOLPAY_TEST_SNAPSHOT_SECRET";
+    let env: Environment = [(
+        var.to_string(),
+        bs58::encode(keypair.to_bytes()).into_string(),
+    )]
+    .into_iter()
+    .collect();
+
+    let signer = KeypairSigner::from_env_in(var, &env).unwrap();
+    assert_eq!(signer.pubkey(), keypair.pubkey());
+    assert!(std::env::var(var).is_err());
+}
+
+#[tokio::test]
+async fn signer_must_be_required_by_the_transaction() {
+    let signer = KeypairSigner::new(Keypair::new());
+    let mut tx = paid_by(&Pubkey::new_unique());
+    assert!(sign_transaction(&signer, &mut tx).await.is_err());
+}
+
+#[tokio::test]
+async fn active_signer_rotates() {
+    let first = Keypair::new();
+    let second = Keypair::new();
+    let (first_key, second_key) = (first.pubkey(), second.pubkey());
+
+    let active = ActiveSigner::new(Arc::new(KeypairSigner::new(first)));
+    let snapshot = active.current();
+
+    let old = active.rotate(Arc::new(KeypairSigner::new(second)));
+    assert_eq!(old, first_key);
+    assert_eq!(active.pubkey(), second_key);
+    // Work that started before the rotation finishes with the key it began with
+    assert_eq!(snapshot.pubkey(), first_key);
+}
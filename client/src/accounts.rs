@@ -0,0 +1,227 @@
+//! Program accounts, decoded only when their discriminator matches.
+
+use crate::types::{Discount, PlanMint, PriceFeed, UnitPrice, UsdPrice};
+use crate::{IdlType, discriminator};
+use anchor_lang::prelude::*;
+
+/// An account type owned by the program, stored as its discriminator then Borsh data.
+pub trait ProgramAccount: IdlType + AnchorDeserialize {
+    fn discriminator() -> [u8; 8] {
+        discriminator("account", Self::NAME)
+    }
+
+    /// Accounts are allocated at their largest size, so trailing bytes are ignored.
+    /// Accounts created before their type grew end early; the missing fields decode
+    /// as zero, which is what `migrate_account` fills them with.
+    fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        let Some((tag, body)) = data.split_first_chunk::<8>() else {
+            anyhow::bail!("Account data too small for a {}", Self::NAME);
+        };
+        if *tag != Self::discriminator() {
+            anyhow::bail!("Account is not a {}", Self::NAME);
+        }
+        match Self::deserialize(&mut &body[..]) {
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                let mut padded = body.to_vec();
+                padded.resize(body.len() + LEGACY_PADDING, 0);
+                Ok(Self::deserialize(&mut padded.as_slice())?)
+            }
+            decoded => Ok(decoded?),
+        }
+    }
+}
+
+/// More than any account type has grown by since its first deployment.
+const LEGACY_PADDING: usize = 1024;
+
+/// Types whose accounts `migrate_account` can grow to the current layout.
+pub trait Migratable: ProgramAccount {
+    /// Allocated size of a current account, discriminator included.
+    const SPACE: usize;
+
+    /// Whether an account of this size predates the current layout.
+    fn needs_migration(data: &[u8]) -> bool {
+        data.len() < Self::SPACE
+    }
+}
+
+idl_structs! {
+    pub struct Plan {
+        pub creator: Pubkey = "pubkey",
+        pub mint: Pubkey = "pubkey",
+        pub receiver: Pubkey = "pubkey",
+        pub name: String = "string",
+        pub token_symbol: String = "string",
+        pub token_image: String = "string",
+        /// Compressed tier list
+        pub tiers: Vec<u8> = "bytes",
+        pub bump: u8 = "u8",
+        pub max_pause_seconds: i64 = "i64",
+        pub accepted_mints: Vec<PlanMint> = r#"{"vec":{"defined":{"name":"PlanMint"}}}"#,
+        /// Unused since feeds are kept per mint in `price_feeds`
+        pub price_feed: Pubkey = "pubkey",
+        pub max_price_age: i64 = "i64",
+        pub max_confidence_bps: u16 = "u16",
+        pub unit_prices: Vec<UnitPrice> = r#"{"vec":{"defined":{"name":"UnitPrice"}}}"#,
+        pub usd_prices: Vec<UsdPrice> = r#"{"vec":{"defined":{"name":"UsdPrice"}}}"#,
+        pub price_feeds: Vec<PriceFeed> = r#"{"vec":{"defined":{"name":"PriceFeed"}}}"#,
+    }
+
+    pub struct Subscription {
+        pub payer: Pubkey = "pubkey",
+        pub plan_pda: Pubkey = "pubkey",
+        pub tier_name: String = "string",
+        pub next_payment_ts: i64 = "i64",
+        pub auto_renew: bool = "bool",
+        pub active: bool = "bool",
+        pub bump: u8 = "u8",
+        pub unique_seed: [u8; 8] = r#"{"array":["u8",8]}"#,
+        pub amount: u64 = "u64",
+        pub period_seconds: i64 = "i64",
+        pub payment_count: u64 = "u64",
+        pub paused_at: Option<i64> = r#"{"option":"i64"}"#,
+        pub max_cycles: Option<u64> = r#"{"option":"u64"}"#,
+        pub end_ts: Option<i64> = r#"{"option":"i64"}"#,
+        pub usage_cap: u64 = "u64",
+        pub accrued_units: u64 = "u64",
+        pub coupon: Option<Pubkey> = r#"{"option":"pubkey"}"#,
+        pub discount: Option<Discount> = r#"{"option":{"defined":{"name":"Discount"}}}"#,
+        pub discount_cycles_left: Option<u64> = r#"{"option":"u64"}"#,
+        pub mint: Pubkey = "pubkey",
+        pub max_token_amount: u64 = "u64",
+        pub pending_usage_cap: Option<u64> = r#"{"option":"u64"}"#,
+    }
+
+    pub struct PaymentRecord {
+        pub subscription: Pubkey = "pubkey",
+        pub plan: Pubkey = "pubkey",
+        pub payer: Pubkey = "pubkey",
+        pub mint: Pubkey = "pubkey",
+        pub index: u64 = "u64",
+        pub amount: u64 = "u64",
+        pub refunded_amount: u64 = "u64",
+        pub paid_at: i64 = "i64",
+        pub bump: u8 = "u8",
+        pub usage_units: u64 = "u64",
+        pub usage_amount: u64 = "u64",
+        pub discount_amount: u64 = "u64",
+        pub transfer_fee: u64 = "u64",
+    }
+
+    pub struct Coupon {
+        pub plan: Pubkey = "pubkey",
+        pub code: String = "string",
+        pub discount: Discount = r#"{"defined":{"name":"Discount"}}"#,
+        pub duration_cycles: Option<u64> = r#"{"option":"u64"}"#,
+        pub max_redemptions: Option<u64> = r#"{"option":"u64"}"#,
+        pub redemptions: u64 = "u64",
+        pub expires_at: Option<i64> = r#"{"option":"i64"}"#,
+        pub tier_name: Option<String> = r#"{"option":"string"}"#,
+        pub active: bool = "bool",
+        pub bump: u8 = "u8",
+    }
+
+    pub struct GlobalStats {
+        pub total_subscriptions: u64 = "u64",
+        pub total_payments_executed: u64 = "u64",
+        pub total_value_released: u128 = "u128",
+        pub bump: u8 = "u8",
+    }
+}
+
+impl ProgramAccount for Plan {}
+impl ProgramAccount for Subscription {}
+impl ProgramAccount for PaymentRecord {}
+impl ProgramAccount for Coupon {}
+impl ProgramAccount for GlobalStats {}
+
+impl Migratable for Plan {
+    const SPACE: usize = 2985;
+}
+impl Migratable for Subscription {
+    const SPACE: usize = 303;
+}
+
+impl Plan {
+    /// Symbol for one of the plan's mints, falling back to the primary one.
+    pub fn token_symbol_for(&self, mint: &Pubkey) -> &str {
+        self.accepted_mints
+            .iter()
+            .find(|m| m.mint == *mint)
+            .map_or(&self.token_symbol, |m| &m.token_symbol)
+    }
+
+    /// Price per unit of usage on `tier_name` billed in `mint`, 0 when the tier is flat.
+    pub fn unit_price(&self, tier_name: &str, mint: &Pubkey) -> u64 {
+        self.unit_prices
+            .iter()
+            .find(|p| p.tier_name == tier_name && p.mint == *mint)
+            .map_or(0, |p| p.unit_price)
+    }
+
+    /// USD price per cycle of `tier_name` with 6 decimals, 0 when priced in tokens.
+    pub fn usd_price(&self, tier_name: &str) -> u64 {
+        self.usd_prices
+            .iter()
+            .find(|p| p.tier_name == tier_name)
+            .map_or(0, |p| p.usd_amount)
+    }
+
+    /// Feed that prices `mint` in USD, if the creator has set one.
+    pub fn feed_for(&self, mint: &Pubkey) -> Option<&PriceFeed> {
+        self.price_feeds.iter().find(|f| f.mint == *mint)
+    }
+}
+
+impl Subscription {
+    /// Coupon discount on the next charge, as `execute_payment` computes it.
+    pub fn pending_discount_amount(&self) -> u64 {
+        self.discount
+            .map_or(0, |discount| discount.amount_off(self.amount))
+    }
+
+    /// Older subscriptions were always billed in the plan's primary mint.
+    pub fn billing_mint(&self, plan: &Plan) -> Pubkey {
+        if self.mint == Pubkey::default() {
+            plan.mint
+        } else {
+            self.mint
+        }
+    }
+
+    /// Tiers the plan prices in USD are converted at the oracle price when charged.
+    pub fn is_usd_priced(&self, plan: &Plan) -> bool {
+        plan.usd_price(&self.tier_name) > 0
+    }
+
+    /// Estimate of the next charge: discounted base plus capped usage.
+    pub fn next_charge_amount(&self, plan: &Plan) -> u64 {
+        self.charge_on(plan, self.amount)
+    }
+
+    /// The next charge on a base of `base_amount`, such as a USD tier's quote in tokens.
+    pub fn charge_on(&self, plan: &Plan, base_amount: u64) -> u64 {
+        let discount = self
+            .discount
+            .map_or(0, |discount| discount.amount_off(base_amount));
+        base_amount - discount + self.pending_usage_amount(plan)
+    }
+
+    /// The plan's price per unit for this subscription's tier and mint.
+    pub fn unit_price(&self, plan: &Plan) -> u64 {
+        plan.unit_price(&self.tier_name, &self.billing_mint(plan))
+    }
+
+    /// What the usage part of the next charge would be, as `execute_payment` computes it.
+    pub fn pending_usage_amount(&self, plan: &Plan) -> u64 {
+        self.accrued_units
+            .saturating_mul(self.unit_price(plan))
+            .min(self.usage_cap)
+    }
+
+    /// Same rule the program uses to stop charging a fixed-term subscription.
+    pub fn term_reached(&self) -> bool {
+        self.max_cycles.is_some_and(|max| self.payment_count >= max)
+            || self.end_ts.is_some_and(|end| self.next_payment_ts >= end)
+    }
+}
@@ -0,0 +1,214 @@
+//! Events the program emits, read back from `Program data:` transaction log lines. Events
+//! it declares but never emits are left out, as they are from the IDL.
+
+use crate::types::Discount;
+use crate::{IdlType, discriminator};
+use anchor_lang::prelude::*;
+use base64::{Engine, engine::general_purpose::STANDARD};
+
+const PROGRAM_DATA: &str = "Program data: ";
+
+/// An event emitted with `emit!`: its discriminator then Borsh data, base64 in the logs.
+pub trait ProgramEvent: IdlType + AnchorDeserialize {
+    fn discriminator() -> [u8; 8] {
+        discriminator("event", Self::NAME)
+    }
+
+    /// `None` when the data belongs to another event.
+    fn decode(data: &[u8]) -> anyhow::Result<Option<Self>> {
+        match data.split_first_chunk::<8>() {
+            Some((tag, mut body)) if *tag == Self::discriminator() => {
+                Ok(Some(Self::deserialize(&mut body)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Every event of this type in a transaction's log messages, in emission order.
+    fn from_logs(logs: &[String]) -> anyhow::Result<Vec<Self>> {
+        let mut events = Vec::new();
+        for line in logs {
+            let Some(encoded) = line.strip_prefix(PROGRAM_DATA) else {
+                continue;
+            };
+            // Other programs log data too; only well-formed payloads can match
+            let Ok(data) = STANDARD.decode(encoded.trim()) else {
+                continue;
+            };
+            events.extend(Self::decode(&data)?);
+        }
+        Ok(events)
+    }
+}
+
+idl_structs! {
+    pub struct SubscriptionInitialized {
+        pub subscription: Pubkey = "pubkey",
+        pub payer: Pubkey = "pubkey",
+        pub tier_name: String = "string",
+        pub plan_pda: String = "string",
+        pub amount: u64 = "u64",
+        pub next_payment_ts: i64 = "i64",
+        pub auto_renew: bool = "bool",
+        pub active: bool = "bool",
+        pub bump: u8 = "u8",
+        pub unique_seed: [u8; 8] = r#"{"array":["u8",8]}"#,
+        pub max_cycles: Option<u64> = r#"{"option":"u64"}"#,
+        pub end_ts: Option<i64> = r#"{"option":"i64"}"#,
+        pub mint: Pubkey = "pubkey",
+    }
+
+    pub struct SubscriptionTopup {
+        pub subscription: Pubkey = "pubkey",
+        pub topup_amount: u64 = "u64",
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct PaymentExecuted {
+        pub subscription: Pubkey = "pubkey",
+        pub payer: Pubkey = "pubkey",
+        pub payee: Pubkey = "pubkey",
+        pub amount: u64 = "u64",
+        pub base_amount: u64 = "u64",
+        pub usage_units: u64 = "u64",
+        pub usage_amount: u64 = "u64",
+        pub discount_amount: u64 = "u64",
+        pub transfer_fee: u64 = "u64",
+        pub next_payment_ts: i64 = "i64",
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct UsageReported {
+        pub subscription: Pubkey = "pubkey",
+        pub units: u64 = "u64",
+        pub accrued_units: u64 = "u64",
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct Refunded {
+        pub payment_record: Pubkey = "pubkey",
+        pub subscription: Pubkey = "pubkey",
+        pub plan: Pubkey = "pubkey",
+        pub payer: Pubkey = "pubkey",
+        pub receiver: Pubkey = "pubkey",
+        pub payment_index: u64 = "u64",
+        pub amount: u64 = "u64",
+        pub total_refunded: u64 = "u64",
+        pub original_amount: u64 = "u64",
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct PaymentFailed {
+        pub subscription: Pubkey = "pubkey",
+        pub reason: u8 = "u8",
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct SubscriptionCancelled {
+        pub subscription: Pubkey = "pubkey",
+        pub payer: Pubkey = "pubkey",
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct SubscriptionCompleted {
+        pub subscription: Pubkey = "pubkey",
+        pub payer: Pubkey = "pubkey",
+        pub payments_made: u64 = "u64",
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct SubscriptionPaused {
+        pub subscription: Pubkey = "pubkey",
+        pub payer: Pubkey = "pubkey",
+        pub paused_at: i64 = "i64",
+    }
+
+    pub struct SubscriptionResumed {
+        pub subscription: Pubkey = "pubkey",
+        pub payer: Pubkey = "pubkey",
+        pub paused_seconds: i64 = "i64",
+        pub next_payment_ts: i64 = "i64",
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct WithdrawnRemaining {
+        pub subscription: Pubkey = "pubkey",
+        pub payer: Pubkey = "pubkey",
+        pub withdrawn_amount: u64 = "u64",
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct ScheduleUpdated {
+        pub subscription: Pubkey = "pubkey",
+        pub new_amount: u64 = "u64",
+        pub new_period_seconds: i64 = "i64",
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct GlobalStatsInitialized {
+        pub bump: u8 = "u8",
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct CouponCreated {
+        pub coupon: Pubkey = "pubkey",
+        pub plan: Pubkey = "pubkey",
+        pub code: String = "string",
+        pub discount: Discount = r#"{"defined":{"name":"Discount"}}"#,
+        pub duration_cycles: Option<u64> = r#"{"option":"u64"}"#,
+        pub max_redemptions: Option<u64> = r#"{"option":"u64"}"#,
+        pub expires_at: Option<i64> = r#"{"option":"i64"}"#,
+        pub tier_name: Option<String> = r#"{"option":"string"}"#,
+    }
+
+    pub struct CouponRedeemed {
+        pub coupon: Pubkey = "pubkey",
+        pub subscription: Pubkey = "pubkey",
+        pub payer: Pubkey = "pubkey",
+        pub redemptions: u64 = "u64",
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct PlanMintsUpdated {
+        pub plan: Pubkey = "pubkey",
+        pub mints: Vec<Pubkey> = r#"{"vec":"pubkey"}"#,
+        pub timestamp: i64 = "i64",
+    }
+
+    pub struct SolWrapped {
+        pub owner: Pubkey = "pubkey",
+        pub lamports: u64 = "u64",
+        pub balance: u64 = "u64",
+    }
+
+    pub struct SolUnwrapped {
+        pub owner: Pubkey = "pubkey",
+        pub lamports: u64 = "u64",
+    }
+
+    pub struct AccountMigrated {
+        pub account: Pubkey = "pubkey",
+        pub previous_size: u64 = "u64",
+        pub size: u64 = "u64",
+    }
+}
+
+impl ProgramEvent for SubscriptionInitialized {}
+impl ProgramEvent for SubscriptionTopup {}
+impl ProgramEvent for PaymentExecuted {}
+impl ProgramEvent for UsageReported {}
+impl ProgramEvent for Refunded {}
+impl ProgramEvent for PaymentFailed {}
+impl ProgramEvent for SubscriptionCancelled {}
+impl ProgramEvent for SubscriptionCompleted {}
+impl ProgramEvent for SubscriptionPaused {}
+impl ProgramEvent for SubscriptionResumed {}
+impl ProgramEvent for WithdrawnRemaining {}
+impl ProgramEvent for ScheduleUpdated {}
+impl ProgramEvent for GlobalStatsInitialized {}
+impl ProgramEvent for CouponCreated {}
+impl ProgramEvent for CouponRedeemed {}
+impl ProgramEvent for PlanMintsUpdated {}
+impl ProgramEvent for SolWrapped {}
+impl ProgramEvent for SolUnwrapped {}
+impl ProgramEvent for AccountMigrated {}
@@ -0,0 +1,493 @@
+//! One module per program instruction, each with its `Accounts` in program order, its
+//! `Args` in signature order and an `instruction` builder.
+
+use crate::FieldSpec;
+use crate::types::{Discount, SubscriptionField, UpdateValue};
+use anchor_lang::prelude::*;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccountKind {
+    Readonly,
+    Writable,
+    Signer,
+    WritableSigner,
+    /// Anchor `Option<...>` accounts; the program id stands in when absent
+    OptionalReadonly,
+    OptionalWritable,
+}
+
+impl AccountKind {
+    pub const fn writable(self) -> bool {
+        matches!(
+            self,
+            AccountKind::Writable | AccountKind::WritableSigner | AccountKind::OptionalWritable
+        )
+    }
+
+    pub const fn signer(self) -> bool {
+        matches!(self, AccountKind::Signer | AccountKind::WritableSigner)
+    }
+
+    pub const fn optional(self) -> bool {
+        matches!(
+            self,
+            AccountKind::OptionalReadonly | AccountKind::OptionalWritable
+        )
+    }
+
+    const fn spec(self, name: &'static str) -> AccountSpec {
+        AccountSpec {
+            name,
+            writable: self.writable(),
+            signer: self.signer(),
+            optional: self.optional(),
+        }
+    }
+
+    fn meta(self, key: Pubkey) -> AccountMeta {
+        if self.writable() {
+            AccountMeta::new(key, self.signer())
+        } else {
+            AccountMeta::new_readonly(key, self.signer())
+        }
+    }
+}
+
+/// An entry of an instruction's account list, as the IDL describes it.
+#[derive(Debug, Clone, Copy)]
+pub struct AccountSpec {
+    pub name: &'static str,
+    pub writable: bool,
+    pub signer: bool,
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct InstructionSpec {
+    pub name: &'static str,
+    pub discriminator: fn() -> [u8; 8],
+    pub accounts: &'static [AccountSpec],
+    pub args: &'static [FieldSpec],
+}
+
+trait AccountKey {
+    fn meta(&self, kind: AccountKind, program_id: &Pubkey) -> AccountMeta;
+}
+
+impl AccountKey for Pubkey {
+    fn meta(&self, kind: AccountKind, _program_id: &Pubkey) -> AccountMeta {
+        kind.meta(*self)
+    }
+}
+
+impl AccountKey for Option<Pubkey> {
+    fn meta(&self, kind: AccountKind, program_id: &Pubkey) -> AccountMeta {
+        match self {
+            Some(key) => kind.meta(*key),
+            None => AccountMeta::new_readonly(*program_id, false),
+        }
+    }
+}
+
+macro_rules! account_type {
+    (OptionalReadonly) => { Option<Pubkey> };
+    (OptionalWritable) => { Option<Pubkey> };
+    ($kind:ident) => { Pubkey };
+}
+
+macro_rules! instructions {
+    (
+        $(
+            $(#[$meta:meta])*
+            $name:ident {
+                accounts { $($acc:ident: $kind:ident,)* }
+                args { $($arg:ident: $ty:ty = $idl:literal,)* }
+            }
+        )*
+    ) => {
+        $(
+            $(#[$meta])*
+            pub mod $name {
+                use super::*;
+
+                pub const NAME: &str = stringify!($name);
+
+                pub const ACCOUNTS: &[AccountSpec] = &[
+                    $(AccountKind::$kind.spec(stringify!($acc)),)*
+                ];
+
+                pub const ARGS: &[FieldSpec] = &[
+                    $(FieldSpec { name: stringify!($arg), idl_type: $idl },)*
+                ];
+
+                #[derive(Debug, Clone)]
+                pub struct Accounts {
+                    $(pub $acc: account_type!($kind),)*
+                }
+
+                #[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
+                pub struct Args {
+                    $(pub $arg: $ty,)*
+                }
+
+                pub fn discriminator() -> [u8; 8] {
+                    crate::discriminator("global", NAME)
+                }
+
+                /// Remaining accounts, such as transfer-hook extras, are appended to the result.
+                pub fn instruction(
+                    program_id: &Pubkey,
+                    accounts: &Accounts,
+                    args: &Args,
+                ) -> Instruction {
+                    let mut data = discriminator().to_vec();
+                    args.serialize(&mut data)
+                        .expect("serializing into a Vec cannot fail");
+                    Instruction {
+                        program_id: *program_id,
+                        accounts: vec![
+                            $(accounts.$acc.meta(AccountKind::$kind, program_id),)*
+                        ],
+                        data,
+                    }
+                }
+            }
+        )*
+
+        /// Every instruction the client can build, for checking against the IDL.
+        pub const ALL: &[InstructionSpec] = &[
+            $(InstructionSpec {
+                name: $name::NAME,
+                discriminator: $name::discriminator,
+                accounts: $name::ACCOUNTS,
+                args: $name::ARGS,
+            },)*
+        ];
+    };
+}
+
+instructions! {
+    initialize_global_stats {
+        accounts {
+            global_stats: Writable,
+            payer: WritableSigner,
+            system_program: Readonly,
+        }
+        args {}
+    }
+
+    /// `payment_record` is the record of the first charge, index 0.
+    initialize_subscription {
+        accounts {
+            payer: WritableSigner,
+            subscription: Writable,
+            user_token_account: Writable,
+            receiver_token_account: Writable,
+            mint: Readonly,
+            token_program: Readonly,
+            global_stats: Writable,
+            system_program: Readonly,
+            rent: Readonly,
+            payment_record: Writable,
+            plan: Readonly,
+            coupon: OptionalWritable,
+            price_feed: OptionalReadonly,
+        }
+        args {
+            tier_name: String = "string",
+            plan_pda: Pubkey = "pubkey",
+            period_seconds: i64 = "i64",
+            amount: u64 = "u64",
+            auto_renew: bool = "bool",
+            unique_seed: [u8; 8] = r#"{"array":["u8",8]}"#,
+            max_cycles: Option<u64> = r#"{"option":"u64"}"#,
+            end_ts: Option<i64> = r#"{"option":"i64"}"#,
+        }
+    }
+
+    /// Renewal charge; the keeper pays rent for the payment record.
+    execute_payment {
+        accounts {
+            subscription: Writable,
+            plan: Readonly,
+            user_token_account: Writable,
+            receiver_token_account: Writable,
+            mint: Readonly,
+            system_program: Readonly,
+            token_program: Readonly,
+            keeper: WritableSigner,
+            payment_record: Writable,
+            price_feed: OptionalReadonly,
+            sol_delegate: OptionalReadonly,
+        }
+        args {
+            new_amount: u64 = "u64",
+            new_period_seconds: i64 = "i64",
+        }
+    }
+
+    /// A lower cap only applies after the next charge.
+    set_usage_cap {
+        accounts {
+            payer: Signer,
+            subscription: Writable,
+        }
+        args {
+            usage_cap: u64 = "u64",
+        }
+    }
+
+    /// The subscriber's limit on one charge of a USD-priced tier, in the billing mint.
+    set_max_token_amount {
+        accounts {
+            payer: Signer,
+            subscription: Writable,
+        }
+        args {
+            max_token_amount: u64 = "u64",
+        }
+    }
+
+    report_usage {
+        accounts {
+            creator: Signer,
+            plan: Readonly,
+            subscription: Writable,
+        }
+        args {
+            units: u64 = "u64",
+        }
+    }
+
+    refund_payment {
+        accounts {
+            receiver: Signer,
+            plan: Readonly,
+            payment_record: Writable,
+            receiver_token_account: Writable,
+            payer_token_account: Writable,
+            mint: Readonly,
+            token_program: Readonly,
+        }
+        args {
+            amount: u64 = "u64",
+        }
+    }
+
+    create_coupon {
+        accounts {
+            creator: WritableSigner,
+            plan: Readonly,
+            coupon: Writable,
+            system_program: Readonly,
+        }
+        args {
+            code: String = "string",
+            discount: Discount = r#"{"defined":{"name":"Discount"}}"#,
+            duration_cycles: Option<u64> = r#"{"option":"u64"}"#,
+            max_redemptions: Option<u64> = r#"{"option":"u64"}"#,
+            expires_at: Option<i64> = r#"{"option":"i64"}"#,
+            tier_name: Option<String> = r#"{"option":"string"}"#,
+        }
+    }
+
+    deactivate_coupon {
+        accounts {
+            creator: Signer,
+            plan: Readonly,
+            coupon: Writable,
+        }
+        args {}
+    }
+
+    apply_coupon {
+        accounts {
+            payer: Signer,
+            subscription: Writable,
+            coupon: Writable,
+        }
+        args {}
+    }
+
+    pause_subscription {
+        accounts {
+            payer: Signer,
+            subscription: Writable,
+        }
+        args {}
+    }
+
+    /// `authority` is the subscriber, or anyone once the plan's pause limit has passed.
+    resume_subscription {
+        accounts {
+            authority: Signer,
+            subscription: Writable,
+            plan: Readonly,
+        }
+        args {}
+    }
+
+    wrap_sol {
+        accounts {
+            owner: WritableSigner,
+            wsol_account: Writable,
+            native_mint: Readonly,
+            token_program: Readonly,
+            associated_token_program: Readonly,
+            system_program: Readonly,
+            subscription: OptionalReadonly,
+            sol_delegate: Readonly,
+        }
+        args {
+            lamports: u64 = "u64",
+        }
+    }
+
+    unwrap_sol {
+        accounts {
+            owner: WritableSigner,
+            wsol_account: Writable,
+            native_mint: Readonly,
+            token_program: Readonly,
+        }
+        args {}
+    }
+
+    cancel_subscription {
+        accounts {
+            payer: WritableSigner,
+            subscription: Writable,
+            global_stats: Writable,
+        }
+        args {}
+    }
+
+    /// `tiers` is the compressed tier list, as stored on the plan.
+    create_plan {
+        accounts {
+            creator: WritableSigner,
+            plan: Writable,
+            mint: Readonly,
+            receiver: Readonly,
+            system_program: Readonly,
+        }
+        args {
+            name: String = "string",
+            token_symbol: String = "string",
+            token_image: String = "string",
+            tiers: Vec<u8> = "bytes",
+        }
+    }
+
+    update_plan {
+        accounts {
+            plan: Writable,
+            creator: Signer,
+            receiver: Readonly,
+        }
+        args {
+            name: String = "string",
+            tiers: Vec<u8> = "bytes",
+        }
+    }
+
+    add_plan_mint {
+        accounts {
+            plan: Writable,
+            creator: Signer,
+            mint: Readonly,
+        }
+        args {
+            token_symbol: String = "string",
+        }
+    }
+
+    remove_plan_mint {
+        accounts {
+            plan: Writable,
+            creator: Signer,
+        }
+        args {
+            mint: Pubkey = "pubkey",
+        }
+    }
+
+    set_plan_pause_limit {
+        accounts {
+            plan: Writable,
+            creator: Signer,
+        }
+        args {
+            max_pause_seconds: i64 = "i64",
+        }
+    }
+
+    set_plan_price_feed {
+        accounts {
+            plan: Writable,
+            creator: Signer,
+            price_feed: Readonly,
+        }
+        args {
+            mint: Pubkey = "pubkey",
+            feed_id: [u8; 32] = r#"{"array":["u8",32]}"#,
+            max_price_age: i64 = "i64",
+            max_confidence_bps: u16 = "u16",
+        }
+    }
+
+    /// 0 makes the tier flat in that mint again.
+    set_tier_unit_price {
+        accounts {
+            plan: Writable,
+            creator: Signer,
+        }
+        args {
+            tier_name: String = "string",
+            mint: Pubkey = "pubkey",
+            unit_price: u64 = "u64",
+        }
+    }
+
+    /// Dollars with 6 decimals; 0 prices the tier in tokens again.
+    set_tier_usd_price {
+        accounts {
+            plan: Writable,
+            creator: Signer,
+        }
+        args {
+            tier_name: String = "string",
+            usd_amount: u64 = "u64",
+        }
+    }
+
+    /// Grows a subscription or plan made before its newest fields; anyone may pay.
+    migrate_account {
+        accounts {
+            payer: WritableSigner,
+            account: Writable,
+            system_program: Readonly,
+        }
+        args {}
+    }
+
+    cancel_plan {
+        accounts {
+            creator: WritableSigner,
+            plan: Writable,
+        }
+        args {}
+    }
+
+    update_subscription_status {
+        accounts {
+            payer: WritableSigner,
+            subscription: Writable,
+        }
+        args {
+            field: SubscriptionField = r#"{"defined":{"name":"SubscriptionField"}}"#,
+            value: UpdateValue = r#"{"defined":{"name":"UpdateValue"}}"#,
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Typed client for the SolPay program: instruction builders, account and event decoders
+//! and PDA helpers, kept in step with the program's IDL by `tests/idl.rs`.
+
+use anchor_lang::prelude::Pubkey;
+use solana_sdk::hash::hash;
+
+/// Declares Borsh structs that mirror a program type, with the field list the IDL test
+/// compares against. `$idl` is the field's IDL type, in the IDL's JSON notation.
+macro_rules! idl_structs {
+    (
+        $(
+            $(#[$meta:meta])*
+            pub struct $name:ident {
+                $(
+                    $(#[$field_meta:meta])*
+                    pub $field:ident: $ty:ty = $idl:literal,
+                )*
+            }
+        )*
+    ) => {
+        $(
+            $(#[$meta])*
+            #[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone)]
+            pub struct $name {
+                $(
+                    $(#[$field_meta])*
+                    pub $field: $ty,
+                )*
+            }
+
+            impl $crate::IdlType for $name {
+                const NAME: &'static str = stringify!($name);
+                const FIELDS: &'static [$crate::FieldSpec] = &[
+                    $($crate::FieldSpec { name: stringify!($field), idl_type: $idl },)*
+                ];
+            }
+        )*
+
+        /// Every type declared here, for checking against the IDL.
+        pub const ALL: &[$crate::TypeSpec] = &[
+            $($crate::TypeSpec {
+                name: stringify!($name),
+                fields: <$name as $crate::IdlType>::FIELDS,
+            },)*
+        ];
+    };
+}
+
+pub mod accounts;
+pub mod events;
+pub mod instructions;
+pub mod oracle;
+pub mod pda;
+pub mod types;
+
+/// Devnet deployment, as in `Anchor.toml`.
+pub const ID: Pubkey = Pubkey::from_str_const("DUNyVxYZBG7YvU5Nsbci75stbBnjBtBjjibH6FtVPFaL");
+
+/// Anchor's 8-byte tag: the first bytes of `sha256("<namespace>:<name>")`.
+pub fn discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let mut out = [0u8; 8];
+    out.copy_from_slice(&hash(format!("{}:{}", namespace, name).as_bytes()).to_bytes()[..8]);
+    out
+}
+
+/// A named value and its IDL type, e.g. `("amount", "u64")` or
+/// `("max_cycles", r#"{"option":"u64"}"#)`.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldSpec {
+    pub name: &'static str,
+    pub idl_type: &'static str,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TypeSpec {
+    pub name: &'static str,
+    pub fields: &'static [FieldSpec],
+}
+
+/// An enum variant and the IDL types of its tuple fields.
+#[derive(Debug, Clone, Copy)]
+pub struct VariantSpec {
+    pub name: &'static str,
+    pub fields: &'static [&'static str],
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EnumSpec {
+    pub name: &'static str,
+    pub variants: &'static [VariantSpec],
+}
+
+/// A struct the IDL describes field by field.
+pub trait IdlType {
+    const NAME: &'static str;
+    const FIELDS: &'static [FieldSpec];
+}
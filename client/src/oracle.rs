@@ -0,0 +1,72 @@
+//! Off-chain reading of the Pyth price updates USD-priced tiers are charged at, so a
+//! first charge can be quoted the way `execute_payment` converts later ones.
+
+use anchor_lang::prelude::Pubkey;
+
+/// Pyth pull-oracle receiver; price updates are `PriceUpdateV2` accounts it owns.
+pub const PYTH_RECEIVER_ID: Pubkey =
+    Pubkey::from_str_const("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// USD amounts are stored with 6 decimals, like USDC.
+pub const USD_DECIMALS: u8 = 6;
+
+// discriminator (8) + write authority (32), then the verification level
+const VERIFICATION_LEVEL_OFFSET: usize = 40;
+const VERIFICATION_FULL: u8 = 1;
+// feed id (32) + price (8) + conf (8) + exponent (4) + publish time (8)
+const MESSAGE_LEN: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+/// Parses a fully verified `PriceUpdateV2` of `feed_id`. Staleness and confidence are
+/// left to the program, which checks them against the plan when it charges.
+pub fn parse_price_update(data: &[u8], feed_id: &[u8; 32]) -> anyhow::Result<OraclePrice> {
+    if data.len() <= VERIFICATION_LEVEL_OFFSET
+        || data[..8] != crate::discriminator("account", "PriceUpdateV2")
+    {
+        anyhow::bail!("Not a Pyth price update");
+    }
+    if data[VERIFICATION_LEVEL_OFFSET] != VERIFICATION_FULL {
+        anyhow::bail!("Price update is only partially verified");
+    }
+    let message = data
+        .get(VERIFICATION_LEVEL_OFFSET + 1..VERIFICATION_LEVEL_OFFSET + 1 + MESSAGE_LEN)
+        .ok_or_else(|| anyhow::anyhow!("Price update is truncated"))?;
+    if message[..32] != feed_id[..] {
+        anyhow::bail!("Price update is for a different feed");
+    }
+
+    let read_i64 = |at: usize| i64::from_le_bytes(message[at..at + 8].try_into().unwrap());
+    let price = OraclePrice {
+        price: read_i64(32),
+        conf: u64::from_le_bytes(message[40..48].try_into().unwrap()),
+        exponent: i32::from_le_bytes(message[48..52].try_into().unwrap()),
+        publish_time: read_i64(52),
+    };
+    if price.price <= 0 {
+        anyhow::bail!("Price update has no positive price");
+    }
+    Ok(price)
+}
+
+/// Token base units worth `usd_amount`, rounded up as the program rounds them.
+pub fn usd_to_tokens(usd_amount: u64, price: &OraclePrice, decimals: u8) -> Option<u64> {
+    let pow10 = |exp: u32| 10u128.checked_pow(exp);
+
+    // tokens = usd / 10^6 / (price * 10^exponent) * 10^decimals
+    let mut numerator = (usd_amount as u128).checked_mul(pow10(decimals as u32)?)?;
+    let mut denominator = (price.price as u128).checked_mul(pow10(USD_DECIMALS as u32)?)?;
+    if price.exponent < 0 {
+        numerator = numerator.checked_mul(pow10(price.exponent.unsigned_abs())?)?;
+    } else {
+        denominator = denominator.checked_mul(pow10(price.exponent as u32)?)?;
+    }
+
+    u64::try_from(numerator.div_ceil(denominator)).ok()
+}
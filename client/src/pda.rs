@@ -0,0 +1,50 @@
+//! Program-derived addresses, with the seeds the program's account constraints use.
+
+use anchor_lang::prelude::Pubkey;
+
+// Plans use the literal `b"plan"`, not the program's unused `PLAN_SEED`
+pub const PLAN_SEED: &[u8] = b"plan";
+pub const SUBSCRIPTION_SEED: &[u8] = b"subscription";
+pub const GLOBAL_STATS_SEED: &[u8] = b"global_stats";
+pub const PAYMENT_SEED: &[u8] = b"payment";
+pub const COUPON_SEED: &[u8] = b"coupon";
+pub const SOL_DELEGATE_SEED: &[u8] = b"sol_delegate";
+
+/// Renewals a wSOL approval covers for subscriptions without a cycle limit, as in the program.
+pub const SOL_APPROVAL_CYCLES: u64 = 12;
+
+/// One plan per creator.
+pub fn plan(program_id: &Pubkey, creator: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[PLAN_SEED, creator.as_ref()], program_id).0
+}
+
+pub fn subscription(program_id: &Pubkey, payer: &Pubkey, unique_seed: &[u8; 8]) -> Pubkey {
+    Pubkey::find_program_address(
+        &[SUBSCRIPTION_SEED, payer.as_ref(), unique_seed],
+        program_id,
+    )
+    .0
+}
+
+pub fn global_stats(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[GLOBAL_STATS_SEED], program_id).0
+}
+
+/// Each charge gets a record at `[b"payment", subscription, index]`.
+pub fn payment_record(program_id: &Pubkey, subscription: &Pubkey, index: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[PAYMENT_SEED, subscription.as_ref(), &index.to_le_bytes()],
+        program_id,
+    )
+    .0
+}
+
+/// Delegate that all SOL subscriptions renew through, since an owner's wSOL account is shared.
+pub fn sol_delegate(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SOL_DELEGATE_SEED], program_id).0
+}
+
+/// Coupons live at `[b"coupon", plan, code]`.
+pub fn coupon(program_id: &Pubkey, plan: &Pubkey, code: &str) -> Pubkey {
+    Pubkey::find_program_address(&[COUPON_SEED, plan.as_ref(), code.as_bytes()], program_id).0
+}
@@ -0,0 +1,109 @@
+//! Argument and field types shared by instructions, accounts and events.
+
+use crate::{EnumSpec, VariantSpec};
+use anchor_lang::prelude::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq)]
+pub enum SubscriptionField {
+    AutoRenew, // 0
+    Active,    // 1
+    Tier,      // 2
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq)]
+pub enum UpdateValue {
+    Bool(bool),     // 0
+    U64(u64),       // 1
+    String(String), // 2
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Discount {
+    Percent(u16), // basis points
+    Fixed(u64),
+}
+
+impl Discount {
+    pub fn amount_off(&self, base_amount: u64) -> u64 {
+        match *self {
+            Discount::Percent(bps) => (base_amount as u128 * bps as u128 / 10_000) as u64,
+            Discount::Fixed(amount) => amount.min(base_amount),
+        }
+    }
+}
+
+idl_structs! {
+    pub struct PlanMint {
+        pub mint: Pubkey = "pubkey",
+        pub token_symbol: String = "string",
+    }
+
+    pub struct UnitPrice {
+        pub tier_name: String = "string",
+        pub mint: Pubkey = "pubkey",
+        pub unit_price: u64 = "u64",
+    }
+
+    pub struct UsdPrice {
+        pub tier_name: String = "string",
+        pub usd_amount: u64 = "u64",
+    }
+
+    pub struct PriceFeed {
+        pub mint: Pubkey = "pubkey",
+        /// `PriceUpdateV2` account
+        pub price_feed: Pubkey = "pubkey",
+        pub feed_id: [u8; 32] = r#"{"array":["u8",32]}"#,
+    }
+}
+
+/// Borsh encodes variants by position, so the order here must match the program.
+pub const ENUMS: &[EnumSpec] = &[
+    EnumSpec {
+        name: "SubscriptionField",
+        variants: &[
+            VariantSpec {
+                name: "AutoRenew",
+                fields: &[],
+            },
+            VariantSpec {
+                name: "Active",
+                fields: &[],
+            },
+            VariantSpec {
+                name: "Tier",
+                fields: &[],
+            },
+        ],
+    },
+    EnumSpec {
+        name: "UpdateValue",
+        variants: &[
+            VariantSpec {
+                name: "Bool",
+                fields: &["bool"],
+            },
+            VariantSpec {
+                name: "U64",
+                fields: &["u64"],
+            },
+            VariantSpec {
+                name: "String",
+                fields: &["string"],
+            },
+        ],
+    },
+    EnumSpec {
+        name: "Discount",
+        variants: &[
+            VariantSpec {
+                name: "Percent",
+                fields: &["u16"],
+            },
+            VariantSpec {
+                name: "Fixed",
+                fields: &["u64"],
+            },
+        ],
+    },
+];
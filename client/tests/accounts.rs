@@ -0,0 +1,58 @@
+use anchor_lang::prelude::*;
+use solpay_client::accounts::{Migratable, Plan, ProgramAccount, Subscription};
+
+/// A subscription as the first deployment wrote it, zero-padded to its old allocation.
+fn legacy_subscription(payer: Pubkey, plan: Pubkey) -> Vec<u8> {
+    let mut data = Subscription::discriminator().to_vec();
+    (
+        payer,
+        plan,
+        "Pro".to_string(),
+        1_700_000_000i64,
+        true,
+        true,
+        254u8,
+        [7u8; 8],
+        5_000_000u64,
+        2_592_000i64,
+    )
+        .serialize(&mut data)
+        .unwrap();
+    data.resize(8 + 32 + 32 + 36 + 8 + 1 + 1 + 1 + 8 + 8 + 8, 0);
+    data
+}
+
+#[test]
+fn legacy_subscriptions_decode_with_unset_fields() {
+    let (payer, plan) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let data = legacy_subscription(payer, plan);
+    assert!(Subscription::needs_migration(&data));
+
+    let subscription = Subscription::decode(&data).unwrap();
+    assert_eq!(subscription.payer, payer);
+    assert_eq!(subscription.plan_pda, plan);
+    assert_eq!(subscription.tier_name, "Pro");
+    assert_eq!(subscription.amount, 5_000_000);
+    assert_eq!(subscription.payment_count, 0);
+    assert_eq!(subscription.paused_at, None);
+    assert_eq!(subscription.coupon, None);
+    assert_eq!(subscription.mint, Pubkey::default());
+}
+
+#[test]
+fn migrated_subscriptions_decode_the_same() {
+    let (payer, plan) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut data = legacy_subscription(payer, plan);
+    data.resize(Subscription::SPACE, 0);
+    assert!(!Subscription::needs_migration(&data));
+
+    let subscription = Subscription::decode(&data).unwrap();
+    assert_eq!(subscription.payer, payer);
+    assert_eq!(subscription.usage_cap, 0);
+}
+
+#[test]
+fn decode_checks_the_discriminator() {
+    let data = legacy_subscription(Pubkey::new_unique(), Pubkey::new_unique());
+    assert!(Plan::decode(&data).is_err());
+}
@@ -0,0 +1,241 @@
+//! Fails when the client and the program's IDL disagree. Locally that is the committed
+//! `client/idl/recurring_payments.json`. CI builds the IDL from the program with
+//! `anchor idl build`, fails if the committed file differs from it, and points
+//! `SOLPAY_IDL` at the built one, so these checks never only compare the client with
+//! a file written from it.
+
+use serde_json::Value;
+use solpay_client::{FieldSpec, TypeSpec, accounts, discriminator, events, instructions, types};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+fn load_idl() -> Value {
+    let path = match std::env::var("SOLPAY_IDL") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("idl/recurring_payments.json"),
+    };
+    let text = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("cannot read the IDL at {}: {}", path.display(), e));
+    serde_json::from_str(&text).expect("IDL is not valid JSON")
+}
+
+/// Client specs write primitives bare and compound types as JSON.
+fn idl_type(spec: &str) -> Value {
+    if spec.starts_with('{') {
+        serde_json::from_str(spec).unwrap_or_else(|e| panic!("bad type spec {}: {}", spec, e))
+    } else {
+        Value::String(spec.to_string())
+    }
+}
+
+fn entries<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value[key].as_array().map_or(&[][..], Vec::as_slice)
+}
+
+fn find<'a>(idl: &'a Value, section: &str, name: &str) -> Option<&'a Value> {
+    entries(idl, section).iter().find(|e| e["name"] == name)
+}
+
+fn names<'a>(idl: &'a Value, section: &str) -> BTreeSet<&'a str> {
+    entries(idl, section)
+        .iter()
+        .filter_map(|e| e["name"].as_str())
+        .collect()
+}
+
+fn idl_discriminator(entry: &Value) -> Vec<u8> {
+    entry["discriminator"]
+        .as_array()
+        .map(|bytes| {
+            bytes
+                .iter()
+                .filter_map(|b| b.as_u64())
+                .map(|b| b as u8)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn compare_fields(
+    errors: &mut Vec<String>,
+    context: &str,
+    idl_type_def: &Value,
+    fields: &[FieldSpec],
+) {
+    let expected: Vec<(&str, Value)> = fields
+        .iter()
+        .map(|f| (f.name, idl_type(f.idl_type)))
+        .collect();
+    let actual: Vec<(&str, Value)> = entries(idl_type_def, "fields")
+        .iter()
+        .map(|f| (f["name"].as_str().unwrap_or_default(), f["type"].clone()))
+        .collect();
+    if expected != actual {
+        errors.push(format!(
+            "{}: client has {:?}, IDL has {:?}",
+            context, expected, actual
+        ));
+    }
+}
+
+/// Accounts and events: same set of names, matching discriminators, matching layouts.
+fn compare_structs(
+    errors: &mut Vec<String>,
+    idl: &Value,
+    section: &str,
+    namespace: &str,
+    specs: &[TypeSpec],
+) {
+    let client: BTreeSet<&str> = specs.iter().map(|s| s.name).collect();
+    if client != names(idl, section) {
+        errors.push(format!(
+            "{}: client has {:?}, IDL has {:?}",
+            section,
+            client,
+            names(idl, section)
+        ));
+    }
+
+    for spec in specs {
+        let tag = find(idl, section, spec.name).map(idl_discriminator);
+        if tag.is_some_and(|tag| tag != discriminator(namespace, spec.name)) {
+            errors.push(format!("{} {}: discriminator differs", section, spec.name));
+        }
+        match find(idl, "types", spec.name) {
+            Some(ty) => compare_fields(errors, spec.name, &ty["type"], spec.fields),
+            None => errors.push(format!("{}: no type definition in the IDL", spec.name)),
+        }
+    }
+}
+
+fn assert_no_errors(errors: Vec<String>) {
+    assert!(
+        errors.is_empty(),
+        "client and IDL diverge:\n{}",
+        errors.join("\n")
+    );
+}
+
+#[test]
+fn instructions_match_idl() {
+    let idl = load_idl();
+    let mut errors = Vec::new();
+
+    let client: BTreeSet<&str> = instructions::ALL.iter().map(|ix| ix.name).collect();
+    if client != names(&idl, "instructions") {
+        errors.push(format!(
+            "instructions: client has {:?}, IDL has {:?}",
+            client,
+            names(&idl, "instructions")
+        ));
+    }
+
+    for ix in instructions::ALL {
+        let Some(entry) = find(&idl, "instructions", ix.name) else {
+            continue;
+        };
+        if idl_discriminator(entry) != (ix.discriminator)() {
+            errors.push(format!("{}: discriminator differs", ix.name));
+        }
+
+        let expected: Vec<(&str, bool, bool, bool)> = ix
+            .accounts
+            .iter()
+            .map(|a| (a.name, a.writable, a.signer, a.optional))
+            .collect();
+        let flag = |account: &Value, key: &str| account[key].as_bool().unwrap_or(false);
+        let actual: Vec<(&str, bool, bool, bool)> = entries(entry, "accounts")
+            .iter()
+            .map(|a| {
+                (
+                    a["name"].as_str().unwrap_or_default(),
+                    flag(a, "writable"),
+                    flag(a, "signer"),
+                    flag(a, "optional"),
+                )
+            })
+            .collect();
+        if expected != actual {
+            errors.push(format!(
+                "{} accounts: client has {:?}, IDL has {:?}",
+                ix.name, expected, actual
+            ));
+        }
+
+        compare_fields(&mut errors, &format!("{} args", ix.name), entry, ix.args);
+    }
+
+    assert_no_errors(errors);
+}
+
+#[test]
+fn accounts_match_idl() {
+    let idl = load_idl();
+    let mut errors = Vec::new();
+    compare_structs(&mut errors, &idl, "accounts", "account", accounts::ALL);
+    assert_no_errors(errors);
+}
+
+#[test]
+fn events_match_idl() {
+    let idl = load_idl();
+    let mut errors = Vec::new();
+    compare_structs(&mut errors, &idl, "events", "event", events::ALL);
+    assert_no_errors(errors);
+}
+
+#[test]
+fn defined_types_match_idl() {
+    let idl = load_idl();
+    let mut errors = Vec::new();
+
+    for spec in types::ALL {
+        match find(&idl, "types", spec.name) {
+            Some(ty) => compare_fields(&mut errors, spec.name, &ty["type"], spec.fields),
+            None => errors.push(format!("{}: no type definition in the IDL", spec.name)),
+        }
+    }
+
+    for spec in types::ENUMS {
+        let Some(ty) = find(&idl, "types", spec.name) else {
+            errors.push(format!("{}: no type definition in the IDL", spec.name));
+            continue;
+        };
+        let expected: Vec<(&str, Vec<Value>)> = spec
+            .variants
+            .iter()
+            .map(|v| (v.name, v.fields.iter().copied().map(idl_type).collect()))
+            .collect();
+        let actual: Vec<(&str, Vec<Value>)> = entries(&ty["type"], "variants")
+            .iter()
+            .map(|v| {
+                (
+                    v["name"].as_str().unwrap_or_default(),
+                    entries(v, "fields").to_vec(),
+                )
+            })
+            .collect();
+        if expected != actual {
+            errors.push(format!(
+                "{} variants: client has {:?}, IDL has {:?}",
+                spec.name, expected, actual
+            ));
+        }
+    }
+
+    // A type the client does not know about means an account, event or argument changed
+    let known: BTreeSet<&str> = accounts::ALL
+        .iter()
+        .chain(events::ALL)
+        .chain(types::ALL)
+        .map(|s| s.name)
+        .chain(types::ENUMS.iter().map(|e| e.name))
+        .collect();
+    for name in names(&idl, "types") {
+        if !known.contains(name) {
+            errors.push(format!("{}: IDL type has no client counterpart", name));
+        }
+    }
+
+    assert_no_errors(errors);
+}
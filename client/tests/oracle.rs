@@ -0,0 +1,40 @@
+use solpay_client::oracle::{OraclePrice, parse_price_update, usd_to_tokens};
+
+const FEED_ID: [u8; 32] = [1u8; 32];
+
+fn price_update(price: i64, verification: u8) -> Vec<u8> {
+    let mut data = solpay_client::discriminator("account", "PriceUpdateV2").to_vec();
+    data.extend_from_slice(&[7u8; 32]); // write authority
+    data.push(verification);
+    data.extend_from_slice(&FEED_ID);
+    data.extend_from_slice(&price.to_le_bytes());
+    data.extend_from_slice(&5_000_000u64.to_le_bytes());
+    data.extend_from_slice(&(-8i32).to_le_bytes());
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+    data.extend_from_slice(&[0u8; 32]); // prev publish time, ema price, ema conf, posted slot
+    data
+}
+
+#[test]
+fn parses_verified_price_updates() {
+    let price = parse_price_update(&price_update(15_000_000_000, 1), &FEED_ID).unwrap();
+    assert_eq!(price.price, 15_000_000_000);
+    assert_eq!(price.exponent, -8);
+    assert_eq!(price.publish_time, 1_700_000_000);
+
+    assert!(parse_price_update(&price_update(15_000_000_000, 0), &FEED_ID).is_err());
+    assert!(parse_price_update(&price_update(0, 1), &FEED_ID).is_err());
+    assert!(parse_price_update(&price_update(15_000_000_000, 1), &[2u8; 32]).is_err());
+}
+
+#[test]
+fn quotes_like_the_program() {
+    let price = OraclePrice {
+        price: 15_000_000_000,
+        conf: 0,
+        exponent: -8,
+        publish_time: 0,
+    };
+    // $10 of a 9-decimal token at $150, rounded up in base units
+    assert_eq!(usd_to_tokens(10_000_000, &price, 9), Some(66_666_667));
+}
@@ -2,11 +2,12 @@
 import Header from '@/app/components/ui/layout/Header';
 import Loader from '@/app/components/ui/extras/Loader';
 import { useProgram } from '@/app/hooks/useProgram';
-import { Transaction } from '@/app/types';
-import { useQuery } from '@tanstack/react-query';
+import { TransactionPage } from '@/app/types';
+import { useInfiniteQuery } from '@tanstack/react-query';
 import axios from 'axios';
 import { ArrowUpRight, CircleCheck, RotateCw } from 'lucide-react';
 import Error from '@/app/components/ui/extras/Error';
+import LoadMore from '@/app/components/ui/extras/LoadMore';
 import { formatDate } from '@/app/utils/duration';
 import { useDbActions } from '@/app/hooks/useDbActions';
 import { TABLE_HEADERS } from '@/app/utils/headers';
@@ -18,22 +19,29 @@ const page = () => {
     const { deleteTransaction, renewSubscription } = useDbActions()
 
     const {
-        data: transactions,
+        data,
         isLoading,
         isFetching,
+        isFetchingNextPage,
+        hasNextPage,
+        fetchNextPage,
         isError: isQueryError,
         refetch,
-    } = useQuery<Transaction[]>({
+    } = useInfiniteQuery({
         queryKey: ["transactions"],
-        queryFn: async () => {
-            const res = await axios.get<Transaction[]>(
-                `http://127.0.0.1:3001/api/transactions/user/${publicKey}`
+        queryFn: async ({ pageParam }) => {
+            const res = await axios.get<TransactionPage>(
+                `http://127.0.0.1:3001/api/transactions/user/${publicKey}`,
+                { params: { cursor: pageParam } }
             );
             return res.data;
         },
+        initialPageParam: undefined as string | undefined,
+        getNextPageParam: (lastPage) => lastPage.nextCursor ?? undefined,
         enabled: !!publicKey, // Only fetch if pubkey exists
         staleTime: 1000 * 60 * 5, // Cache for 5 minutes
     });
+    const transactions = data?.pages.flatMap((page) => page.items);
 
     const { searchQuery, setSearchQuery, filteredData } = useSearch(transactions, ['plan', 'tier']);
 
@@ -41,7 +49,7 @@ const page = () => {
         <div className='space-y-4 font-mono'>
             <Header title="History" refetch={refetch} isFetching={isFetching} setSearchQuery={setSearchQuery} />
             <div className=''>
-                {isLoading || isFetching ? (
+                {isLoading || (isFetching && !isFetchingNextPage) ? (
                     <Loader />
                 ) :
                     isQueryError ? <Error refetch={refetch} /> :
@@ -123,6 +131,7 @@ const page = () => {
                                         </div>
                                     </div>
                                 </div>
+                                <LoadMore hasNextPage={hasNextPage} isFetchingNextPage={isFetchingNextPage} fetchNextPage={fetchNextPage} />
                             </>
                             :
                             !searchQuery && <p className='text-center col-span-4 text-gray-400 text-2xl'>No transactions found.</p>
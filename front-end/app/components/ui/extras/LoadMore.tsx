@@ -0,0 +1,24 @@
+import Loader from './Loader';
+
+interface LoadMoreProps {
+    hasNextPage: boolean;
+    isFetchingNextPage: boolean;
+    fetchNextPage: () => void;
+}
+
+const LoadMore = ({ hasNextPage, isFetchingNextPage, fetchNextPage }: LoadMoreProps) => {
+    if (!hasNextPage) return null;
+    return (
+        <div className='flex justify-center pt-4'>
+            <button
+                onClick={() => fetchNextPage()}
+                disabled={isFetchingNextPage}
+                className='px-5 py-2 rounded-xl bg-white/5 text-blue-400 hover:text-blue-300 font-semibold transition cursor-pointer flex gap-2 items-center disabled:cursor-wait'
+            >
+                {isFetchingNextPage ? <Loader /> : "Load more"}
+            </button>
+        </div>
+    )
+}
+
+export default LoadMore
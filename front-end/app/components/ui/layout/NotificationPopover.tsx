@@ -133,7 +133,7 @@ import axios from 'axios';
 import { useProgram } from '@/app/hooks/useProgram';
 import Loader from '../extras/Loader';
 import { formatDistanceToNow } from 'date-fns';
-import { Notification, NotificationType } from '@/app/types';
+import { Notification, NotificationPage, NotificationType } from '@/app/types';
 import { useMutations } from '@/app/hooks/useMutations';
 
 export default function NotificationPopover() {
@@ -149,10 +149,10 @@ export default function NotificationPopover() {
     } = useQuery<Notification[]>({
         queryKey: ["notifications", publicKey?.toString()],
         queryFn: async () => {
-            const res = await axios.get<Notification[]>(
+            const res = await axios.get<NotificationPage>(
                 `http://127.0.0.1:3001/api/notifications/user/${publicKey}`
             );
-            return res.data;
+            return res.data.items;
         },
         enabled: !!publicKey,
         staleTime: 1000 * 60, // 1 min cache (tweak if needed)
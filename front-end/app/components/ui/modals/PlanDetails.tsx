@@ -96,6 +96,18 @@ const PlanDetails = ({ plan, planPDA, open, setOpen, onSubscribe, type, subscrip
     // const [amount, setAmount] = useState(safeToNumber(processedPlan?.tiers[0].amount))
     const [selectedTier, setSelectedTier] = useState<Tier | null>(processedPlan?.tiers[0]!);
     const [autoRenew, setAutoRenew] = useState<boolean>(false)
+    const [selectedMint, setSelectedMint] = useState<string | null>(null)
+    // The primary mint plus any others the plan accepts
+    const mintOptions = useMemo(() => processedPlan ? [
+        { mint: processedPlan.mint.toString(), tokenSymbol: processedPlan.tokenSymbol },
+        ...(processedPlan.acceptedMints ?? []).map(m => ({ mint: m.mint.toString(), tokenSymbol: m.tokenSymbol })),
+    ] : [], [processedPlan]);
+    const payMint = mintOptions.find(m => m.mint === selectedMint) ?? mintOptions[0];
+    const payFeed = processedPlan?.priceFeeds?.find(f => f.mint.toString() === payMint?.mint);
+    const priceIn = (tier: Tier, mint: string | undefined) =>
+        !mint || mint === processedPlan?.mint.toString()
+            ? tier.amount
+            : tier.prices?.find(p => p.mint === mint)?.amount;
     const handleTierSelect = (tier: Tier) => {
         setSelectedTier(tier);
         // setDuration(tier.periodSeconds)
@@ -256,9 +268,9 @@ const PlanDetails = ({ plan, planPDA, open, setOpen, onSubscribe, type, subscrip
                                                                         <div className=" flex gap-1 items-end ">
                                                                             {/* Displaying stringified amount */}
                                                                             <span className='text-3xl font-bold text-white'>
-                                                                                {tier.amount}
+                                                                                {String(priceIn(tier, payMint?.mint) ?? "—")}
                                                                             </span>
-                                                                            <span className=" font-medium text-gray-400">{processedPlan.tokenSymbol}</span>
+                                                                            <span className=" font-medium text-gray-400">{payMint?.tokenSymbol ?? processedPlan.tokenSymbol}</span>
                                                                         </div>
                                                                         <div className="text-sm font-medium text-gray-300 ">
                                                                             Every {formatDuration(tier.periodSeconds)}
@@ -275,11 +287,25 @@ const PlanDetails = ({ plan, planPDA, open, setOpen, onSubscribe, type, subscrip
                                                 )}
                                     </div>
                                 </div>
+                                {mintOptions.length > 1 && type == "new" && (
+                                    <div className="flex items-center gap-3 justify-center">
+                                        <span className="text-gray-400">Pay with</span>
+                                        <select
+                                            value={payMint?.mint}
+                                            onChange={(e) => setSelectedMint(e.target.value)}
+                                            className="rounded-xl bg-white/5 border border-white/6 px-3 py-2 text-white"
+                                        >
+                                            {mintOptions.map(m => (
+                                                <option key={m.mint} value={m.mint}>{m.tokenSymbol}</option>
+                                            ))}
+                                        </select>
+                                    </div>
+                                )}
                                 <div className='h-0.5 w-full bg-white/5' />
                                 <div className="flex flex-col sm:flex-row items-center gap-4 justify-center">
                                     <button
-                                        onClick={() => type == "new" ? createSubscription.mutateAsync({ tier: selectedTier!.tierName, planPDA, payerKey: publicKey!, periodSeconds: Number(selectedTier?.periodSeconds), amount: Number(selectedTier?.amount), autoRenew, receiver: new PublicKey(processedPlan.receiver), mint: new PublicKey(processedPlan.mint), planName: processedPlan.name, creator: processedPlan.creator }).then(() => closeModal()) : updateSubscription.mutate({ subscriptionPDA: subscriptionPDA!, field: "tier", value: selectedTier?.tierName!, payerKey: subscriptionPayer!, mint: new PublicKey(processedPlan.mint) })}
-                                        disabled={!selectedTier || createSubscription.isPending || updateSubscription.isPending}
+                                        onClick={() => type == "new" ? createSubscription.mutateAsync({ tier: selectedTier!.tierName, planPDA, payerKey: publicKey!, periodSeconds: Number(selectedTier?.periodSeconds), amount: Number(priceIn(selectedTier!, payMint?.mint)), autoRenew, receiver: new PublicKey(processedPlan.receiver), mint: new PublicKey(payMint?.mint ?? processedPlan.mint), planName: processedPlan.name, creator: processedPlan.creator, maxCycles: selectedTier?.maxCycles, endTs: selectedTier?.endTs, metering: selectedTier?.unitPrice ? { usageCap: selectedTier.usageCap ?? 0 } : undefined, usdPricing: selectedTier?.usdPrice && payFeed ? { priceFeed: new PublicKey(payFeed.priceFeed) } : undefined }).then(() => closeModal()) : updateSubscription.mutate({ subscriptionPDA: subscriptionPDA!, field: "tier", value: selectedTier?.tierName!, payerKey: subscriptionPayer!, mint: new PublicKey(processedPlan.mint) })}
+                                        disabled={!selectedTier || (type == "new" && priceIn(selectedTier, payMint?.mint) === undefined) || createSubscription.isPending || updateSubscription.isPending}
                                         className="w-full sm:w-auto flex items-center justify-center gap-2 p-3 rounded-lg font-semibold text-lg transition-all bg-blue-400/70  text-white cursor-pointer disabled:bg-white/5 disabled:text-gray-600 disabled:cursor-not-allowed disabled:border disabled:border-gray-700"
                                     >
                                         {
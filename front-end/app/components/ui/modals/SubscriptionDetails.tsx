@@ -1,15 +1,16 @@
 import { useMutations } from '@/app/hooks/useMutations';
 import { useProgram } from '@/app/hooks/useProgram';
-import { Plan, StatCardProps, Subscription, Tier, Transaction } from '@/app/types';
+import { Plan, StatCardProps, Subscription, Tier, Transaction, TransactionPage } from '@/app/types';
 import { formatDate, formatDuration, timeRemainingUntil } from '@/app/utils/duration';
 import { Dialog, DialogPanel, Transition, TransitionChild } from '@headlessui/react';
 import { ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddressSync, TOKEN_2022_PROGRAM_ID } from '@solana/spl-token';
 import { PublicKey } from '@solana/web3.js';
-import { useQuery } from '@tanstack/react-query';
+import { useInfiniteQuery } from '@tanstack/react-query';
 import axios from 'axios';
 import { ArrowUpRight, Check, CircleAlert, CircleCheck, CircleEllipsis, CircleX, Coins, Download, Repeat2, RotateCw, Timer, Trash, User, UserPlus, UserStar, X } from 'lucide-react';
-import React, { Dispatch, SetStateAction, useEffect, useState } from 'react';
+import React, { Dispatch, SetStateAction, useEffect, useMemo, useState } from 'react';
 import Loader from '../extras/Loader';
+import LoadMore from '../extras/LoadMore';
 import TableHeaders from '../layout/TableHeaders';
 import { TABLE_HEADERS } from '@/app/utils/headers';
 import { truncateAddress } from '@/app/utils/token';
@@ -32,43 +33,55 @@ const subscriptionDetails = ({ isOpen, subscription, setPlan, setPlanDetailsOpen
     const { publicKey } = useProgram()
 
     const {
-        data: transactions,
+        data,
         isLoading: areTransactionsLoading,
         isError: isQueryError,
-    } = useQuery<Transaction[]>({
+        isFetchingNextPage,
+        hasNextPage,
+        fetchNextPage,
+    } = useInfiniteQuery({
         queryKey: ["CompanyTransactions"],
-        queryFn: async () => {
-            const res = await axios.get<Transaction[]>(
-                `http://127.0.0.1:3001/api/transactions/${subscription.payer}/${subscription?.publicKey}`
+        queryFn: async ({ pageParam }) => {
+            const res = await axios.get<TransactionPage>(
+                `http://127.0.0.1:3001/api/transactions/${subscription.payer}/${subscription?.publicKey}`,
+                { params: { cursor: pageParam } }
             );
-            let transactions = res.data;
-            if (transactions.length < 6) {
-                const count = 5 - transactions.length;
-                const lastDate = new Date(transactions[transactions.length - 1].createdAt);
-                const upcomingTransactions = Array.from({ length: count }, (_, i) => {
-                    const nextPaymentDate = new Date(
-                        lastDate.getTime() + Number(currentTier?.periodSeconds) * (i + 1) * 1000
-                    );
-                    return {
-                        id: -(i + 1),
-                        userPubkey: subscription.payer.toString(),
-                        plan: transactions[0]?.plan,
-                        tier: transactions[0]?.tier,
-                        amount: transactions[0]?.amount || 0,
-                        status: "pending",
-                        subscriptionPda: subscription.publicKey.toString(),
-                        txSignature: null, // Changed undefined to null (common mismatch source)
-                        createdAt: nextPaymentDate.toISOString(),
-                    } as unknown as Transaction;
-                });
-                transactions = [...transactions, ...upcomingTransactions];
-            }
-            return transactions;
+            return res.data;
         },
+        initialPageParam: undefined as string | undefined,
+        getNextPageParam: (lastPage) => lastPage.nextCursor ?? undefined,
         enabled: !!publicKey && !!subscription,
         staleTime: 1000 * 3000,
     });
 
+    // Upcoming payments fill out a short history once every page is loaded
+    const transactions = useMemo(() => {
+        if (!data) return undefined;
+        let transactions: Transaction[] = data.pages.flatMap((page) => page.items);
+        if (!hasNextPage && transactions.length < 6) {
+            const count = 5 - transactions.length;
+            const lastDate = new Date(transactions[transactions.length - 1].createdAt);
+            const upcomingTransactions = Array.from({ length: count }, (_, i) => {
+                const nextPaymentDate = new Date(
+                    lastDate.getTime() + Number(currentTier?.periodSeconds) * (i + 1) * 1000
+                );
+                return {
+                    id: -(i + 1),
+                    userPubkey: subscription.payer.toString(),
+                    plan: transactions[0]?.plan,
+                    tier: transactions[0]?.tier,
+                    amount: transactions[0]?.amount || 0,
+                    status: "pending",
+                    subscriptionPda: subscription.publicKey.toString(),
+                    txSignature: null, // Changed undefined to null (common mismatch source)
+                    createdAt: nextPaymentDate.toISOString(),
+                } as unknown as Transaction;
+            });
+            transactions = [...transactions, ...upcomingTransactions];
+        }
+        return transactions;
+    }, [data, hasNextPage, currentTier, subscription]);
+
     useEffect(() => {
         const tier = subscription?.planMetadata?.tiers.find((tier: Tier) => tier.tierName == subscription?.tierName)
         setCurrentTier(tier)
@@ -354,6 +367,7 @@ const subscriptionDetails = ({ isOpen, subscription, setPlan, setPlanDetailsOpen
                                                     })}
                                                 </div>
                                             </div>
+                                            <LoadMore hasNextPage={hasNextPage} isFetchingNextPage={isFetchingNextPage} fetchNextPage={fetchNextPage} />
                                         </div>
                                     </div>
                                     <div className='h-0.5 w-full bg-white/5 flex' />
@@ -1,10 +1,13 @@
 import { useMutation, useQueryClient } from "@tanstack/react-query";
-import { Notification, Transaction, ScheduleSubscriptionRequest, ScheduleSubscriptionResponse, SubscriptionAccount, UpdateParams, UpdateSubscriptionParams } from "../types";
+import { Notification, Transaction, TransactionPage, ScheduleSubscriptionRequest, ScheduleSubscriptionResponse, SubscriptionAccount, UpdateParams, UpdateSubscriptionParams } from "../types";
 import axios from "axios";
+import { useWallet } from "@solana/wallet-adapter-react";
+import { signWalletAuth } from "../utils/walletAuth";
 
 export const useDbActions = () => {
     const API_BASE = "http://127.0.0.1:3001"
     const queryClient = useQueryClient();
+    const { publicKey, signMessage } = useWallet();
 
     const useGetUserTransactions = () => {
         return useMutation({
@@ -13,10 +16,10 @@ export const useDbActions = () => {
             }: {
                 userPubkey: string;
             }) => {
-                const res = await axios.get<Transaction[]>(
-                    `${API_BASE}/transactions/${userPubkey}`
+                const res = await axios.get<TransactionPage>(
+                    `${API_BASE}/api/transactions/user/${userPubkey}`
                 );
-                return res.data;
+                return res.data.items;
             },
 
             onSuccess: (data) => {
@@ -33,8 +36,13 @@ export const useDbActions = () => {
         mutationFn: async ({ notificationId }: {
             notificationId: string; // or bigint if IDs are large
         }) => {
+            if (!publicKey) {
+                throw new Error("Wallet not connected");
+            }
+            const auth = await signWalletAuth(signMessage, "notifications", publicKey);
             const response = await axios.delete(
-                `${API_BASE}/api/notifications/${notificationId}`
+                `${API_BASE}/api/notifications/${notificationId}`,
+                { params: { userPubkey: publicKey.toString(), ...auth } }
             );
 
             if (response.status !== 200) {
@@ -4,9 +4,12 @@ import { PublicKey } from "@solana/web3.js";
 import { Plan, Tier, UpdateField } from "../types";
 import { useDbActions } from "./useDbActions";
 import axios from "axios";
+import { useWallet } from "@solana/wallet-adapter-react";
+import { signWalletAuth } from "../utils/walletAuth";
 
 export const useMutations = () => {
     const programActions = useProgramActions();
+    const { signMessage } = useWallet();
     const { createSubscriptionDb, deleteSubscriptionDb, updateSubscriptionDb } = useDbActions()
     const queryClient = useQueryClient();
 
@@ -87,7 +90,12 @@ export const useMutations = () => {
             autoRenew,
             receiver,
             mint,
-            creator
+            creator,
+            maxCycles,
+            endTs,
+            metering,
+            couponCode,
+            usdPricing
         }: {
             tier: string;
             planPDA: PublicKey;
@@ -98,7 +106,12 @@ export const useMutations = () => {
             autoRenew?: boolean;
             receiver: PublicKey,
             mint: PublicKey,
-            creator: string
+            creator: string,
+            maxCycles?: number | string,
+            endTs?: number | string,
+            metering?: { usageCap: number | string },
+            couponCode?: string,
+            usdPricing?: { priceFeed: PublicKey; slippageBps?: number }
 
         }) => {
             const subscription = await programActions.initializeSubscription(
@@ -110,6 +123,11 @@ export const useMutations = () => {
                 autoRenew,
                 receiver,
                 mint,
+                maxCycles,
+                endTs,
+                metering,
+                couponCode,
+                usdPricing,
             );
             if (!subscription) {
                 throw new Error("Failed to create subscription");
@@ -134,7 +152,8 @@ export const useMutations = () => {
     });
     const markReadMutation = useMutation({
         mutationFn: async (publicKey: PublicKey) => {
-            await axios.put(`http://127.0.0.1:3001/api/notifications/read/${publicKey}`);
+            const params = await signWalletAuth(signMessage, "notifications", publicKey);
+            await axios.put(`http://127.0.0.1:3001/api/notifications/read/${publicKey}`, undefined, { params });
             console.log("called")
         },
         onSuccess: (_, publicKey) => {
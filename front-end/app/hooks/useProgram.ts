@@ -61,8 +61,34 @@ export const useProgram = () => {
         );
         return pda;
     };
+
+    const getPaymentRecordPDA = (subscriptionPDA: PublicKey, index: number) => {
+        const indexBytes = Buffer.alloc(8);
+        indexBytes.writeBigUInt64LE(BigInt(index));
+        const [pda] = PublicKey.findProgramAddressSync(
+            [Buffer.from("payment"), subscriptionPDA.toBuffer(), indexBytes],
+            PROGRAM_ID
+        );
+        return pda;
+    };
+
+    const getCouponPDA = (planPDA: PublicKey, code: string) => {
+        const [pda] = PublicKey.findProgramAddressSync(
+            [Buffer.from("coupon"), planPDA.toBuffer(), Buffer.from(code)],
+            PROGRAM_ID
+        );
+        return pda;
+    };
+    // Every wSOL account approves this one delegate, shared by its owner's SOL subscriptions
+    const getSolDelegatePDA = () => {
+        const [pda] = PublicKey.findProgramAddressSync([Buffer.from("sol_delegate")], PROGRAM_ID);
+        return pda;
+    };
     return {
         getVaultPDA,
+        getPaymentRecordPDA,
+        getCouponPDA,
+        getSolDelegatePDA,
         getEscrowStatePDA,
         getGlobalStatsPDA,
         sendTransaction,
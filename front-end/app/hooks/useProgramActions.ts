@@ -2,15 +2,15 @@ import * as anchor from "@coral-xyz/anchor";
 import { web3 } from "@coral-xyz/anchor";
 import { useProgram } from "./useProgram";
 import { PublicKey, TransactionInstruction } from "@solana/web3.js";
-import { fetchTokenMetadata, getApproveInstructions, getMintProgramId } from "../utils/token";
-import { ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddressSync, getMint } from "@solana/spl-token";
+import { fetchTokenMetadata, getApproveInstructions, getMintProgramId, getPythPrice } from "../utils/token";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, NATIVE_MINT, TOKEN_PROGRAM_ID, addExtraAccountMetasForExecute, createApproveInstruction, getAccount, getAssociatedTokenAddressSync, getMint, getTransferHook } from "@solana/spl-token";
 import { Plan, planQuery, Tier } from "../types";
 import { compressData, decompressData } from "../utils/compression";
 import { useWallet } from "@solana/wallet-adapter-react";
 
 export const useProgramActions = () => {
     const wallet = useWallet();
-    const { program, getGlobalStatsPDA, PROGRAM_ID, connection } = useProgram()
+    const { program, getGlobalStatsPDA, getPaymentRecordPDA, getCouponPDA, getSolDelegatePDA, PROGRAM_ID, connection } = useProgram()
 
     async function getMyPlan() {
         const [planPDA] = PublicKey.findProgramAddressSync(
@@ -174,6 +174,11 @@ export const useProgramActions = () => {
         autoRenew: boolean = true,
         receiver: PublicKey,
         mint: PublicKey,
+        maxCycles?: number | string,
+        endTs?: number | string,
+        metering?: { usageCap: number | string },
+        couponCode?: string,
+        usdPricing?: { priceFeed: PublicKey; slippageBps?: number },
     ) {
         if (!program || !payerKey) {
             alert("Wallet or program not connected");
@@ -218,9 +223,56 @@ export const useProgramActions = () => {
                 "confirmed",
                 tokenProgramId
             );
-            const rawAmount = new anchor.BN(amount).mul(
+            let rawAmount = new anchor.BN(amount).mul(
                 new anchor.BN(10).pow(new anchor.BN(mintInfo.decimals))
             );
+            // Metered tiers: the subscriber's usage cap, in base units; the plan sets the unit price
+            const toBaseUnits = (value: number | string) =>
+                new anchor.BN(Math.round(Number(value) * 10 ** mintInfo.decimals));
+            const postInstructions: TransactionInstruction[] = [];
+            if (metering) {
+                postInstructions.push(
+                    await program.methods
+                        .setUsageCap(toBaseUnits(metering.usageCap))
+                        .accounts({ payer: payerKey, subscription: subscriptionPDA } as any)
+                        .instruction()
+                );
+            }
+            // USD-priced tiers: the merchant's USD price on the plan, quoted at today's oracle
+            // price; every charge, the first included, may take up to the buffer more
+            if (usdPricing) {
+                const planAccount = await (program.account as any).plan.fetch(planPda);
+                const usdPrice = planAccount.usdPrices.find((p: any) => p.tierName === tierName);
+                if (!usdPrice) {
+                    throw new Error(`Tier ${tierName} has no USD price on chain`);
+                }
+                const price = await getPythPrice(usdPricing.priceFeed);
+                rawAmount = toBaseUnits(usdPrice.usdAmount.toNumber() / 1e6 / price)
+                    .muln(10_000 + (usdPricing.slippageBps ?? 2_000))
+                    .divn(10_000);
+            }
+            const couponPDA = couponCode ? getCouponPDA(planPda, couponCode.trim()) : null;
+            // SOL plans: wrap the first charge so the subscriber only ever spends SOL
+            const preInstructions: TransactionInstruction[] = [];
+            if (mint.equals(NATIVE_MINT)) {
+                preInstructions.push(await wrapSolInstruction(payerKey, rawAmount));
+            }
+            // Transfer-hook mints need the hook's extra accounts on the first charge
+            const hookAccounts = new TransactionInstruction({ keys: [], programId: PROGRAM_ID });
+            const transferHook = getTransferHook(mintInfo);
+            if (transferHook && !transferHook.programId.equals(PublicKey.default)) {
+                await addExtraAccountMetasForExecute(
+                    connection,
+                    hookAccounts,
+                    transferHook.programId,
+                    userTokenAccount,
+                    mint,
+                    receiverTokenAccount,
+                    payerKey,
+                    BigInt(rawAmount.toString()),
+                    "confirmed"
+                );
+            }
             // 4. Build and send transaction
             const txSig = await program.methods
                 .initializeSubscription(
@@ -230,6 +282,8 @@ export const useProgramActions = () => {
                     rawAmount,
                     autoRenew,
                     Array.from(uniqueSeed),
+                    maxCycles ? new anchor.BN(maxCycles) : null,
+                    endTs ? new anchor.BN(endTs) : null,
                 )
                 .accounts({
                     payer: payerKey,
@@ -241,7 +295,14 @@ export const useProgramActions = () => {
                     globalStats: getGlobalStatsPDA(PROGRAM_ID),
                     systemProgram: web3.SystemProgram.programId,
                     rent: web3.SYSVAR_RENT_PUBKEY,
-                })
+                    paymentRecord: getPaymentRecordPDA(subscriptionPDA, 0),
+                    plan: planPda,
+                    coupon: couponPDA,
+                    priceFeed: usdPricing?.priceFeed ?? null,
+                } as any)
+                .remainingAccounts(hookAccounts.keys)
+                .preInstructions(preInstructions)
+                .postInstructions(postInstructions)
                 .rpc();
 
             console.log("✅ Subscription created successfully!");
@@ -253,7 +314,7 @@ export const useProgramActions = () => {
 
             return {
                 subscriptionPDA: subscriptionPDA.toBase58(),
-                account: { ...account, txSignature: txSig },
+                account: { ...account, txSignature: txSig, coupon: couponPDA?.toBase58() },
             };
         } catch (error: any) {
             console.error("Failed to create subscription:", error);
@@ -262,6 +323,134 @@ export const useProgramActions = () => {
         }
     }
 
+    // What `wrap_sol` approves for a subscription: its remaining renewals at the most each
+    // may charge, on top of what the owner's other SOL subscriptions are still approved for
+    async function solRenewalApproval(owner: PublicKey, subscriptionPDA: PublicKey) {
+        const sub: any = await (program!.account as any).subscription.fetch(subscriptionPDA);
+        const perCharge = anchor.BN.max(sub.amount, sub.maxTokenAmount).add(sub.usageCap);
+        const renewals = sub.maxCycles
+            ? anchor.BN.max(sub.maxCycles.sub(sub.paymentCount), new anchor.BN(0))
+            : new anchor.BN(12); // SOL_APPROVAL_CYCLES
+        const wsolAccount = getAssociatedTokenAddressSync(NATIVE_MINT, owner, false, TOKEN_PROGRAM_ID);
+        const solDelegate = getSolDelegatePDA();
+        const existing = await getAccount(connection, wsolAccount).catch(() => null);
+        const approved = existing?.delegate?.equals(solDelegate) ? existing.delegatedAmount : BigInt(0);
+        return createApproveInstruction(
+            wsolAccount,
+            solDelegate,
+            owner,
+            approved + BigInt(perCharge.mul(renewals).toString()),
+            [],
+            TOKEN_PROGRAM_ID
+        );
+    }
+
+    async function wrapSolInstruction(owner: PublicKey, lamports: anchor.BN) {
+        return program!.methods
+            .wrapSol(lamports)
+            .accounts({
+                owner,
+                wsolAccount: getAssociatedTokenAddressSync(NATIVE_MINT, owner, false, TOKEN_PROGRAM_ID),
+                nativeMint: NATIVE_MINT,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: web3.SystemProgram.programId,
+                // Not created yet; the subscribe transaction approves it itself
+                subscription: null,
+                solDelegate: getSolDelegatePDA(),
+            } as any)
+            .instruction();
+    }
+
+    // Metered tiers: per-unit prices live on the plan, set by the creator.
+    // Prices for tiers that dropped metering are cleared first.
+    async function tierUnitPriceInstructions(
+        creatorKey: PublicKey,
+        planPDA: PublicKey,
+        mint: PublicKey,
+        tiers: Tier[],
+        current: { tierName: string; mint: PublicKey }[] = []
+    ) {
+        const mintInfo = await getMint(connection, mint, "confirmed", await getMintProgramId(mint));
+        const wanted = tiers.filter((tier) => Number(tier.unitPrice ?? 0) > 0);
+        const cleared = current
+            .filter((price) => price.mint.equals(mint))
+            .filter((price) => !wanted.some((tier) => tier.tierName === price.tierName))
+            .map((price) => ({ tierName: price.tierName, unitPrice: new anchor.BN(0) }));
+        const priced = wanted.map((tier) => ({
+            tierName: tier.tierName,
+            unitPrice: new anchor.BN(Math.round(Number(tier.unitPrice) * 10 ** mintInfo.decimals)),
+        }));
+        return Promise.all(
+            [...cleared, ...priced].map(({ tierName, unitPrice }) =>
+                program!.methods
+                    .setTierUnitPrice(tierName, mint, unitPrice)
+                    .accounts({ plan: planPDA, creator: creatorKey } as any)
+                    .instruction()
+            )
+        );
+    }
+
+    // USD-priced tiers: dollars per cycle live on the plan, 6 decimals, set by the creator
+    async function tierUsdPriceInstructions(
+        creatorKey: PublicKey,
+        planPDA: PublicKey,
+        tiers: Tier[],
+        current: { tierName: string }[] = []
+    ) {
+        const wanted = tiers.filter((tier) => Number(tier.usdPrice ?? 0) > 0);
+        const cleared = current
+            .filter((price) => !wanted.some((tier) => tier.tierName === price.tierName))
+            .map((price) => ({ tierName: price.tierName, usdAmount: new anchor.BN(0) }));
+        const priced = wanted.map((tier) => ({
+            tierName: tier.tierName,
+            usdAmount: new anchor.BN(Math.round(Number(tier.usdPrice) * 1e6)),
+        }));
+        return Promise.all(
+            [...cleared, ...priced].map(({ tierName, usdAmount }) =>
+                program!.methods
+                    .setTierUsdPrice(tierName, usdAmount)
+                    .accounts({ plan: planPDA, creator: creatorKey } as any)
+                    .instruction()
+            )
+        );
+    }
+
+    // Sets aside SOL for upcoming renewals of SOL plans. Unwrapping closes the wSOL
+    // account and drops the shared approval, so pass a subscription to approve its renewals again.
+    async function topUpSol(owner: PublicKey, sol: number, subscription?: PublicKey) {
+        if (!program) return undefined;
+        const lamports = new anchor.BN(Math.round(sol * web3.LAMPORTS_PER_SOL));
+        return program.methods
+            .wrapSol(lamports)
+            .accounts({
+                owner,
+                wsolAccount: getAssociatedTokenAddressSync(NATIVE_MINT, owner, false, TOKEN_PROGRAM_ID),
+                nativeMint: NATIVE_MINT,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: web3.SystemProgram.programId,
+                subscription: subscription ?? null,
+                solDelegate: getSolDelegatePDA(),
+            } as any)
+            .rpc();
+    }
+
+    // Returns everything set aside (or, for merchants, SOL revenue) to the wallet.
+    // Renewals of SOL subscriptions stop until the next top-up re-approves them.
+    async function unwrapSol(owner: PublicKey) {
+        if (!program) return undefined;
+        return program.methods
+            .unwrapSol()
+            .accounts({
+                owner,
+                wsolAccount: getAssociatedTokenAddressSync(NATIVE_MINT, owner, false, TOKEN_PROGRAM_ID),
+                nativeMint: NATIVE_MINT,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            } as any)
+            .rpc();
+    }
+
     type UpdateField = "autoRenew" | "active" | "duration" | "tier";
 
     async function updateSubscription(
@@ -284,6 +473,16 @@ export const useProgramActions = () => {
             // 1. Map string field to IDL Enum variants
             switch (field) {
                 case "autoRenew":
+                    fieldEnum = { autoRenew: {} } as const;
+                    valueEnum = { bool: [value as boolean] };
+                    // wSOL renewals share one delegate, so turning this one off must not revoke
+                    // it; turning it on adds this subscription's renewals to the approval
+                    if (mint.equals(NATIVE_MINT)) {
+                        if (value === true) {
+                            preInstructions.push(await solRenewalApproval(payerKey, subscriptionPDA));
+                        }
+                        break;
+                    }
                     const allowanceAmount = value === true
                         ? BigInt("18446744073709551615")
                         : BigInt(0);
@@ -298,8 +497,6 @@ export const useProgramActions = () => {
 
                     // Add them to our list
                     preInstructions.push(...approveInstrs);
-                    fieldEnum = { autoRenew: {} } as const;
-                    valueEnum = { bool: [value as boolean] };
                     break;
 
                 case "active":
@@ -428,6 +625,10 @@ export const useProgramActions = () => {
                     receiver: new PublicKey(plan.receiver),
                     systemProgram: web3.SystemProgram.programId,
                 })
+                .postInstructions([
+                    ...(await tierUnitPriceInstructions(creatorKey, planPDA, new PublicKey(plan.mint), plan.tiers)),
+                    ...(await tierUsdPriceInstructions(creatorKey, planPDA, plan.tiers)),
+                ])
                 .rpc();
             return txSig
         } catch (error: any) {
@@ -463,6 +664,8 @@ export const useProgramActions = () => {
             PROGRAM_ID
         );
 
+        const current = await (program!.account as any).plan.fetch(planPDA);
+
         return await program!.methods
             .updatePlan(
                 name,
@@ -473,6 +676,10 @@ export const useProgramActions = () => {
                 creator: creatorKey,
                 receiver,
             })
+            .postInstructions([
+                ...(await tierUnitPriceInstructions(creatorKey, planPDA, current.mint, tiers, current.unitPrices ?? [])),
+                ...(await tierUsdPriceInstructions(creatorKey, planPDA, tiers, current.usdPrices ?? [])),
+            ])
             .rpc();
     }
 
@@ -502,7 +709,7 @@ export const useProgramActions = () => {
     }
 
 
-    return { fetchUserSubscriptions, initializeSubscription, cancelSubscription, fetchAllSubscriptionPlans, createPlan, cancelPlan, updatePlan, getPlan, updateSubscription, getMyPlan, fetchSubscriptionsByPlan }
+    return { fetchUserSubscriptions, initializeSubscription, cancelSubscription, fetchAllSubscriptionPlans, createPlan, cancelPlan, updatePlan, getPlan, updateSubscription, getMyPlan, fetchSubscriptionsByPlan, topUpSol, unwrapSol }
 }
 
 
@@ -36,7 +36,7 @@ export interface Subscription {
     planMetadata?: Plan
     planCreator: string
 }
-export type NotificationType = 'error' | 'success' | 'warning' | 'info';
+export type NotificationType = 'error' | 'success' | 'warning' | 'info' | 'reminder';
 
 export type Notification = {
     id: string;                 // DB primary key
@@ -51,6 +51,12 @@ export type Notification = {
     is_read: string
 };
 
+export type NotificationPage = {
+    items: Notification[];
+    nextCursor: string | null;
+    unreadCount: number;
+};
+
 export interface Transaction {
     id: number;
     userPubkey: string;
@@ -61,6 +67,17 @@ export interface Transaction {
     subscriptionPda: string;    // related subscription
     txSignature?: string;
     createdAt: string; // ISO string
+    direction: "paid" | "received";
+    paymentRecord?: string | null; // on-chain record, required for refunds
+    refundOf?: number | null;      // set on "refunded" rows
+    usageUnits?: number | null;    // metered tiers only
+    usageAmount?: number | null;
+}
+
+export interface TransactionPage {
+    items: Transaction[];
+    nextCursor: string | null;
+    totals: { count: number; paid: number; received: number };
 }
 
 export interface UpdateParams {
@@ -127,6 +144,12 @@ export interface Tier {
     amount: number | string | anchor.BN; // Flexible input
     periodSeconds: number | string | anchor.BN; // Flexible input
     description: string;
+    maxCycles?: number | string; // installment tiers: charges before completion
+    endTs?: number | string;     // unix seconds, no charges after this
+    unitPrice?: number | string; // metered tiers: price per reported unit
+    usageCap?: number | string;  // suggested cap on usage per charge
+    prices?: { mint: string; amount: number | string }[]; // prices in the plan's other mints
+    usdPrice?: number | string;  // USD-priced tiers: dollars per cycle, charged at the oracle price
 }
 export type ScheduleSubscriptionRequest = {
     subscriptionPda: string
@@ -152,6 +175,8 @@ export interface Plan {
     name: string,
     tiers: Tier[],
     bump?: number;
+    acceptedMints?: { mint: PublicKey | string; tokenSymbol: string }[];
+    priceFeeds?: { mint: PublicKey | string; priceFeed: PublicKey | string; feedId: number[] }[]; // Pyth feed per mint for USD-priced tiers
 }
 
 export interface planQuery {
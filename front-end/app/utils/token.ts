@@ -183,3 +183,16 @@ export async function getApproveInstructions({
 }
 
 
+
+/// Price from a Pyth `PriceUpdateV2` account, in USD per whole token.
+export const getPythPrice = async (priceFeed: PublicKey): Promise<number> => {
+    const info = await connection.getAccountInfo(priceFeed);
+    if (!info) {
+        throw new Error(`Price feed ${priceFeed.toBase58()} not found`);
+    }
+    // discriminator (8) + write authority (32) + verification level (1) + feed id (32)
+    const message = 8 + 32 + 1 + 32;
+    const price = info.data.readBigInt64LE(message);
+    const exponent = info.data.readInt32LE(message + 16);
+    return Number(price) * 10 ** exponent;
+};
@@ -0,0 +1,20 @@
+import bs58 from "bs58";
+import { PublicKey } from "@solana/web3.js";
+
+export type SignMessage = (message: Uint8Array) => Promise<Uint8Array>;
+
+// Query params proving wallet ownership, matching the backend's `WalletAuth`:
+// a signature over `solpay:<action>:<pubkey>:<timestamp>`.
+export const signWalletAuth = async (
+    signMessage: SignMessage | undefined,
+    action: string,
+    publicKey: PublicKey | string,
+) => {
+    if (!signMessage) {
+        throw new Error("Wallet does not support message signing");
+    }
+    const timestamp = Math.floor(Date.now() / 1000);
+    const message = new TextEncoder().encode(`solpay:${action}:${publicKey.toString()}:${timestamp}`);
+    const signature = bs58.encode(await signMessage(message));
+    return { signature, timestamp };
+};
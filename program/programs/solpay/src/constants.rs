@@ -1,9 +1,22 @@
-// use anchor_lang::prelude::{pubkey::pubkey, Pubkey};
+use anchor_lang::prelude::Pubkey;
 
 pub const SUBSCRIPTION_SEED: &[u8] = b"subscription";
 pub const VAULT_SEED: &[u8] = b"vault";
 pub const GLOBAL_STATS_SEED: &[u8] = b"global_stats";
 pub const PLAN_SEED: &[u8] = b"subscription_plan";
+pub const PAYMENT_SEED: &[u8] = b"payment";
+pub const COUPON_SEED: &[u8] = b"coupon";
+/// Delegate of every wSOL account, which all of an owner's SOL subscriptions share
+pub const SOL_DELEGATE_SEED: &[u8] = b"sol_delegate";
+/// Renewals a wSOL approval covers for subscriptions without a cycle limit
+pub const SOL_APPROVAL_CYCLES: u64 = 12;
 pub const MAX_PLAN_NAME_LENGTH: usize = 64;
+pub const MAX_PLAN_MINTS: usize = 4;
+pub const MAX_TIER_PRICES: usize = 8;
+/// The primary mint and each accepted one
+pub const MAX_PRICE_FEEDS: usize = MAX_PLAN_MINTS + 1;
+/// Wrapped SOL; plans priced in SOL use this as their mint
+pub const NATIVE_MINT: Pubkey =
+    anchor_lang::solana_program::pubkey!("So11111111111111111111111111111111111111112");
 pub const ADMIN_PUBKEY: &str = "FUk2WGh5Kcxk8sRm6V9jRYgWiQML7X8DPTKaK9Eqc1ry";
 // pub const TUKTUK_PROGRAM_ID: Pubkey = pubkey!("tuktuk1111111111111111111111111111111111");
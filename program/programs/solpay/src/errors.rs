@@ -22,4 +22,58 @@ pub enum ErrorCode {
     TierNotFound,
     #[msg("Missing Signer")]
     MissingSigner,
+    #[msg("Refund amount must be greater than zero")]
+    InvalidRefundAmount,
+    #[msg("Refund exceeds the amount left on this payment")]
+    RefundExceedsPayment,
+    #[msg("Subscription is paused")]
+    SubscriptionPaused,
+    #[msg("Subscription is not paused")]
+    SubscriptionNotPaused,
+    #[msg("Subscription term is invalid")]
+    InvalidTerm,
+    #[msg("Subscription has completed its term")]
+    SubscriptionCompleted,
+    #[msg("Subscription is not metered")]
+    MeteringNotEnabled,
+    #[msg("Coupon terms are invalid")]
+    InvalidCoupon,
+    #[msg("Coupon is no longer active")]
+    CouponInactive,
+    #[msg("Coupon has expired")]
+    CouponExpired,
+    #[msg("Coupon has reached its redemption limit")]
+    CouponExhausted,
+    #[msg("Coupon does not apply to this tier")]
+    CouponTierMismatch,
+    #[msg("Subscription already has a coupon")]
+    CouponAlreadyApplied,
+    #[msg("Plan does not accept this mint")]
+    MintNotAccepted,
+    #[msg("Plan already accepts the maximum number of mints")]
+    TooManyMints,
+    #[msg("Amount must be greater than zero")]
+    InvalidAmount,
+    #[msg("Mint uses a Token-2022 extension subscriptions cannot support")]
+    UnsupportedMintExtension,
+    #[msg("Price feed is not a verified Pyth price update")]
+    InvalidPriceFeed,
+    #[msg("USD-priced subscription needs the plan's price feed")]
+    PriceFeedRequired,
+    #[msg("Oracle price is too old")]
+    StalePrice,
+    #[msg("Oracle price confidence is too wide")]
+    PriceConfidenceTooWide,
+    #[msg("Charge at the current price exceeds the subscriber's limit")]
+    PriceAboveSubscriberLimit,
+    #[msg("Only subscriptions and plans can be migrated")]
+    NotMigratable,
+    #[msg("Account already uses the current layout")]
+    AlreadyMigrated,
+    #[msg("Plan already has the maximum number of tier prices")]
+    TooManyTierPrices,
+    #[msg("Price update is for a different feed")]
+    PriceFeedMismatch,
+    #[msg("Subscription is not active")]
+    SubscriptionInactive,
 }
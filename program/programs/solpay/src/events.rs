@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use crate::states::Discount;
 
 #[event]
 pub struct SubscriptionInitialized {
@@ -12,6 +13,9 @@ pub struct SubscriptionInitialized {
     pub active: bool,
     pub bump: u8,
     pub unique_seed: [u8; 8],
+    pub max_cycles: Option<u64>,
+    pub end_ts: Option<i64>,
+    pub mint: Pubkey,
 }
 
 #[event]
@@ -27,10 +31,37 @@ pub struct PaymentExecuted {
     pub payer: Pubkey,
     pub payee: Pubkey,
     pub amount: u64,
+    pub base_amount: u64,
+    pub usage_units: u64,
+    pub usage_amount: u64,
+    pub discount_amount: u64,
+    pub transfer_fee: u64,
     pub next_payment_ts: i64,
     pub timestamp: i64,
 }
 
+#[event]
+pub struct UsageReported {
+    pub subscription: Pubkey,
+    pub units: u64,
+    pub accrued_units: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Refunded {
+    pub payment_record: Pubkey,
+    pub subscription: Pubkey,
+    pub plan: Pubkey,
+    pub payer: Pubkey,
+    pub receiver: Pubkey,
+    pub payment_index: u64,
+    pub amount: u64,
+    pub total_refunded: u64,
+    pub original_amount: u64,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct PaymentFailed {
     pub subscription: Pubkey,
@@ -45,6 +76,30 @@ pub struct SubscriptionCancelled {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct SubscriptionCompleted {
+    pub subscription: Pubkey,
+    pub payer: Pubkey,
+    pub payments_made: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SubscriptionPaused {
+    pub subscription: Pubkey,
+    pub payer: Pubkey,
+    pub paused_at: i64,
+}
+
+#[event]
+pub struct SubscriptionResumed {
+    pub subscription: Pubkey,
+    pub payer: Pubkey,
+    pub paused_seconds: i64,
+    pub next_payment_ts: i64,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct WithdrawnRemaining {
     pub subscription: Pubkey,
@@ -66,3 +121,51 @@ pub struct GlobalStatsInitialized {
     pub bump: u8,
     pub timestamp: i64,
 }
+
+#[event]
+pub struct CouponCreated {
+    pub coupon: Pubkey,
+    pub plan: Pubkey,
+    pub code: String,
+    pub discount: Discount,
+    pub duration_cycles: Option<u64>,
+    pub max_redemptions: Option<u64>,
+    pub expires_at: Option<i64>,
+    pub tier_name: Option<String>,
+}
+
+#[event]
+pub struct CouponRedeemed {
+    pub coupon: Pubkey,
+    pub subscription: Pubkey,
+    pub payer: Pubkey,
+    pub redemptions: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PlanMintsUpdated {
+    pub plan: Pubkey,
+    pub mints: Vec<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SolWrapped {
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub balance: u64,
+}
+
+#[event]
+pub struct SolUnwrapped {
+    pub owner: Pubkey,
+    pub lamports: u64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub previous_size: u64,
+    pub size: u64,
+}
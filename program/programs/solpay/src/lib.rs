@@ -1,13 +1,20 @@
 pub mod constants;
 pub mod errors;
 pub mod events;
+pub mod oracle;
 pub mod states;
 use crate::constants::*;
 use crate::errors::ErrorCode;
 use crate::{events::*, states::*};
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::system_program;
+use anchor_spl::token::{self, Approve, CloseAccount, SyncNative};
 use anchor_spl::token_interface::{transfer_checked, TransferChecked};
+use spl_token_2022::extension::{
+    transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
 
 declare_id!("DUNyVxYZBG7YvU5Nsbci75stbBnjBtBjjibH6FtVPFaL");
 
@@ -28,19 +35,31 @@ pub mod recurring_payments {
         Ok(())
     }
 
+    /// `amount` is the charge per cycle, or for USD-priced tiers the most the subscriber
+    /// pays per charge in tokens; the first charge converts at the mint's feed too.
     #[allow(clippy::too_many_arguments)]
-    pub fn initialize_subscription(
-        ctx: Context<InitializeSubscription>,
+    pub fn initialize_subscription<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializeSubscription<'info>>,
         tier_name: String,
         plan_pda: Pubkey,
         period_seconds: i64,
         amount: u64,
         auto_renew: bool,
         unique_seed: [u8; 8],
+        max_cycles: Option<u64>,
+        end_ts: Option<i64>,
     ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(max_cycles != Some(0), ErrorCode::InvalidTerm);
+        require!(end_ts.map_or(true, |end| end > now), ErrorCode::InvalidTerm);
+        require!(
+            ctx.accounts.plan.accepts_mint(&ctx.accounts.mint.key()),
+            ErrorCode::MintNotAccepted
+        );
+
         // 2. INITIALIZE SUBSCRIPTION STATE
         let subscription = &mut ctx.accounts.subscription;
-        let next_payment_ts = Clock::get()?.unix_timestamp + period_seconds;
+        let next_payment_ts = now + period_seconds;
         subscription.tier_name = tier_name.clone();
         subscription.plan_pda = plan_pda.clone();
         subscription.payer = ctx.accounts.payer.key();
@@ -51,24 +70,64 @@ pub mod recurring_payments {
         subscription.amount = amount;
         subscription.next_payment_ts = next_payment_ts;
         subscription.period_seconds = period_seconds;
+        subscription.max_cycles = max_cycles;
+        subscription.end_ts = end_ts;
+        subscription.mint = ctx.accounts.mint.key();
+        let usd_amount = ctx.accounts.plan.usd_price(&tier_name);
+        let base_amount = if usd_amount > 0 {
+            subscription.max_token_amount = amount;
+            usd_charge(
+                &ctx.accounts.plan,
+                &ctx.accounts.mint.key(),
+                ctx.accounts.mint.decimals,
+                ctx.accounts.price_feed.as_ref(),
+                usd_amount,
+                amount,
+                now,
+            )?
+        } else {
+            amount
+        };
         let stats = &mut ctx.accounts.global_stats;
         stats.total_subscriptions = stats
             .total_subscriptions
             .checked_add(1)
             .ok_or(ErrorCode::NumericalOverflow)?;
 
+        if let Some(coupon) = ctx.accounts.coupon.as_mut() {
+            redeem_coupon(coupon, subscription)?;
+        }
+        let discount_amount = take_discount(subscription, base_amount);
+        let charged = base_amount - discount_amount;
+
+        let transfer_fee = transfer_fee_for(&ctx.accounts.mint.to_account_info(), charged)?;
         perform_payment(
             subscription,
             ctx.accounts.user_token_account.to_account_info(),
             ctx.accounts.receiver_token_account.to_account_info(),
             ctx.accounts.mint.to_account_info(),
             ctx.accounts.token_program.to_account_info(),
-            amount,
+            charged,
             ctx.accounts.mint.decimals,
             Some(&ctx.accounts.payer),
             false,
+            None,
+            ctx.remaining_accounts,
         )?;
 
+        record_payment(
+            &mut ctx.accounts.payment_record,
+            subscription,
+            ctx.accounts.mint.key(),
+            charged,
+            0,
+            0,
+            discount_amount,
+            transfer_fee,
+            ctx.bumps.payment_record,
+        )?;
+        complete_if_term_reached(subscription)?;
+
         emit!(SubscriptionInitialized {
             subscription: ctx.accounts.subscription.key(),
             tier_name: tier_name.to_string(),
@@ -76,39 +135,109 @@ pub mod recurring_payments {
             payer: ctx.accounts.payer.key(),
             amount: amount,
             next_payment_ts: next_payment_ts,
-            auto_renew: auto_renew,
-            active: true,
+            auto_renew: ctx.accounts.subscription.auto_renew,
+            active: ctx.accounts.subscription.active,
             bump: ctx.bumps.subscription,
             unique_seed: unique_seed,
+            max_cycles,
+            end_ts,
+            mint: ctx.accounts.mint.key(),
         });
 
         Ok(())
     }
 
-    pub fn execute_payment(
-        ctx: Context<ExecutePayment>,
+    pub fn execute_payment<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecutePayment<'info>>,
         new_amount: u64,
         new_period_seconds: i64,
     ) -> Result<()> {
         let clock = Clock::get()?;
         let subscription = &mut ctx.accounts.subscription;
 
+        require!(
+            subscription.paused_at.is_none(),
+            ErrorCode::SubscriptionPaused
+        );
+        require!(
+            !term_reached(subscription),
+            ErrorCode::SubscriptionCompleted
+        );
         require!(
             clock.unix_timestamp >= subscription.next_payment_ts,
             ErrorCode::PaymentNotDue
         );
 
+        // USD-priced tiers convert at the oracle price, within the subscriber's limit
+        let plan = &ctx.accounts.plan;
+        let usd_amount = plan.usd_price(&subscription.tier_name);
+        let base_amount = if usd_amount > 0 {
+            usd_charge(
+                plan,
+                &ctx.accounts.mint.key(),
+                ctx.accounts.mint.decimals,
+                ctx.accounts.price_feed.as_ref(),
+                usd_amount,
+                subscription.max_token_amount,
+                clock.unix_timestamp,
+            )?
+        } else {
+            subscription.amount
+        };
+        let discount_amount = take_discount(subscription, base_amount);
+        let unit_price = ctx
+            .accounts
+            .plan
+            .unit_price(&subscription.tier_name, &ctx.accounts.mint.key());
+        let usage_units = subscription.accrued_units;
+        let usage_amount = usage_units
+            .saturating_mul(unit_price)
+            .min(subscription.usage_cap);
+        let charged = (base_amount - discount_amount)
+            .checked_add(usage_amount)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+
+        let transfer_fee = transfer_fee_for(&ctx.accounts.mint.to_account_info(), charged)?;
+        // wSOL accounts approve the shared delegate; other mints the subscription itself
+        let sol_delegate = ctx
+            .accounts
+            .sol_delegate
+            .as_ref()
+            .filter(|delegate| {
+                ctx.accounts.user_token_account.delegate == COption::Some(delegate.key())
+            })
+            .zip(ctx.bumps.sol_delegate)
+            .map(|(delegate, bump)| (delegate.to_account_info(), bump));
         perform_payment(
             subscription,
             ctx.accounts.user_token_account.to_account_info(),
             ctx.accounts.receiver_token_account.to_account_info(),
             ctx.accounts.mint.to_account_info(),
             ctx.accounts.token_program.to_account_info(),
-            subscription.amount,
+            charged,
             ctx.accounts.mint.decimals,
             None,
             true,
+            sol_delegate,
+            ctx.remaining_accounts,
+        )?;
+
+        record_payment(
+            &mut ctx.accounts.payment_record,
+            subscription,
+            ctx.accounts.mint.key(),
+            charged,
+            usage_units,
+            usage_amount,
+            discount_amount,
+            transfer_fee,
+            ctx.bumps.payment_record,
         )?;
+        // Usage beyond the cap is not carried into the next cycle
+        subscription.accrued_units = 0;
+        if let Some(usage_cap) = subscription.pending_usage_cap.take() {
+            subscription.usage_cap = usage_cap;
+        }
 
         // ---------- UPDATE SUBSCRIPTION FOR NEXT CYCLE ----------
         subscription.amount = new_amount;
@@ -117,6 +246,348 @@ pub mod recurring_payments {
             .next_payment_ts
             .checked_add(new_period_seconds)
             .ok_or(ErrorCode::NumericalOverflow)?;
+        complete_if_term_reached(subscription)?;
+
+        emit!(PaymentExecuted {
+            subscription: subscription.key(),
+            payer: subscription.payer,
+            payee: ctx.accounts.receiver_token_account.owner,
+            amount: charged,
+            base_amount,
+            usage_units,
+            usage_amount,
+            discount_amount,
+            transfer_fee,
+            next_payment_ts: subscription.next_payment_ts,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// The subscriber's limit on usage per charge. Raising it applies at once; lowering
+    /// it waits for the next charge, so usage already reported this cycle is still billed.
+    pub fn set_usage_cap(ctx: Context<SetUsageCap>, usage_cap: u64) -> Result<()> {
+        let subscription = &mut ctx.accounts.subscription;
+        if usage_cap >= subscription.usage_cap {
+            subscription.usage_cap = usage_cap;
+            subscription.pending_usage_cap = None;
+        } else {
+            subscription.pending_usage_cap = Some(usage_cap);
+        }
+        Ok(())
+    }
+
+    /// The most one charge of a USD-priced tier may take, in the billing mint. The
+    /// merchant sets the USD price on the plan; this is the subscriber's side of it.
+    pub fn set_max_token_amount(
+        ctx: Context<SetMaxTokenAmount>,
+        max_token_amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.subscription.max_token_amount = max_token_amount;
+        Ok(())
+    }
+
+    pub fn report_usage(ctx: Context<ReportUsage>, units: u64) -> Result<()> {
+        let plan = &ctx.accounts.plan;
+        let subscription = &mut ctx.accounts.subscription;
+        require!(
+            plan.unit_price(&subscription.tier_name, &subscription.billing_mint(plan)) > 0,
+            ErrorCode::MeteringNotEnabled
+        );
+        // Usage only accrues while the subscription is being billed
+        require!(subscription.active, ErrorCode::SubscriptionInactive);
+        require!(
+            !term_reached(subscription),
+            ErrorCode::SubscriptionCompleted
+        );
+        require!(
+            subscription.paused_at.is_none(),
+            ErrorCode::SubscriptionPaused
+        );
+
+        subscription.accrued_units = subscription
+            .accrued_units
+            .checked_add(units)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+
+        emit!(UsageReported {
+            subscription: subscription.key(),
+            units,
+            accrued_units: subscription.accrued_units,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sends all or part of one payment back to the subscriber, from the receiver's account.
+    pub fn refund_payment<'info>(
+        ctx: Context<'_, '_, '_, 'info, RefundPayment<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidRefundAmount);
+
+        let record = &mut ctx.accounts.payment_record;
+        let total_refunded = record
+            .refunded_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+        require!(
+            total_refunded <= record.amount,
+            ErrorCode::RefundExceedsPayment
+        );
+
+        if ctx.remaining_accounts.is_empty() {
+            let cpi_accounts = TransferChecked {
+                from: ctx.accounts.receiver_token_account.to_account_info(),
+                to: ctx.accounts.payer_token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: ctx.accounts.receiver.to_account_info(),
+            };
+            let cpi_ctx =
+                CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+            transfer_checked(cpi_ctx, amount, ctx.accounts.mint.decimals)?;
+        } else {
+            // Transfer-hook mints: the hook program, its validation account and extra metas
+            spl_token_2022::onchain::invoke_transfer_checked(
+                ctx.accounts.token_program.key,
+                ctx.accounts.receiver_token_account.to_account_info(),
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.payer_token_account.to_account_info(),
+                ctx.accounts.receiver.to_account_info(),
+                ctx.remaining_accounts,
+                amount,
+                ctx.accounts.mint.decimals,
+                &[],
+            )?;
+        }
+
+        record.refunded_amount = total_refunded;
+
+        emit!(Refunded {
+            payment_record: record.key(),
+            subscription: record.subscription,
+            plan: record.plan,
+            payer: record.payer,
+            receiver: ctx.accounts.receiver.key(),
+            payment_index: record.index,
+            amount,
+            total_refunded,
+            original_amount: record.amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_coupon(
+        ctx: Context<CreateCoupon>,
+        code: String,
+        discount: Discount,
+        duration_cycles: Option<u64>,
+        max_redemptions: Option<u64>,
+        expires_at: Option<i64>,
+        tier_name: Option<String>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(!code.is_empty() && code.len() <= 32, ErrorCode::InvalidCoupon);
+        require!(
+            match discount {
+                Discount::Percent(bps) => bps > 0 && bps <= 10_000,
+                Discount::Fixed(amount) => amount > 0,
+            },
+            ErrorCode::InvalidCoupon
+        );
+        require!(
+            duration_cycles != Some(0) && max_redemptions != Some(0),
+            ErrorCode::InvalidCoupon
+        );
+        require!(
+            expires_at.map_or(true, |exp| exp > now),
+            ErrorCode::InvalidCoupon
+        );
+        require!(
+            tier_name.as_ref().map_or(true, |t| t.len() <= 32),
+            ErrorCode::InvalidCoupon
+        );
+
+        let coupon = &mut ctx.accounts.coupon;
+        coupon.plan = ctx.accounts.plan.key();
+        coupon.code = code;
+        coupon.discount = discount;
+        coupon.duration_cycles = duration_cycles;
+        coupon.max_redemptions = max_redemptions;
+        coupon.redemptions = 0;
+        coupon.expires_at = expires_at;
+        coupon.tier_name = tier_name;
+        coupon.active = true;
+        coupon.bump = ctx.bumps.coupon;
+
+        emit!(CouponCreated {
+            coupon: coupon.key(),
+            plan: coupon.plan,
+            code: coupon.code.clone(),
+            discount,
+            duration_cycles,
+            max_redemptions,
+            expires_at,
+            tier_name: coupon.tier_name.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Stops new redemptions; subscriptions that already hold the coupon keep their discount.
+    pub fn deactivate_coupon(ctx: Context<DeactivateCoupon>) -> Result<()> {
+        ctx.accounts.coupon.active = false;
+        Ok(())
+    }
+
+    pub fn apply_coupon(ctx: Context<ApplyCoupon>) -> Result<()> {
+        redeem_coupon(&mut ctx.accounts.coupon, &mut ctx.accounts.subscription)
+    }
+
+    pub fn pause_subscription(ctx: Context<PauseSubscription>) -> Result<()> {
+        let subscription = &mut ctx.accounts.subscription;
+        require!(subscription.active, ErrorCode::SubscriptionInactive);
+        require!(
+            !term_reached(subscription),
+            ErrorCode::SubscriptionCompleted
+        );
+        require!(
+            subscription.paused_at.is_none(),
+            ErrorCode::SubscriptionPaused
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        subscription.paused_at = Some(now);
+
+        emit!(SubscriptionPaused {
+            subscription: subscription.key(),
+            payer: subscription.payer,
+            paused_at: now,
+        });
+
+        Ok(())
+    }
+
+    /// Pushes the schedule back by the time spent paused, up to the plan's limit.
+    pub fn resume_subscription(ctx: Context<ResumeSubscription>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let max_pause = ctx.accounts.plan.max_pause_seconds;
+        let subscription = &mut ctx.accounts.subscription;
+
+        let paused_at = subscription
+            .paused_at
+            .ok_or(ErrorCode::SubscriptionNotPaused)?;
+        let paused_for = now
+            .checked_sub(paused_at)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+        let limit_reached = max_pause > 0 && paused_for >= max_pause;
+
+        // Anyone (e.g. the keeper) may end a pause once the plan's limit has passed
+        require!(
+            ctx.accounts.authority.key() == subscription.payer || limit_reached,
+            ErrorCode::Unauthorized
+        );
+
+        let credited = if max_pause > 0 {
+            paused_for.min(max_pause)
+        } else {
+            paused_for
+        };
+        subscription.next_payment_ts = subscription
+            .next_payment_ts
+            .checked_add(credited)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+        subscription.paused_at = None;
+
+        emit!(SubscriptionResumed {
+            subscription: subscription.key(),
+            payer: subscription.payer,
+            paused_seconds: credited,
+            next_payment_ts: subscription.next_payment_ts,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Tops up the owner's wSOL balance; renewals of SOL plans draw from it.
+    pub fn wrap_sol(ctx: Context<WrapSol>, lamports: u64) -> Result<()> {
+        require!(lamports > 0, ErrorCode::InvalidAmount);
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.owner.to_account_info(),
+                    to: ctx.accounts.wsol_account.to_account_info(),
+                },
+            ),
+            lamports,
+        )?;
+        token::sync_native(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SyncNative {
+                account: ctx.accounts.wsol_account.to_account_info(),
+            },
+        ))?;
+
+        // One wSOL account backs all of the owner's SOL subscriptions, so renewals pull
+        // through a shared delegate; each approval adds to what the others still have
+        if let Some(subscription) = &ctx.accounts.subscription {
+            if subscription.auto_renew {
+                let sol_delegate = ctx.accounts.sol_delegate.key();
+                let approved = if ctx.accounts.wsol_account.delegate == COption::Some(sol_delegate)
+                {
+                    ctx.accounts.wsol_account.delegated_amount
+                } else {
+                    0
+                };
+                token::approve(
+                    CpiContext::new(
+                        ctx.accounts.token_program.to_account_info(),
+                        Approve {
+                            to: ctx.accounts.wsol_account.to_account_info(),
+                            delegate: ctx.accounts.sol_delegate.to_account_info(),
+                            authority: ctx.accounts.owner.to_account_info(),
+                        },
+                    ),
+                    approved.saturating_add(subscription.approval_bound()),
+                )?;
+            }
+        }
+
+        ctx.accounts.wsol_account.reload()?;
+        emit!(SolWrapped {
+            owner: ctx.accounts.owner.key(),
+            lamports,
+            balance: ctx.accounts.wsol_account.amount,
+        });
+
+        Ok(())
+    }
+
+    /// Used by subscribers leaving SOL plans and by merchants collecting SOL revenue.
+    pub fn unwrap_sol(ctx: Context<UnwrapSol>) -> Result<()> {
+        let lamports = ctx.accounts.wsol_account.amount;
+
+        token::close_account(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.wsol_account.to_account_info(),
+                destination: ctx.accounts.owner.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        ))?;
+
+        emit!(SolUnwrapped {
+            owner: ctx.accounts.owner.key(),
+            lamports,
+        });
 
         Ok(())
     }
@@ -148,6 +619,7 @@ pub mod recurring_payments {
         token_image: String,
         tiers: Vec<u8>,
     ) -> Result<()> {
+        check_mint_extensions(&ctx.accounts.mint.to_account_info())?;
         let plan = &mut ctx.accounts.plan;
 
         plan.creator = ctx.accounts.creator.key();
@@ -178,6 +650,196 @@ pub mod recurring_payments {
         Ok(())
     }
 
+    /// Lets subscribers pay in another mint; tiers need a price in it off-chain.
+    pub fn add_plan_mint(ctx: Context<AddPlanMint>, token_symbol: String) -> Result<()> {
+        check_mint_extensions(&ctx.accounts.mint.to_account_info())?;
+        let mint = ctx.accounts.mint.key();
+        let plan = &mut ctx.accounts.plan;
+        require!(token_symbol.len() <= 10, ErrorCode::InvalidFieldValue);
+        require!(!plan.accepts_mint(&mint), ErrorCode::InvalidFieldValue);
+        require!(
+            plan.accepted_mints.len() < MAX_PLAN_MINTS,
+            ErrorCode::TooManyMints
+        );
+
+        plan.accepted_mints.push(PlanMint { mint, token_symbol });
+        emit_plan_mints(plan)
+    }
+
+    /// Existing subscriptions in the mint keep renewing; only new ones are refused.
+    pub fn remove_plan_mint(ctx: Context<RemovePlanMint>, mint: Pubkey) -> Result<()> {
+        let plan = &mut ctx.accounts.plan;
+        let before = plan.accepted_mints.len();
+        plan.accepted_mints.retain(|m| m.mint != mint);
+        require!(
+            plan.accepted_mints.len() < before,
+            ErrorCode::MintNotAccepted
+        );
+        plan.price_feeds.retain(|f| f.mint != mint);
+
+        emit_plan_mints(plan)
+    }
+
+    pub fn set_plan_pause_limit(
+        ctx: Context<SetPlanPauseLimit>,
+        max_pause_seconds: i64,
+    ) -> Result<()> {
+        require!(max_pause_seconds >= 0, ErrorCode::InvalidFieldValue);
+        ctx.accounts.plan.max_pause_seconds = max_pause_seconds;
+        Ok(())
+    }
+
+    /// Sets the USD price feed of `mint`, which must carry `feed_id`. The age and
+    /// confidence limits apply to every mint's feed.
+    pub fn set_plan_price_feed(
+        ctx: Context<SetPlanPriceFeed>,
+        mint: Pubkey,
+        feed_id: [u8; 32],
+        max_price_age: i64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        require!(
+            oracle::read_feed_id(&ctx.accounts.price_feed.to_account_info())? == feed_id,
+            ErrorCode::PriceFeedMismatch
+        );
+        require!(max_price_age > 0, ErrorCode::InvalidFieldValue);
+        require!(
+            max_confidence_bps > 0 && max_confidence_bps <= 10_000,
+            ErrorCode::InvalidFieldValue
+        );
+
+        let plan = &mut ctx.accounts.plan;
+        require!(plan.accepts_mint(&mint), ErrorCode::MintNotAccepted);
+        plan.price_feeds.retain(|f| f.mint != mint);
+        require!(
+            plan.price_feeds.len() < MAX_PRICE_FEEDS,
+            ErrorCode::TooManyTierPrices
+        );
+        plan.price_feeds.push(PriceFeed {
+            mint,
+            price_feed: ctx.accounts.price_feed.key(),
+            feed_id,
+        });
+        plan.max_price_age = max_price_age;
+        plan.max_confidence_bps = max_confidence_bps;
+        Ok(())
+    }
+
+    /// Reallocates an account made before its struct grew. Fields were only ever
+    /// appended and each reads zero as unset, so the new space is left zeroed.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let account = ctx.accounts.account.to_account_info();
+        let previous_size = account.data_len();
+        let is_plan = {
+            let data = account.try_borrow_data()?;
+            require!(data.len() >= 8, ErrorCode::NotMigratable);
+            match &data[..8] {
+                d if d == Plan::DISCRIMINATOR => true,
+                d if d == Subscription::DISCRIMINATOR => false,
+                _ => return err!(ErrorCode::NotMigratable),
+            }
+        };
+        let size = if is_plan {
+            8 + Plan::INIT_SPACE
+        } else {
+            8 + Subscription::INIT_SPACE
+        };
+        require!(previous_size < size, ErrorCode::AlreadyMigrated);
+
+        let shortfall = Rent::get()?
+            .minimum_balance(size)
+            .saturating_sub(account.lamports());
+        if shortfall > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: account.clone(),
+                    },
+                ),
+                shortfall,
+            )?;
+        }
+        account.resize(size)?;
+
+        // Refuse to leave behind an account that still does not decode
+        {
+            let data = account.try_borrow_data()?;
+            if is_plan {
+                Plan::try_deserialize(&mut &data[..])?;
+            } else {
+                Subscription::try_deserialize(&mut &data[..])?;
+            }
+        }
+
+        emit!(AccountMigrated {
+            account: account.key(),
+            previous_size: previous_size as u64,
+            size: size as u64,
+        });
+
+        Ok(())
+    }
+
+    /// Prices usage on a tier in one of the plan's mints; 0 makes the tier flat again.
+    /// Applies from the next charge of every subscription on the tier.
+    pub fn set_tier_unit_price(
+        ctx: Context<SetTierUnitPrice>,
+        tier_name: String,
+        mint: Pubkey,
+        unit_price: u64,
+    ) -> Result<()> {
+        let plan = &mut ctx.accounts.plan;
+        require!(
+            !tier_name.is_empty() && tier_name.len() <= 32,
+            ErrorCode::InvalidFieldValue
+        );
+        require!(plan.accepts_mint(&mint), ErrorCode::MintNotAccepted);
+
+        plan.unit_prices
+            .retain(|p| !(p.tier_name == tier_name && p.mint == mint));
+        if unit_price > 0 {
+            require!(
+                plan.unit_prices.len() < MAX_TIER_PRICES,
+                ErrorCode::TooManyTierPrices
+            );
+            plan.unit_prices.push(UnitPrice {
+                tier_name,
+                mint,
+                unit_price,
+            });
+        }
+        Ok(())
+    }
+
+    /// Prices `tier_name` in USD, 6 decimals; 0 prices it in tokens again. Charges
+    /// need a price feed for the billing mint, which `set_plan_price_feed` sets.
+    pub fn set_tier_usd_price(
+        ctx: Context<SetTierUsdPrice>,
+        tier_name: String,
+        usd_amount: u64,
+    ) -> Result<()> {
+        let plan = &mut ctx.accounts.plan;
+        require!(
+            !tier_name.is_empty() && tier_name.len() <= 32,
+            ErrorCode::InvalidFieldValue
+        );
+
+        plan.usd_prices.retain(|p| p.tier_name != tier_name);
+        if usd_amount > 0 {
+            require!(
+                plan.usd_prices.len() < MAX_TIER_PRICES,
+                ErrorCode::TooManyTierPrices
+            );
+            plan.usd_prices.push(UsdPrice {
+                tier_name,
+                usd_amount,
+            });
+        }
+        Ok(())
+    }
+
     pub fn cancel_plan(_ctx: Context<CancelPlan>) -> Result<()> {
         msg!("Plan cancelled and account closed.");
         Ok(())
@@ -210,6 +872,226 @@ pub mod recurring_payments {
     }
 }
 
+fn emit_plan_mints(plan: &Account<Plan>) -> Result<()> {
+    let mut mints = vec![plan.mint];
+    mints.extend(plan.accepted_mints.iter().map(|m| m.mint));
+
+    emit!(PlanMintsUpdated {
+        plan: plan.key(),
+        mints,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+fn term_reached(subscription: &Subscription) -> bool {
+    subscription
+        .max_cycles
+        .is_some_and(|max| subscription.payment_count >= max)
+        || subscription
+            .end_ts
+            .is_some_and(|end| subscription.next_payment_ts >= end)
+}
+
+/// Deactivates a fixed-term subscription once its last charge has gone through.
+fn complete_if_term_reached(subscription: &mut Account<Subscription>) -> Result<()> {
+    if !term_reached(subscription) {
+        return Ok(());
+    }
+
+    subscription.active = false;
+    subscription.auto_renew = false;
+
+    emit!(SubscriptionCompleted {
+        subscription: subscription.key(),
+        payer: subscription.payer,
+        payments_made: subscription.payment_count,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Checks the coupon can still be redeemed and copies its discount onto the subscription.
+/// Tokens of `mint` worth `usd_amount` at its feed on the plan, within `max_token_amount`.
+fn usd_charge(
+    plan: &Plan,
+    mint: &Pubkey,
+    decimals: u8,
+    price_feed: Option<&UncheckedAccount>,
+    usd_amount: u64,
+    max_token_amount: u64,
+    now: i64,
+) -> Result<u64> {
+    let feed = plan.feed_for(mint).ok_or(ErrorCode::PriceFeedRequired)?;
+    let account = price_feed.ok_or(ErrorCode::PriceFeedRequired)?;
+    let price = oracle::read_price(
+        &account.to_account_info(),
+        &feed.feed_id,
+        now,
+        plan.max_price_age,
+        plan.max_confidence_bps,
+    )?;
+    let tokens = oracle::usd_to_tokens(usd_amount, &price, decimals)?;
+    require!(
+        tokens <= max_token_amount,
+        ErrorCode::PriceAboveSubscriberLimit
+    );
+    Ok(tokens)
+}
+
+fn redeem_coupon(
+    coupon: &mut Account<Coupon>,
+    subscription: &mut Account<Subscription>,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+        subscription.coupon.is_none(),
+        ErrorCode::CouponAlreadyApplied
+    );
+    require!(coupon.active, ErrorCode::CouponInactive);
+    require!(
+        coupon.expires_at.map_or(true, |exp| now < exp),
+        ErrorCode::CouponExpired
+    );
+    require!(
+        coupon
+            .max_redemptions
+            .map_or(true, |max| coupon.redemptions < max),
+        ErrorCode::CouponExhausted
+    );
+    require!(
+        coupon
+            .tier_name
+            .as_ref()
+            .map_or(true, |tier| *tier == subscription.tier_name),
+        ErrorCode::CouponTierMismatch
+    );
+
+    coupon.redemptions = coupon
+        .redemptions
+        .checked_add(1)
+        .ok_or(ErrorCode::NumericalOverflow)?;
+    subscription.coupon = Some(coupon.key());
+    subscription.discount = Some(coupon.discount);
+    subscription.discount_cycles_left = coupon.duration_cycles;
+
+    emit!(CouponRedeemed {
+        coupon: coupon.key(),
+        subscription: subscription.key(),
+        payer: subscription.payer,
+        redemptions: coupon.redemptions,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+/// Discount for this charge, never more than `base_amount`; counts down its remaining cycles.
+fn take_discount(subscription: &mut Account<Subscription>, base_amount: u64) -> u64 {
+    let Some(discount) = subscription.discount else {
+        return 0;
+    };
+    let off = match discount {
+        Discount::Percent(bps) => (base_amount as u128 * bps as u128 / 10_000) as u64,
+        Discount::Fixed(amount) => amount.min(base_amount),
+    };
+
+    match subscription.discount_cycles_left {
+        Some(left) if left <= 1 => {
+            subscription.discount = None;
+            subscription.discount_cycles_left = Some(0);
+        }
+        Some(left) => subscription.discount_cycles_left = Some(left - 1),
+        None => {}
+    }
+
+    off
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record_payment(
+    record: &mut Account<PaymentRecord>,
+    subscription: &mut Account<Subscription>,
+    mint: Pubkey,
+    amount: u64,
+    usage_units: u64,
+    usage_amount: u64,
+    discount_amount: u64,
+    transfer_fee: u64,
+    bump: u8,
+) -> Result<()> {
+    record.subscription = subscription.key();
+    record.plan = subscription.plan_pda;
+    record.payer = subscription.payer;
+    record.mint = mint;
+    record.index = subscription.payment_count;
+    record.amount = amount;
+    record.refunded_amount = 0;
+    record.paid_at = Clock::get()?.unix_timestamp;
+    record.bump = bump;
+    record.usage_units = usage_units;
+    record.usage_amount = usage_amount;
+    record.discount_amount = discount_amount;
+    record.transfer_fee = transfer_fee;
+
+    subscription.payment_count = subscription
+        .payment_count
+        .checked_add(1)
+        .ok_or(ErrorCode::NumericalOverflow)?;
+
+    Ok(())
+}
+
+/// Token-2022 extensions a plan's mint may carry. Anything else either blocks
+/// delegated transfers (non-transferable, default-frozen, pausable) or lets a third
+/// party move subscriber funds (permanent delegate), so the mint is refused.
+fn check_mint_extensions(mint: &AccountInfo) -> Result<()> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    for extension in state.get_extension_types()? {
+        let supported = matches!(
+            extension,
+            ExtensionType::TransferFeeConfig
+                | ExtensionType::TransferHook
+                | ExtensionType::MintCloseAuthority
+                | ExtensionType::InterestBearingConfig
+                | ExtensionType::MetadataPointer
+                | ExtensionType::TokenMetadata
+                | ExtensionType::GroupPointer
+                | ExtensionType::TokenGroup
+                | ExtensionType::GroupMemberPointer
+                | ExtensionType::TokenGroupMember
+        );
+        require!(supported, ErrorCode::UnsupportedMintExtension);
+    }
+
+    Ok(())
+}
+
+/// Fee a transfer-fee mint withholds from `amount`; the merchant receives the rest.
+fn transfer_fee_for(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(0);
+    }
+
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let Ok(config) = state.get_extension::<TransferFeeConfig>() else {
+        return Ok(0);
+    };
+
+    config
+        .calculate_epoch_fee(Clock::get()?.epoch, amount)
+        .ok_or_else(|| error!(ErrorCode::NumericalOverflow))
+}
+
+#[allow(clippy::too_many_arguments)]
 fn perform_payment<'info>(
     subscription: &mut Account<'info, Subscription>,
     payer_token_account: AccountInfo<'info>,
@@ -220,16 +1102,50 @@ fn perform_payment<'info>(
     decimals: u8,
     payer: Option<&Signer<'info>>,
     use_pda_authority: bool,
+    // The shared wSOL delegate and its bump, signing instead of the subscription
+    sol_delegate: Option<(AccountInfo<'info>, u8)>,
+    // Transfer-hook mints: the hook program, its validation account and extra metas
+    hook_accounts: &[AccountInfo<'info>],
 ) -> Result<()> {
     let clock = Clock::get()?;
 
     subscription.next_payment_ts = clock.unix_timestamp + subscription.period_seconds;
 
     // ---------- Resolve authority ----------
-    let authority: AccountInfo<'info> = match payer {
-        Some(payer_signer) => payer_signer.to_account_info(),
-        None => subscription.to_account_info(),
-    };
+    let (authority, delegate_bump): (AccountInfo<'info>, Option<u8>) =
+        match (payer, sol_delegate) {
+            (Some(payer_signer), _) => (payer_signer.to_account_info(), None),
+            (None, Some((delegate, bump))) => (delegate, Some(bump)),
+            (None, None) => (subscription.to_account_info(), None),
+        };
+
+    let bump_seed = [subscription.bump];
+    let seeds: &[&[u8]] = &[
+        SUBSCRIPTION_SEED,
+        subscription.payer.as_ref(),
+        subscription.unique_seed.as_ref(),
+        &bump_seed,
+    ];
+    let delegate_bump_seed = [delegate_bump.unwrap_or_default()];
+    let delegate_seeds: &[&[u8]] = &[SOL_DELEGATE_SEED, &delegate_bump_seed];
+    let pda_signer = [if delegate_bump.is_some() { delegate_seeds } else { seeds }];
+    let signer_seeds: &[&[&[u8]]] = if use_pda_authority { &pda_signer } else { &[] };
+
+    if !hook_accounts.is_empty() {
+        // The plain CPI drops remaining accounts, which the hook needs to run
+        spl_token_2022::onchain::invoke_transfer_checked(
+            token_program.key,
+            payer_token_account,
+            mint,
+            receiver_token_account,
+            authority,
+            hook_accounts,
+            pay_amount,
+            decimals,
+            signer_seeds,
+        )?;
+        return Ok(());
+    }
 
     // ---------- CPI accounts ----------
     let cpi_accounts = TransferChecked {
@@ -240,30 +1156,8 @@ fn perform_payment<'info>(
     };
 
     // ---------- CPI ----------
-    if use_pda_authority {
-        let bump_seed = [subscription.bump];
-
-        let seeds: &[&[u8]] = &[
-            SUBSCRIPTION_SEED,
-            subscription.payer.as_ref(),
-            subscription.unique_seed.as_ref(),
-            &bump_seed,
-        ];
-
-        let signer_seeds: &[&[&[u8]]] = &[seeds];
-
-        let cpi_ctx = CpiContext::new_with_signer(
-            token_program.to_account_info(),
-            cpi_accounts,
-            signer_seeds,
-        );
-
-        transfer_checked(cpi_ctx, pay_amount, decimals)?;
-    } else {
-        let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
-
-        transfer_checked(cpi_ctx, pay_amount, decimals)?;
-    }
+    let cpi_ctx = CpiContext::new_with_signer(token_program, cpi_accounts, signer_seeds);
+    transfer_checked(cpi_ctx, pay_amount, decimals)?;
 
     Ok(())
 }
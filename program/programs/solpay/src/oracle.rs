@@ -0,0 +1,215 @@
+use crate::errors::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+/// Pyth pull-oracle receiver; price updates are `PriceUpdateV2` accounts it owns.
+pub const PYTH_RECEIVER_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// USD amounts are stored with 6 decimals, like USDC.
+pub const USD_DECIMALS: u32 = 6;
+
+// discriminator (8) + write authority (32), then the verification level
+const VERIFICATION_LEVEL_OFFSET: usize = 40;
+const VERIFICATION_FULL: u8 = 1;
+// feed id (32) + price (8) + conf (8) + exponent (4) + publish time (8)
+const MESSAGE_LEN: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+fn price_update_discriminator() -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(b"account:PriceUpdateV2").to_bytes()[..8]);
+    discriminator
+}
+
+/// The price message of a fully verified `PriceUpdateV2`, feed id first.
+fn price_message(data: &[u8]) -> Result<&[u8]> {
+    require!(
+        data.len() > VERIFICATION_LEVEL_OFFSET && data[..8] == price_update_discriminator(),
+        ErrorCode::InvalidPriceFeed
+    );
+    // Partially verified updates carry a signature count after the level byte
+    require!(
+        data[VERIFICATION_LEVEL_OFFSET] == VERIFICATION_FULL,
+        ErrorCode::InvalidPriceFeed
+    );
+    data.get(VERIFICATION_LEVEL_OFFSET + 1..VERIFICATION_LEVEL_OFFSET + 1 + MESSAGE_LEN)
+        .ok_or_else(|| error!(ErrorCode::InvalidPriceFeed))
+}
+
+/// Id of the Pyth feed a price update account carries.
+pub fn read_feed_id(account: &AccountInfo) -> Result<[u8; 32]> {
+    require_keys_eq!(
+        *account.owner,
+        PYTH_RECEIVER_ID,
+        ErrorCode::InvalidPriceFeed
+    );
+
+    let data = account.try_borrow_data()?;
+    let message = price_message(&data)?;
+    Ok(message[..32].try_into().unwrap())
+}
+
+/// Reads a fully verified price of `feed_id`, refusing stale or low-confidence ones.
+pub fn read_price(
+    account: &AccountInfo,
+    feed_id: &[u8; 32],
+    now: i64,
+    max_age: i64,
+    max_confidence_bps: u16,
+) -> Result<OraclePrice> {
+    require_keys_eq!(
+        *account.owner,
+        PYTH_RECEIVER_ID,
+        ErrorCode::InvalidPriceFeed
+    );
+
+    let data = account.try_borrow_data()?;
+    let message = price_message(&data)?;
+    // Any fresh update the receiver owns passes the checks above, whatever its asset
+    require!(message[..32] == feed_id[..], ErrorCode::PriceFeedMismatch);
+
+    let read_i64 = |at: usize| i64::from_le_bytes(message[at..at + 8].try_into().unwrap());
+    let price = OraclePrice {
+        price: read_i64(32),
+        conf: u64::from_le_bytes(message[40..48].try_into().unwrap()),
+        exponent: i32::from_le_bytes(message[48..52].try_into().unwrap()),
+        publish_time: read_i64(52),
+    };
+
+    require!(price.price > 0, ErrorCode::InvalidPriceFeed);
+    require!(
+        now.saturating_sub(price.publish_time) <= max_age,
+        ErrorCode::StalePrice
+    );
+    require!(
+        (price.conf as u128) * 10_000 <= (price.price as u128) * max_confidence_bps as u128,
+        ErrorCode::PriceConfidenceTooWide
+    );
+
+    Ok(price)
+}
+
+/// Token base units worth `usd_amount`, rounded up so the merchant is never short.
+pub fn usd_to_tokens(usd_amount: u64, price: &OraclePrice, decimals: u8) -> Result<u64> {
+    let pow10 = |exp: u32| 10u128.checked_pow(exp).ok_or(ErrorCode::NumericalOverflow);
+
+    // tokens = usd / 10^6 / (price * 10^exponent) * 10^decimals
+    let mut numerator = (usd_amount as u128)
+        .checked_mul(pow10(decimals as u32)?)
+        .ok_or(ErrorCode::NumericalOverflow)?;
+    let mut denominator = (price.price as u128)
+        .checked_mul(pow10(USD_DECIMALS)?)
+        .ok_or(ErrorCode::NumericalOverflow)?;
+    if price.exponent < 0 {
+        numerator = numerator
+            .checked_mul(pow10(price.exponent.unsigned_abs())?)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+    } else {
+        denominator = denominator
+            .checked_mul(pow10(price.exponent as u32)?)
+            .ok_or(ErrorCode::NumericalOverflow)?;
+    }
+
+    let tokens = numerator.div_ceil(denominator);
+    u64::try_from(tokens).map_err(|_| error!(ErrorCode::NumericalOverflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+    const FEED_ID: [u8; 32] = [1u8; 32];
+
+    fn mock_price_update(price: i64, conf: u64, exponent: i32, publish_time: i64) -> Vec<u8> {
+        let mut data = price_update_discriminator().to_vec();
+        data.extend_from_slice(&[7u8; 32]); // write authority
+        data.push(VERIFICATION_FULL);
+        data.extend_from_slice(&FEED_ID);
+        data.extend_from_slice(&price.to_le_bytes());
+        data.extend_from_slice(&conf.to_le_bytes());
+        data.extend_from_slice(&exponent.to_le_bytes());
+        data.extend_from_slice(&publish_time.to_le_bytes());
+        data.extend_from_slice(&[0u8; 24]); // prev publish time, ema price, ema conf
+        data.extend_from_slice(&0u64.to_le_bytes()); // posted slot
+        data
+    }
+
+    fn read_mock_feed(
+        data: &mut [u8],
+        owner: &Pubkey,
+        feed_id: &[u8; 32],
+        max_age: i64,
+        max_bps: u16,
+    ) -> Result<OraclePrice> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 1_000_000;
+        let account = AccountInfo::new(&key, false, false, &mut lamports, data, owner, false, 0);
+        read_price(&account, feed_id, NOW, max_age, max_bps)
+    }
+
+    fn read_mock(
+        data: &mut [u8],
+        owner: &Pubkey,
+        max_age: i64,
+        max_bps: u16,
+    ) -> Result<OraclePrice> {
+        read_mock_feed(data, owner, &FEED_ID, max_age, max_bps)
+    }
+
+    #[test]
+    fn reads_fresh_price() {
+        // SOL at $150.00000000
+        let mut data = mock_price_update(15_000_000_000, 5_000_000, -8, NOW - 10);
+        let price = read_mock(&mut data, &PYTH_RECEIVER_ID, 60, 100).unwrap();
+        assert_eq!(price.price, 15_000_000_000);
+        assert_eq!(price.exponent, -8);
+    }
+
+    #[test]
+    fn rejects_stale_price() {
+        let mut data = mock_price_update(15_000_000_000, 5_000_000, -8, NOW - 61);
+        assert!(read_mock(&mut data, &PYTH_RECEIVER_ID, 60, 100).is_err());
+    }
+
+    #[test]
+    fn rejects_wide_confidence() {
+        // conf is 2% of the price, the plan allows 1%
+        let mut data = mock_price_update(15_000_000_000, 300_000_000, -8, NOW);
+        assert!(read_mock(&mut data, &PYTH_RECEIVER_ID, 60, 100).is_err());
+    }
+
+    #[test]
+    fn rejects_other_feeds() {
+        let mut data = mock_price_update(15_000_000_000, 5_000_000, -8, NOW);
+        assert!(read_mock_feed(&mut data, &PYTH_RECEIVER_ID, &[2u8; 32], 60, 100).is_err());
+    }
+
+    #[test]
+    fn rejects_foreign_owner() {
+        let mut data = mock_price_update(15_000_000_000, 5_000_000, -8, NOW);
+        assert!(read_mock(&mut data, &Pubkey::new_unique(), 60, 100).is_err());
+    }
+
+    #[test]
+    fn converts_usd_to_tokens_rounding_up() {
+        let price = OraclePrice {
+            price: 15_000_000_000,
+            conf: 0,
+            exponent: -8,
+            publish_time: NOW,
+        };
+        // $15 of a 9-decimal token at $150 is 0.1 token
+        assert_eq!(usd_to_tokens(15_000_000, &price, 9).unwrap(), 100_000_000);
+        // $10 at $150 is 0.0666..., rounded up in base units
+        assert_eq!(usd_to_tokens(10_000_000, &price, 9).unwrap(), 66_666_667);
+    }
+}
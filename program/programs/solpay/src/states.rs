@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 use crate::constants::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::Token;
 use anchor_spl::token_interface::{
     Mint, TokenAccount, TokenInterface,
 };
@@ -46,6 +48,28 @@ pub struct InitializeSubscription<'info> {
     pub global_stats: Account<'info, GlobalStats>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
+    /// Record of the first charge, index 0
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PaymentRecord::INIT_SPACE,
+        seeds = [PAYMENT_SEED, subscription.key().as_ref(), &0u64.to_le_bytes()],
+        bump
+    )]
+    pub payment_record: Account<'info, PaymentRecord>,
+    #[account(constraint = plan.key() == plan_pda @ ErrorCode::Unauthorized)]
+    pub plan: Account<'info, Plan>,
+    /// Optional coupon, redeemed before the first charge
+    #[account(
+        mut,
+        seeds = [COUPON_SEED, coupon.plan.as_ref(), coupon.code.as_bytes()],
+        bump = coupon.bump,
+        constraint = coupon.plan == plan_pda @ ErrorCode::InvalidCoupon,
+    )]
+    pub coupon: Option<Account<'info, Coupon>>,
+    /// CHECK: the mint's feed on the plan, parsed by `oracle::read_price`; only USD-priced tiers need it
+    #[account(constraint = plan.feed_for(&mint.key()).is_some_and(|feed| feed.price_feed == price_feed.key()) @ ErrorCode::InvalidPriceFeed)]
+    pub price_feed: Option<UncheckedAccount<'info>>,
 }
 
 
@@ -53,18 +77,104 @@ pub struct InitializeSubscription<'info> {
 pub struct ExecutePayment<'info> {
     #[account(mut)]
     pub subscription: Account<'info, Subscription>,
+    #[account(constraint = plan.key() == subscription.plan_pda @ ErrorCode::Unauthorized)]
     pub plan: Account<'info, Plan>,
     /// CHECK: user-owned token account (SPL or Token-2022)
-    #[account(mut)]
+    #[account(mut, constraint = user_token_account.owner == subscription.payer @ ErrorCode::Unauthorized)]
     pub user_token_account: InterfaceAccount<'info, TokenAccount>,
     /// CHECK: merchant receiver
     #[account(mut)]
     pub receiver_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(constraint = mint.key() == subscription.billing_mint(&plan) @ ErrorCode::IncorrectMint)]
     pub mint: InterfaceAccount<'info, Mint>,
     pub system_program: Program<'info, System>,
     pub token_program: Interface<'info, TokenInterface>,
+    /// Pays rent for the payment record
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    #[account(
+        init,
+        payer = keeper,
+        space = 8 + PaymentRecord::INIT_SPACE,
+        seeds = [PAYMENT_SEED, subscription.key().as_ref(), &subscription.payment_count.to_le_bytes()],
+        bump
+    )]
+    pub payment_record: Account<'info, PaymentRecord>,
+    /// CHECK: the billing mint's feed on the plan, parsed by `oracle::read_price`; only USD-priced subscriptions need it
+    #[account(constraint = plan.feed_for(&mint.key()).is_some_and(|feed| feed.price_feed == price_feed.key()) @ ErrorCode::InvalidPriceFeed)]
+    pub price_feed: Option<UncheckedAccount<'info>>,
+    /// CHECK: shared wSOL delegate; pulls the charge when it, not the subscription, is approved
+    #[account(seeds = [SOL_DELEGATE_SEED], bump)]
+    pub sol_delegate: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct RefundPayment<'info> {
+    /// The plan's receiver, who sends the refund from their own token account
+    pub receiver: Signer<'info>,
+    #[account(has_one = receiver @ ErrorCode::Unauthorized)]
+    pub plan: Account<'info, Plan>,
+    #[account(
+        mut,
+        seeds = [PAYMENT_SEED, payment_record.subscription.as_ref(), &payment_record.index.to_le_bytes()],
+        bump = payment_record.bump,
+        constraint = payment_record.plan == plan.key() @ ErrorCode::Unauthorized,
+        constraint = payment_record.mint == mint.key() @ ErrorCode::IncorrectMint,
+    )]
+    pub payment_record: Account<'info, PaymentRecord>,
+    #[account(mut, token::mint = mint, token::authority = receiver)]
+    pub receiver_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = payment_record.payer)]
+    pub payer_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+/// Moves SOL into the owner's wSOL account so SOL plans can charge it like any token.
+#[derive(Accounts)]
+pub struct WrapSol<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = native_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub wsol_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = NATIVE_MINT @ ErrorCode::IncorrectMint)]
+    pub native_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    /// SOL subscription whose remaining renewals to approve; `unwrap_sol` closes the
+    /// account and the approval goes with it
+    #[account(constraint = subscription.payer == owner.key() @ ErrorCode::Unauthorized)]
+    pub subscription: Option<Account<'info, Subscription>>,
+    /// CHECK: signs nothing here; only approved as the wSOL account's delegate
+    #[account(seeds = [SOL_DELEGATE_SEED], bump)]
+    pub sol_delegate: UncheckedAccount<'info>,
 }
 
+/// Closes the owner's wSOL account, returning its balance and rent as SOL. Any
+/// subscription approved on it must be approved again by the next `wrap_sol`.
+#[derive(Accounts)]
+pub struct UnwrapSol<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = native_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub wsol_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = NATIVE_MINT @ ErrorCode::IncorrectMint)]
+    pub native_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Program<'info, Token>,
+}
 
 #[derive(Accounts)]
 pub struct CancelSubscription<'info> {
@@ -90,6 +200,190 @@ pub struct UpdatePlan<'info> {
     pub receiver: SystemAccount<'info>, // ✅ SAFE
 }
 
+#[derive(Accounts)]
+pub struct AddPlanMint<'info> {
+    #[account(
+        mut,
+        seeds = [b"plan", creator.key().as_ref()],
+        bump = plan.bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub plan: Account<'info, Plan>,
+    pub creator: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+}
+
+#[derive(Accounts)]
+pub struct RemovePlanMint<'info> {
+    #[account(
+        mut,
+        seeds = [b"plan", creator.key().as_ref()],
+        bump = plan.bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub plan: Account<'info, Plan>,
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPlanPriceFeed<'info> {
+    #[account(
+        mut,
+        seeds = [b"plan", creator.key().as_ref()],
+        bump = plan.bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub plan: Account<'info, Plan>,
+    pub creator: Signer<'info>,
+    /// CHECK: owner and feed id are checked by `oracle::read_feed_id` in the handler
+    pub price_feed: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPlanPauseLimit<'info> {
+    #[account(
+        mut,
+        seeds = [b"plan", creator.key().as_ref()],
+        bump = plan.bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub plan: Account<'info, Plan>,
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PauseSubscription<'info> {
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [SUBSCRIPTION_SEED, subscription.payer.as_ref(), subscription.unique_seed.as_ref()],
+        bump = subscription.bump,
+        has_one = payer @ ErrorCode::Unauthorized,
+    )]
+    pub subscription: Account<'info, Subscription>,
+}
+
+#[derive(Accounts)]
+pub struct ResumeSubscription<'info> {
+    /// The subscriber, or anyone once the plan's pause limit has passed
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [SUBSCRIPTION_SEED, subscription.payer.as_ref(), subscription.unique_seed.as_ref()],
+        bump = subscription.bump,
+    )]
+    pub subscription: Account<'info, Subscription>,
+    #[account(constraint = plan.key() == subscription.plan_pda @ ErrorCode::Unauthorized)]
+    pub plan: Account<'info, Plan>,
+}
+
+#[derive(Accounts)]
+pub struct SetTierUnitPrice<'info> {
+    #[account(
+        mut,
+        seeds = [b"plan", creator.key().as_ref()],
+        bump = plan.bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub plan: Account<'info, Plan>,
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTierUsdPrice<'info> {
+    #[account(
+        mut,
+        seeds = [b"plan", creator.key().as_ref()],
+        bump = plan.bump,
+        has_one = creator @ ErrorCode::Unauthorized,
+    )]
+    pub plan: Account<'info, Plan>,
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetUsageCap<'info> {
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [SUBSCRIPTION_SEED, subscription.payer.as_ref(), subscription.unique_seed.as_ref()],
+        bump = subscription.bump,
+        has_one = payer @ ErrorCode::Unauthorized,
+    )]
+    pub subscription: Account<'info, Subscription>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxTokenAmount<'info> {
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [SUBSCRIPTION_SEED, subscription.payer.as_ref(), subscription.unique_seed.as_ref()],
+        bump = subscription.bump,
+        has_one = payer @ ErrorCode::Unauthorized,
+    )]
+    pub subscription: Account<'info, Subscription>,
+}
+
+#[derive(Accounts)]
+pub struct ReportUsage<'info> {
+    /// The plan creator vouches for the usage
+    pub creator: Signer<'info>,
+    #[account(has_one = creator @ ErrorCode::Unauthorized)]
+    pub plan: Account<'info, Plan>,
+    #[account(
+        mut,
+        constraint = subscription.plan_pda == plan.key() @ ErrorCode::Unauthorized,
+    )]
+    pub subscription: Account<'info, Subscription>,
+}
+
+#[derive(Accounts)]
+#[instruction(code: String)]
+pub struct CreateCoupon<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    #[account(has_one = creator @ ErrorCode::Unauthorized)]
+    pub plan: Account<'info, Plan>,
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Coupon::INIT_SPACE,
+        seeds = [COUPON_SEED, plan.key().as_ref(), code.as_bytes()],
+        bump
+    )]
+    pub coupon: Account<'info, Coupon>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeactivateCoupon<'info> {
+    pub creator: Signer<'info>,
+    #[account(has_one = creator @ ErrorCode::Unauthorized)]
+    pub plan: Account<'info, Plan>,
+    #[account(mut, has_one = plan @ ErrorCode::Unauthorized)]
+    pub coupon: Account<'info, Coupon>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyCoupon<'info> {
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [SUBSCRIPTION_SEED, subscription.payer.as_ref(), subscription.unique_seed.as_ref()],
+        bump = subscription.bump,
+        has_one = payer @ ErrorCode::Unauthorized,
+    )]
+    pub subscription: Account<'info, Subscription>,
+    #[account(
+        mut,
+        seeds = [COUPON_SEED, coupon.plan.as_ref(), coupon.code.as_bytes()],
+        bump = coupon.bump,
+        constraint = coupon.plan == subscription.plan_pda @ ErrorCode::InvalidCoupon,
+    )]
+    pub coupon: Account<'info, Coupon>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateSubscriptionStatus<'info> {
     #[account(mut)]
@@ -138,6 +432,18 @@ pub struct CancelPlan<'info> {
 
 }
 
+/// Grows a subscription or plan created before its newest fields were added.
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    /// Anyone may pay the extra rent
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: must be owned by this program; its type is read from the discriminator
+    #[account(mut, owner = crate::ID @ ErrorCode::NotMigratable)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
 pub enum UpdateValue {
     Bool(bool),
@@ -171,6 +477,95 @@ pub struct Plan {
     pub tiers: Vec<u8>,
 
     pub bump: u8,
+
+    /// Longest pause credited back to a subscriber, 0 for no limit
+    pub max_pause_seconds: i64,
+
+    /// Mints accepted besides `mint`; tiers carry a price for each
+    #[max_len(4)] // MAX_PLAN_MINTS
+    pub accepted_mints: Vec<PlanMint>,
+
+    /// Unused since feeds are kept per mint in `price_feeds`; left for the account layout
+    pub price_feed: Pubkey,
+    /// Oldest and least confident prices accepted from any of `price_feeds`
+    pub max_price_age: i64,
+    /// Widest confidence interval accepted, in basis points of the price
+    pub max_confidence_bps: u16,
+
+    /// Metered tiers: price per reported unit in each mint, set by the creator
+    #[max_len(8)] // MAX_TIER_PRICES
+    pub unit_prices: Vec<UnitPrice>,
+
+    /// USD-priced tiers: price per cycle, converted at the billing mint's feed on each charge
+    #[max_len(8)] // MAX_TIER_PRICES
+    pub usd_prices: Vec<UsdPrice>,
+
+    /// Pyth feed of each mint USD-priced tiers can be billed in
+    #[max_len(5)] // MAX_PRICE_FEEDS
+    pub price_feeds: Vec<PriceFeed>,
+}
+
+impl Plan {
+    pub fn accepts_mint(&self, mint: &Pubkey) -> bool {
+        self.mint == *mint || self.accepted_mints.iter().any(|m| m.mint == *mint)
+    }
+
+    /// Price per unit of usage on `tier_name` billed in `mint`, 0 when the tier is flat.
+    pub fn unit_price(&self, tier_name: &str, mint: &Pubkey) -> u64 {
+        self.unit_prices
+            .iter()
+            .find(|p| p.tier_name == tier_name && p.mint == *mint)
+            .map_or(0, |p| p.unit_price)
+    }
+
+    /// USD price per cycle of `tier_name` with 6 decimals, 0 when priced in tokens.
+    pub fn usd_price(&self, tier_name: &str) -> u64 {
+        self.usd_prices
+            .iter()
+            .find(|p| p.tier_name == tier_name)
+            .map_or(0, |p| p.usd_amount)
+    }
+
+    /// Feed that prices `mint` in USD, if the creator has set one.
+    pub fn feed_for(&self, mint: &Pubkey) -> Option<&PriceFeed> {
+        self.price_feeds.iter().find(|f| f.mint == *mint)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+#[derive(InitSpace)]
+pub struct UnitPrice {
+    #[max_len(32)]
+    pub tier_name: String,
+    pub mint: Pubkey,
+    pub unit_price: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+#[derive(InitSpace)]
+pub struct UsdPrice {
+    #[max_len(32)]
+    pub tier_name: String,
+    /// Dollars with 6 decimals
+    pub usd_amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+#[derive(InitSpace)]
+pub struct PriceFeed {
+    pub mint: Pubkey,
+    /// `PriceUpdateV2` account the receiver keeps up to date
+    pub price_feed: Pubkey,
+    /// Pyth id of the mint's USD price, checked against every update read
+    pub feed_id: [u8; 32],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+#[derive(InitSpace)]
+pub struct PlanMint {
+    pub mint: Pubkey,
+    #[max_len(10)]
+    pub token_symbol: String,
 }
 
 
@@ -205,6 +600,104 @@ pub struct Subscription {
     pub unique_seed: [u8; 8],
     pub amount: u64,        
     pub period_seconds: i64,
+    /// Number of charges so far; the next payment record uses this as its index
+    pub payment_count: u64,
+    pub paused_at: Option<i64>,
+    /// Fixed-term subscriptions stop after this many charges
+    pub max_cycles: Option<u64>,
+    /// ...or once the next charge would fall on or after this time
+    pub end_ts: Option<i64>,
+    /// Most the usage part may add to a single charge, approved by the subscriber
+    pub usage_cap: u64,
+    /// Units reported since the last charge
+    pub accrued_units: u64,
+    /// Coupon redeemed by this subscription, at most one
+    pub coupon: Option<Pubkey>,
+    /// Taken off the base amount while set; cleared once its cycles run out
+    pub discount: Option<Discount>,
+    /// Discounted charges left, None for every charge
+    pub discount_cycles_left: Option<u64>,
+    /// Mint the subscriber pays in; left default on legacy subscriptions grown by
+    /// `migrate_account`, which keep billing in the plan's primary mint
+    pub mint: Pubkey,
+    /// Most one charge of a USD-priced tier may take, approved by the subscriber
+    pub max_token_amount: u64,
+    /// Lower cap the subscriber asked for, applied once the current cycle is charged
+    pub pending_usage_cap: Option<u64>,
+}
+
+impl Subscription {
+    pub fn billing_mint(&self, plan: &Plan) -> Pubkey {
+        if self.mint == Pubkey::default() {
+            plan.mint
+        } else {
+            self.mint
+        }
+    }
+
+    /// Most the remaining renewals can charge: each at the token limit or amount plus
+    /// the usage cap, for the cycles left or `SOL_APPROVAL_CYCLES` when unlimited.
+    pub fn approval_bound(&self) -> u64 {
+        let per_charge = self
+            .amount
+            .max(self.max_token_amount)
+            .saturating_add(self.usage_cap);
+        let cycles = self
+            .max_cycles
+            .map_or(SOL_APPROVAL_CYCLES, |max| max.saturating_sub(self.payment_count));
+        per_charge.saturating_mul(cycles)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(InitSpace)]
+pub enum Discount {
+    /// Basis points off the base amount, 10_000 = free
+    Percent(u16),
+    /// Token base units off the base amount
+    Fixed(u64),
+}
+
+/// Merchant promotion at `[b"coupon", plan, code]`.
+#[account]
+#[derive(InitSpace)]
+pub struct Coupon {
+    pub plan: Pubkey,
+    #[max_len(32)]
+    pub code: String,
+    pub discount: Discount,
+    /// Charges the discount lasts for, None for the life of the subscription
+    pub duration_cycles: Option<u64>,
+    pub max_redemptions: Option<u64>,
+    pub redemptions: u64,
+    pub expires_at: Option<i64>,
+    /// Restricts the coupon to one tier
+    #[max_len(32)]
+    pub tier_name: Option<String>,
+    pub active: bool,
+    pub bump: u8,
+}
+
+/// One per charge, so refunds can be capped at what was actually paid.
+#[account]
+#[derive(InitSpace)]
+pub struct PaymentRecord {
+    pub subscription: Pubkey,
+    pub plan: Pubkey,
+    pub payer: Pubkey,
+    pub mint: Pubkey,
+    pub index: u64,
+    pub amount: u64,
+    pub refunded_amount: u64,
+    pub paid_at: i64,
+    pub bump: u8,
+    /// Metered part of `amount`
+    pub usage_units: u64,
+    pub usage_amount: u64,
+    /// Coupon discount already taken off `amount`
+    pub discount_amount: u64,
+    /// Withheld by a transfer-fee mint; the merchant received `amount - transfer_fee`
+    pub transfer_fee: u64,
 }
 
 #[account]